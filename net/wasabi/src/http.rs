@@ -1,23 +1,57 @@
 extern crate alloc;
+use crate::random::WasabiRandom;
 use alloc::format;
-use alloc::string::String;
+use alloc::rc::Rc;
 use alloc::string::ToString;
-use alloc::vec::Vec;
 use noli::net::lookup_host;
 use noli::net::SocketAddr;
 use noli::net::TcpStream;
 use saba_core::error::Error;
-use saba_core::http::HttpResponse;
+use saba_core::http::client;
+use saba_core::http::proxy::ProxyConfig;
+use saba_core::http::{HttpRequest, HttpResponse};
+use saba_core::net::tls::TlsConfig;
+use saba_core::net::{Connection, Transport};
 
-pub struct HttpClient {}
+/// WasabiOSのTCP接続
+pub struct WasabiConnection {
+    stream: TcpStream,
+}
 
-impl HttpClient {
+impl Connection for WasabiConnection {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        match self.stream.write(buf) {
+            Ok(bytes) => Ok(bytes),
+            Err(_) => Err(Error::Network(
+                "Failed to send a request to TCP stream".to_string(),
+            )),
+        }
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        match self.stream.read(buf) {
+            Ok(bytes) => Ok(bytes),
+            Err(_) => Err(Error::Network(
+                "Failed to receive a request from TCP stream".to_string(),
+            )),
+        }
+    }
+}
+
+/// noli::netを使って接続を確立するTransport
+pub struct WasabiTransport {}
+
+impl WasabiTransport {
     pub fn new() -> Self {
         Self {}
     }
+}
+
+impl Transport for WasabiTransport {
+    type Connection = WasabiConnection;
 
-    pub fn get(&self, host: String, port: u16, path: String) -> Result<HttpResponse, Error> {
-        let ips = match lookup_host(&host) {
+    fn connect(&self, host: &str, port: u16) -> Result<WasabiConnection, Error> {
+        let ips = match lookup_host(host) {
             Ok(ips) => ips,
             Err(e) => {
                 return Err(Error::Network(format!(
@@ -33,59 +67,55 @@ impl HttpClient {
 
         let socket_addr: SocketAddr = (ips[0], port).into();
 
-        let mut stream = match TcpStream::connect(socket_addr) {
-            Ok(stream) => stream,
-            Err(_) => {
-                return Err(Error::Network(
-                    "Failed to connect to TCP Stream".to_string(),
-                ))
-            }
-        };
+        match TcpStream::connect(socket_addr) {
+            Ok(stream) => Ok(WasabiConnection { stream }),
+            Err(_) => Err(Error::Network(
+                "Failed to connect to TCP Stream".to_string(),
+            )),
+        }
+    }
+}
 
-        // Request Line
-        // https://www.rfc-editor.org/rfc/rfc9112.html#section-3
-        let mut request = String::from("GET /");
-        request.push_str(&path);
-        request.push_str(" HTTP/1.1\n");
-
-        // ヘッダの追加
-        request.push_str("Host: ");
-        request.push_str(&host);
-        request.push('\n');
-        request.push_str("Accept: text/html\n");
-        request.push_str("Connection: close\n");
-        request.push('\n');
-
-        let _bytes_written = match stream.write(request.as_bytes()) {
-            Ok(bytes) => bytes,
-            Err(_) => {
-                return Err(Error::Network(
-                    "Failed to send a request to TCP stream".to_string(),
-                ))
-            }
-        };
+pub struct HttpClient {
+    client: client::HttpClient<WasabiTransport>,
+}
 
-        let mut received = Vec::new();
-        loop {
-            let mut buf = [0u8; 4096];
-            let bytes_read = match stream.read(&mut buf) {
-                Ok(bytes) => bytes,
-                Err(_) => {
-                    return Err(Error::Network(
-                        "Failed to receive a request from TCP stream".to_string(),
-                    ))
-                }
-            };
-
-            if bytes_read == 0 {
-                break;
-            }
-            received.extend_from_slice(&buf[..bytes_read]);
+impl HttpClient {
+    /// 現在時刻が分からないときのクライアントを作ります
+    /// 証明書はsaba_core::net::tls::x509::MIN_VALIDATION_TIMEの時点で期限が切れていないことだけを確認します
+    pub fn new() -> Self {
+        Self::new_with_tls(TlsConfig::new(Rc::new(WasabiRandom::new())))
+    }
+
+    /// 現在時刻（1970年1月1日からの秒数）で証明書の有効期間を確認するクライアントを作ります
+    pub fn new_with_time(time: u64) -> Self {
+        Self::new_with_tls(TlsConfig::new(Rc::new(WasabiRandom::new())).with_time(time))
+    }
+
+    fn new_with_tls(config: TlsConfig) -> Self {
+        Self {
+            client: client::HttpClient::new(WasabiTransport::new()).with_tls(config),
         }
+    }
 
-        match core::str::from_utf8(&received) {
-            Ok(response) => HttpResponse::new(response.to_string()),
-            Err(e) => Err(Error::Network(format!("Invalid received response: {}", e))),
+    /// リクエストを中継するプロキシを指定します
+    pub fn with_proxy(self, config: ProxyConfig) -> Self {
+        Self {
+            client: self.client.with_proxy(config),
         }
     }
+
+    /// リクエストを送信し、サーバーが接続を閉じるまでに受信したレスポンスを返します
+    pub fn send(&self, request: &HttpRequest) -> Result<HttpResponse, Error> {
+        self.client.send(request)
+    }
+
+    /// リクエストを送信し、受信したボディをon_bodyに届いた順に渡します
+    pub fn send_streaming(
+        &self,
+        request: &HttpRequest,
+        on_body: &mut dyn FnMut(&HttpResponse, &[u8]),
+    ) -> Result<HttpResponse, Error> {
+        self.client.send_streaming(request, on_body)
+    }
 }
@@ -0,0 +1,73 @@
+use alloc::vec::Vec;
+use core::arch::x86_64::{__cpuid, _rdrand64_step, _rdtsc};
+use core::cell::Cell;
+use saba_core::crypto::sha2::Sha256;
+use saba_core::crypto::Hash;
+use saba_core::error::Error;
+use saba_core::net::tls::RandomSource;
+
+/// CPUのタイムスタンプカウンタと、使えればRDRAND命令の値をSHA-256で混ぜて作る乱数
+/// WasabiOSはアプリケーションに乱数の供給源を提供していないため、
+/// RDRAND命令が使えないCPUでは暗号論的に十分な強さがあるとは限りません
+pub struct WasabiRandom {
+    seed: Vec<u8>,
+    counter: Cell<u64>,
+}
+
+impl WasabiRandom {
+    pub fn new() -> Self {
+        let mut hash = Sha256::new();
+        for _ in 0..256 {
+            hash.update(&timestamp().to_le_bytes());
+            if let Some(value) = rdrand() {
+                hash.update(&value.to_le_bytes());
+            }
+        }
+        Self {
+            seed: hash.finalize(),
+            counter: Cell::new(0),
+        }
+    }
+}
+
+impl Default for WasabiRandom {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RandomSource for WasabiRandom {
+    fn fill_bytes(&self, buf: &mut [u8]) -> Result<(), Error> {
+        for chunk in buf.chunks_mut(Sha256::OUTPUT_SIZE) {
+            let mut hash = Sha256::new();
+            hash.update(&self.seed);
+            hash.update(&self.counter.get().to_le_bytes());
+            hash.update(&timestamp().to_le_bytes());
+            self.counter.set(self.counter.get() + 1);
+            chunk.copy_from_slice(&hash.finalize()[..chunk.len()]);
+        }
+        Ok(())
+    }
+}
+
+fn timestamp() -> u64 {
+    // SAFETY: RDTSC命令はx86_64のすべてのCPUで使える
+    unsafe { _rdtsc() }
+}
+
+/// CPUがRDRAND命令に対応していれば、その乱数を返します
+fn rdrand() -> Option<u64> {
+    // CPUID.01H:ECX.RDRAND[bit 30]
+    // SAFETY: CPUID命令はx86_64のすべてのCPUで使える
+    if unsafe { __cpuid(1) }.ecx & (1 << 30) == 0 {
+        return None;
+    }
+    let mut value = 0;
+    // SAFETY: CPUIDでRDRAND命令に対応していることを確かめた
+    (unsafe { rdrand64(&mut value) } == 1).then_some(value)
+}
+
+#[target_feature(enable = "rdrand")]
+unsafe fn rdrand64(value: &mut u64) -> i32 {
+    _rdrand64_step(value)
+}
@@ -0,0 +1,20 @@
+//! 証明書の有効期間の確認に使う時刻の下限として、ビルドした時刻（1970年1月1日からの秒数）を
+//! 環境変数SABA_BUILD_TIMEに設定します
+//! rerun-if-changedを指定していないため、パッケージ内のファイルが変わるたびに設定し直されます
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    // 再現可能なビルドのために、SOURCE_DATE_EPOCHが指定されていればその時刻を使う
+    // https://reproducible-builds.org/specs/source-date-epoch/
+    let time = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|time| time.trim().parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or(0)
+        });
+    println!("cargo:rustc-env=SABA_BUILD_TIME={}", time);
+}
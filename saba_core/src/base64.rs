@@ -0,0 +1,103 @@
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::error::Error;
+
+/// https://infra.spec.whatwg.org/#forgiving-base64-decode
+pub fn decode(input: &str) -> Result<Vec<u8>, Error> {
+    let mut data: String = input
+        .chars()
+        .filter(|c| !matches!(c, '\t' | '\n' | '\x0c' | '\r' | ' '))
+        .collect();
+
+    if data.len() % 4 == 0 {
+        if data.ends_with("==") {
+            data.truncate(data.len() - 2);
+        } else if data.ends_with('=') {
+            data.truncate(data.len() - 1);
+        }
+    }
+
+    if data.len() % 4 == 1 {
+        return Err(Error::UnexpectedInput(
+            "invalid length of base64 data".to_string(),
+        ));
+    }
+
+    let mut output = Vec::with_capacity(data.len() * 3 / 4);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for c in data.chars() {
+        let value = match c {
+            'A'..='Z' => c as u32 - 'A' as u32,
+            'a'..='z' => c as u32 - 'a' as u32 + 26,
+            '0'..='9' => c as u32 - '0' as u32 + 52,
+            '+' => 62,
+            '/' => 63,
+            _ => {
+                return Err(Error::UnexpectedInput(
+                    "invalid character in base64 data".to_string(),
+                ))
+            }
+        };
+        buffer = buffer << 6 | value;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            output.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+
+    Ok(output)
+}
+
+/// https://infra.spec.whatwg.org/#forgiving-base64-encode
+pub fn encode(input: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut output = String::with_capacity((input.len() + 2) / 3 * 4);
+    for chunk in input.chunks(3) {
+        let buffer = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |buffer, (i, &b)| buffer | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                output.push(ALPHABET[(buffer >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(Ok(b"hello".to_vec()), decode("aGVsbG8="));
+        assert_eq!(Ok(b"hello".to_vec()), decode("aGVs bG8"));
+        assert_eq!(Ok(b"".to_vec()), decode(""));
+    }
+
+    #[test]
+    fn test_encode() {
+        assert_eq!("aGVsbG8=", encode(b"hello"));
+        assert_eq!("dXNlcjpwYXNz", encode(b"user:pass"));
+        assert_eq!("YQ==", encode(b"a"));
+        assert_eq!("", encode(b""));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(decode("aGVsbG8==").is_err());
+        assert!(decode("a").is_err());
+        assert!(decode("aG*s").is_err());
+    }
+}
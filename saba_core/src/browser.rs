@@ -2,12 +2,15 @@ use core::cell::RefCell;
 
 use alloc::{rc::Rc, vec::Vec};
 
+use crate::http::cookie::CookieJar;
 use crate::renderer::page::Page;
 
 #[derive(Debug, Clone)]
 pub struct Browser {
     active_page_index: usize,
     pages: Vec<Rc<RefCell<Page>>>,
+    /// すべてのページで共有するCookie
+    cookie_jar: Rc<RefCell<CookieJar>>,
 }
 
 impl Browser {
@@ -17,6 +20,7 @@ impl Browser {
         let browser = Rc::new(RefCell::new(Self {
             active_page_index: 0,
             pages: Vec::new(),
+            cookie_jar: Rc::new(RefCell::new(CookieJar::new())),
         }));
 
         page.set_browser(Rc::downgrade(&browser));
@@ -29,4 +33,8 @@ impl Browser {
         assert!(self.pages.len() > 0, "browser must have a page at least");
         self.pages[self.active_page_index].clone()
     }
+
+    pub fn cookie_jar(&self) -> Rc<RefCell<CookieJar>> {
+        self.cookie_jar.clone()
+    }
 }
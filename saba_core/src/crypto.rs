@@ -0,0 +1,82 @@
+//! TLSで使う暗号の実装
+
+pub mod aes;
+pub mod bigint;
+pub mod chacha20poly1305;
+pub mod der;
+pub mod ecdsa;
+pub mod gcm;
+pub mod hmac;
+pub mod rsa;
+pub mod sha2;
+pub mod x25519;
+
+use alloc::vec::Vec;
+
+/// ハッシュ関数
+pub trait Hash: Clone {
+    /// 入力を処理するブロックのバイト数
+    const BLOCK_SIZE: usize;
+    /// ハッシュ値のバイト数
+    const OUTPUT_SIZE: usize;
+
+    fn new() -> Self;
+
+    fn update(&mut self, data: &[u8]);
+
+    fn finalize(self) -> Vec<u8>;
+
+    fn digest(data: &[u8]) -> Vec<u8> {
+        let mut hash = Self::new();
+        hash.update(data);
+        hash.finalize()
+    }
+}
+
+/// 署名の検証などで使うハッシュ関数の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl HashAlgorithm {
+    pub fn digest(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            HashAlgorithm::Sha256 => sha2::Sha256::digest(data),
+            HashAlgorithm::Sha384 => sha2::Sha384::digest(data),
+            HashAlgorithm::Sha512 => sha2::Sha512::digest(data),
+        }
+    }
+
+    pub fn output_size(&self) -> usize {
+        match self {
+            HashAlgorithm::Sha256 => sha2::Sha256::OUTPUT_SIZE,
+            HashAlgorithm::Sha384 => sha2::Sha384::OUTPUT_SIZE,
+            HashAlgorithm::Sha512 => sha2::Sha512::OUTPUT_SIZE,
+        }
+    }
+}
+
+/// 比較にかかる時間から内容を推測されないように、すべてのバイトを比較します
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+pub(crate) fn from_hex(s: &str) -> Vec<u8> {
+    let s: Vec<u8> = s.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    s.chunks(2)
+        .map(|pair| u8::from_str_radix(core::str::from_utf8(pair).unwrap(), 16).unwrap())
+        .collect()
+}
+
+#[cfg(test)]
+pub(crate) fn to_hex(bytes: &[u8]) -> alloc::string::String {
+    use core::fmt::Write;
+    bytes.iter().fold(alloc::string::String::new(), |mut s, b| {
+        let _ = write!(s, "{:02x}", b);
+        s
+    })
+}
@@ -0,0 +1,169 @@
+//! AESのブロック暗号化
+//! https://nvlpubs.nist.gov/nistpubs/FIPS/NIST.FIPS.197-upd1.pdf
+
+use alloc::vec::Vec;
+
+use crate::error::Error;
+
+/// https://nvlpubs.nist.gov/nistpubs/FIPS/NIST.FIPS.197-upd1.pdf#section.5.1.1
+const SBOX: [u8; 256] = [
+    0x63, 0x7c, 0x77, 0x7b, 0xf2, 0x6b, 0x6f, 0xc5, 0x30, 0x01, 0x67, 0x2b, 0xfe, 0xd7, 0xab, 0x76,
+    0xca, 0x82, 0xc9, 0x7d, 0xfa, 0x59, 0x47, 0xf0, 0xad, 0xd4, 0xa2, 0xaf, 0x9c, 0xa4, 0x72, 0xc0,
+    0xb7, 0xfd, 0x93, 0x26, 0x36, 0x3f, 0xf7, 0xcc, 0x34, 0xa5, 0xe5, 0xf1, 0x71, 0xd8, 0x31, 0x15,
+    0x04, 0xc7, 0x23, 0xc3, 0x18, 0x96, 0x05, 0x9a, 0x07, 0x12, 0x80, 0xe2, 0xeb, 0x27, 0xb2, 0x75,
+    0x09, 0x83, 0x2c, 0x1a, 0x1b, 0x6e, 0x5a, 0xa0, 0x52, 0x3b, 0xd6, 0xb3, 0x29, 0xe3, 0x2f, 0x84,
+    0x53, 0xd1, 0x00, 0xed, 0x20, 0xfc, 0xb1, 0x5b, 0x6a, 0xcb, 0xbe, 0x39, 0x4a, 0x4c, 0x58, 0xcf,
+    0xd0, 0xef, 0xaa, 0xfb, 0x43, 0x4d, 0x33, 0x85, 0x45, 0xf9, 0x02, 0x7f, 0x50, 0x3c, 0x9f, 0xa8,
+    0x51, 0xa3, 0x40, 0x8f, 0x92, 0x9d, 0x38, 0xf5, 0xbc, 0xb6, 0xda, 0x21, 0x10, 0xff, 0xf3, 0xd2,
+    0xcd, 0x0c, 0x13, 0xec, 0x5f, 0x97, 0x44, 0x17, 0xc4, 0xa7, 0x7e, 0x3d, 0x64, 0x5d, 0x19, 0x73,
+    0x60, 0x81, 0x4f, 0xdc, 0x22, 0x2a, 0x90, 0x88, 0x46, 0xee, 0xb8, 0x14, 0xde, 0x5e, 0x0b, 0xdb,
+    0xe0, 0x32, 0x3a, 0x0a, 0x49, 0x06, 0x24, 0x5c, 0xc2, 0xd3, 0xac, 0x62, 0x91, 0x95, 0xe4, 0x79,
+    0xe7, 0xc8, 0x37, 0x6d, 0x8d, 0xd5, 0x4e, 0xa9, 0x6c, 0x56, 0xf4, 0xea, 0x65, 0x7a, 0xae, 0x08,
+    0xba, 0x78, 0x25, 0x2e, 0x1c, 0xa6, 0xb4, 0xc6, 0xe8, 0xdd, 0x74, 0x1f, 0x4b, 0xbd, 0x8b, 0x8a,
+    0x70, 0x3e, 0xb5, 0x66, 0x48, 0x03, 0xf6, 0x0e, 0x61, 0x35, 0x57, 0xb9, 0x86, 0xc1, 0x1d, 0x9e,
+    0xe1, 0xf8, 0x98, 0x11, 0x69, 0xd9, 0x8e, 0x94, 0x9b, 0x1e, 0x87, 0xe9, 0xce, 0x55, 0x28, 0xdf,
+    0x8c, 0xa1, 0x89, 0x0d, 0xbf, 0xe6, 0x42, 0x68, 0x41, 0x99, 0x2d, 0x0f, 0xb0, 0x54, 0xbb, 0x16,
+];
+
+/// GF(2^8)でxを掛けます
+fn xtime(b: u8) -> u8 {
+    (b << 1) ^ if b & 0x80 != 0 { 0x1b } else { 0 }
+}
+
+/// AES-128またはAES-256の暗号化に使うラウンド鍵
+#[derive(Debug, Clone)]
+pub struct Aes {
+    round_keys: Vec<[u8; 16]>,
+}
+
+impl Aes {
+    /// 16バイト（AES-128）または32バイト（AES-256）の鍵からラウンド鍵を作ります
+    /// https://nvlpubs.nist.gov/nistpubs/FIPS/NIST.FIPS.197-upd1.pdf#section.5.2
+    pub fn new(key: &[u8]) -> Result<Self, Error> {
+        let nk = match key.len() {
+            16 => 4,
+            32 => 8,
+            n => {
+                return Err(Error::Other(alloc::format!(
+                    "invalid AES key length: {}",
+                    n
+                )))
+            }
+        };
+        let rounds = nk + 6;
+
+        let mut words: Vec<[u8; 4]> = key.chunks(4).map(|w| [w[0], w[1], w[2], w[3]]).collect();
+        let mut rcon = 1u8;
+        for i in nk..4 * (rounds + 1) {
+            let mut temp = words[i - 1];
+            if i % nk == 0 {
+                temp = [
+                    SBOX[temp[1] as usize] ^ rcon,
+                    SBOX[temp[2] as usize],
+                    SBOX[temp[3] as usize],
+                    SBOX[temp[0] as usize],
+                ];
+                rcon = xtime(rcon);
+            } else if nk > 6 && i % nk == 4 {
+                temp = temp.map(|b| SBOX[b as usize]);
+            }
+            let prev = words[i - nk];
+            words.push([
+                prev[0] ^ temp[0],
+                prev[1] ^ temp[1],
+                prev[2] ^ temp[2],
+                prev[3] ^ temp[3],
+            ]);
+        }
+
+        let round_keys = words
+            .chunks(4)
+            .map(|w| {
+                let mut key = [0u8; 16];
+                for (i, word) in w.iter().enumerate() {
+                    key[i * 4..i * 4 + 4].copy_from_slice(word);
+                }
+                key
+            })
+            .collect();
+        Ok(Self { round_keys })
+    }
+
+    /// https://nvlpubs.nist.gov/nistpubs/FIPS/NIST.FIPS.197-upd1.pdf#section.5.1
+    pub fn encrypt_block(&self, block: &[u8; 16]) -> [u8; 16] {
+        let mut state = *block;
+        let add_round_key = |state: &mut [u8; 16], key: &[u8; 16]| {
+            for (s, k) in state.iter_mut().zip(key) {
+                *s ^= k;
+            }
+        };
+
+        let rounds = self.round_keys.len() - 1;
+        add_round_key(&mut state, &self.round_keys[0]);
+        for round in 1..=rounds {
+            // SubBytesとShiftRows。stateは列ごとに並んでいる
+            let mut shifted = [0u8; 16];
+            for c in 0..4 {
+                for r in 0..4 {
+                    shifted[c * 4 + r] = SBOX[state[((c + r) % 4) * 4 + r] as usize];
+                }
+            }
+            state = shifted;
+
+            if round != rounds {
+                // MixColumns
+                for c in 0..4 {
+                    let col = [
+                        state[c * 4],
+                        state[c * 4 + 1],
+                        state[c * 4 + 2],
+                        state[c * 4 + 3],
+                    ];
+                    let all = col[0] ^ col[1] ^ col[2] ^ col[3];
+                    for r in 0..4 {
+                        state[c * 4 + r] = col[r] ^ all ^ xtime(col[r] ^ col[(r + 1) % 4]);
+                    }
+                }
+            }
+            add_round_key(&mut state, &self.round_keys[round]);
+        }
+        state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{from_hex, to_hex};
+
+    fn encrypt(key: &str, plaintext: &str) -> alloc::string::String {
+        let aes = Aes::new(&from_hex(key)).expect("invalid key");
+        let mut block = [0u8; 16];
+        block.copy_from_slice(&from_hex(plaintext));
+        to_hex(&aes.encrypt_block(&block))
+    }
+
+    /// https://nvlpubs.nist.gov/nistpubs/FIPS/NIST.FIPS.197-upd1.pdf#appendix.C
+    #[test]
+    fn test_encrypt_block() {
+        assert_eq!(
+            "69c4e0d86a7b0430d8cdb78070b4c55a",
+            encrypt(
+                "000102030405060708090a0b0c0d0e0f",
+                "00112233445566778899aabbccddeeff"
+            )
+        );
+        assert_eq!(
+            "8ea2b7ca516745bfeafc49904b496089",
+            encrypt(
+                "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+                "00112233445566778899aabbccddeeff"
+            )
+        );
+    }
+
+    #[test]
+    fn test_invalid_key() {
+        assert!(Aes::new(&[0; 24]).is_err());
+    }
+}
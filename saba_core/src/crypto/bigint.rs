@@ -0,0 +1,349 @@
+//! 公開鍵暗号で使う任意精度の符号なし整数
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+/// 32ビットのリムを下位から並べた符号なし整数。上位の0のリムは持たない
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BigUint {
+    limbs: Vec<u32>,
+}
+
+impl BigUint {
+    pub fn zero() -> Self {
+        Self { limbs: Vec::new() }
+    }
+
+    pub fn from_u32(n: u32) -> Self {
+        Self::from_limbs(vec![n])
+    }
+
+    fn from_limbs(limbs: Vec<u32>) -> Self {
+        let mut n = Self { limbs };
+        n.normalize();
+        n
+    }
+
+    fn normalize(&mut self) {
+        while self.limbs.last() == Some(&0) {
+            self.limbs.pop();
+        }
+    }
+
+    /// ビッグエンディアンのバイト列から作ります
+    pub fn from_bytes_be(bytes: &[u8]) -> Self {
+        let limbs = bytes
+            .rchunks(4)
+            .map(|chunk| chunk.iter().fold(0u32, |acc, &b| (acc << 8) | b as u32))
+            .collect();
+        Self::from_limbs(limbs)
+    }
+
+    /// lenバイトのビッグエンディアンのバイト列にします。収まらない上位のバイトは切り捨てます
+    pub fn to_bytes_be(&self, len: usize) -> Vec<u8> {
+        let mut bytes = vec![0u8; len];
+        for (i, byte) in bytes.iter_mut().rev().enumerate() {
+            if let Some(limb) = self.limbs.get(i / 4) {
+                *byte = (limb >> ((i % 4) * 8)) as u8;
+            }
+        }
+        bytes
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    /// 最上位の1のビットの位置+1を返します
+    pub fn bits(&self) -> usize {
+        match self.limbs.last() {
+            Some(top) => self.limbs.len() * 32 - top.leading_zeros() as usize,
+            None => 0,
+        }
+    }
+
+    pub fn bit(&self, i: usize) -> bool {
+        self.limbs
+            .get(i / 32)
+            .is_some_and(|limb| (limb >> (i % 32)) & 1 == 1)
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        let len = self.limbs.len().max(other.limbs.len());
+        let mut limbs = Vec::with_capacity(len + 1);
+        let mut carry = 0u64;
+        for i in 0..len {
+            let sum = *self.limbs.get(i).unwrap_or(&0) as u64
+                + *other.limbs.get(i).unwrap_or(&0) as u64
+                + carry;
+            limbs.push(sum as u32);
+            carry = sum >> 32;
+        }
+        limbs.push(carry as u32);
+        Self::from_limbs(limbs)
+    }
+
+    /// self - otherを返します。selfはother以上でなければなりません
+    pub fn sub(&self, other: &Self) -> Self {
+        debug_assert!(*self >= *other);
+        let mut limbs = Vec::with_capacity(self.limbs.len());
+        let mut borrow = 0i64;
+        for i in 0..self.limbs.len() {
+            let mut diff = self.limbs[i] as i64 - *other.limbs.get(i).unwrap_or(&0) as i64 - borrow;
+            borrow = 0;
+            if diff < 0 {
+                diff += 1 << 32;
+                borrow = 1;
+            }
+            limbs.push(diff as u32);
+        }
+        Self::from_limbs(limbs)
+    }
+
+    pub fn mul(&self, other: &Self) -> Self {
+        if self.is_zero() || other.is_zero() {
+            return Self::zero();
+        }
+        let mut limbs = vec![0u32; self.limbs.len() + other.limbs.len()];
+        for (i, &a) in self.limbs.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, &b) in other.limbs.iter().enumerate() {
+                let t = a as u64 * b as u64 + limbs[i + j] as u64 + carry;
+                limbs[i + j] = t as u32;
+                carry = t >> 32;
+            }
+            limbs[i + other.limbs.len()] = carry as u32;
+        }
+        Self::from_limbs(limbs)
+    }
+
+    fn shl_bits(&self, shift: u32) -> Vec<u32> {
+        let mut limbs = Vec::with_capacity(self.limbs.len() + 1);
+        let mut carry = 0u32;
+        for &limb in &self.limbs {
+            if shift == 0 {
+                limbs.push(limb);
+            } else {
+                limbs.push((limb << shift) | carry);
+                carry = limb >> (32 - shift);
+            }
+        }
+        limbs.push(carry);
+        limbs
+    }
+
+    /// (商, 余り)を返します。divisorは0であってはなりません
+    /// The Art of Computer Programming Vol. 2, 4.3.1 Algorithm D
+    pub fn div_rem(&self, divisor: &Self) -> (Self, Self) {
+        assert!(!divisor.is_zero(), "division by zero");
+        if *self < *divisor {
+            return (Self::zero(), self.clone());
+        }
+
+        if divisor.limbs.len() == 1 {
+            let d = divisor.limbs[0] as u64;
+            let mut quotient = vec![0u32; self.limbs.len()];
+            let mut rem = 0u64;
+            for i in (0..self.limbs.len()).rev() {
+                let cur = (rem << 32) | self.limbs[i] as u64;
+                quotient[i] = (cur / d) as u32;
+                rem = cur % d;
+            }
+            return (Self::from_limbs(quotient), Self::from_u32(rem as u32));
+        }
+
+        // 除数の最上位のリムの最上位ビットが1になるように正規化する
+        let shift = divisor.limbs.last().map_or(0, |top| top.leading_zeros());
+        let v = {
+            let mut v = divisor.shl_bits(shift);
+            v.pop();
+            v
+        };
+        let mut u = self.shl_bits(shift);
+        let n = v.len();
+        let m = u.len() - n - 1;
+        let mut quotient = vec![0u32; m + 1];
+        let base = 1u64 << 32;
+
+        for j in (0..=m).rev() {
+            let numerator = ((u[j + n] as u64) << 32) | u[j + n - 1] as u64;
+            let mut qhat = numerator / v[n - 1] as u64;
+            let mut rhat = numerator % v[n - 1] as u64;
+            while qhat >= base || qhat * v[n - 2] as u64 > ((rhat << 32) | u[j + n - 2] as u64) {
+                qhat -= 1;
+                rhat += v[n - 1] as u64;
+                if rhat >= base {
+                    break;
+                }
+            }
+
+            // u[j..j+n+1] -= qhat * v
+            let mut borrow = 0i64;
+            let mut carry = 0u64;
+            for i in 0..n {
+                let p = qhat * v[i] as u64 + carry;
+                carry = p >> 32;
+                let t = u[i + j] as i64 - borrow - (p & 0xffff_ffff) as i64;
+                u[i + j] = t as u32;
+                borrow = if t < 0 { 1 } else { 0 };
+            }
+            let t = u[j + n] as i64 - borrow - carry as i64;
+            u[j + n] = t as u32;
+
+            if t < 0 {
+                // 引きすぎたので1つ戻す
+                qhat -= 1;
+                let mut carry = 0u64;
+                for i in 0..n {
+                    let sum = u[i + j] as u64 + v[i] as u64 + carry;
+                    u[i + j] = sum as u32;
+                    carry = sum >> 32;
+                }
+                u[j + n] = u[j + n].wrapping_add(carry as u32);
+            }
+            quotient[j] = qhat as u32;
+        }
+
+        // 余りの正規化を戻す
+        let mut rem = vec![0u32; n];
+        for i in 0..n {
+            rem[i] = if shift == 0 {
+                u[i]
+            } else {
+                (u[i] >> shift) | (u[i + 1] << (32 - shift))
+            };
+        }
+        (Self::from_limbs(quotient), Self::from_limbs(rem))
+    }
+
+    pub fn rem(&self, modulus: &Self) -> Self {
+        self.div_rem(modulus).1
+    }
+
+    pub fn mod_add(&self, other: &Self, modulus: &Self) -> Self {
+        let sum = self.add(other);
+        if sum >= *modulus {
+            sum.sub(modulus)
+        } else {
+            sum
+        }
+    }
+
+    /// (self - other) mod modulusを返します。selfとotherはmodulus未満でなければなりません
+    pub fn mod_sub(&self, other: &Self, modulus: &Self) -> Self {
+        if *self >= *other {
+            self.sub(other)
+        } else {
+            self.add(modulus).sub(other)
+        }
+    }
+
+    pub fn mod_mul(&self, other: &Self, modulus: &Self) -> Self {
+        self.mul(other).rem(modulus)
+    }
+
+    pub fn mod_pow(&self, exponent: &Self, modulus: &Self) -> Self {
+        let mut result = Self::from_u32(1).rem(modulus);
+        let base = self.rem(modulus);
+        for i in (0..exponent.bits()).rev() {
+            result = result.mod_mul(&result, modulus);
+            if exponent.bit(i) {
+                result = result.mod_mul(&base, modulus);
+            }
+        }
+        result
+    }
+
+    /// 素数modulusを法とする逆元をフェルマーの小定理で求めます
+    pub fn mod_inv_prime(&self, modulus: &Self) -> Self {
+        self.mod_pow(&modulus.sub(&Self::from_u32(2)), modulus)
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.limbs
+            .len()
+            .cmp(&other.limbs.len())
+            .then_with(|| self.limbs.iter().rev().cmp(other.limbs.iter().rev()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{from_hex, to_hex};
+
+    fn n(hex: &str) -> BigUint {
+        BigUint::from_bytes_be(&from_hex(hex))
+    }
+
+    fn hex(n: &BigUint) -> alloc::string::String {
+        to_hex(&n.to_bytes_be(n.bits().div_ceil(8)))
+    }
+
+    #[test]
+    fn test_bytes() {
+        let x = n("0102030405060708090a");
+        assert_eq!(73, x.bits());
+        assert_eq!(from_hex("0102030405060708090a"), x.to_bytes_be(10));
+        assert_eq!(from_hex("090a"), x.to_bytes_be(2));
+        assert!(BigUint::from_bytes_be(&[0, 0]).is_zero());
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let a = n("ffffffffffffffffffffffff");
+        let b = n("01");
+        assert_eq!("01000000000000000000000000", hex(&a.add(&b)));
+        assert_eq!("fffffffffffffffffffffffe", hex(&a.sub(&b)));
+        assert_eq!(
+            "fffffffffffffffffffffffe000000000000000000000001",
+            hex(&a.mul(&a))
+        );
+    }
+
+    #[test]
+    fn test_div_rem() {
+        let a = n("fffffffffffffffffffffffe000000000000000000000001");
+        let b = n("ffffffffffffffffffffffff");
+        let (q, r) = a.div_rem(&b);
+        assert_eq!("ffffffffffffffffffffffff", hex(&q));
+        assert!(r.is_zero());
+
+        // 商の推定値の補正が必要になる除算
+        let a = n("7fffffff800000010000000000000000");
+        let b = n("800000008000000200000005");
+        let (q, r) = a.div_rem(&b);
+        assert_eq!(a, q.mul(&b).add(&r));
+        assert!(r < b);
+
+        let (q, r) = n("1234567890abcdef").div_rem(&n("10"));
+        assert_eq!("01234567890abcde", to_hex(&q.to_bytes_be(8)));
+        assert_eq!("0f", hex(&r));
+    }
+
+    #[test]
+    fn test_mod_pow() {
+        // 2^255 - 19を法とした計算
+        let p = n("7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffed");
+        let x = n("09");
+        let inv = x.mod_inv_prime(&p);
+        assert_eq!(BigUint::from_u32(1), x.mod_mul(&inv, &p));
+        assert_eq!(
+            BigUint::from_u32(1024),
+            BigUint::from_u32(2).mod_pow(&BigUint::from_u32(10), &p)
+        );
+        assert_eq!(
+            p.sub(&BigUint::from_u32(1)),
+            BigUint::zero().mod_sub(&BigUint::from_u32(1), &p)
+        );
+    }
+}
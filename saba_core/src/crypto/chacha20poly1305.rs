@@ -0,0 +1,284 @@
+//! ChaCha20-Poly1305による認証付き暗号
+//! https://www.rfc-editor.org/rfc/rfc8439
+
+use alloc::vec::Vec;
+
+use super::constant_time_eq;
+use crate::error::Error;
+
+pub const KEY_SIZE: usize = 32;
+pub const NONCE_SIZE: usize = 12;
+pub const TAG_SIZE: usize = 16;
+
+/// "expand 32-byte k"
+const CONSTANTS: [u32; 4] = [0x61707865, 0x3320646e, 0x79622d32, 0x6b206574];
+
+fn le_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// https://www.rfc-editor.org/rfc/rfc8439#section-2.1
+fn quarter_round(state: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    state[a] = state[a].wrapping_add(state[b]);
+    state[d] = (state[d] ^ state[a]).rotate_left(16);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_left(12);
+    state[a] = state[a].wrapping_add(state[b]);
+    state[d] = (state[d] ^ state[a]).rotate_left(8);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_left(7);
+}
+
+/// ChaCha20のブロック関数
+/// https://www.rfc-editor.org/rfc/rfc8439#section-2.3
+fn chacha20_block(key: &[u8; KEY_SIZE], counter: u32, nonce: &[u8; NONCE_SIZE]) -> [u8; 64] {
+    let mut initial = [0u32; 16];
+    initial[..4].copy_from_slice(&CONSTANTS);
+    for (i, word) in key.chunks(4).enumerate() {
+        initial[4 + i] = le_u32(word);
+    }
+    initial[12] = counter;
+    for (i, word) in nonce.chunks(4).enumerate() {
+        initial[13 + i] = le_u32(word);
+    }
+
+    let mut state = initial;
+    for _ in 0..10 {
+        quarter_round(&mut state, 0, 4, 8, 12);
+        quarter_round(&mut state, 1, 5, 9, 13);
+        quarter_round(&mut state, 2, 6, 10, 14);
+        quarter_round(&mut state, 3, 7, 11, 15);
+        quarter_round(&mut state, 0, 5, 10, 15);
+        quarter_round(&mut state, 1, 6, 11, 12);
+        quarter_round(&mut state, 2, 7, 8, 13);
+        quarter_round(&mut state, 3, 4, 9, 14);
+    }
+
+    let mut block = [0u8; 64];
+    for (i, (word, init)) in state.iter().zip(initial).enumerate() {
+        block[i * 4..i * 4 + 4].copy_from_slice(&word.wrapping_add(init).to_le_bytes());
+    }
+    block
+}
+
+/// ChaCha20でdataを暗号化（復号）します。counterは最初のブロックのカウンタ値です
+/// https://www.rfc-editor.org/rfc/rfc8439#section-2.4
+fn chacha20(key: &[u8; KEY_SIZE], counter: u32, nonce: &[u8; NONCE_SIZE], data: &mut [u8]) {
+    for (i, chunk) in data.chunks_mut(64).enumerate() {
+        let keystream = chacha20_block(key, counter.wrapping_add(i as u32), nonce);
+        for (b, k) in chunk.iter_mut().zip(keystream) {
+            *b ^= k;
+        }
+    }
+}
+
+/// Poly1305によるメッセージ認証コード
+/// 2^130 - 5を法とする計算を、26ビットずつ5つに分けた整数で行います
+/// https://www.rfc-editor.org/rfc/rfc8439#section-2.5
+fn poly1305(key: &[u8; 32], message: &[u8]) -> [u8; TAG_SIZE] {
+    const MASK: u32 = 0x3ffffff;
+    // rの一部のビットを0にする（clamp）
+    let r = [
+        le_u32(&key[0..]) & 0x3ffffff,
+        (le_u32(&key[3..]) >> 2) & 0x3ffff03,
+        (le_u32(&key[6..]) >> 4) & 0x3ffc0ff,
+        (le_u32(&key[9..]) >> 6) & 0x3f03fff,
+        (le_u32(&key[12..]) >> 8) & 0x00fffff,
+    ];
+    let s = [r[1] * 5, r[2] * 5, r[3] * 5, r[4] * 5];
+    let mut h = [0u32; 5];
+
+    for chunk in message.chunks(16) {
+        // 各ブロックの末尾に1のバイトを加えた値を足す
+        let mut block = [0u8; 17];
+        block[..chunk.len()].copy_from_slice(chunk);
+        block[chunk.len()] = 1;
+        h[0] += le_u32(&block[0..]) & MASK;
+        h[1] += (le_u32(&block[3..]) >> 2) & MASK;
+        h[2] += (le_u32(&block[6..]) >> 4) & MASK;
+        h[3] += (le_u32(&block[9..]) >> 6) & MASK;
+        h[4] += (le_u32(&block[12..]) >> 8) | ((block[16] as u32) << 24);
+
+        let m = |a: u32, b: u32| a as u64 * b as u64;
+        let d = [
+            m(h[0], r[0]) + m(h[1], s[3]) + m(h[2], s[2]) + m(h[3], s[1]) + m(h[4], s[0]),
+            m(h[0], r[1]) + m(h[1], r[0]) + m(h[2], s[3]) + m(h[3], s[2]) + m(h[4], s[1]),
+            m(h[0], r[2]) + m(h[1], r[1]) + m(h[2], r[0]) + m(h[3], s[3]) + m(h[4], s[2]),
+            m(h[0], r[3]) + m(h[1], r[2]) + m(h[2], r[1]) + m(h[3], r[0]) + m(h[4], s[3]),
+            m(h[0], r[4]) + m(h[1], r[3]) + m(h[2], r[2]) + m(h[3], r[1]) + m(h[4], r[0]),
+        ];
+        let mut carry = 0u64;
+        for i in 0..5 {
+            let d = d[i] + carry;
+            h[i] = d as u32 & MASK;
+            carry = d >> 26;
+        }
+        // 2^130は5と合同
+        h[0] += carry as u32 * 5;
+        h[1] += h[0] >> 26;
+        h[0] &= MASK;
+    }
+
+    // 繰り上がりを伝播させてから、h - pが負でなければそちらを選ぶ
+    let mut carry = 0;
+    for limb in h.iter_mut().skip(1) {
+        *limb += carry;
+        carry = *limb >> 26;
+        *limb &= MASK;
+    }
+    h[0] += carry * 5;
+    h[1] += h[0] >> 26;
+    h[0] &= MASK;
+
+    let mut g = [0u32; 5];
+    let mut carry = 5;
+    for i in 0..5 {
+        g[i] = h[i].wrapping_add(carry);
+        carry = g[i] >> 26;
+        g[i] &= MASK;
+    }
+    // h + 5が2^130以上ならh >= p
+    let select = 0u32.wrapping_sub(carry);
+    for i in 0..5 {
+        h[i] = (h[i] & !select) | (g[i] & select);
+    }
+
+    // 2^128を法としてsを足す
+    let h = [
+        h[0] | (h[1] << 26),
+        (h[1] >> 6) | (h[2] << 20),
+        (h[2] >> 12) | (h[3] << 14),
+        (h[3] >> 18) | (h[4] << 8),
+    ];
+    let mut tag = [0u8; TAG_SIZE];
+    let mut carry = 0u64;
+    for i in 0..4 {
+        let sum = h[i] as u64 + le_u32(&key[16 + i * 4..]) as u64 + carry;
+        tag[i * 4..i * 4 + 4].copy_from_slice(&(sum as u32).to_le_bytes());
+        carry = sum >> 32;
+    }
+    tag
+}
+
+/// https://www.rfc-editor.org/rfc/rfc8439#section-2.8
+#[derive(Debug, Clone)]
+pub struct ChaCha20Poly1305 {
+    key: [u8; KEY_SIZE],
+}
+
+impl ChaCha20Poly1305 {
+    pub fn new(key: &[u8]) -> Result<Self, Error> {
+        let key = key
+            .try_into()
+            .map_err(|_| Error::Other("invalid ChaCha20 key length".into()))?;
+        Ok(Self { key })
+    }
+
+    /// カウンタ0のブロックから作った使い捨ての鍵で、AADと暗号文の認証タグを計算します
+    /// https://www.rfc-editor.org/rfc/rfc8439#section-2.6
+    fn tag(&self, nonce: &[u8; NONCE_SIZE], aad: &[u8], ciphertext: &[u8]) -> [u8; TAG_SIZE] {
+        let mut one_time_key = [0u8; 32];
+        one_time_key.copy_from_slice(&chacha20_block(&self.key, 0, nonce)[..32]);
+        let padding = |len: usize| (16 - len % 16) % 16;
+        let mut mac_data = aad.to_vec();
+        mac_data.resize(mac_data.len() + padding(aad.len()), 0);
+        mac_data.extend_from_slice(ciphertext);
+        mac_data.resize(mac_data.len() + padding(ciphertext.len()), 0);
+        mac_data.extend_from_slice(&(aad.len() as u64).to_le_bytes());
+        mac_data.extend_from_slice(&(ciphertext.len() as u64).to_le_bytes());
+        poly1305(&one_time_key, &mac_data)
+    }
+
+    /// 平文を暗号化し、認証タグを付けた暗号文を返します
+    pub fn seal(&self, nonce: &[u8; NONCE_SIZE], aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+        let mut output = plaintext.to_vec();
+        chacha20(&self.key, 1, nonce, &mut output);
+        let tag = self.tag(nonce, aad, &output);
+        output.extend_from_slice(&tag);
+        output
+    }
+
+    /// 認証タグを確認してから暗号文を復号します
+    pub fn open(
+        &self,
+        nonce: &[u8; NONCE_SIZE],
+        aad: &[u8],
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        if ciphertext.len() < TAG_SIZE {
+            return Err(Error::Other(
+                "ChaCha20-Poly1305 ciphertext is too short".into(),
+            ));
+        }
+        let (ciphertext, tag) = ciphertext.split_at(ciphertext.len() - TAG_SIZE);
+        if !constant_time_eq(&self.tag(nonce, aad, ciphertext), tag) {
+            return Err(Error::Other(
+                "ChaCha20-Poly1305 authentication failed".into(),
+            ));
+        }
+        let mut output = ciphertext.to_vec();
+        chacha20(&self.key, 1, nonce, &mut output);
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{from_hex, to_hex};
+
+    /// https://www.rfc-editor.org/rfc/rfc8439#section-2.3.2
+    #[test]
+    fn test_chacha20_block() {
+        let mut key = [0u8; KEY_SIZE];
+        key.copy_from_slice(&from_hex(
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+        ));
+        let mut nonce = [0u8; NONCE_SIZE];
+        nonce.copy_from_slice(&from_hex("000000090000004a00000000"));
+        assert_eq!(
+            "10f1e7e4d13b5915500fdd1fa32071c4c7d1f4c733c068030422aa9ac3d46c4e\
+             d2826446079faa0914c2d705d98b02a2b5129cd1de164eb9cbd083e8a2503c4e",
+            to_hex(&chacha20_block(&key, 1, &nonce))
+        );
+    }
+
+    /// https://www.rfc-editor.org/rfc/rfc8439#section-2.5.2
+    #[test]
+    fn test_poly1305() {
+        let mut key = [0u8; 32];
+        key.copy_from_slice(&from_hex(
+            "85d6be7857556d337f4452fe42d506a80103808afb0db2fd4abff6af4149f51b",
+        ));
+        assert_eq!(
+            "a8061dc1305136c6c22b8baf0c0127a9",
+            to_hex(&poly1305(&key, b"Cryptographic Forum Research Group"))
+        );
+    }
+
+    /// https://www.rfc-editor.org/rfc/rfc8439#section-2.8.2
+    #[test]
+    fn test_seal_and_open() {
+        let key = from_hex("808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f");
+        let mut nonce = [0u8; NONCE_SIZE];
+        nonce.copy_from_slice(&from_hex("070000004041424344454647"));
+        let plaintext = b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.";
+        let aad = from_hex("50515253c0c1c2c3c4c5c6c7");
+
+        let aead = ChaCha20Poly1305::new(&key).expect("invalid key");
+        let sealed = aead.seal(&nonce, &aad, plaintext);
+        assert_eq!(
+            "d31a8d34648e60db7b86afbc53ef7ec2a4aded51296e08fea9e2b5a736ee62d63dbea45e8ca9671282fafb69da92728b\
+             1a71de0a9e060b2905d6a5b67ecd3b3692ddbd7f2d778b8c9803aee328091b58fab324e4fad675945585808b4831d7bc\
+             3ff4def08e4b7a9de576d26586cec64b6116\
+             1ae10b594f09e26a7e902ecbd0600691",
+            to_hex(&sealed)
+        );
+        assert_eq!(Ok(plaintext.to_vec()), aead.open(&nonce, &aad, &sealed));
+
+        let mut tampered = sealed.clone();
+        tampered[0] ^= 1;
+        assert!(aead.open(&nonce, &aad, &tampered).is_err());
+        assert!(aead.open(&nonce, b"", &sealed).is_err());
+    }
+}
@@ -0,0 +1,277 @@
+//! ASN.1 DERの読み取り
+//! https://www.itu.int/rec/T-REC-X.690
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::error::Error;
+
+pub const TAG_BOOLEAN: u8 = 0x01;
+pub const TAG_INTEGER: u8 = 0x02;
+pub const TAG_BIT_STRING: u8 = 0x03;
+pub const TAG_OCTET_STRING: u8 = 0x04;
+pub const TAG_NULL: u8 = 0x05;
+pub const TAG_OID: u8 = 0x06;
+pub const TAG_UTF8_STRING: u8 = 0x0c;
+pub const TAG_PRINTABLE_STRING: u8 = 0x13;
+pub const TAG_IA5_STRING: u8 = 0x16;
+pub const TAG_UTC_TIME: u8 = 0x17;
+pub const TAG_GENERALIZED_TIME: u8 = 0x18;
+pub const TAG_SEQUENCE: u8 = 0x30;
+pub const TAG_SET: u8 = 0x31;
+
+/// 文脈固有のタグ[n]（構造化）
+pub const fn context_specific(n: u8) -> u8 {
+    0xa0 | n
+}
+
+fn invalid(message: &str) -> Error {
+    Error::UnexpectedInput(format!("invalid DER: {}", message))
+}
+
+/// 1つのTLV（タグ、長さ、値）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Der<'a> {
+    tag: u8,
+    contents: &'a [u8],
+    /// タグと長さを含むエンコードされたバイト列
+    raw: &'a [u8],
+}
+
+impl<'a> Der<'a> {
+    pub fn tag(&self) -> u8 {
+        self.tag
+    }
+
+    pub fn contents(&self) -> &'a [u8] {
+        self.contents
+    }
+
+    pub fn raw(&self) -> &'a [u8] {
+        self.raw
+    }
+
+    /// 構造化された値の中身を読み取るDerReaderを返します
+    pub fn reader(&self) -> DerReader<'a> {
+        DerReader::new(self.contents)
+    }
+
+    /// INTEGERの値を、先頭の符号用の0を除いたビッグエンディアンのバイト列として返します
+    pub fn unsigned_integer(&self) -> Result<&'a [u8], Error> {
+        self.expect(TAG_INTEGER)?;
+        match self.contents {
+            [] => Err(invalid("empty integer")),
+            [first, ..] if first & 0x80 != 0 => Err(invalid("negative integer")),
+            [0, rest @ ..] if !rest.is_empty() => Ok(rest),
+            contents => Ok(contents),
+        }
+    }
+
+    /// 小さいINTEGERの値を返します
+    pub fn small_integer(&self) -> Result<u64, Error> {
+        let bytes = self.unsigned_integer()?;
+        if bytes.len() > 8 {
+            return Err(invalid("integer is too large"));
+        }
+        Ok(bytes.iter().fold(0u64, |acc, &b| (acc << 8) | b as u64))
+    }
+
+    /// 未使用ビットのないBIT STRINGの中身を返します
+    pub fn bit_string(&self) -> Result<&'a [u8], Error> {
+        self.expect(TAG_BIT_STRING)?;
+        match self.contents {
+            [0, rest @ ..] => Ok(rest),
+            _ => Err(invalid("unsupported bit string")),
+        }
+    }
+
+    pub fn boolean(&self) -> Result<bool, Error> {
+        self.expect(TAG_BOOLEAN)?;
+        match self.contents {
+            [0] => Ok(false),
+            [0xff] => Ok(true),
+            _ => Err(invalid("invalid boolean")),
+        }
+    }
+
+    /// OBJECT IDENTIFIERを"1.2.840.113549"のようなドット区切りの文字列にします
+    pub fn oid(&self) -> Result<String, Error> {
+        self.expect(TAG_OID)?;
+        let mut arcs: Vec<u64> = Vec::new();
+        let mut value = 0u64;
+        for (i, &b) in self.contents.iter().enumerate() {
+            if value > (u64::MAX >> 7) {
+                return Err(invalid("object identifier is too large"));
+            }
+            value = (value << 7) | (b & 0x7f) as u64;
+            if b & 0x80 == 0 {
+                if arcs.is_empty() {
+                    let first = (value / 40).min(2);
+                    arcs.push(first);
+                    arcs.push(value - first * 40);
+                } else {
+                    arcs.push(value);
+                }
+                value = 0;
+            } else if i == self.contents.len() - 1 {
+                return Err(invalid("truncated object identifier"));
+            }
+        }
+        Ok(arcs
+            .iter()
+            .map(|arc| format!("{}", arc))
+            .collect::<Vec<String>>()
+            .join("."))
+    }
+
+    pub fn expect(&self, tag: u8) -> Result<(), Error> {
+        if self.tag == tag {
+            Ok(())
+        } else {
+            Err(invalid(&format!(
+                "expected tag {:#04x} but found {:#04x}",
+                tag, self.tag
+            )))
+        }
+    }
+}
+
+/// DERでエンコードされた値を順に読み取ります
+#[derive(Debug, Clone)]
+pub struct DerReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> DerReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    pub fn peek_tag(&self) -> Option<u8> {
+        self.data.get(self.pos).copied()
+    }
+
+    pub fn read(&mut self) -> Result<Der<'a>, Error> {
+        let start = self.pos;
+        let tag = *self
+            .data
+            .get(self.pos)
+            .ok_or_else(|| invalid("unexpected end"))?;
+        if tag & 0x1f == 0x1f {
+            return Err(invalid("unsupported high tag number"));
+        }
+        let first = *self
+            .data
+            .get(self.pos + 1)
+            .ok_or_else(|| invalid("unexpected end"))?;
+        self.pos += 2;
+
+        let length = if first & 0x80 == 0 {
+            first as usize
+        } else {
+            let count = (first & 0x7f) as usize;
+            if count == 0 || count > 4 {
+                return Err(invalid("unsupported length"));
+            }
+            let bytes = self
+                .data
+                .get(self.pos..self.pos + count)
+                .ok_or_else(|| invalid("unexpected end"))?;
+            self.pos += count;
+            let length = bytes.iter().fold(0usize, |acc, &b| (acc << 8) | b as usize);
+            // 最短の形式でエンコードされていなければならない
+            if length < 0x80 || bytes[0] == 0 {
+                return Err(invalid("non-minimal length"));
+            }
+            length
+        };
+
+        let end = self
+            .pos
+            .checked_add(length)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| invalid("length exceeds data"))?;
+        let contents = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(Der {
+            tag,
+            contents,
+            raw: &self.data[start..end],
+        })
+    }
+
+    /// 指定したタグの値を読み取ります
+    pub fn read_tag(&mut self, tag: u8) -> Result<Der<'a>, Error> {
+        let der = self.read()?;
+        der.expect(tag)?;
+        Ok(der)
+    }
+
+    /// 次の値が指定したタグであれば読み取ります
+    pub fn read_optional(&mut self, tag: u8) -> Result<Option<Der<'a>>, Error> {
+        if self.peek_tag() == Some(tag) {
+            self.read().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// すべての値を読み終えたことを確認します
+    pub fn finish(&self) -> Result<(), Error> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(invalid("trailing data"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::from_hex;
+
+    #[test]
+    fn test_read() {
+        // SEQUENCE { INTEGER 128, OID 1.2.840.113549.1.1.11, NULL }
+        let data = from_hex("3011020200800609 2a864886f70d01010b 0500");
+        let mut reader = DerReader::new(&data);
+        let sequence = reader.read_tag(TAG_SEQUENCE).expect("sequence");
+        reader.finish().expect("finish");
+
+        let mut reader = sequence.reader();
+        assert_eq!(Ok(128), reader.read().and_then(|der| der.small_integer()));
+        assert_eq!(
+            Ok("1.2.840.113549.1.1.11".into()),
+            reader.read().and_then(|der| der.oid())
+        );
+        assert_eq!(None, reader.read_optional(TAG_INTEGER).expect("optional"));
+        reader.read_tag(TAG_NULL).expect("null");
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn test_long_length() {
+        let mut data = from_hex("048181");
+        data.extend_from_slice(&[7; 0x81]);
+        let der = DerReader::new(&data).read().expect("octet string");
+        assert_eq!(0x81, der.contents().len());
+        assert_eq!(data.len(), der.raw().len());
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(DerReader::new(&from_hex("3005020100")).read().is_err());
+        assert!(DerReader::new(&from_hex("04810100")).read().is_err());
+        assert!(DerReader::new(&from_hex("0201ff"))
+            .read()
+            .and_then(|der| der.unsigned_integer().map(|_| ()))
+            .is_err());
+    }
+}
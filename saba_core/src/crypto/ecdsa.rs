@@ -0,0 +1,429 @@
+//! NIST P-256とP-384のECDSA署名の検証と、ECDHによる鍵共有
+//! https://nvlpubs.nist.gov/nistpubs/FIPS/NIST.FIPS.186-5.pdf
+//! https://www.secg.org/sec1-v2.pdf
+
+use alloc::vec::Vec;
+
+use super::bigint::BigUint;
+use super::der::{DerReader, TAG_SEQUENCE};
+use crate::error::Error;
+
+/// 係数a = -3の短いワイエルシュトラス曲線
+/// https://nvlpubs.nist.gov/nistpubs/SpecialPublications/NIST.SP.800-186.pdf#section.3.2
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Curve {
+    P256,
+    P384,
+}
+
+struct CurveParams {
+    p: &'static [u8],
+    n: &'static [u8],
+    b: &'static [u8],
+    gx: &'static [u8],
+    gy: &'static [u8],
+}
+
+const P256: CurveParams = CurveParams {
+    p: b"\xff\xff\xff\xff\x00\x00\x00\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff",
+    n: b"\xff\xff\xff\xff\x00\x00\x00\x00\xff\xff\xff\xff\xff\xff\xff\xff\xbc\xe6\xfa\xad\xa7\x17\x9e\x84\xf3\xb9\xca\xc2\xfc\x63\x25\x51",
+    b: b"\x5a\xc6\x35\xd8\xaa\x3a\x93\xe7\xb3\xeb\xbd\x55\x76\x98\x86\xbc\x65\x1d\x06\xb0\xcc\x53\xb0\xf6\x3b\xce\x3c\x3e\x27\xd2\x60\x4b",
+    gx: b"\x6b\x17\xd1\xf2\xe1\x2c\x42\x47\xf8\xbc\xe6\xe5\x63\xa4\x40\xf2\x77\x03\x7d\x81\x2d\xeb\x33\xa0\xf4\xa1\x39\x45\xd8\x98\xc2\x96",
+    gy: b"\x4f\xe3\x42\xe2\xfe\x1a\x7f\x9b\x8e\xe7\xeb\x4a\x7c\x0f\x9e\x16\x2b\xce\x33\x57\x6b\x31\x5e\xce\xcb\xb6\x40\x68\x37\xbf\x51\xf5",
+};
+
+const P384: CurveParams = CurveParams {
+    p: b"\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xfe\xff\xff\xff\xff\x00\x00\x00\x00\x00\x00\x00\x00\xff\xff\xff\xff",
+    n: b"\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xc7\x63\x4d\x81\xf4\x37\x2d\xdf\x58\x1a\x0d\xb2\x48\xb0\xa7\x7a\xec\xec\x19\x6a\xcc\xc5\x29\x73",
+    b: b"\xb3\x31\x2f\xa7\xe2\x3e\xe7\xe4\x98\x8e\x05\x6b\xe3\xf8\x2d\x19\x18\x1d\x9c\x6e\xfe\x81\x41\x12\x03\x14\x08\x8f\x50\x13\x87\x5a\xc6\x56\x39\x8d\x8a\x2e\xd1\x9d\x2a\x85\xc8\xed\xd3\xec\x2a\xef",
+    gx: b"\xaa\x87\xca\x22\xbe\x8b\x05\x37\x8e\xb1\xc7\x1e\xf3\x20\xad\x74\x6e\x1d\x3b\x62\x8b\xa7\x9b\x98\x59\xf7\x41\xe0\x82\x54\x2a\x38\x55\x02\xf2\x5d\xbf\x55\x29\x6c\x3a\x54\x5e\x38\x72\x76\x0a\xb7",
+    gy: b"\x36\x17\xde\x4a\x96\x26\x2c\x6f\x5d\x9e\x98\xbf\x92\x92\xdc\x29\xf8\xf4\x1d\xbd\x28\x9a\x14\x7c\xe9\xda\x31\x13\xb5\xf0\xb8\xc0\x0a\x60\xb1\xce\x1d\x7e\x81\x9d\x7a\x43\x1d\x7c\x90\xea\x0e\x5f",
+};
+
+impl Curve {
+    fn params(&self) -> &'static CurveParams {
+        match self {
+            Curve::P256 => &P256,
+            Curve::P384 => &P384,
+        }
+    }
+
+    /// 座標のバイト数
+    pub fn field_size(&self) -> usize {
+        self.params().p.len()
+    }
+
+    fn p(&self) -> BigUint {
+        BigUint::from_bytes_be(self.params().p)
+    }
+
+    fn n(&self) -> BigUint {
+        BigUint::from_bytes_be(self.params().n)
+    }
+
+    fn generator(&self) -> Point {
+        Point::affine(
+            BigUint::from_bytes_be(self.params().gx),
+            BigUint::from_bytes_be(self.params().gy),
+        )
+    }
+}
+
+/// ヤコビアン座標の点。Zが0の点は無限遠点を表す
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Point {
+    x: BigUint,
+    y: BigUint,
+    z: BigUint,
+}
+
+impl Point {
+    fn affine(x: BigUint, y: BigUint) -> Self {
+        Self {
+            x,
+            y,
+            z: BigUint::from_u32(1),
+        }
+    }
+
+    fn infinity() -> Self {
+        Self {
+            x: BigUint::from_u32(1),
+            y: BigUint::from_u32(1),
+            z: BigUint::zero(),
+        }
+    }
+
+    fn is_infinity(&self) -> bool {
+        self.z.is_zero()
+    }
+
+    /// https://hyperelliptic.org/EFD/g1p/auto-shortw-jacobian-3.html#doubling-dbl-2001-b
+    fn double(&self, p: &BigUint) -> Self {
+        if self.is_infinity() || self.y.is_zero() {
+            return Self::infinity();
+        }
+        let delta = self.z.mod_mul(&self.z, p);
+        let gamma = self.y.mod_mul(&self.y, p);
+        let beta = self.x.mod_mul(&gamma, p);
+        let t = self
+            .x
+            .mod_sub(&delta, p)
+            .mod_mul(&self.x.mod_add(&delta, p), p);
+        let alpha = t.mod_add(&t, p).mod_add(&t, p);
+        let beta4 = beta.mod_add(&beta, p).mod_add(&beta.mod_add(&beta, p), p);
+        let beta8 = beta4.mod_add(&beta4, p);
+        let x = alpha.mod_mul(&alpha, p).mod_sub(&beta8, p);
+        let yz = self.y.mod_add(&self.z, p);
+        let z = yz.mod_mul(&yz, p).mod_sub(&gamma, p).mod_sub(&delta, p);
+        let gamma2 = gamma.mod_mul(&gamma, p);
+        let gamma2_8 = {
+            let g2 = gamma2.mod_add(&gamma2, p);
+            let g4 = g2.mod_add(&g2, p);
+            g4.mod_add(&g4, p)
+        };
+        let y = alpha
+            .mod_mul(&beta4.mod_sub(&x, p), p)
+            .mod_sub(&gamma2_8, p);
+        Self { x, y, z }
+    }
+
+    /// https://hyperelliptic.org/EFD/g1p/auto-shortw-jacobian-3.html#addition-add-2007-bl
+    fn add(&self, other: &Self, p: &BigUint) -> Self {
+        if self.is_infinity() {
+            return other.clone();
+        }
+        if other.is_infinity() {
+            return self.clone();
+        }
+        let z1z1 = self.z.mod_mul(&self.z, p);
+        let z2z2 = other.z.mod_mul(&other.z, p);
+        let u1 = self.x.mod_mul(&z2z2, p);
+        let u2 = other.x.mod_mul(&z1z1, p);
+        let s1 = self.y.mod_mul(&other.z, p).mod_mul(&z2z2, p);
+        let s2 = other.y.mod_mul(&self.z, p).mod_mul(&z1z1, p);
+        let h = u2.mod_sub(&u1, p);
+        let r = s2.mod_sub(&s1, p);
+        if h.is_zero() {
+            return if r.is_zero() {
+                self.double(p)
+            } else {
+                Self::infinity()
+            };
+        }
+        let h2 = h.mod_add(&h, p);
+        let i = h2.mod_mul(&h2, p);
+        let j = h.mod_mul(&i, p);
+        let r = r.mod_add(&r, p);
+        let v = u1.mod_mul(&i, p);
+        let x = r
+            .mod_mul(&r, p)
+            .mod_sub(&j, p)
+            .mod_sub(&v.mod_add(&v, p), p);
+        let s1j = s1.mod_mul(&j, p);
+        let y = r
+            .mod_mul(&v.mod_sub(&x, p), p)
+            .mod_sub(&s1j.mod_add(&s1j, p), p);
+        let z1z2 = self.z.mod_add(&other.z, p);
+        let z = z1z2
+            .mod_mul(&z1z2, p)
+            .mod_sub(&z1z1, p)
+            .mod_sub(&z2z2, p)
+            .mod_mul(&h, p);
+        Self { x, y, z }
+    }
+
+    fn mul(&self, k: &BigUint, p: &BigUint) -> Self {
+        let mut result = Self::infinity();
+        for i in (0..k.bits()).rev() {
+            result = result.double(p);
+            if k.bit(i) {
+                result = result.add(self, p);
+            }
+        }
+        result
+    }
+
+    /// アフィン座標のxを返します
+    fn affine_x(&self, p: &BigUint) -> BigUint {
+        let z_inv = self.z.mod_inv_prime(p);
+        self.x.mod_mul(&z_inv.mod_mul(&z_inv, p), p)
+    }
+}
+
+/// 非圧縮形式（0x04 || X || Y）の公開鍵を読み取り、曲線上の点であることを確認します
+/// https://www.secg.org/sec1-v2.pdf#subsubsection.2.3.4
+fn decode_public_key(curve: Curve, public_key: &[u8]) -> Result<Point, Error> {
+    let size = curve.field_size();
+    if public_key.len() != 1 + 2 * size || public_key[0] != 0x04 {
+        return Err(Error::Other("unsupported EC public key format".into()));
+    }
+    let p = curve.p();
+    let x = BigUint::from_bytes_be(&public_key[1..1 + size]);
+    let y = BigUint::from_bytes_be(&public_key[1 + size..]);
+    if x >= p || y >= p {
+        return Err(Error::Other("EC public key is out of range".into()));
+    }
+
+    // y^2 = x^3 - 3x + b
+    let b = BigUint::from_bytes_be(curve.params().b);
+    let x3 = x.mod_mul(&x, &p).mod_mul(&x, &p);
+    let three_x = x.mod_add(&x, &p).mod_add(&x, &p);
+    let rhs = x3.mod_sub(&three_x, &p).mod_add(&b, &p);
+    if y.mod_mul(&y, &p) != rhs {
+        return Err(Error::Other("EC public key is not on the curve".into()));
+    }
+    Ok(Point::affine(x, y))
+}
+
+/// ハッシュ値の左端から位数のビット数だけを整数として取り出します
+fn hash_to_integer(curve: Curve, digest: &[u8]) -> BigUint {
+    let n_bits = curve.n().bits();
+    let e = BigUint::from_bytes_be(digest);
+    let digest_bits = digest.len() * 8;
+    if digest_bits <= n_bits {
+        return e;
+    }
+    let mut bytes = digest.to_vec();
+    bytes.truncate(n_bits.div_ceil(8));
+    let e = BigUint::from_bytes_be(&bytes);
+    // n_bitsが8の倍数でない場合の残りのビットを捨てる
+    let (e, _) = e.div_rem(&BigUint::from_u32(1 << ((8 - n_bits % 8) % 8)));
+    e
+}
+
+/// DERでエンコードされたECDSA署名（SEQUENCE { r INTEGER, s INTEGER }）を(r, s)にします
+/// https://www.rfc-editor.org/rfc/rfc3279#section-2.2.3
+pub fn decode_signature(signature: &[u8]) -> Result<(Vec<u8>, Vec<u8>), Error> {
+    let mut reader = DerReader::new(signature);
+    let sequence = reader.read_tag(TAG_SEQUENCE)?;
+    reader.finish()?;
+    let mut reader = sequence.reader();
+    let r = reader.read()?.unsigned_integer()?.to_vec();
+    let s = reader.read()?.unsigned_integer()?.to_vec();
+    reader.finish()?;
+    Ok((r, s))
+}
+
+/// 公開鍵public_keyでハッシュ値digestに対するDERエンコードの署名signatureを検証します
+/// https://nvlpubs.nist.gov/nistpubs/FIPS/NIST.FIPS.186-5.pdf#subsection.6.4.2
+pub fn verify(
+    curve: Curve,
+    public_key: &[u8],
+    digest: &[u8],
+    signature: &[u8],
+) -> Result<(), Error> {
+    let q = decode_public_key(curve, public_key)?;
+    let (r, s) = decode_signature(signature)?;
+    let r = BigUint::from_bytes_be(&r);
+    let s = BigUint::from_bytes_be(&s);
+    let n = curve.n();
+    let p = curve.p();
+    if r.is_zero() || s.is_zero() || r >= n || s >= n {
+        return Err(Error::Other("ECDSA signature is out of range".into()));
+    }
+
+    let e = hash_to_integer(curve, digest).rem(&n);
+    let w = s.mod_inv_prime(&n);
+    let u1 = e.mod_mul(&w, &n);
+    let u2 = r.mod_mul(&w, &n);
+    let point = curve.generator().mul(&u1, &p).add(&q.mul(&u2, &p), &p);
+    if point.is_infinity() || point.affine_x(&p).rem(&n) != r {
+        return Err(Error::Other("ECDSA signature verification failed".into()));
+    }
+    Ok(())
+}
+
+/// テスト用のサーバーで使う署名。kは署名ごとに異なる秘密の値でなければなりません
+#[cfg(test)]
+pub(crate) fn sign(curve: Curve, private_key: &[u8], digest: &[u8], k: &[u8]) -> Vec<u8> {
+    let n = curve.n();
+    let p = curve.p();
+    let d = BigUint::from_bytes_be(private_key);
+    let k = BigUint::from_bytes_be(k).rem(&n);
+    let r = curve.generator().mul(&k, &p).affine_x(&p).rem(&n);
+    let e = hash_to_integer(curve, digest).rem(&n);
+    let s = k
+        .mod_inv_prime(&n)
+        .mod_mul(&e.mod_add(&r.mod_mul(&d, &n), &n), &n);
+
+    let integer = |n: &BigUint| {
+        let mut bytes = n.to_bytes_be(n.bits() / 8 + 1);
+        while bytes.len() > 1 && bytes[0] == 0 && bytes[1] & 0x80 == 0 {
+            bytes.remove(0);
+        }
+        let mut der = alloc::vec![0x02, bytes.len() as u8];
+        der.extend_from_slice(&bytes);
+        der
+    };
+    let mut body = integer(&r);
+    body.extend_from_slice(&integer(&s));
+    let mut der = alloc::vec![TAG_SEQUENCE, body.len() as u8];
+    der.extend_from_slice(&body);
+    der
+}
+
+/// 秘密鍵を1以上n未満の整数として読み取ります
+fn private_scalar(curve: Curve, private_key: &[u8]) -> Result<BigUint, Error> {
+    let d = BigUint::from_bytes_be(private_key).rem(&curve.n());
+    if d.is_zero() {
+        return Err(Error::Other("invalid EC private key".into()));
+    }
+    Ok(d)
+}
+
+/// 秘密鍵から非圧縮形式の公開鍵を計算します。秘密鍵はnを法として扱います
+pub fn public_key(curve: Curve, private_key: &[u8]) -> Result<Vec<u8>, Error> {
+    let p = curve.p();
+    let point = curve
+        .generator()
+        .mul(&private_scalar(curve, private_key)?, &p);
+    let z_inv = point.z.mod_inv_prime(&p);
+    let z_inv2 = z_inv.mod_mul(&z_inv, &p);
+    let x = point.x.mod_mul(&z_inv2, &p);
+    let y = point.y.mod_mul(&z_inv2.mod_mul(&z_inv, &p), &p);
+    let mut key = alloc::vec![0x04];
+    key.extend_from_slice(&x.to_bytes_be(curve.field_size()));
+    key.extend_from_slice(&y.to_bytes_be(curve.field_size()));
+    Ok(key)
+}
+
+/// ECDHで共有する値（相手の公開鍵と秘密鍵の積のx座標）を計算します
+/// https://www.secg.org/sec1-v2.pdf#subsubsection.3.3.1
+pub fn ecdh(curve: Curve, private_key: &[u8], peer_public_key: &[u8]) -> Result<Vec<u8>, Error> {
+    let p = curve.p();
+    let point =
+        decode_public_key(curve, peer_public_key)?.mul(&private_scalar(curve, private_key)?, &p);
+    if point.is_infinity() {
+        return Err(Error::Other(
+            "ECDH shared secret is the point at infinity".into(),
+        ));
+    }
+    Ok(point.affine_x(&p).to_bytes_be(curve.field_size()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::from_hex;
+    use crate::crypto::sha2::{Sha256, Sha384};
+    use crate::crypto::Hash;
+
+    #[test]
+    fn test_verify_p256() {
+        let public_key = from_hex(
+            "044ca9a5a8e7cca998df0f11f512642222f1a39e292e19fb7c29f28f5d272035f25c1b6a506a7c60a013f99125ff6cc4719ccfe15a4664fc09e613f66a28550b58",
+        );
+        let signature = from_hex(
+            "304402207757bf23c8908031b4f8df9f05582f3a70f64a97f458c24e5eeb869e6061e02f022032a949475b247e73565db9b99520f3ff05bae481ff8ffdb54079713cb839f3b2",
+        );
+        let digest = Sha256::digest(b"saba");
+        assert_eq!(
+            Ok(()),
+            verify(Curve::P256, &public_key, &digest, &signature)
+        );
+        assert!(verify(
+            Curve::P256,
+            &public_key,
+            &Sha256::digest(b"sabb"),
+            &signature
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_verify_p384() {
+        let public_key = from_hex(
+            "04dfced2bc848aceb459eecf61c2a80f52d9c430b67c15d64de4d03a61693d5f287ec528a19ee5fcc137314dd9848d5d80a82237756edb25a762f27b57afd57c8498483cb1911010150027590190c57a93f00da50f1eead07db7bb3188dc5606b7",
+        );
+        let signature = from_hex(
+            "3065023072c2d28c972233d9adffead780650a10dc1cf1108b8df6a36346560a32666ee17eba00aa53aeca883bebd999089121b90231008ceff2a373b64d3fc5bf2d5b91249b7e957c893065fee58fc0684b7e387da7ecd943ed8146cf14559ecdc3716ec4d002",
+        );
+        let digest = Sha384::digest(b"saba");
+        assert_eq!(
+            Ok(()),
+            verify(Curve::P384, &public_key, &digest, &signature)
+        );
+    }
+
+    #[test]
+    fn test_sign() {
+        let private_key =
+            from_hex("a03e3898441699e4215fd315275e7569d4ef9c23671bd782d4e2ce49330c08f4");
+        let public_key = public_key(Curve::P256, &private_key).expect("invalid private key");
+        assert_eq!(
+            from_hex("044ca9a5a8e7cca998df0f11f512642222f1a39e292e19fb7c29f28f5d272035f25c1b6a506a7c60a013f99125ff6cc4719ccfe15a4664fc09e613f66a28550b58"),
+            public_key
+        );
+        let digest = Sha256::digest(b"saba");
+        let signature = sign(Curve::P256, &private_key, &digest, &[42; 32]);
+        assert_eq!(
+            Ok(()),
+            verify(Curve::P256, &public_key, &digest, &signature)
+        );
+    }
+
+    /// https://csrc.nist.gov/projects/cryptographic-algorithm-validation-program/component-testing
+    #[test]
+    fn test_ecdh() {
+        let private_key =
+            from_hex("7d7dc5f71eb29ddaf80d6214632eeae03d9058af1fb6d22ed80badb62bc1a534");
+        let peer_public_key = from_hex(
+            "04700c48f77f56584c5cc632ca65640db91b6bacce3a4df6b42ce7cc838833d287db71e509e3fd9b060ddb20ba5c51dcc5948d46fbf640dfe0441782cab85fa4ac",
+        );
+        assert_eq!(
+            Ok(from_hex(
+                "46fc62106420ff012e54a434fbdd2d25ccc5852060561e68040dd7778997bd7b"
+            )),
+            ecdh(Curve::P256, &private_key, &peer_public_key)
+        );
+        assert!(ecdh(Curve::P256, &[0; 32], &peer_public_key).is_err());
+    }
+
+    #[test]
+    fn test_invalid_public_key() {
+        let mut public_key = alloc::vec![0x04];
+        public_key.extend_from_slice(&[1; 64]);
+        assert!(decode_public_key(Curve::P256, &public_key).is_err());
+    }
+}
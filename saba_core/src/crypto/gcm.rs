@@ -0,0 +1,135 @@
+//! AES-GCMによる認証付き暗号
+//! https://nvlpubs.nist.gov/nistpubs/Legacy/SP/nistspecialpublication800-38d.pdf
+
+use alloc::vec::Vec;
+
+use super::aes::Aes;
+use super::constant_time_eq;
+use crate::error::Error;
+
+pub const NONCE_SIZE: usize = 12;
+pub const TAG_SIZE: usize = 16;
+
+/// GF(2^128)での乗算
+/// https://nvlpubs.nist.gov/nistpubs/Legacy/SP/nistspecialpublication800-38d.pdf#page=19
+fn gf_mul(x: u128, y: u128) -> u128 {
+    const R: u128 = 0xe1 << 120;
+    let mut z = 0u128;
+    let mut v = y;
+    for i in 0..128 {
+        if (x >> (127 - i)) & 1 == 1 {
+            z ^= v;
+        }
+        v = if v & 1 == 1 { (v >> 1) ^ R } else { v >> 1 };
+    }
+    z
+}
+
+fn block_to_u128(block: &[u8]) -> u128 {
+    let mut bytes = [0u8; 16];
+    bytes[..block.len()].copy_from_slice(block);
+    u128::from_be_bytes(bytes)
+}
+
+#[derive(Debug, Clone)]
+pub struct AesGcm {
+    aes: Aes,
+    /// ハッシュ鍵H
+    h: u128,
+}
+
+impl AesGcm {
+    pub fn new(key: &[u8]) -> Result<Self, Error> {
+        let aes = Aes::new(key)?;
+        let h = u128::from_be_bytes(aes.encrypt_block(&[0; 16]));
+        Ok(Self { aes, h })
+    }
+
+    fn ghash(&self, aad: &[u8], ciphertext: &[u8]) -> u128 {
+        let mut y = 0u128;
+        for block in aad.chunks(16).chain(ciphertext.chunks(16)) {
+            y = gf_mul(y ^ block_to_u128(block), self.h);
+        }
+        let lengths = ((aad.len() as u128 * 8) << 64) | (ciphertext.len() as u128 * 8);
+        gf_mul(y ^ lengths, self.h)
+    }
+
+    /// カウンタモードでdataを暗号化（復号）します。counterは最初のブロックのカウンタ値です
+    fn ctr(&self, nonce: &[u8; NONCE_SIZE], counter: u32, data: &mut [u8]) {
+        let mut block = [0u8; 16];
+        block[..NONCE_SIZE].copy_from_slice(nonce);
+        for (i, chunk) in data.chunks_mut(16).enumerate() {
+            block[12..].copy_from_slice(&counter.wrapping_add(i as u32).to_be_bytes());
+            let keystream = self.aes.encrypt_block(&block);
+            for (b, k) in chunk.iter_mut().zip(keystream) {
+                *b ^= k;
+            }
+        }
+    }
+
+    fn tag(&self, nonce: &[u8; NONCE_SIZE], aad: &[u8], ciphertext: &[u8]) -> [u8; TAG_SIZE] {
+        let mut tag = self.ghash(aad, ciphertext).to_be_bytes();
+        self.ctr(nonce, 1, &mut tag);
+        tag
+    }
+
+    /// 平文を暗号化し、認証タグを付けた暗号文を返します
+    pub fn seal(&self, nonce: &[u8; NONCE_SIZE], aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+        let mut output = plaintext.to_vec();
+        self.ctr(nonce, 2, &mut output);
+        let tag = self.tag(nonce, aad, &output);
+        output.extend_from_slice(&tag);
+        output
+    }
+
+    /// 認証タグを確認してから暗号文を復号します
+    pub fn open(
+        &self,
+        nonce: &[u8; NONCE_SIZE],
+        aad: &[u8],
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        if ciphertext.len() < TAG_SIZE {
+            return Err(Error::Other("AES-GCM ciphertext is too short".into()));
+        }
+        let (ciphertext, tag) = ciphertext.split_at(ciphertext.len() - TAG_SIZE);
+        if !constant_time_eq(&self.tag(nonce, aad, ciphertext), tag) {
+            return Err(Error::Other("AES-GCM authentication failed".into()));
+        }
+        let mut output = ciphertext.to_vec();
+        self.ctr(nonce, 2, &mut output);
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{from_hex, to_hex};
+
+    /// The Galois/Counter Mode of Operation (GCM) Test Case 4
+    #[test]
+    fn test_seal_and_open() {
+        let key = from_hex("feffe9928665731c6d6a8f9467308308");
+        let mut nonce = [0u8; NONCE_SIZE];
+        nonce.copy_from_slice(&from_hex("cafebabefacedbaddecaf888"));
+        let plaintext = from_hex(
+            "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39",
+        );
+        let aad = from_hex("feedfacedeadbeeffeedfacedeadbeefabaddad2");
+
+        let gcm = AesGcm::new(&key).expect("invalid key");
+        let sealed = gcm.seal(&nonce, &aad, &plaintext);
+        assert_eq!(
+            "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091\
+             5bc94fbc3221a5db94fae95ae7121a47",
+            to_hex(&sealed)
+        );
+        assert_eq!(Ok(plaintext), gcm.open(&nonce, &aad, &sealed));
+
+        let mut tampered = sealed.clone();
+        tampered[0] ^= 1;
+        assert!(gcm.open(&nonce, &aad, &tampered).is_err());
+        assert!(gcm.open(&nonce, b"", &sealed).is_err());
+    }
+}
@@ -0,0 +1,124 @@
+//! HMACとHKDF
+//! https://www.rfc-editor.org/rfc/rfc2104
+//! https://www.rfc-editor.org/rfc/rfc5869
+
+use alloc::vec::Vec;
+
+use super::Hash;
+
+/// https://www.rfc-editor.org/rfc/rfc2104#section-2
+#[derive(Debug, Clone)]
+pub struct Hmac<H: Hash> {
+    inner: H,
+    outer: H,
+}
+
+impl<H: Hash> Hmac<H> {
+    pub fn new(key: &[u8]) -> Self {
+        let mut block = if key.len() > H::BLOCK_SIZE {
+            H::digest(key)
+        } else {
+            key.to_vec()
+        };
+        block.resize(H::BLOCK_SIZE, 0);
+
+        let ipad: Vec<u8> = block.iter().map(|b| b ^ 0x36).collect();
+        let opad: Vec<u8> = block.iter().map(|b| b ^ 0x5c).collect();
+        let mut inner = H::new();
+        inner.update(&ipad);
+        let mut outer = H::new();
+        outer.update(&opad);
+        Self { inner, outer }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.inner.update(data);
+    }
+
+    pub fn finalize(self) -> Vec<u8> {
+        let mut outer = self.outer;
+        outer.update(&self.inner.finalize());
+        outer.finalize()
+    }
+}
+
+pub fn hmac<H: Hash>(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<H>::new(key);
+    mac.update(data);
+    mac.finalize()
+}
+
+/// https://www.rfc-editor.org/rfc/rfc5869#section-2.2
+pub fn hkdf_extract<H: Hash>(salt: &[u8], ikm: &[u8]) -> Vec<u8> {
+    if salt.is_empty() {
+        hmac::<H>(&alloc::vec![0; H::OUTPUT_SIZE], ikm)
+    } else {
+        hmac::<H>(salt, ikm)
+    }
+}
+
+/// https://www.rfc-editor.org/rfc/rfc5869#section-2.3
+pub fn hkdf_expand<H: Hash>(prk: &[u8], info: &[u8], length: usize) -> Vec<u8> {
+    let mut okm = Vec::new();
+    let mut t = Vec::new();
+    let mut counter = 1u8;
+    while okm.len() < length {
+        let mut mac = Hmac::<H>::new(prk);
+        mac.update(&t);
+        mac.update(info);
+        mac.update(&[counter]);
+        t = mac.finalize();
+        okm.extend_from_slice(&t);
+        counter = counter.wrapping_add(1);
+    }
+    okm.truncate(length);
+    okm
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::sha2::Sha256;
+    use crate::crypto::sha2::Sha512;
+    use crate::crypto::{from_hex, to_hex};
+
+    /// https://www.rfc-editor.org/rfc/rfc4231#section-4.2
+    #[test]
+    fn test_hmac() {
+        let key = from_hex("0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b");
+        assert_eq!(
+            "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7",
+            to_hex(&hmac::<Sha256>(&key, b"Hi There"))
+        );
+        assert_eq!(
+            "87aa7cdea5ef619d4ff0b4241a1d6cb02379f4e2ce4ec2787ad0b30545e17cdedaa833b7d6b8a702038b274eaea3f4e4be9d914eeb61f1702e696c203a126854",
+            to_hex(&hmac::<Sha512>(&key, b"Hi There"))
+        );
+        // ブロックより長い鍵
+        let key = from_hex(&"aa".repeat(131));
+        assert_eq!(
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54",
+            to_hex(&hmac::<Sha256>(
+                &key,
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            ))
+        );
+    }
+
+    /// https://www.rfc-editor.org/rfc/rfc5869#appendix-A.1
+    #[test]
+    fn test_hkdf() {
+        let ikm = from_hex("0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b");
+        let salt = from_hex("000102030405060708090a0b0c");
+        let info = from_hex("f0f1f2f3f4f5f6f7f8f9");
+        let prk = hkdf_extract::<Sha256>(&salt, &ikm);
+        assert_eq!(
+            "077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5",
+            to_hex(&prk)
+        );
+        assert_eq!(
+            "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865",
+            to_hex(&hkdf_expand::<Sha256>(&prk, &info, 42))
+        );
+    }
+}
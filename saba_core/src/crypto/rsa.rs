@@ -0,0 +1,218 @@
+//! RSA署名の検証
+//! https://www.rfc-editor.org/rfc/rfc8017
+
+use alloc::vec::Vec;
+
+use super::bigint::BigUint;
+use super::der::{DerReader, TAG_SEQUENCE};
+use super::{constant_time_eq, HashAlgorithm};
+use crate::error::Error;
+
+/// 受け付ける法の最小ビット数
+const MIN_MODULUS_BITS: usize = 1024;
+/// 検証に時間がかかりすぎないように制限する法の最大ビット数
+const MAX_MODULUS_BITS: usize = 8192;
+
+fn verification_failed() -> Error {
+    Error::Other("RSA signature verification failed".into())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RsaPublicKey {
+    n: BigUint,
+    e: BigUint,
+}
+
+impl RsaPublicKey {
+    /// RSAPublicKey（SEQUENCE { modulus INTEGER, publicExponent INTEGER }）を読み取ります
+    /// https://www.rfc-editor.org/rfc/rfc8017#appendix-A.1.1
+    pub fn from_der(der: &[u8]) -> Result<Self, Error> {
+        let mut reader = DerReader::new(der);
+        let sequence = reader.read_tag(TAG_SEQUENCE)?;
+        reader.finish()?;
+        let mut reader = sequence.reader();
+        let n = BigUint::from_bytes_be(reader.read()?.unsigned_integer()?);
+        let e = BigUint::from_bytes_be(reader.read()?.unsigned_integer()?);
+        reader.finish()?;
+
+        if !(MIN_MODULUS_BITS..=MAX_MODULUS_BITS).contains(&n.bits()) {
+            return Err(Error::Other("unsupported RSA key size".into()));
+        }
+        if e.bits() < 2 || !e.bit(0) || e >= n {
+            return Err(Error::Other("invalid RSA public exponent".into()));
+        }
+        Ok(Self { n, e })
+    }
+
+    /// 法のバイト数
+    fn size(&self) -> usize {
+        self.n.bits().div_ceil(8)
+    }
+
+    /// https://www.rfc-editor.org/rfc/rfc8017#section-5.2.2
+    fn rsavp1(&self, signature: &[u8]) -> Result<Vec<u8>, Error> {
+        if signature.len() != self.size() {
+            return Err(verification_failed());
+        }
+        let s = BigUint::from_bytes_be(signature);
+        if s >= self.n {
+            return Err(verification_failed());
+        }
+        Ok(s.mod_pow(&self.e, &self.n).to_bytes_be(self.size()))
+    }
+
+    /// RSASSA-PKCS1-v1_5の署名を検証します
+    /// https://www.rfc-editor.org/rfc/rfc8017#section-8.2.2
+    pub fn verify_pkcs1_v15(
+        &self,
+        hash: HashAlgorithm,
+        message: &[u8],
+        signature: &[u8],
+    ) -> Result<(), Error> {
+        let em = self.rsavp1(signature)?;
+
+        // DigestInfoのハッシュ値より前の部分
+        // https://www.rfc-editor.org/rfc/rfc8017#section-9.2
+        let prefix: &[u8] = match hash {
+            HashAlgorithm::Sha256 => {
+                b"\x30\x31\x30\x0d\x06\x09\x60\x86\x48\x01\x65\x03\x04\x02\x01\x05\x00\x04\x20"
+            }
+            HashAlgorithm::Sha384 => {
+                b"\x30\x41\x30\x0d\x06\x09\x60\x86\x48\x01\x65\x03\x04\x02\x02\x05\x00\x04\x30"
+            }
+            HashAlgorithm::Sha512 => {
+                b"\x30\x51\x30\x0d\x06\x09\x60\x86\x48\x01\x65\x03\x04\x02\x03\x05\x00\x04\x40"
+            }
+        };
+        let mut t = prefix.to_vec();
+        t.extend_from_slice(&hash.digest(message));
+        if em.len() < t.len() + 11 {
+            return Err(verification_failed());
+        }
+
+        let mut expected = alloc::vec![0x00, 0x01];
+        expected.resize(em.len() - t.len() - 1, 0xff);
+        expected.push(0x00);
+        expected.extend_from_slice(&t);
+        if constant_time_eq(&em, &expected) {
+            Ok(())
+        } else {
+            Err(verification_failed())
+        }
+    }
+
+    /// ソルトの長さがハッシュ値の長さと等しいRSASSA-PSSの署名を検証します
+    /// https://www.rfc-editor.org/rfc/rfc8017#section-8.1.2
+    pub fn verify_pss(
+        &self,
+        hash: HashAlgorithm,
+        message: &[u8],
+        signature: &[u8],
+    ) -> Result<(), Error> {
+        let em_bits = self.n.bits() - 1;
+        let em_len = em_bits.div_ceil(8);
+        let em = self.rsavp1(signature)?;
+        // emBitsが8の倍数の場合、RSAVP1の結果の先頭の1バイトは0でなければならない
+        let (leading, em) = em.split_at(em.len() - em_len);
+        if leading.iter().any(|&b| b != 0) {
+            return Err(verification_failed());
+        }
+
+        // EMSA-PSS-VERIFY
+        // https://www.rfc-editor.org/rfc/rfc8017#section-9.1.2
+        let h_len = hash.output_size();
+        let s_len = h_len;
+        let m_hash = hash.digest(message);
+        if em_len < h_len + s_len + 2 || em[em_len - 1] != 0xbc {
+            return Err(verification_failed());
+        }
+        let (masked_db, h) = em[..em_len - 1].split_at(em_len - h_len - 1);
+        let unused_bits = 8 * em_len - em_bits;
+        if unused_bits != 0 && masked_db[0] >> (8 - unused_bits) != 0 {
+            return Err(verification_failed());
+        }
+
+        let mask = mgf1(hash, h, masked_db.len());
+        let mut db: Vec<u8> = masked_db.iter().zip(&mask).map(|(a, b)| a ^ b).collect();
+        if unused_bits != 0 {
+            db[0] &= 0xff >> unused_bits;
+        }
+        let padding_len = em_len - h_len - s_len - 2;
+        if db[..padding_len].iter().any(|&b| b != 0) || db[padding_len] != 0x01 {
+            return Err(verification_failed());
+        }
+        let salt = &db[db.len() - s_len..];
+
+        let mut m_prime = alloc::vec![0u8; 8];
+        m_prime.extend_from_slice(&m_hash);
+        m_prime.extend_from_slice(salt);
+        if constant_time_eq(&hash.digest(&m_prime), h) {
+            Ok(())
+        } else {
+            Err(verification_failed())
+        }
+    }
+}
+
+/// https://www.rfc-editor.org/rfc/rfc8017#appendix-B.2.1
+fn mgf1(hash: HashAlgorithm, seed: &[u8], length: usize) -> Vec<u8> {
+    let mut output = Vec::new();
+    let mut counter = 0u32;
+    while output.len() < length {
+        let mut input = seed.to_vec();
+        input.extend_from_slice(&counter.to_be_bytes());
+        output.extend_from_slice(&hash.digest(&input));
+        counter += 1;
+    }
+    output.truncate(length);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::from_hex;
+
+    fn public_key() -> RsaPublicKey {
+        RsaPublicKey::from_der(&from_hex(
+            "30818902818100c3df728ba8b8fb289c082aebb61a81dd4250353f87ae5f0e298b67632193b61ab5a7766d6ce8ca464c8bf88691380d40f8720f275f6d3097252493aa08befd076c27869ceef4ed2f010101a8ca86b5bf9604882dac9e77045cc8cc0271920ef0da2ba14b160c1160be34fa10cef3918ee2b8263db47a0425b2796c5943260f7d0203010001",
+        ))
+        .expect("invalid public key")
+    }
+
+    #[test]
+    fn test_pkcs1_v15() {
+        let signature = from_hex(
+            "5c04578edd6f1e6f856168bef5264196377a98f081be7770375b97107ea2b855a93a50000c4ab4c160fd3a744a74e626cf423751917c4820fe6371cfe07f55600a3e69862b3d2ff10d1f568134da3b97732bacf9f5b8a776a36df78e5facc33df96c2a4ada5006455bd996452126f74cea701de4b9ebff75ed5d95a5041964e0",
+        );
+        let key = public_key();
+        assert_eq!(
+            Ok(()),
+            key.verify_pkcs1_v15(HashAlgorithm::Sha256, b"saba", &signature)
+        );
+        assert!(key
+            .verify_pkcs1_v15(HashAlgorithm::Sha256, b"sabb", &signature)
+            .is_err());
+        assert!(key
+            .verify_pkcs1_v15(HashAlgorithm::Sha384, b"saba", &signature)
+            .is_err());
+    }
+
+    #[test]
+    fn test_pss() {
+        let signature = from_hex(
+            "1ac767c0c0c639eb1e8678e5f819aee7898a984844fb452fbebfcc2577035f676fa0567359ad2a16f34963ab87c2bba22d125f973f98463408b6b041061c4f7f1214bc6ad70174c34a0eac80905935f4f303ec603cad089ffa115a8267e3bb71444576d3265d69b5f9f694d6dfd66a20baacffd317944cf5ead53a64d2228814",
+        );
+        let key = public_key();
+        assert_eq!(
+            Ok(()),
+            key.verify_pss(HashAlgorithm::Sha256, b"saba", &signature)
+        );
+        assert!(key
+            .verify_pss(HashAlgorithm::Sha256, b"sabb", &signature)
+            .is_err());
+        assert!(key
+            .verify_pss(HashAlgorithm::Sha256, b"saba", &signature[1..])
+            .is_err());
+    }
+}
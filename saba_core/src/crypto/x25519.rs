@@ -0,0 +1,135 @@
+//! X25519による鍵共有
+//! https://www.rfc-editor.org/rfc/rfc7748
+
+use alloc::vec::Vec;
+
+use super::bigint::BigUint;
+
+pub const KEY_SIZE: usize = 32;
+
+/// 2^255 - 19
+fn prime() -> BigUint {
+    let mut bytes = [0xffu8; KEY_SIZE];
+    bytes[0] = 0x7f;
+    bytes[KEY_SIZE - 1] = 0xed;
+    BigUint::from_bytes_be(&bytes)
+}
+
+fn decode_little_endian(bytes: &[u8; KEY_SIZE]) -> BigUint {
+    let reversed: Vec<u8> = bytes.iter().rev().copied().collect();
+    BigUint::from_bytes_be(&reversed)
+}
+
+fn encode_little_endian(n: &BigUint) -> [u8; KEY_SIZE] {
+    let mut bytes = [0u8; KEY_SIZE];
+    for (b, v) in bytes.iter_mut().zip(n.to_bytes_be(KEY_SIZE).iter().rev()) {
+        *b = *v;
+    }
+    bytes
+}
+
+/// スカラー倍を計算します。scalarは秘密鍵、uは相手の公開鍵（または基点）です
+/// https://www.rfc-editor.org/rfc/rfc7748#section-5
+pub fn x25519(scalar: &[u8; KEY_SIZE], u: &[u8; KEY_SIZE]) -> [u8; KEY_SIZE] {
+    let p = prime();
+
+    let mut k = *scalar;
+    k[0] &= 248;
+    k[31] &= 127;
+    k[31] |= 64;
+    let k = decode_little_endian(&k);
+
+    let mut u = *u;
+    u[31] &= 127;
+    let x1 = decode_little_endian(&u).rem(&p);
+
+    let a24 = BigUint::from_u32(121665);
+    let mut x2 = BigUint::from_u32(1);
+    let mut z2 = BigUint::zero();
+    let mut x3 = x1.clone();
+    let mut z3 = BigUint::from_u32(1);
+    let mut swap = false;
+
+    for t in (0..255).rev() {
+        let k_t = k.bit(t);
+        if swap != k_t {
+            core::mem::swap(&mut x2, &mut x3);
+            core::mem::swap(&mut z2, &mut z3);
+        }
+        swap = k_t;
+
+        let a = x2.mod_add(&z2, &p);
+        let aa = a.mod_mul(&a, &p);
+        let b = x2.mod_sub(&z2, &p);
+        let bb = b.mod_mul(&b, &p);
+        let e = aa.mod_sub(&bb, &p);
+        let c = x3.mod_add(&z3, &p);
+        let d = x3.mod_sub(&z3, &p);
+        let da = d.mod_mul(&a, &p);
+        let cb = c.mod_mul(&b, &p);
+        let sum = da.mod_add(&cb, &p);
+        let diff = da.mod_sub(&cb, &p);
+        x3 = sum.mod_mul(&sum, &p);
+        z3 = x1.mod_mul(&diff.mod_mul(&diff, &p), &p);
+        x2 = aa.mod_mul(&bb, &p);
+        z2 = e.mod_mul(&aa.mod_add(&a24.mod_mul(&e, &p), &p), &p);
+    }
+    if swap {
+        core::mem::swap(&mut x2, &mut x3);
+        core::mem::swap(&mut z2, &mut z3);
+    }
+
+    encode_little_endian(&x2.mod_mul(&z2.mod_inv_prime(&p), &p))
+}
+
+/// 秘密鍵から公開鍵を計算します
+pub fn public_key(private_key: &[u8; KEY_SIZE]) -> [u8; KEY_SIZE] {
+    let mut base = [0u8; KEY_SIZE];
+    base[0] = 9;
+    x25519(private_key, &base)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{from_hex, to_hex};
+
+    fn key(hex: &str) -> [u8; KEY_SIZE] {
+        let mut key = [0u8; KEY_SIZE];
+        key.copy_from_slice(&from_hex(hex));
+        key
+    }
+
+    /// https://www.rfc-editor.org/rfc/rfc7748#section-5.2
+    #[test]
+    fn test_x25519() {
+        assert_eq!(
+            "c3da55379de9c6908e94ea4df28d084f32eccf03491c71f754b4075577a28552",
+            to_hex(&x25519(
+                &key("a546e36bf0527c9d3b16154b82465edd62144c0ac1fc5a18506a2244ba449ac4"),
+                &key("e6db6867583030db3594c1a424b15f7c726624ec26b3353b10a903a6d0ab1c4c")
+            ))
+        );
+    }
+
+    /// https://www.rfc-editor.org/rfc/rfc7748#section-6.1
+    #[test]
+    fn test_key_agreement() {
+        let alice = key("77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a");
+        let bob = key("5dab087e624a8a4b79e17f8b83800ee66f3bb1292618b6fd1c2f8b27ff88e0eb");
+        assert_eq!(
+            "8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a",
+            to_hex(&public_key(&alice))
+        );
+        assert_eq!(
+            "de9edb7d7b7dc1b4d35b61c2ece435373f8343c85b78674dadfc7e146f882b4f",
+            to_hex(&public_key(&bob))
+        );
+        let shared = x25519(&alice, &public_key(&bob));
+        assert_eq!(shared, x25519(&bob, &public_key(&alice)));
+        assert_eq!(
+            "4a5d9d5ba4ce2de1728e3bf480350f25e07e21c947d19e3376f09b3c1e161742",
+            to_hex(&shared)
+        );
+    }
+}
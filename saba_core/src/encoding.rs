@@ -0,0 +1,336 @@
+//! https://encoding.spec.whatwg.org/
+
+pub mod japanese;
+pub mod jis0208;
+pub mod jis0212;
+pub mod utf16;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+
+pub const REPLACEMENT_CHARACTER: char = '\u{FFFD}';
+
+/// https://encoding.spec.whatwg.org/#names-and-labels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Utf16Be,
+    Utf16Le,
+    ShiftJis,
+    EucJp,
+    Iso2022Jp,
+    Windows1252,
+}
+
+/// windows-1252の0x80から0x9Fまでのバイトに対応するコードポイント
+/// それ以外のバイトは同じ値のコードポイントになります
+/// https://encoding.spec.whatwg.org/index-windows-1252.txt
+const WINDOWS_1252_C1: [char; 32] = [
+    '\u{20AC}', '\u{0081}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{008D}', '\u{017D}', '\u{008F}',
+    '\u{0090}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{009D}', '\u{017E}', '\u{0178}',
+];
+
+impl Encoding {
+    /// ラベルから対応するエンコーディングを返します
+    /// https://encoding.spec.whatwg.org/#concept-encoding-get
+    pub fn for_label(label: &str) -> Option<Self> {
+        let label = label
+            .trim_matches(|c: char| matches!(c, '\t' | '\n' | '\x0C' | '\r' | ' '))
+            .to_ascii_lowercase();
+        let encoding = match label.as_str() {
+            "unicode-1-1-utf-8" | "unicode11utf8" | "unicode20utf8" | "utf-8" | "utf8"
+            | "x-unicode20utf8" => Encoding::Utf8,
+            "unicodefffe" | "utf-16be" => Encoding::Utf16Be,
+            "csunicode" | "iso-10646-ucs-2" | "ucs-2" | "unicode" | "unicodefeff" | "utf-16"
+            | "utf-16le" => Encoding::Utf16Le,
+            "csshiftjis" | "ms932" | "ms_kanji" | "shift-jis" | "shift_jis" | "sjis"
+            | "windows-31j" | "x-sjis" => Encoding::ShiftJis,
+            "cseucpkdfmtjapanese" | "euc-jp" | "x-euc-jp" => Encoding::EucJp,
+            "csiso2022jp" | "iso-2022-jp" => Encoding::Iso2022Jp,
+            "ansi_x3.4-1968" | "ascii" | "cp1252" | "cp819" | "csisolatin1" | "ibm819"
+            | "iso-8859-1" | "iso-ir-100" | "iso8859-1" | "iso88591" | "iso_8859-1"
+            | "iso_8859-1:1987" | "l1" | "latin1" | "us-ascii" | "windows-1252" | "x-cp1252" => {
+                Encoding::Windows1252
+            }
+            _ => return None,
+        };
+        Some(encoding)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf16Be => "UTF-16BE",
+            Encoding::Utf16Le => "UTF-16LE",
+            Encoding::ShiftJis => "Shift_JIS",
+            Encoding::EucJp => "EUC-JP",
+            Encoding::Iso2022Jp => "ISO-2022-JP",
+            Encoding::Windows1252 => "windows-1252",
+        }
+    }
+
+    /// バイト列の先頭にあるBOMからエンコーディングを判定します
+    /// https://encoding.spec.whatwg.org/#bom-sniff
+    pub fn sniff_bom(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [0xEF, 0xBB, 0xBF, ..] => Some(Encoding::Utf8),
+            [0xFE, 0xFF, ..] => Some(Encoding::Utf16Be),
+            [0xFF, 0xFE, ..] => Some(Encoding::Utf16Le),
+            _ => None,
+        }
+    }
+
+    fn bom_len(&self) -> usize {
+        match self {
+            Encoding::Utf8 => 3,
+            Encoding::Utf16Be | Encoding::Utf16Le => 2,
+            _ => 0,
+        }
+    }
+
+    /// バイト列をデコードします。先頭にBOMがある場合はBOMが示すエンコーディングを優先します
+    /// https://encoding.spec.whatwg.org/#decode
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        match Self::sniff_bom(bytes) {
+            Some(encoding) => encoding.decode_without_bom(&bytes[encoding.bom_len()..]),
+            None => self.decode_without_bom(bytes),
+        }
+    }
+
+    /// BOMを考慮せずにバイト列をデコードします。不正なバイト列はU+FFFDに置き換えます
+    pub fn decode_without_bom(&self, bytes: &[u8]) -> String {
+        match self {
+            // Rustの置き換えの方法はEncoding StandardのUTF-8デコーダーと同じ
+            Encoding::Utf8 => String::from_utf8_lossy(bytes).into_owned(),
+            Encoding::Utf16Be => utf16::decode(bytes, true),
+            Encoding::Utf16Le => utf16::decode(bytes, false),
+            Encoding::ShiftJis => japanese::decode_shift_jis(bytes),
+            Encoding::EucJp => japanese::decode_euc_jp(bytes),
+            Encoding::Iso2022Jp => japanese::decode_iso_2022_jp(bytes),
+            Encoding::Windows1252 => bytes
+                .iter()
+                .map(|&b| match b {
+                    0x80..=0x9F => WINDOWS_1252_C1[(b - 0x80) as usize],
+                    _ => b as char,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Display for Encoding {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// 分割して届くバイト列を順にデコードするデコーダ
+/// 文字の途中で分割されたバイト列を別々にデコードしないように、文字の区切りだとわかる位置までをデコードし、
+/// 残りは次に届いたバイト列と合わせてデコードします
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decoder {
+    encoding: Encoding,
+    /// まだデコードしていないバイト列
+    pending: Vec<u8>,
+    /// 先頭のBOMを確認したかどうか
+    bom_checked: bool,
+    /// ISO-2022-JPのエスケープシーケンスをすでに読んだかどうか
+    escaped: bool,
+}
+
+impl Decoder {
+    pub fn new(encoding: Encoding) -> Self {
+        Self {
+            encoding,
+            pending: Vec::new(),
+            bom_checked: false,
+            escaped: false,
+        }
+    }
+
+    /// デコードに使うエンコーディングを返します。先頭にBOMがあればBOMが示すエンコーディングになります
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// バイト列の続きを受け取り、デコードできたところまでの文字列を返します
+    pub fn decode(&mut self, bytes: &[u8]) -> String {
+        self.pending.extend_from_slice(bytes);
+        // BOMかどうかを判断できるだけのバイト列が届くまで待つ
+        if !self.bom_checked && self.pending.len() < 3 {
+            return String::new();
+        }
+        self.check_bom();
+
+        let len = self.complete_len();
+        let complete: Vec<u8> = self.pending.drain(..len).collect();
+        if self.encoding == Encoding::Iso2022Jp && complete.contains(&0x1B) {
+            self.escaped = true;
+        }
+        self.encoding.decode_without_bom(&complete)
+    }
+
+    /// 残りのバイト列をすべてデコードします
+    pub fn finish(&mut self) -> String {
+        self.check_bom();
+        let rest: Vec<u8> = self.pending.drain(..).collect();
+        self.encoding.decode_without_bom(&rest)
+    }
+
+    fn check_bom(&mut self) {
+        if self.bom_checked {
+            return;
+        }
+        self.bom_checked = true;
+        if let Some(encoding) = Encoding::sniff_bom(&self.pending) {
+            self.encoding = encoding;
+            self.pending.drain(..encoding.bom_len());
+        }
+    }
+
+    /// まだデコードしていないバイト列のうち、文字の区切りまでのバイト数を返します
+    fn complete_len(&self) -> usize {
+        let bytes = &self.pending;
+        match self.encoding {
+            Encoding::Utf16Be | Encoding::Utf16Le => {
+                let mut len = bytes.len() & !1;
+                if len >= 2 {
+                    let unit = match self.encoding {
+                        Encoding::Utf16Be => u16::from_be_bytes([bytes[len - 2], bytes[len - 1]]),
+                        _ => u16::from_le_bytes([bytes[len - 2], bytes[len - 1]]),
+                    };
+                    // サロゲートペアの上位サロゲートは、下位サロゲートと合わせてデコードする
+                    if (0xD800..0xDC00).contains(&unit) {
+                        len -= 2;
+                    }
+                }
+                len
+            }
+            // 前のエスケープシーケンスで切り替わった状態に依存するため、エスケープシーケンスの直前で区切る
+            Encoding::Iso2022Jp => match bytes.iter().rposition(|&b| b == 0x1B) {
+                Some(pos) => pos,
+                None if self.escaped => 0,
+                None => bytes.len(),
+            },
+            Encoding::Windows1252 => bytes.len(),
+            Encoding::Utf8 => {
+                // 末尾で途切れている文字があれば、その先頭のバイトの前で区切る
+                let tail = bytes.len().saturating_sub(3);
+                let sequence_len = |b: u8| match b {
+                    0xC0..=0xDF => 2,
+                    0xE0..=0xEF => 3,
+                    0xF0..=0xF7 => 4,
+                    _ => 1,
+                };
+                match (tail..bytes.len()).rev().find(|&i| bytes[i] & 0xC0 != 0x80) {
+                    Some(pos) if bytes.len() - pos < sequence_len(bytes[pos]) => pos,
+                    _ => bytes.len(),
+                }
+            }
+            Encoding::ShiftJis | Encoding::EucJp => {
+                // 0x40未満のバイトは複数バイトの文字の一部にならないため、その直後から文字の長さをたどる
+                let sequence_len = |b: u8| match (self.encoding, b) {
+                    (Encoding::ShiftJis, 0x81..=0x9F | 0xE0..=0xFC) => 2,
+                    (Encoding::EucJp, 0x8F) => 3,
+                    (Encoding::EucJp, 0x8E | 0xA1..=0xFE) => 2,
+                    _ => 1,
+                };
+                let mut pos = bytes
+                    .iter()
+                    .rposition(|&b| b < 0x40)
+                    .map_or(0, |pos| pos + 1);
+                while pos < bytes.len() && pos + sequence_len(bytes[pos]) <= bytes.len() {
+                    pos += sequence_len(bytes[pos]);
+                }
+                pos
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_for_label() {
+        assert_eq!(
+            Some(Encoding::ShiftJis),
+            Encoding::for_label(" Shift_JIS\n")
+        );
+        assert_eq!(Some(Encoding::ShiftJis), Encoding::for_label("windows-31j"));
+        assert_eq!(Some(Encoding::EucJp), Encoding::for_label("EUC-JP"));
+        assert_eq!(Some(Encoding::Utf16Le), Encoding::for_label("utf-16"));
+        assert_eq!(
+            Some(Encoding::Windows1252),
+            Encoding::for_label("ISO-8859-1")
+        );
+        assert_eq!(None, Encoding::for_label("utf-7"));
+    }
+
+    #[test]
+    fn test_decode_with_bom() {
+        assert_eq!("a", Encoding::ShiftJis.decode(b"\xEF\xBB\xBFa"));
+        assert_eq!("a", Encoding::Utf8.decode(b"\xFE\xFF\x00a"));
+        assert_eq!("a", Encoding::Utf8.decode(b"\xFF\xFEa\x00"));
+    }
+
+    #[test]
+    fn test_utf8() {
+        assert_eq!("あ\u{FFFD}", Encoding::Utf8.decode(b"\xE3\x81\x82\xE3\x81"));
+    }
+
+    /// 1バイトずつ渡してデコードした結果を返します
+    fn decode_bytewise(encoding: Encoding, bytes: &[u8]) -> String {
+        let mut decoder = Decoder::new(encoding);
+        let mut output = String::new();
+        for b in bytes {
+            output.push_str(&decoder.decode(&[*b]));
+        }
+        output.push_str(&decoder.finish());
+        output
+    }
+
+    #[test]
+    fn test_decoder() {
+        assert_eq!(
+            "<p>日本語</p>",
+            decode_bytewise(Encoding::Utf8, "<p>日本語</p>".as_bytes())
+        );
+        assert_eq!(
+            "<p>日本</p>",
+            decode_bytewise(Encoding::ShiftJis, b"<p>\x93\xfa\x96\x7b</p>")
+        );
+        assert_eq!(
+            "<p>日本</p>",
+            decode_bytewise(Encoding::EucJp, b"<p>\xc6\xfc\xcb\xdc</p>")
+        );
+        assert_eq!(
+            "<p>日本</p>",
+            decode_bytewise(Encoding::Iso2022Jp, b"<p>\x1b$BF|K\\\x1b(B</p>")
+        );
+        assert_eq!(
+            "a\u{1F600}",
+            decode_bytewise(Encoding::Utf16Le, b"a\x00\x3d\xd8\x00\xde")
+        );
+        assert_eq!("a", decode_bytewise(Encoding::ShiftJis, b"\xEF\xBB\xBFa"));
+        // 途切れたままの文字は最後にU+FFFDになる
+        assert_eq!(
+            "あ\u{FFFD}",
+            decode_bytewise(Encoding::Utf8, b"\xE3\x81\x82\xE3\x81")
+        );
+
+        let mut decoder = Decoder::new(Encoding::ShiftJis);
+        assert_eq!("", decoder.decode(b"\xFE\xFF"));
+        assert_eq!("a", decoder.decode(b"\x00a"));
+        assert_eq!(Encoding::Utf16Be, decoder.encoding());
+    }
+
+    #[test]
+    fn test_windows_1252() {
+        assert_eq!("€ é", Encoding::Windows1252.decode(b"\x80 \xE9"));
+    }
+}
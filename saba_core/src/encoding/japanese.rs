@@ -0,0 +1,311 @@
+//! 日本語のレガシーエンコーディングのデコーダー
+//! https://encoding.spec.whatwg.org/#legacy-multi-byte-japanese-encodings
+
+use alloc::string::String;
+
+use super::{jis0208, jis0212, REPLACEMENT_CHARACTER};
+
+/// 半角カタカナ（JIS X 0201の片仮名）をコードポイントに変換します
+fn katakana(offset: u8) -> char {
+    char::from_u32(0xFF61 + offset as u32).unwrap_or(REPLACEMENT_CHARACTER)
+}
+
+/// https://encoding.spec.whatwg.org/#shift_jis-decoder
+pub fn decode_shift_jis(bytes: &[u8]) -> String {
+    let mut output = String::new();
+    let mut lead: u8 = 0;
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        let byte = bytes[pos];
+        pos += 1;
+
+        if lead != 0 {
+            let offset = if byte < 0x7F { 0x40 } else { 0x41 };
+            let lead_offset = if lead < 0xA0 { 0x81 } else { 0xC1 };
+            let pointer = match byte {
+                0x40..=0x7E | 0x80..=0xFC => {
+                    Some((lead - lead_offset) as usize * 188 + (byte - offset) as usize)
+                }
+                _ => None,
+            };
+            lead = 0;
+
+            let code_point = pointer.and_then(|pointer| match pointer {
+                // 利用者外字領域は私用領域に割り当てる
+                8836..=10715 => char::from_u32(0xE000 - 8836 + pointer as u32),
+                _ => jis0208::code_point(pointer),
+            });
+            match code_point {
+                Some(c) => output.push(c),
+                None => {
+                    // 後続バイトがASCIIの場合は、そのバイトを改めて処理する
+                    if byte.is_ascii() {
+                        pos -= 1;
+                    }
+                    output.push(REPLACEMENT_CHARACTER);
+                }
+            }
+            continue;
+        }
+
+        match byte {
+            0x00..=0x80 => output.push(byte as char),
+            0xA1..=0xDF => output.push(katakana(byte - 0xA1)),
+            0x81..=0x9F | 0xE0..=0xFC => lead = byte,
+            _ => output.push(REPLACEMENT_CHARACTER),
+        }
+    }
+
+    if lead != 0 {
+        output.push(REPLACEMENT_CHARACTER);
+    }
+    output
+}
+
+/// https://encoding.spec.whatwg.org/#euc-jp-decoder
+pub fn decode_euc_jp(bytes: &[u8]) -> String {
+    let mut output = String::new();
+    let mut lead: u8 = 0;
+    let mut jis0212 = false;
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        let byte = bytes[pos];
+        pos += 1;
+
+        if lead == 0x8E && (0xA1..=0xDF).contains(&byte) {
+            lead = 0;
+            output.push(katakana(byte - 0xA1));
+            continue;
+        }
+
+        if lead == 0x8F && (0xA1..=0xFE).contains(&byte) {
+            jis0212 = true;
+            lead = byte;
+            continue;
+        }
+
+        if lead != 0 {
+            let mut code_point = None;
+            if (0xA1..=0xFE).contains(&lead) && (0xA1..=0xFE).contains(&byte) {
+                let pointer = (lead - 0xA1) as usize * 94 + (byte - 0xA1) as usize;
+                code_point = if jis0212 {
+                    jis0212::code_point(pointer)
+                } else {
+                    jis0208::code_point(pointer)
+                };
+            }
+            lead = 0;
+            jis0212 = false;
+
+            match code_point {
+                Some(c) => output.push(c),
+                None => {
+                    if byte.is_ascii() {
+                        pos -= 1;
+                    }
+                    output.push(REPLACEMENT_CHARACTER);
+                }
+            }
+            continue;
+        }
+
+        match byte {
+            0x00..=0x7F => output.push(byte as char),
+            0x8E | 0x8F | 0xA1..=0xFE => lead = byte,
+            _ => output.push(REPLACEMENT_CHARACTER),
+        }
+    }
+
+    if lead != 0 {
+        output.push(REPLACEMENT_CHARACTER);
+    }
+    output
+}
+
+/// https://encoding.spec.whatwg.org/#iso-2022-jp-decoder-state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Iso2022JpState {
+    Ascii,
+    Roman,
+    Katakana,
+    LeadByte,
+    TrailByte,
+    EscapeStart,
+    Escape,
+}
+
+/// https://encoding.spec.whatwg.org/#iso-2022-jp-decoder
+pub fn decode_iso_2022_jp(bytes: &[u8]) -> String {
+    let mut output = String::new();
+    let mut state = Iso2022JpState::Ascii;
+    let mut output_state = Iso2022JpState::Ascii;
+    let mut lead: u8 = 0;
+    // 直前にエスケープシーケンスを処理して、まだ文字を出力していないかどうか
+    let mut output_flag = false;
+    let mut pos = 0;
+
+    // 入力の終わりでもエスケープシーケンスの途中であれば処理を続ける必要があるため、
+    // 入力の終わりをNoneとして扱う
+    loop {
+        let byte = bytes.get(pos).copied();
+        pos += 1;
+
+        match state {
+            Iso2022JpState::Ascii | Iso2022JpState::Roman => match byte {
+                None => break,
+                Some(0x1B) => state = Iso2022JpState::EscapeStart,
+                Some(0x0E) | Some(0x0F) | Some(0x80..=0xFF) => {
+                    output_flag = false;
+                    output.push(REPLACEMENT_CHARACTER);
+                }
+                Some(b) => {
+                    output_flag = false;
+                    let c = match (state, b) {
+                        (Iso2022JpState::Roman, 0x5C) => '\u{A5}',
+                        (Iso2022JpState::Roman, 0x7E) => '\u{203E}',
+                        _ => b as char,
+                    };
+                    output.push(c);
+                }
+            },
+            Iso2022JpState::Katakana => match byte {
+                None => break,
+                Some(0x1B) => state = Iso2022JpState::EscapeStart,
+                Some(b @ 0x21..=0x5F) => {
+                    output_flag = false;
+                    output.push(katakana(b - 0x21));
+                }
+                Some(_) => {
+                    output_flag = false;
+                    output.push(REPLACEMENT_CHARACTER);
+                }
+            },
+            Iso2022JpState::LeadByte => match byte {
+                None => break,
+                Some(0x1B) => state = Iso2022JpState::EscapeStart,
+                Some(b @ 0x21..=0x7E) => {
+                    output_flag = false;
+                    lead = b;
+                    state = Iso2022JpState::TrailByte;
+                }
+                Some(_) => {
+                    output_flag = false;
+                    output.push(REPLACEMENT_CHARACTER);
+                }
+            },
+            Iso2022JpState::TrailByte => {
+                match byte {
+                    Some(0x1B) => state = Iso2022JpState::EscapeStart,
+                    Some(b @ 0x21..=0x7E) => {
+                        state = Iso2022JpState::LeadByte;
+                        let pointer = (lead - 0x21) as usize * 94 + (b - 0x21) as usize;
+                        if let Some(c) = jis0208::code_point(pointer) {
+                            output.push(c);
+                            continue;
+                        }
+                    }
+                    // 入力の終わりでも一度だけエラーにして、次は先頭バイトの状態で終了する
+                    None => {
+                        state = Iso2022JpState::LeadByte;
+                        pos -= 1;
+                    }
+                    Some(_) => state = Iso2022JpState::LeadByte,
+                }
+                output.push(REPLACEMENT_CHARACTER);
+            }
+            Iso2022JpState::EscapeStart => match byte {
+                Some(b @ (0x24 | 0x28)) => {
+                    lead = b;
+                    state = Iso2022JpState::Escape;
+                }
+                _ => {
+                    // ESCの次のバイトは、エスケープシーケンスの前の状態で改めて処理する
+                    pos -= 1;
+                    output_flag = false;
+                    state = output_state;
+                    output.push(REPLACEMENT_CHARACTER);
+                }
+            },
+            Iso2022JpState::Escape => {
+                let next_state = match (lead, byte) {
+                    (0x28, Some(0x42)) => Some(Iso2022JpState::Ascii),
+                    (0x28, Some(0x4A)) => Some(Iso2022JpState::Roman),
+                    (0x28, Some(0x49)) => Some(Iso2022JpState::Katakana),
+                    (0x24, Some(0x40 | 0x42)) => Some(Iso2022JpState::LeadByte),
+                    _ => None,
+                };
+                lead = 0;
+
+                match next_state {
+                    Some(next_state) => {
+                        state = next_state;
+                        output_state = next_state;
+                        // 文字を挟まずにエスケープシーケンスが続いた場合はエラーにする
+                        if output_flag {
+                            output.push(REPLACEMENT_CHARACTER);
+                        }
+                        output_flag = true;
+                    }
+                    None => {
+                        // ESCの次のバイトから改めて処理する
+                        pos -= 2;
+                        output_flag = false;
+                        state = output_state;
+                        output.push(REPLACEMENT_CHARACTER);
+                    }
+                }
+            }
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shift_jis() {
+        assert_eq!(
+            "日本語ｶﾅ a①",
+            decode_shift_jis(b"\x93\xfa\x96\x7b\x8c\xea\xb6\xc5 a\x87\x40")
+        );
+    }
+
+    #[test]
+    fn test_shift_jis_invalid() {
+        // 後続バイトがASCIIの場合はそのバイトを残す
+        assert_eq!("\u{FFFD}\n", decode_shift_jis(b"\x93\n"));
+        assert_eq!("\u{FFFD}", decode_shift_jis(b"\x93"));
+        assert_eq!("\u{E000}", decode_shift_jis(b"\xF0\x40"));
+    }
+
+    #[test]
+    fn test_euc_jp() {
+        assert_eq!(
+            "日本語ｶﾅ a丂",
+            decode_euc_jp(b"\xc6\xfc\xcb\xdc\xb8\xec\x8e\xb6\x8e\xc5 a\x8f\xb0\xa1")
+        );
+        assert_eq!("\u{FFFD}a", decode_euc_jp(b"\xc6a"));
+    }
+
+    #[test]
+    fn test_iso_2022_jp() {
+        assert_eq!(
+            "a日本語ｶ\\¥",
+            decode_iso_2022_jp(b"a\x1b$BF|K\\8l\x1b(I6\x1b(B\\\x1b(J\\")
+        );
+    }
+
+    #[test]
+    fn test_iso_2022_jp_invalid() {
+        // 文字を挟まずに続いたエスケープシーケンス
+        assert_eq!("\u{FFFD}a", decode_iso_2022_jp(b"\x1b$B\x1b(Ba"));
+        // 不明なエスケープシーケンスはESCだけをエラーにする
+        assert_eq!("\u{FFFD}(Za", decode_iso_2022_jp(b"\x1b(Za"));
+        assert_eq!("\u{FFFD}", decode_iso_2022_jp(b"\x1b$BF"));
+    }
+}
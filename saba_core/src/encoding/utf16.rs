@@ -0,0 +1,41 @@
+use alloc::string::String;
+
+use super::REPLACEMENT_CHARACTER;
+
+/// UTF-16のバイト列をデコードします
+/// 対になっていないサロゲートと末尾の余ったバイトはU+FFFDに置き換えます
+/// https://encoding.spec.whatwg.org/#shared-utf-16-decoder
+pub fn decode(bytes: &[u8], big_endian: bool) -> String {
+    let units = bytes.chunks_exact(2).map(|pair| {
+        if big_endian {
+            u16::from_be_bytes([pair[0], pair[1]])
+        } else {
+            u16::from_le_bytes([pair[0], pair[1]])
+        }
+    });
+
+    let mut output: String = char::decode_utf16(units)
+        .map(|c| c.unwrap_or(REPLACEMENT_CHARACTER))
+        .collect();
+    if bytes.len() % 2 != 0 {
+        output.push(REPLACEMENT_CHARACTER);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        assert_eq!("aあ😀", decode(b"\x00a\x30\x42\xD8\x3D\xDE\x00", true));
+        assert_eq!("aあ😀", decode(b"a\x00\x42\x30\x3D\xD8\x00\xDE", false));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!("\u{FFFD}a", decode(b"\xD8\x3D\x00a", true));
+        assert_eq!("a\u{FFFD}", decode(b"\x00a\x00", true));
+    }
+}
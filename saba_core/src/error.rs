@@ -1,8 +1,12 @@
+use crate::http::HttpParseError;
+use crate::net::tls::TlsError;
 use alloc::string::String;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Error {
     Network(String),
+    Http(HttpParseError),
+    Tls(TlsError),
     UnexpectedInput(String),
     InvalidUI(String),
     Other(String),
@@ -0,0 +1,153 @@
+//! ナビゲーションに失敗したときに表示する、ブラウザが生成するエラーページ
+
+use alloc::format;
+use alloc::string::String;
+
+use crate::error::Error;
+
+/// テキストをHTMLの文字データや属性値として埋め込めるようにエスケープします
+/// https://html.spec.whatwg.org/multipage/parsing.html#escapingString
+fn escape_html(s: &str) -> String {
+    let mut escaped = String::new();
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\u{00A0}' => escaped.push_str("&nbsp;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// エラーの種類を表す見出しを返します
+pub fn error_kind(error: &Error) -> &'static str {
+    match error {
+        Error::Network(_) => "Network error",
+        Error::Http(_) => "Invalid response",
+        Error::Tls(_) => "Secure connection failed",
+        Error::UnexpectedInput(_) => "Invalid request",
+        Error::InvalidUI(_) => "Browser error",
+        Error::Other(_) => "Error",
+    }
+}
+
+fn error_detail(error: &Error) -> String {
+    match error {
+        Error::Network(message)
+        | Error::UnexpectedInput(message)
+        | Error::InvalidUI(message)
+        | Error::Other(message) => message.clone(),
+        Error::Http(e) => format!("{:?}", e),
+        Error::Tls(e) => format!("{:?}", e),
+    }
+}
+
+fn render(url: &str, kind: &str, detail: &str) -> String {
+    let url = escape_html(url);
+    format!(
+        concat!(
+            "<html><head></head><body>",
+            "<h1>{kind}</h1>",
+            "<p>saba could not load {url}</p>",
+            "<p>{detail}</p>",
+            "<p><a href=\"{url}\">Retry</a></p>",
+            "</body></html>"
+        ),
+        kind = escape_html(kind),
+        url = url,
+        detail = escape_html(detail),
+    )
+}
+
+/// urlへのナビゲーションがerrorで失敗したことを伝えるページのHTMLを返します
+pub fn error_page(url: &str, error: &Error) -> String {
+    render(url, error_kind(error), &error_detail(error))
+}
+
+/// サーバーがエラーの状態コード（4xxまたは5xx）を返したことを伝えるページのHTMLを返します
+/// https://www.rfc-editor.org/rfc/rfc9110#section-15.5
+pub fn http_error_page(url: &str, status_code: u32, reason: &str) -> String {
+    let kind = if status_code >= 500 {
+        "Server error"
+    } else {
+        "Client error"
+    };
+    render(
+        url,
+        kind,
+        &format!("The server responded with {} {}", status_code, reason),
+    )
+}
+
+/// 受信したコンテンツがブラウザで表示できない種類であることを伝えるページのHTMLを返します
+pub fn unsupported_content_page(url: &str, mime_type: &str) -> String {
+    render(
+        url,
+        "Unsupported content",
+        &format!("saba cannot display content of type {}", mime_type),
+    )
+}
+
+/// 本文の受信が途中でerrorにより失敗したとき、表示済みのページの末尾に添える文を返します
+/// HTMLではなく、テキストノードにそのまま入れる文字列です
+pub fn interrupted_notice(error: &Error) -> String {
+    format!(
+        "saba could not load the rest of this page. {}: {}",
+        error_kind(error),
+        error_detail(error)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_error_page() {
+        let html = error_page(
+            "http://example.com/",
+            &Error::Network("connection refused".to_string()),
+        );
+        assert!(html.contains("<h1>Network error</h1>"));
+        assert!(html.contains("<p>connection refused</p>"));
+        assert!(html.contains("<a href=\"http://example.com/\">Retry</a>"));
+    }
+
+    #[test]
+    fn test_http_error_page() {
+        let html = http_error_page("http://example.com/missing", 404, "Not Found");
+        assert!(html.contains("<h1>Client error</h1>"));
+        assert!(html.contains("404 Not Found"));
+        let html = http_error_page("http://example.com/", 503, "Service Unavailable");
+        assert!(html.contains("<h1>Server error</h1>"));
+    }
+
+    #[test]
+    fn test_interrupted_notice() {
+        let notice = interrupted_notice(&Error::Network("connection reset".to_string()));
+        assert!(notice.contains("Network error"));
+        assert!(notice.contains("connection reset"));
+    }
+
+    #[test]
+    fn test_unsupported_content_page() {
+        let html = unsupported_content_page("http://example.com/a.png", "image/png");
+        assert!(html.contains("<h1>Unsupported content</h1>"));
+        assert!(html.contains("image/png"));
+    }
+
+    #[test]
+    fn test_escape() {
+        let html = error_page(
+            "http://example.com/?a=1&b=\"><script>",
+            &Error::UnexpectedInput("<invalid>".to_string()),
+        );
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("href=\"http://example.com/?a=1&amp;b=&quot;&gt;&lt;script&gt;\""));
+        assert!(html.contains("<p>&lt;invalid&gt;</p>"));
+    }
+}
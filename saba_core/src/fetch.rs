@@ -0,0 +1,241 @@
+pub mod about;
+pub mod data;
+pub mod file;
+#[cfg(any(test, feature = "std"))]
+pub mod std_file;
+
+use alloc::{format, vec::Vec};
+
+use crate::{
+    error::Error,
+    http::{HttpResponse, Method},
+    url::Url,
+};
+
+use self::file::FileSystem;
+
+/// URLのスキームに応じてレスポンスを取得します
+/// http:とhttps:のURLは引数のhttp_fetchに委譲し、それ以外のスキームはブラウザ内でレスポンスを生成します
+/// https://fetch.spec.whatwg.org/#scheme-fetch
+pub fn scheme_fetch<F>(
+    url: &Url,
+    file_system: &dyn FileSystem,
+    http_fetch: F,
+) -> Result<HttpResponse, Error>
+where
+    F: FnOnce(&Url) -> Result<HttpResponse, Error>,
+{
+    match url.scheme().as_str() {
+        "about" => about::fetch(url),
+        "data" => data::fetch(url),
+        "file" => file::fetch(url, file_system),
+        "http" | "https" => http_fetch(url),
+        scheme => Err(Error::UnexpectedInput(format!(
+            "{} scheme is not supported",
+            scheme
+        ))),
+    }
+}
+
+/// リダイレクトをたどる回数の上限の既定値
+/// https://fetch.spec.whatwg.org/#http-redirect-fetch
+pub const DEFAULT_REDIRECT_LIMIT: usize = 20;
+
+/// https://fetch.spec.whatwg.org/#redirect-status
+fn is_redirect_status(status_code: u32) -> bool {
+    matches!(status_code, 301 | 302 | 303 | 307 | 308)
+}
+
+/// URLを取得し、リダイレクトのレスポンスであればLocationヘッダのURLを上限回数までたどります
+/// 返すレスポンスのurl()はリダイレクト後の最終的なURLになります
+/// https://fetch.spec.whatwg.org/#http-redirect-fetch
+pub fn fetch<F>(
+    url: &Url,
+    method: Method,
+    file_system: &dyn FileSystem,
+    redirect_limit: usize,
+    mut http_fetch: F,
+) -> Result<HttpResponse, Error>
+where
+    F: FnMut(Method, &Url) -> Result<HttpResponse, Error>,
+{
+    let mut url_list = Vec::new();
+    let mut url = url.clone();
+    let mut method = method;
+
+    loop {
+        let mut response = scheme_fetch(&url, file_system, |url| http_fetch(method, url))?;
+        url_list.push(url.clone());
+
+        let location = match response.header_value("Location") {
+            Ok(location) if is_redirect_status(response.status_code()) => location,
+            _ => {
+                response.set_url_list(url_list);
+                return Ok(response);
+            }
+        };
+
+        if url_list.len() > redirect_limit {
+            return Err(Error::Network(format!(
+                "too many redirects: {}",
+                url_list[0]
+            )));
+        }
+
+        // 相対URLはリダイレクト元のURLを基準に解決する
+        let location_url = url
+            .join(&location)
+            .map_err(|e| Error::Network(format!("invalid Location header {}: {}", location, e)))?;
+        if !matches!(location_url.scheme().as_str(), "http" | "https") {
+            return Err(Error::Network(format!(
+                "redirect to {} is not allowed",
+                location_url
+            )));
+        }
+
+        // 303はHEAD以外をGETに変え、301と302はPOSTをGETに変える
+        let status_code = response.status_code();
+        if (status_code == 303 && method != Method::Head)
+            || ((status_code == 301 || status_code == 302) && method == Method::Post)
+        {
+            method = Method::Get;
+        }
+
+        url = location_url;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetch::file::MemoryFileSystem;
+    use crate::http::Header;
+    use alloc::string::{String, ToString};
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    fn fetch(url: &str, file_system: &dyn FileSystem) -> Result<HttpResponse, Error> {
+        let url = Url::new(url.to_string()).parse().expect("invalid url");
+        scheme_fetch(&url, file_system, |url| {
+            Ok(HttpResponse::from_parts(
+                200,
+                "OK",
+                Vec::new(),
+                format!("network: {}", url.href()).into_bytes(),
+            ))
+        })
+    }
+
+    #[test]
+    fn test_dispatch() {
+        let mut file_system = MemoryFileSystem::new();
+        file_system.add_file("/index.html", b"<p>file</p>".to_vec());
+
+        let body = |res: Result<HttpResponse, Error>| res.map(|r| r.body());
+        assert_eq!(Ok(String::new()), body(fetch("about:blank", &file_system)));
+        assert_eq!(
+            Ok("hello".to_string()),
+            body(fetch("data:,hello", &file_system))
+        );
+        assert_eq!(
+            Ok("<p>file</p>".to_string()),
+            body(fetch("file:///index.html", &file_system))
+        );
+        assert_eq!(
+            Ok("network: http://example.com/".to_string()),
+            body(fetch("http://example.com", &file_system))
+        );
+    }
+
+    fn redirect(status_code: u32, location: &str) -> HttpResponse {
+        HttpResponse::from_parts(
+            status_code,
+            "Redirect",
+            vec![Header::new("Location".to_string(), location.to_string())],
+            b"redirect body".to_vec(),
+        )
+    }
+
+    /// example.comのサーバーを模した関数
+    fn server(method: Method, url: &Url) -> Result<HttpResponse, Error> {
+        match url.path().as_str() {
+            "/moved" => Ok(redirect(301, "/docs/")),
+            "/docs/" => Ok(redirect(302, "page.html?q=1")),
+            "/form" => Ok(redirect(303, "http://example.org/done")),
+            "/temporary" => Ok(redirect(307, "/echo")),
+            "/loop" => Ok(redirect(302, "/loop")),
+            "/other-scheme" => Ok(redirect(302, "data:,hello")),
+            _ => Ok(HttpResponse::from_parts(
+                200,
+                "OK",
+                Vec::new(),
+                format!("{} {}", method, url).into_bytes(),
+            )),
+        }
+    }
+
+    fn fetch_with_redirects(url: &str, method: Method) -> Result<HttpResponse, Error> {
+        let url = Url::new(url.to_string()).parse().expect("invalid url");
+        super::fetch(
+            &url,
+            method,
+            &MemoryFileSystem::new(),
+            DEFAULT_REDIRECT_LIMIT,
+            server,
+        )
+    }
+
+    #[test]
+    fn test_follow_redirects() {
+        let res =
+            fetch_with_redirects("http://example.com/moved", Method::Get).expect("failed to fetch");
+        assert_eq!(200, res.status_code());
+        assert_eq!("GET http://example.com/docs/page.html?q=1", res.body());
+        assert_eq!(
+            Some("http://example.com/docs/page.html?q=1".to_string()),
+            res.url().map(|url| url.to_string())
+        );
+        assert_eq!(3, res.url_list().len());
+    }
+
+    #[test]
+    fn test_no_redirect() {
+        let res =
+            fetch_with_redirects("http://example.com/", Method::Get).expect("failed to fetch");
+        assert_eq!(
+            Some("http://example.com/".to_string()),
+            res.url().map(|url| url.to_string())
+        );
+    }
+
+    #[test]
+    fn test_method_change() {
+        let res =
+            fetch_with_redirects("http://example.com/form", Method::Post).expect("failed to fetch");
+        assert_eq!("GET http://example.org/done", res.body());
+
+        let res =
+            fetch_with_redirects("http://example.com/form", Method::Head).expect("failed to fetch");
+        assert_eq!("HEAD http://example.org/done", res.body());
+
+        let res = fetch_with_redirects("http://example.com/temporary", Method::Post)
+            .expect("failed to fetch");
+        assert_eq!("POST http://example.com/echo", res.body());
+    }
+
+    #[test]
+    fn test_redirect_limit() {
+        assert!(fetch_with_redirects("http://example.com/loop", Method::Get).is_err());
+
+        let url = Url::new("http://example.com/moved".to_string())
+            .parse()
+            .expect("invalid url");
+        assert!(super::fetch(&url, Method::Get, &MemoryFileSystem::new(), 1, server).is_err());
+        assert!(super::fetch(&url, Method::Get, &MemoryFileSystem::new(), 2, server).is_ok());
+    }
+
+    #[test]
+    fn test_redirect_to_non_http_scheme() {
+        assert!(fetch_with_redirects("http://example.com/other-scheme", Method::Get).is_err());
+    }
+}
@@ -0,0 +1,98 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+};
+
+use crate::{
+    error::Error,
+    http::{Header, HttpResponse},
+    url::Url,
+};
+
+/// ブラウザに組み込まれているabout:ページの名前と内容
+/// https://fetch.spec.whatwg.org/#scheme-fetch
+const ABOUT_PAGES: [(&str, &str); 3] = [
+    ("blank", ""),
+    (
+        "saba",
+        concat!(
+            "<html><head></head><body>",
+            "<h1>saba</h1>",
+            "<p>saba is a toy web browser written in Rust.</p>",
+            "<p>version ",
+            env!("CARGO_PKG_VERSION"),
+            "</p>",
+            "</body></html>"
+        ),
+    ),
+    // about:aboutの内容はページ一覧から生成する
+    ("about", ""),
+];
+
+fn about_about() -> String {
+    let mut html = String::from("<html><head></head><body><h1>about: pages</h1>");
+    for (name, _) in ABOUT_PAGES {
+        html.push_str(&format!("<p><a href=\"about:{0}\">about:{0}</a></p>", name));
+    }
+    html.push_str("</body></html>");
+    html
+}
+
+pub fn fetch(url: &Url) -> Result<HttpResponse, Error> {
+    let name = url.path();
+    let body = match ABOUT_PAGES.iter().find(|(page, _)| *page == name) {
+        Some(("about", _)) => about_about(),
+        Some((_, content)) => content.to_string(),
+        None => {
+            return Err(Error::UnexpectedInput(format!(
+                "unknown about page: {}",
+                url.href()
+            )))
+        }
+    };
+
+    Ok(HttpResponse::from_parts(
+        200,
+        "OK",
+        vec![Header::new(
+            "Content-Type".to_string(),
+            "text/html;charset=utf-8".to_string(),
+        )],
+        body.into_bytes(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn about(name: &str) -> Result<HttpResponse, Error> {
+        let url = Url::new(format!("about:{}", name))
+            .parse()
+            .expect("invalid url");
+        fetch(&url)
+    }
+
+    #[test]
+    fn test_blank() {
+        let res = about("blank").expect("failed to fetch about:blank");
+        assert_eq!(200, res.status_code());
+        assert_eq!("", res.body());
+        assert_eq!(
+            Ok("text/html;charset=utf-8".to_string()),
+            res.header_value("Content-Type")
+        );
+    }
+
+    #[test]
+    fn test_about() {
+        let res = about("about").expect("failed to fetch about:about");
+        assert!(res.body().contains("<a href=\"about:saba\">"));
+    }
+
+    #[test]
+    fn test_unknown() {
+        assert!(about("unknown").is_err());
+    }
+}
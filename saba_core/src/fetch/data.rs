@@ -0,0 +1,155 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use crate::{
+    base64,
+    error::Error,
+    http::{Header, HttpResponse},
+    mime::MimeType,
+    url::{percent_encoding::percent_decode, Url},
+};
+
+/// https://fetch.spec.whatwg.org/#data-url-struct
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataUrl {
+    mime_type: MimeType,
+    body: Vec<u8>,
+}
+
+impl DataUrl {
+    /// https://fetch.spec.whatwg.org/#data-url-processor
+    pub fn process(url: &Url) -> Result<Self, Error> {
+        if url.scheme() != "data" {
+            return Err(Error::UnexpectedInput(format!(
+                "{} is not a data: URL",
+                url.href()
+            )));
+        }
+
+        // フラグメントを除いたURLの"data:"より後ろ
+        let href = url.href();
+        let input = match url.fragment() {
+            Some(fragment) => &href[..href.len() - fragment.len() - 1],
+            None => &href,
+        };
+        let input = input["data:".len()..].trim_matches(|c: char| c.is_ascii_whitespace());
+
+        let (mime_type, encoded_body) = input.split_once(',').ok_or_else(|| {
+            Error::UnexpectedInput(format!("data: URL has no comma: {}", url.href()))
+        })?;
+        let mut mime_type = mime_type
+            .trim_matches(|c: char| c.is_ascii_whitespace())
+            .to_string();
+
+        let mut body = percent_decode(encoded_body.as_bytes());
+
+        if let Some(without_base64) = strip_base64_suffix(&mime_type) {
+            // 同型デコード: 各バイトをそのままコードポイントとみなす
+            let string_body: String = body.iter().map(|&b| b as char).collect();
+            body = base64::decode(&string_body)?;
+            mime_type = without_base64;
+        }
+
+        if mime_type.starts_with(';') {
+            mime_type.insert_str(0, "text/plain");
+        }
+
+        let mime_type = MimeType::parse(&mime_type).unwrap_or_else(|| {
+            let mut mime_type = MimeType::new("text", "plain");
+            mime_type.set_parameter("charset", "US-ASCII");
+            mime_type
+        });
+
+        Ok(Self { mime_type, body })
+    }
+
+    pub fn mime_type(&self) -> MimeType {
+        self.mime_type.clone()
+    }
+
+    pub fn body(&self) -> Vec<u8> {
+        self.body.clone()
+    }
+}
+
+/// MIMEタイプが";"、0個以上の空白、"base64"で終わっている場合、";"以降を取り除いた文字列を返します
+fn strip_base64_suffix(mime_type: &str) -> Option<String> {
+    let len = mime_type.len();
+    if len < 6 || !mime_type.is_char_boundary(len - 6) {
+        return None;
+    }
+    let (rest, suffix) = mime_type.split_at(len - 6);
+    if !suffix.eq_ignore_ascii_case("base64") {
+        return None;
+    }
+    rest.trim_end_matches(' ')
+        .strip_suffix(';')
+        .map(|rest| rest.to_string())
+}
+
+pub fn fetch(url: &Url) -> Result<HttpResponse, Error> {
+    let data_url = DataUrl::process(url)?;
+    Ok(HttpResponse::from_parts(
+        200,
+        "OK",
+        vec![Header::new(
+            "Content-Type".to_string(),
+            data_url.mime_type().to_string(),
+        )],
+        data_url.body,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(url: &str) -> Result<DataUrl, Error> {
+        let url = Url::new(url.to_string()).parse().expect("invalid url");
+        DataUrl::process(&url)
+    }
+
+    #[test]
+    fn test_plain() {
+        let data = process("data:,Hello%2C%20World!").expect("failed to process data: URL");
+        assert_eq!("text/plain;charset=US-ASCII", data.mime_type().to_string());
+        assert_eq!(b"Hello, World!".to_vec(), data.body());
+    }
+
+    #[test]
+    fn test_base64() {
+        let data = process("data:text/html;charset=utf-8 ; BASE64,PHA+aGk8L3A+#frag")
+            .expect("failed to process data: URL");
+        assert_eq!("text/html;charset=utf-8", data.mime_type().to_string());
+        assert_eq!(b"<p>hi</p>".to_vec(), data.body());
+    }
+
+    #[test]
+    fn test_parameter_only() {
+        let data = process("data:;charset=utf-8,x").expect("failed to process data: URL");
+        assert_eq!("text/plain;charset=utf-8", data.mime_type().to_string());
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(process("data:text/html").is_err());
+        assert!(process("data:;base64,a").is_err());
+    }
+
+    #[test]
+    fn test_fetch() {
+        let url = Url::new("data:text/html,<p>hi</p>".to_string())
+            .parse()
+            .expect("invalid url");
+        let res = fetch(&url).expect("failed to fetch data: URL");
+        assert_eq!(
+            Ok("text/html".to_string()),
+            res.header_value("Content-Type")
+        );
+        assert_eq!("<p>hi</p>", res.body());
+    }
+}
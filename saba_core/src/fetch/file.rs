@@ -0,0 +1,124 @@
+use alloc::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use crate::{
+    error::Error,
+    http::{Header, HttpResponse},
+    url::{host::Host, percent_encoding::percent_decode_to_string, Url},
+};
+
+/// file:スキームのURLを読み込むためのファイルシステム
+/// 実行環境ごとに実装を差し替えられるようにトレイトとして定義しています
+pub trait FileSystem {
+    /// 絶対パスで指定されたファイルの内容を返します
+    fn read(&self, path: &str) -> Result<Vec<u8>, Error>;
+}
+
+/// パスとファイルの内容をメモリ上に保持するファイルシステム
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryFileSystem {
+    files: BTreeMap<String, Vec<u8>>,
+}
+
+impl MemoryFileSystem {
+    pub fn new() -> Self {
+        Self {
+            files: BTreeMap::new(),
+        }
+    }
+
+    pub fn add_file(&mut self, path: &str, content: Vec<u8>) {
+        self.files.insert(path.to_string(), content);
+    }
+}
+
+impl FileSystem for MemoryFileSystem {
+    fn read(&self, path: &str) -> Result<Vec<u8>, Error> {
+        self.files
+            .get(path)
+            .cloned()
+            .ok_or_else(|| Error::Other(format!("file not found: {}", path)))
+    }
+}
+
+/// ファイルの拡張子からContent-Typeを推測します
+fn content_type(path: &str) -> &'static str {
+    let extension = path
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_ascii_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "js" => "text/javascript",
+        "txt" => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
+
+pub fn fetch(url: &Url, file_system: &dyn FileSystem) -> Result<HttpResponse, Error> {
+    match url.host_kind() {
+        Some(Host::Empty) | None => {}
+        Some(host) => {
+            return Err(Error::UnexpectedInput(format!(
+                "file: URL with a remote host is not supported: {}",
+                host
+            )))
+        }
+    }
+
+    let path = percent_decode_to_string(&url.path());
+    let content = file_system.read(&path)?;
+
+    Ok(HttpResponse::from_parts(
+        200,
+        "OK",
+        vec![Header::new(
+            "Content-Type".to_string(),
+            content_type(&path).to_string(),
+        )],
+        content,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file_system() -> MemoryFileSystem {
+        let mut file_system = MemoryFileSystem::new();
+        file_system.add_file("/pages/test page.html", b"<p>test</p>".to_vec());
+        file_system
+    }
+
+    fn fetch_file(url: &str) -> Result<HttpResponse, Error> {
+        let url = Url::new(url.to_string()).parse().expect("invalid url");
+        fetch(&url, &file_system())
+    }
+
+    #[test]
+    fn test_read() {
+        let res = fetch_file("file:///pages/test%20page.html").expect("failed to read a file");
+        assert_eq!(200, res.status_code());
+        assert_eq!("<p>test</p>", res.body());
+        assert_eq!(
+            Ok("text/html".to_string()),
+            res.header_value("Content-Type")
+        );
+    }
+
+    #[test]
+    fn test_not_found() {
+        assert!(fetch_file("file:///pages/missing.html").is_err());
+    }
+
+    #[test]
+    fn test_remote_host() {
+        assert!(fetch_file("file://server/pages/test%20page.html").is_err());
+    }
+}
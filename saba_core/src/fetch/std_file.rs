@@ -0,0 +1,57 @@
+//! std::fsを使ったファイルシステムの実装
+//! ホストOS上でfile:スキームのURLからローカルのファイルを読み込むために使います
+
+extern crate std;
+
+use alloc::{format, vec::Vec};
+
+use super::file::FileSystem;
+use crate::error::Error;
+
+/// ホストOSのファイルシステムからファイルを読み込みます
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StdFileSystem;
+
+impl StdFileSystem {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl FileSystem for StdFileSystem {
+    fn read(&self, path: &str) -> Result<Vec<u8>, Error> {
+        std::fs::read(path).map_err(|e| Error::Other(format!("failed to read {}: {}", path, e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetch::file::fetch;
+    use crate::url::Url;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_read() {
+        let dir = std::env::temp_dir().join("saba_std_file_test");
+        std::fs::create_dir_all(&dir).expect("failed to create a directory");
+        let path = dir.join("test page.html");
+        std::fs::write(&path, b"<p>local</p>").expect("failed to write a file");
+
+        let url = Url::new(format!(
+            "file://{}",
+            path.to_str().expect("invalid path").replace(' ', "%20")
+        ))
+        .parse()
+        .expect("invalid url");
+        let res = fetch(&url, &StdFileSystem::new()).expect("failed to read a file");
+        assert_eq!("<p>local</p>", res.body());
+        assert_eq!(
+            Ok("text/html".to_string()),
+            res.header_value("Content-Type")
+        );
+
+        std::fs::remove_file(&path).expect("failed to remove a file");
+        assert!(fetch(&url, &StdFileSystem::new()).is_err());
+    }
+}
@@ -0,0 +1,690 @@
+//! ブラウザ内のプライベートなHTTPキャッシュ
+//! https://www.rfc-editor.org/rfc/rfc9111
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use super::date::parse_http_date;
+use super::{Header, HttpRequest, HttpResponse, Method};
+use crate::error::Error;
+use crate::url::Url;
+
+/// キャッシュに保存するレスポンスの合計サイズの上限の既定値（バイト）
+pub const DEFAULT_CACHE_CAPACITY: usize = 4 * 1024 * 1024;
+
+/// 明示的な有効期限がなくても経験的に有効期限を決めてよい状態コード
+/// https://www.rfc-editor.org/rfc/rfc9110#section-15.1
+const HEURISTICALLY_CACHEABLE: [u32; 12] =
+    [200, 203, 204, 206, 300, 301, 308, 404, 405, 410, 414, 501];
+
+/// https://www.rfc-editor.org/rfc/rfc9111#section-5.2
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CacheControl {
+    directives: Vec<(String, Option<String>)>,
+}
+
+impl CacheControl {
+    fn parse(value: Option<String>) -> Self {
+        let directives = value
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|directive| {
+                let (name, value) = match directive.split_once('=') {
+                    Some((name, value)) => (name, Some(value.trim().trim_matches('"').to_string())),
+                    None => (directive, None),
+                };
+                let name = name.trim().to_ascii_lowercase();
+                if name.is_empty() {
+                    None
+                } else {
+                    Some((name, value))
+                }
+            })
+            .collect();
+        Self { directives }
+    }
+
+    fn has(&self, name: &str) -> bool {
+        self.directives.iter().any(|(n, _)| n == name)
+    }
+
+    fn seconds(&self, name: &str) -> Option<u64> {
+        self.directives
+            .iter()
+            .find(|(n, _)| n == name)
+            .and_then(|(_, value)| value.as_ref()?.parse::<u64>().ok())
+    }
+}
+
+/// キャッシュのキー。フラグメントを除いたURL
+/// https://www.rfc-editor.org/rfc/rfc9111#section-2
+fn cache_key(url: &Url) -> String {
+    let href = url.href();
+    match url.fragment() {
+        Some(fragment) => href[..href.len() - fragment.len() - 1].to_string(),
+        None => href,
+    }
+}
+
+fn response_size(response: &HttpResponse) -> usize {
+    response
+        .headers()
+        .iter()
+        .map(|h| h.name().len() + h.value().len())
+        .sum::<usize>()
+        + response.body_bytes().len()
+}
+
+#[derive(Debug, Clone)]
+struct CacheEntry {
+    key: String,
+    /// Varyヘッダで指定されたリクエストヘッダの名前と、保存したときのリクエストでの値
+    vary: Vec<(String, Option<String>)>,
+    response: HttpResponse,
+    /// レスポンスを受信した時刻
+    response_time: u64,
+    size: usize,
+}
+
+impl CacheEntry {
+    fn date(&self, name: &str) -> Option<u64> {
+        self.response
+            .header_value(name)
+            .ok()
+            .and_then(|value| parse_http_date(&value))
+    }
+
+    /// https://www.rfc-editor.org/rfc/rfc9111#section-4.2.1
+    fn freshness_lifetime(&self) -> u64 {
+        let cache_control = CacheControl::parse(self.response.header_value("Cache-Control").ok());
+        if let Some(max_age) = cache_control.seconds("max-age") {
+            return max_age;
+        }
+
+        let date = self.date("Date").unwrap_or(self.response_time);
+        if let Ok(expires) = self.response.header_value("Expires") {
+            // 解析できないExpiresはすでに期限切れとみなす
+            return parse_http_date(&expires)
+                .map(|expires| expires.saturating_sub(date))
+                .unwrap_or(0);
+        }
+
+        // 最終更新からの経過時間の10%を経験的な有効期間とする
+        // https://www.rfc-editor.org/rfc/rfc9111#section-4.2.2
+        match self.date("Last-Modified") {
+            Some(last_modified)
+                if HEURISTICALLY_CACHEABLE.contains(&self.response.status_code()) =>
+            {
+                date.saturating_sub(last_modified) / 10
+            }
+            _ => 0,
+        }
+    }
+
+    /// https://www.rfc-editor.org/rfc/rfc9111#section-4.2.3
+    fn current_age(&self, now: u64) -> u64 {
+        let apparent_age = self
+            .date("Date")
+            .map(|date| self.response_time.saturating_sub(date))
+            .unwrap_or(0);
+        let age_value = self
+            .response
+            .header_value("Age")
+            .ok()
+            .and_then(|age| age.trim().parse::<u64>().ok())
+            .unwrap_or(0);
+        apparent_age.max(age_value) + now.saturating_sub(self.response_time)
+    }
+
+    /// 検証せずにそのまま使えるかどうか
+    fn is_fresh(&self, now: u64) -> bool {
+        let cache_control = CacheControl::parse(self.response.header_value("Cache-Control").ok());
+        !cache_control.has("no-cache") && self.freshness_lifetime() > self.current_age(now)
+    }
+
+    /// 保存したレスポンスのETagやLast-Modifiedを使った条件付きリクエストを作ります
+    /// https://www.rfc-editor.org/rfc/rfc9111#section-4.3.1
+    fn conditional_request(&self, request: &HttpRequest) -> Option<HttpRequest> {
+        let etag = self.response.header_value("ETag").ok();
+        let last_modified = self.response.header_value("Last-Modified").ok();
+        if etag.is_none() && last_modified.is_none() {
+            return None;
+        }
+
+        let mut request = request.clone();
+        if let Some(etag) = etag {
+            request = request.with_header("If-None-Match", &etag);
+        }
+        if let Some(last_modified) = last_modified {
+            request = request.with_header("If-Modified-Since", &last_modified);
+        }
+        Some(request)
+    }
+
+    /// 304レスポンスのヘッダで保存したレスポンスのヘッダを更新します
+    /// https://www.rfc-editor.org/rfc/rfc9111#section-3.2
+    fn freshen(&mut self, not_modified: &HttpResponse, response_time: u64) {
+        // ボディの長さや転送の方法を表すヘッダは保存したボディに合わせたままにする
+        let updates: Vec<Header> = not_modified
+            .headers()
+            .into_iter()
+            .filter(|h| !is_framing_header(h))
+            .collect();
+        let mut headers = self.response.headers();
+        headers.retain(|h| {
+            !updates
+                .iter()
+                .any(|u| u.name().eq_ignore_ascii_case(&h.name()))
+        });
+        headers.extend(updates);
+
+        self.response.set_headers(headers);
+        self.response_time = response_time;
+        self.size = response_size(&self.response);
+    }
+}
+
+/// URLをキーにレスポンスを保存するHTTPキャッシュ
+/// 保存したレスポンスの合計サイズが上限を超えた場合は、最も長く使われていないものから削除します
+#[derive(Debug, Clone)]
+pub struct HttpCache {
+    /// 使われた順に並べたエントリ。末尾が最も最近使われたもの
+    entries: Vec<CacheEntry>,
+    capacity: usize,
+    size: usize,
+}
+
+impl HttpCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Vec::new(),
+            capacity,
+            size: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 保存しているレスポンスの合計サイズ
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.size = 0;
+    }
+
+    /// キャッシュを使ってリクエストを処理します
+    /// 新しいレスポンスが保存されていればそれを返し、そうでなければsendでリクエストを送信します
+    /// 古くなったレスポンスは条件付きリクエストで検証し、304が返ってきた場合は保存したレスポンスを返します
+    /// clockは現在時刻（1970年1月1日からの秒数）を返す関数です
+    /// レスポンスを受信した時刻はsendが返った後に読み直すため、sendの中で時計を進めても構いません
+    /// https://www.rfc-editor.org/rfc/rfc9111#section-4
+    pub fn fetch<F>(
+        &mut self,
+        request: &HttpRequest,
+        clock: &dyn Fn() -> u64,
+        send: F,
+    ) -> Result<HttpResponse, Error>
+    where
+        F: FnOnce(&HttpRequest) -> Result<HttpResponse, Error>,
+    {
+        let key = cache_key(&request.url());
+
+        if request.method() != Method::Get {
+            let response = send(request)?;
+            // 安全でないメソッドが成功した場合は、そのURLのレスポンスを無効にする
+            // https://www.rfc-editor.org/rfc/rfc9111#section-4.4
+            if request.method() != Method::Head && (200..400).contains(&response.status_code()) {
+                self.remove(&key);
+            }
+            return Ok(response);
+        }
+
+        let request_cache_control = CacheControl::parse(request.header_value("Cache-Control"));
+        if request_cache_control.has("no-store") {
+            return send(request);
+        }
+
+        let index = match self.find(&key, request) {
+            Some(index) => index,
+            None => {
+                let response = send(request)?;
+                self.store(key, request, &response, clock());
+                return Ok(response);
+            }
+        };
+
+        if !request_cache_control.has("no-cache") && self.entries[index].is_fresh(clock()) {
+            let entry = self.entries.remove(index);
+            let response = entry.response.clone();
+            self.entries.push(entry);
+            return Ok(response);
+        }
+
+        let conditional = match self.entries[index].conditional_request(request) {
+            Some(conditional) => conditional,
+            None => {
+                let response = send(request)?;
+                self.store(key, request, &response, clock());
+                return Ok(response);
+            }
+        };
+
+        let response = send(&conditional)?;
+        let response_time = clock();
+        if response.status_code() != 304 {
+            self.store(key, request, &response, response_time);
+            return Ok(response);
+        }
+
+        let mut entry = self.entries.remove(index);
+        self.size -= entry.size;
+        entry.freshen(&response, response_time);
+        let cached = entry.response.clone();
+        self.insert(entry);
+        Ok(cached)
+    }
+
+    fn find(&self, key: &str, request: &HttpRequest) -> Option<usize> {
+        self.entries.iter().position(|entry| {
+            entry.key == key
+                && entry
+                    .vary
+                    .iter()
+                    .all(|(name, value)| request.header_value(name) == *value)
+        })
+    }
+
+    fn remove(&mut self, key: &str) {
+        let size = &mut self.size;
+        self.entries.retain(|entry| {
+            if entry.key == key {
+                *size -= entry.size;
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    /// レスポンスが保存できるものであれば保存します
+    /// https://www.rfc-editor.org/rfc/rfc9111#section-3
+    fn store(
+        &mut self,
+        key: String,
+        request: &HttpRequest,
+        response: &HttpResponse,
+        response_time: u64,
+    ) {
+        if let Some(index) = self.find(&key, request) {
+            let entry = self.entries.remove(index);
+            self.size -= entry.size;
+        }
+
+        let cache_control = CacheControl::parse(response.header_value("Cache-Control").ok());
+        let has_explicit_expiration =
+            cache_control.has("max-age") || response.header_value("Expires").is_ok();
+        if cache_control.has("no-store")
+            || response.status_code() == 206
+            || !(has_explicit_expiration
+                || cache_control.has("public")
+                || HEURISTICALLY_CACHEABLE.contains(&response.status_code()))
+        {
+            return;
+        }
+
+        let mut vary = Vec::new();
+        if let Ok(value) = response.header_value("Vary") {
+            for name in value.split(',').map(|name| name.trim()) {
+                // Vary: *のレスポンスは再利用できない
+                if name == "*" {
+                    return;
+                }
+                if !name.is_empty() {
+                    vary.push((name.to_ascii_lowercase(), request.header_value(name)));
+                }
+            }
+        }
+
+        let entry = CacheEntry {
+            key,
+            vary,
+            response: response.clone(),
+            response_time,
+            size: response_size(response),
+        };
+        self.insert(entry);
+    }
+
+    /// エントリを追加し、合計サイズが上限を超えた分だけ古いエントリを削除します
+    fn insert(&mut self, entry: CacheEntry) {
+        if entry.size > self.capacity {
+            return;
+        }
+        while self.size + entry.size > self.capacity && !self.entries.is_empty() {
+            let evicted = self.entries.remove(0);
+            self.size -= evicted.size;
+        }
+        self.size += entry.size;
+        self.entries.push(entry);
+    }
+}
+
+/// 304レスポンスで更新しないヘッダかどうか
+fn is_framing_header(header: &Header) -> bool {
+    ["Content-Length", "Content-Encoding", "Transfer-Encoding"]
+        .iter()
+        .any(|n| n.eq_ignore_ascii_case(&header.name()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::format;
+    use alloc::vec;
+    use core::cell::{Cell, RefCell};
+
+    /// 2024年1月1日0時0分0秒(UTC)
+    const NOW: u64 = 1704067200;
+    const NOW_DATE: &str = "Mon, 01 Jan 2024 00:00:00 GMT";
+
+    fn request(url: &str) -> HttpRequest {
+        let url = Url::new(url.to_string()).parse().expect("invalid url");
+        HttpRequest::new(Method::Get, url)
+    }
+
+    fn response(status_code: u32, headers: &[(&str, &str)], body: &str) -> HttpResponse {
+        let headers = headers
+            .iter()
+            .map(|(name, value)| Header::new(name.to_string(), value.to_string()))
+            .collect();
+        HttpResponse::from_parts(status_code, "", headers, body.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn test_max_age() {
+        let mut cache = HttpCache::new(DEFAULT_CACHE_CAPACITY);
+        let sent = RefCell::new(0);
+        let send = |_: &HttpRequest| {
+            *sent.borrow_mut() += 1;
+            Ok(response(
+                200,
+                &[("Date", NOW_DATE), ("Cache-Control", "max-age=60")],
+                "hello",
+            ))
+        };
+
+        let req = request("http://example.com/index.html");
+        assert_eq!(
+            "hello",
+            cache.fetch(&req, &|| NOW, send).expect("fetch").body()
+        );
+        assert_eq!(
+            "hello",
+            cache.fetch(&req, &|| NOW + 59, send).expect("fetch").body()
+        );
+        // フラグメントはキーに含めない
+        let with_fragment = request("http://example.com/index.html#top");
+        cache
+            .fetch(&with_fragment, &|| NOW + 30, send)
+            .expect("fetch");
+        assert_eq!(1, *sent.borrow());
+        assert_eq!(1, cache.len());
+
+        cache.fetch(&req, &|| NOW + 60, send).expect("fetch");
+        assert_eq!(2, *sent.borrow());
+    }
+
+    #[test]
+    fn test_expires() {
+        let mut cache = HttpCache::new(DEFAULT_CACHE_CAPACITY);
+        let sent = RefCell::new(0);
+        let send = |_: &HttpRequest| {
+            *sent.borrow_mut() += 1;
+            Ok(response(
+                200,
+                &[
+                    ("Date", NOW_DATE),
+                    ("Expires", "Mon, 01 Jan 2024 00:10:00 GMT"),
+                ],
+                "hello",
+            ))
+        };
+
+        let req = request("http://example.com/");
+        cache.fetch(&req, &|| NOW, send).expect("fetch");
+        cache.fetch(&req, &|| NOW + 599, send).expect("fetch");
+        assert_eq!(1, *sent.borrow());
+        cache.fetch(&req, &|| NOW + 600, send).expect("fetch");
+        assert_eq!(2, *sent.borrow());
+    }
+
+    #[test]
+    fn test_no_store() {
+        let mut cache = HttpCache::new(DEFAULT_CACHE_CAPACITY);
+        let req = request("http://example.com/");
+        cache
+            .fetch(&req, &|| NOW, |_| {
+                Ok(response(
+                    200,
+                    &[("Cache-Control", "no-store, max-age=60")],
+                    "",
+                ))
+            })
+            .expect("fetch");
+        assert!(cache.is_empty());
+
+        // Vary: *のレスポンスも保存しない
+        cache
+            .fetch(&req, &|| NOW, |_| {
+                Ok(response(
+                    200,
+                    &[("Cache-Control", "max-age=60"), ("Vary", "*")],
+                    "",
+                ))
+            })
+            .expect("fetch");
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_no_cache_revalidates_with_etag() {
+        let mut cache = HttpCache::new(DEFAULT_CACHE_CAPACITY);
+        let req = request("http://example.com/");
+        cache
+            .fetch(&req, &|| NOW, |_| {
+                Ok(response(
+                    200,
+                    &[
+                        ("Cache-Control", "no-cache"),
+                        ("ETag", "\"v1\""),
+                        ("Content-Length", "5"),
+                    ],
+                    "hello",
+                ))
+            })
+            .expect("fetch");
+
+        let cached = cache
+            .fetch(&req, &|| NOW + 1, |conditional| {
+                assert_eq!(
+                    Some("\"v1\"".to_string()),
+                    conditional.header_value("If-None-Match")
+                );
+                Ok(response(
+                    304,
+                    &[
+                        ("ETag", "\"v1\""),
+                        ("X-Version", "2"),
+                        ("Content-Length", "0"),
+                    ],
+                    "",
+                ))
+            })
+            .expect("fetch");
+        assert_eq!(200, cached.status_code());
+        assert_eq!("hello", cached.body());
+        assert_eq!(Ok("2".to_string()), cached.header_value("X-Version"));
+        assert_eq!(Ok("5".to_string()), cached.header_value("Content-Length"));
+    }
+
+    #[test]
+    fn test_stale_revalidates_with_last_modified() {
+        let mut cache = HttpCache::new(DEFAULT_CACHE_CAPACITY);
+        let req = request("http://example.com/");
+        let last_modified = "Sun, 31 Dec 2023 00:00:00 GMT";
+        cache
+            .fetch(&req, &|| NOW, |_| {
+                Ok(response(
+                    200,
+                    &[("Date", NOW_DATE), ("Last-Modified", last_modified)],
+                    "old",
+                ))
+            })
+            .expect("fetch");
+
+        // 最終更新から1日経っているので、経験的な有効期間は8640秒
+        let sent = RefCell::new(false);
+        cache
+            .fetch(&req, &|| NOW + 8639, |_| {
+                *sent.borrow_mut() = true;
+                Ok(response(200, &[], ""))
+            })
+            .expect("fetch");
+        assert!(!*sent.borrow());
+
+        let fetched = cache
+            .fetch(&req, &|| NOW + 8640, |conditional| {
+                assert_eq!(
+                    Some(last_modified.to_string()),
+                    conditional.header_value("If-Modified-Since")
+                );
+                Ok(response(200, &[("Cache-Control", "max-age=60")], "new"))
+            })
+            .expect("fetch");
+        assert_eq!("new", fetched.body());
+        assert_eq!(1, cache.len());
+    }
+
+    #[test]
+    fn test_vary() {
+        let mut cache = HttpCache::new(DEFAULT_CACHE_CAPACITY);
+        let send = |request: &HttpRequest| {
+            Ok(response(
+                200,
+                &[("Cache-Control", "max-age=60"), ("Vary", "Accept-Language")],
+                &request.header_value("Accept-Language").unwrap_or_default(),
+            ))
+        };
+
+        let ja = request("http://example.com/").with_header("Accept-Language", "ja");
+        let en = request("http://example.com/").with_header("Accept-Language", "en");
+        assert_eq!("ja", cache.fetch(&ja, &|| NOW, send).expect("fetch").body());
+        assert_eq!("en", cache.fetch(&en, &|| NOW, send).expect("fetch").body());
+        assert_eq!(2, cache.len());
+
+        let unexpected = |_: &HttpRequest| Err(Error::Network("unexpected".to_string()));
+        assert_eq!(
+            "ja",
+            cache.fetch(&ja, &|| NOW, unexpected).expect("fetch").body()
+        );
+    }
+
+    #[test]
+    fn test_unsafe_method_invalidates() {
+        let mut cache = HttpCache::new(DEFAULT_CACHE_CAPACITY);
+        let req = request("http://example.com/");
+        cache
+            .fetch(&req, &|| NOW, |_| {
+                Ok(response(200, &[("Cache-Control", "max-age=60")], ""))
+            })
+            .expect("fetch");
+        assert_eq!(1, cache.len());
+
+        let post = HttpRequest::new(Method::Post, req.url());
+        cache
+            .fetch(&post, &|| NOW, |_| Ok(response(200, &[], "")))
+            .expect("fetch");
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_eviction() {
+        let body = "x".repeat(100);
+        let mut cache = HttpCache::new(250);
+        let send = |_: &HttpRequest| Ok(response(200, &[("Cache-Control", "max-age=60")], &body));
+
+        for path in ["a", "b"] {
+            cache
+                .fetch(
+                    &request(&format!("http://example.com/{}", path)),
+                    &|| NOW,
+                    send,
+                )
+                .expect("fetch");
+        }
+        // aを使うと、次に追加したときにbが削除される
+        cache
+            .fetch(&request("http://example.com/a"), &|| NOW, send)
+            .expect("fetch");
+        cache
+            .fetch(&request("http://example.com/c"), &|| NOW, send)
+            .expect("fetch");
+        assert_eq!(2, cache.len());
+        assert!(cache.size() <= 250);
+
+        let keys: Vec<String> = cache.entries.iter().map(|e| e.key.clone()).collect();
+        assert_eq!(
+            vec![
+                "http://example.com/a".to_string(),
+                "http://example.com/c".to_string()
+            ],
+            keys
+        );
+
+        // 上限より大きいレスポンスは保存しない
+        let large = "x".repeat(300);
+        cache
+            .fetch(&request("http://example.com/large"), &|| NOW, |_| {
+                Ok(response(200, &[("Cache-Control", "max-age=60")], &large))
+            })
+            .expect("fetch");
+        assert_eq!(2, cache.len());
+    }
+
+    #[test]
+    fn test_clock_advanced_by_response() {
+        // 時計はレスポンスのDateヘッダで進むため、最初のリクエストを送る前は0になっている
+        let clock = Cell::new(0);
+        let sent = RefCell::new(0);
+        let send = |_: &HttpRequest| {
+            *sent.borrow_mut() += 1;
+            clock.set(NOW);
+            Ok(response(
+                200,
+                &[("Date", NOW_DATE), ("Cache-Control", "max-age=3600")],
+                "hello",
+            ))
+        };
+
+        let mut cache = HttpCache::new(DEFAULT_CACHE_CAPACITY);
+        let req = request("http://example.com/");
+        cache.fetch(&req, &|| clock.get(), send).expect("fetch");
+        assert_eq!(
+            "hello",
+            cache
+                .fetch(&req, &|| clock.get(), send)
+                .expect("fetch")
+                .body()
+        );
+        assert_eq!(1, *sent.borrow());
+    }
+}
@@ -0,0 +1,271 @@
+//! chunked転送コーディング
+//! https://www.rfc-editor.org/rfc/rfc9112.html#section-7.1
+
+use alloc::vec::Vec;
+
+use super::{isomorphic_decode, next_line, Header, HttpParseError, HttpResponse};
+use crate::error::Error;
+
+fn incomplete() -> Error {
+    Error::Http(HttpParseError::Incomplete)
+}
+
+fn invalid_chunk(line: &[u8]) -> Error {
+    Error::Http(HttpParseError::InvalidChunk(isomorphic_decode(line)))
+}
+
+/// chunk-size [ chunk-ext ] CRLF の行からチャンクの大きさを読み取ります
+/// チャンク拡張は無視します
+/// https://www.rfc-editor.org/rfc/rfc9112.html#section-7.1.1
+fn parse_chunk_size(line: &[u8]) -> Result<usize, Error> {
+    let end = line
+        .iter()
+        .position(|&b| b == b';' || b == b' ' || b == b'\t')
+        .unwrap_or(line.len());
+    let digits = &line[..end];
+    if digits.is_empty() {
+        return Err(invalid_chunk(line));
+    }
+
+    digits.iter().try_fold(0usize, |size, &b| {
+        let digit = (b as char)
+            .to_digit(16)
+            .ok_or_else(|| invalid_chunk(line))?;
+        size.checked_mul(16)
+            .and_then(|size| size.checked_add(digit as usize))
+            .ok_or_else(|| invalid_chunk(line))
+    })
+}
+
+/// chunked転送コーディングされたボディをデコードし、ボディとトレーラーフィールドを返します
+/// 最後のチャンク以降のバイト列は無視します
+pub fn decode(input: &[u8]) -> Result<(Vec<u8>, Vec<Header>), Error> {
+    let mut body = Vec::new();
+    let mut pos = 0;
+
+    loop {
+        let (line, next) = next_line(input, pos).ok_or_else(incomplete)?;
+        let size = parse_chunk_size(line)?;
+        pos = next;
+
+        // last-chunk = 1*("0") [ chunk-ext ] CRLF
+        if size == 0 {
+            break;
+        }
+
+        if input.len() - pos < size {
+            return Err(incomplete());
+        }
+        body.extend_from_slice(&input[pos..pos + size]);
+        pos += size;
+
+        // chunk-dataの直後には改行が続く
+        let (line, next) = next_line(input, pos).ok_or_else(incomplete)?;
+        if !line.is_empty() {
+            return Err(invalid_chunk(line));
+        }
+        pos = next;
+    }
+
+    // trailer-section = *( field-line CRLF )
+    // https://www.rfc-editor.org/rfc/rfc9112.html#section-7.1.2
+    let mut trailers = Vec::new();
+    loop {
+        let (line, next) = next_line(input, pos).ok_or_else(incomplete)?;
+        pos = next;
+        if line.is_empty() {
+            break;
+        }
+        trailers.push(HttpResponse::parse_header(line)?);
+    }
+
+    Ok((body, trailers))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DecoderState {
+    /// chunk-sizeの行を待っている
+    Size,
+    /// chunk-dataの残りのバイト数
+    Data(usize),
+    /// chunk-dataの直後の改行を待っている
+    DataEnd,
+    /// 最後のチャンクの後の、トレーラーフィールドと空行を待っている
+    Trailers,
+    /// トレーラーの後の空行まで読み終えた
+    Done,
+}
+
+/// 受信した順に渡されたバイト列から、chunked転送コーディングをデコードできたところまでのボディを取り出します
+/// トレーラーフィールドは読み飛ばします
+#[derive(Debug, Clone)]
+pub struct ChunkedDecoder {
+    state: DecoderState,
+    /// 行の途中で途切れていて、まだデコードしていないバイト列
+    pending: Vec<u8>,
+}
+
+impl ChunkedDecoder {
+    pub fn new() -> Self {
+        Self {
+            state: DecoderState::Size,
+            pending: Vec::new(),
+        }
+    }
+
+    /// 最後のチャンクとトレーラーまで読み終えたかどうか
+    pub fn is_done(&self) -> bool {
+        self.state == DecoderState::Done
+    }
+
+    /// 受信したバイト列の続きを渡し、新たにデコードできたボディを返します
+    pub fn feed(&mut self, input: &[u8]) -> Result<Vec<u8>, Error> {
+        self.pending.extend_from_slice(input);
+        let mut body = Vec::new();
+        let mut pos = 0;
+
+        loop {
+            match self.state {
+                DecoderState::Size => {
+                    let (line, next) = match next_line(&self.pending, pos) {
+                        Some(line) => line,
+                        None => break,
+                    };
+                    self.state = match parse_chunk_size(line)? {
+                        0 => DecoderState::Trailers,
+                        size => DecoderState::Data(size),
+                    };
+                    pos = next;
+                }
+                DecoderState::Data(remaining) => {
+                    let len = remaining.min(self.pending.len() - pos);
+                    if len == 0 {
+                        break;
+                    }
+                    body.extend_from_slice(&self.pending[pos..pos + len]);
+                    pos += len;
+                    self.state = match remaining - len {
+                        0 => DecoderState::DataEnd,
+                        remaining => DecoderState::Data(remaining),
+                    };
+                }
+                DecoderState::DataEnd => {
+                    let (line, next) = match next_line(&self.pending, pos) {
+                        Some(line) => line,
+                        None => break,
+                    };
+                    if !line.is_empty() {
+                        return Err(invalid_chunk(line));
+                    }
+                    self.state = DecoderState::Size;
+                    pos = next;
+                }
+                DecoderState::Trailers => {
+                    let (line, next) = match next_line(&self.pending, pos) {
+                        Some(line) => line,
+                        None => break,
+                    };
+                    if line.is_empty() {
+                        self.state = DecoderState::Done;
+                    }
+                    pos = next;
+                }
+                DecoderState::Done => {
+                    pos = self.pending.len();
+                    break;
+                }
+            }
+        }
+
+        self.pending.drain(..pos);
+        Ok(body)
+    }
+}
+
+impl Default for ChunkedDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    #[test]
+    fn test_decode() {
+        let (body, trailers) =
+            decode(b"4\r\nWiki\r\n6;name=value\r\npedia \r\nE\r\nin \r\n\r\nchunks.\r\n0\r\n\r\n")
+                .expect("failed to decode a chunked body");
+        assert_eq!(b"Wikipedia in \r\n\r\nchunks.".to_vec(), body);
+        assert!(trailers.is_empty());
+    }
+
+    #[test]
+    fn test_trailers() {
+        let (body, trailers) = decode(b"3\r\nabc\r\n000\r\nExpires: never\r\nX-Sum: 1\r\n\r\n")
+            .expect("failed to decode a chunked body");
+        assert_eq!(b"abc".to_vec(), body);
+        assert_eq!(
+            vec![
+                Header::new("Expires".to_string(), "never".to_string()),
+                Header::new("X-Sum".to_string(), "1".to_string()),
+            ],
+            trailers
+        );
+    }
+
+    #[test]
+    fn test_incomplete() {
+        assert_eq!(Err(incomplete()), decode(b"a\r\nabc"));
+        assert_eq!(Err(incomplete()), decode(b"3\r\nabc\r\n"));
+        assert_eq!(Err(incomplete()), decode(b"0\r\n"));
+    }
+
+    #[test]
+    fn test_chunked_decoder() {
+        let input = b"4\r\nWiki\r\n6;name=value\r\npedia \r\nE\r\nin \r\n\r\nchunks.\r\n0\r\nX-Sum: 1\r\n\r\n";
+        let mut decoder = ChunkedDecoder::new();
+        let mut body = Vec::new();
+        for b in input {
+            body.extend(
+                decoder
+                    .feed(&[*b])
+                    .expect("failed to decode a chunked body"),
+            );
+        }
+        assert!(decoder.is_done());
+        assert_eq!(decode(input).expect("failed to decode").0, body);
+
+        // チャンクの途中までしか届いていなくても、届いたところまでは取り出せる
+        let mut decoder = ChunkedDecoder::new();
+        assert_eq!(Ok(b"ab".to_vec()), decoder.feed(b"5\r\nab"));
+        assert_eq!(Ok(b"cde".to_vec()), decoder.feed(b"cde\r"));
+        assert!(!decoder.is_done());
+        assert!(decoder.feed(b"\nxyz\r\n").is_err());
+
+        // トレーラーの後の空行が届くまでは終わっていない
+        let mut decoder = ChunkedDecoder::new();
+        assert_eq!(Ok(b"ok".to_vec()), decoder.feed(b"2\r\nok\r\n0\r\n"));
+        assert!(!decoder.is_done());
+        assert_eq!(Ok(Vec::new()), decoder.feed(b"X-Sum: 1\r\n\r\n"));
+        assert!(decoder.is_done());
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(
+            Err(Error::Http(HttpParseError::InvalidChunk("xyz".to_string()))),
+            decode(b"xyz\r\n\r\n")
+        );
+        assert_eq!(
+            Err(Error::Http(HttpParseError::InvalidChunk(
+                "defg".to_string()
+            ))),
+            decode(b"3\r\nabcdefg\r\n0\r\n\r\n")
+        );
+        assert!(decode(b"ffffffffffffffffffff\r\n").is_err());
+    }
+}
@@ -0,0 +1,535 @@
+use alloc::format;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+use super::chunked::ChunkedDecoder;
+use super::proxy::ProxyConfig;
+use super::{header_end, HttpRequest, HttpResponse, Method, ACCEPT_ENCODING};
+use crate::error::Error;
+use crate::net::tls::{TlsConfig, TlsConnection};
+use crate::net::{Connection, Transport};
+
+/// リクエストを送信し、受信したバイト列を順にreceiverに渡します
+/// ボディの終わりが分かるレスポンスは、ボディを受信し終えたところで読むのをやめます
+fn exchange<C: Connection>(
+    connection: &mut C,
+    request: &[u8],
+    receiver: &mut ResponseReceiver,
+) -> Result<(), Error> {
+    connection.write_all(request)?;
+
+    let mut buf = [0u8; 4096];
+    // close_notifyを送らずに接続を閉じるTLSのサーバーもあるため、接続が閉じられるのを待たない
+    // 接続が閉じられるまでがボディになるレスポンスでは、close_notifyがないとエラーになる
+    while !receiver.is_complete() {
+        let bytes_read = connection.read(&mut buf)?;
+        if bytes_read == 0 {
+            return Ok(());
+        }
+        receiver.receive(&buf[..bytes_read])?;
+    }
+    Ok(())
+}
+
+/// レスポンスのボディの終わりの決め方
+/// https://www.rfc-editor.org/rfc/rfc9112.html#section-6.3
+#[derive(Debug)]
+enum BodyFraming {
+    /// ボディを持たない
+    Empty,
+    /// Content-Lengthで指定された長さの残り
+    Length(usize),
+    Chunked(ChunkedDecoder),
+    /// 接続が閉じられるまでがボディになる
+    Close,
+}
+
+impl BodyFraming {
+    fn new(head: &HttpResponse, method: Method) -> Result<Self, Error> {
+        if method == Method::Head
+            || (100..200).contains(&head.status_code())
+            || head.status_code() == 204
+            || head.status_code() == 304
+        {
+            return Ok(Self::Empty);
+        }
+
+        let transfer_codings = head.header_values("Transfer-Encoding");
+        if !transfer_codings.is_empty() {
+            let is_chunked = transfer_codings
+                .join(",")
+                .rsplit(',')
+                .next()
+                .is_some_and(|coding| coding.trim().eq_ignore_ascii_case("chunked"));
+            return Ok(if is_chunked {
+                Self::Chunked(ChunkedDecoder::new())
+            } else {
+                Self::Close
+            });
+        }
+        Ok(match head.content_length()? {
+            Some(length) => Self::Length(length),
+            None => Self::Close,
+        })
+    }
+
+    /// 受信したバイト列の続きから、ボディの続きを取り出します
+    fn feed(&mut self, bytes: &[u8]) -> Result<Vec<u8>, Error> {
+        match self {
+            Self::Empty => Ok(Vec::new()),
+            Self::Length(remaining) => {
+                let len = bytes.len().min(*remaining);
+                *remaining -= len;
+                Ok(bytes[..len].to_vec())
+            }
+            Self::Chunked(decoder) => decoder.feed(bytes),
+            Self::Close => Ok(bytes.to_vec()),
+        }
+    }
+
+    /// ボディの終わりまで受信したかどうか
+    fn is_complete(&self) -> bool {
+        match self {
+            Self::Empty => true,
+            Self::Length(remaining) => *remaining == 0,
+            Self::Chunked(decoder) => decoder.is_done(),
+            Self::Close => false,
+        }
+    }
+}
+
+/// ヘッダを受信し終えたレスポンスの、ボディを受信している状態
+struct BodyState {
+    /// ヘッダだけをパースしたレスポンス
+    head: HttpResponse,
+    framing: BodyFraming,
+    /// 圧縮されたボディは、すべて受信して展開してから渡す
+    is_encoded: bool,
+}
+
+/// 受信したバイト列をためながら、ヘッダを受信し終えた後はボディを届いた順にon_bodyに渡します
+struct ResponseReceiver<'a> {
+    method: Method,
+    received: Vec<u8>,
+    body: Option<BodyState>,
+    on_body: &'a mut dyn FnMut(&HttpResponse, &[u8]),
+}
+
+impl<'a> ResponseReceiver<'a> {
+    fn new(method: Method, on_body: &'a mut dyn FnMut(&HttpResponse, &[u8])) -> Self {
+        Self {
+            method,
+            received: Vec::new(),
+            body: None,
+            on_body,
+        }
+    }
+
+    fn receive(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        let start = self.received.len();
+        self.received.extend_from_slice(bytes);
+
+        let bytes = match self.body {
+            Some(_) => &self.received[start..],
+            None => {
+                let end = match header_end(&self.received) {
+                    Some(end) => end,
+                    None => return Ok(()),
+                };
+                let head = HttpResponse::new_with_method(&self.received[..end], Method::Head)?;
+                let framing = BodyFraming::new(&head, self.method)?;
+                let is_encoded = head
+                    .header_values("Content-Encoding")
+                    .join(",")
+                    .split(',')
+                    .any(|coding| {
+                        let coding = coding.trim();
+                        !coding.is_empty() && !coding.eq_ignore_ascii_case("identity")
+                    });
+                self.body = Some(BodyState {
+                    head,
+                    framing,
+                    is_encoded,
+                });
+                &self.received[end..]
+            }
+        };
+
+        let state = match self.body.as_mut() {
+            Some(state) => state,
+            None => return Ok(()),
+        };
+        let data = state.framing.feed(bytes)?;
+        if !data.is_empty() && !state.is_encoded {
+            (self.on_body)(&state.head, &data);
+        }
+        Ok(())
+    }
+
+    /// ボディの終わりまで受信したかどうか
+    /// 1xxのレスポンスの後には最終的なレスポンスが続くため、終わりとはみなさない
+    fn is_complete(&self) -> bool {
+        self.body.as_ref().is_some_and(|state| {
+            !(100..200).contains(&state.head.status_code()) && state.framing.is_complete()
+        })
+    }
+
+    /// 受信し終えたバイト列全体をレスポンスとしてパースします
+    fn finish(self) -> Result<HttpResponse, Error> {
+        let response = HttpResponse::new_with_method(&self.received, self.method)?;
+        if let Some(state) = self.body.as_ref().filter(|state| state.is_encoded) {
+            let body = response.body_bytes();
+            if !body.is_empty() {
+                (self.on_body)(&state.head, &body);
+            }
+        }
+        Ok(response)
+    }
+}
+
+/// Transportで確立した接続の上でHTTP/1.1のリクエストを送信するクライアント
+/// TLSの設定があれば、https:のURLへのリクエストをTLSで暗号化した接続で送信します
+/// プロキシの設定があれば、直接接続するホスト以外へのリクエストはプロキシを経由します
+#[derive(Debug, Clone)]
+pub struct HttpClient<T: Transport> {
+    transport: T,
+    tls: Option<TlsConfig>,
+    proxy: Option<ProxyConfig>,
+}
+
+impl<T: Transport> HttpClient<T> {
+    pub fn new(transport: T) -> Self {
+        Self {
+            transport,
+            tls: None,
+            proxy: None,
+        }
+    }
+
+    /// https:のURLへのリクエストに使うTLSの設定を指定します
+    pub fn with_tls(mut self, config: TlsConfig) -> Self {
+        self.tls = Some(config);
+        self
+    }
+
+    /// リクエストを中継するプロキシを指定します
+    pub fn with_proxy(mut self, config: ProxyConfig) -> Self {
+        self.proxy = Some(config);
+        self
+    }
+
+    /// リクエストを送信し、サーバーが接続を閉じるまでに受信したレスポンスを返します
+    pub fn send(&self, request: &HttpRequest) -> Result<HttpResponse, Error> {
+        self.send_streaming(request, &mut |_, _| {})
+    }
+
+    /// リクエストを送信し、受信したボディをon_bodyにヘッダだけのレスポンスとともに届いた順に渡します
+    /// 戻り値はsendと同じく、サーバーが接続を閉じるまでに受信したレスポンス全体です
+    /// 圧縮されたボディは、すべて受信して展開してから一度に渡します
+    pub fn send_streaming(
+        &self,
+        request: &HttpRequest,
+        on_body: &mut dyn FnMut(&HttpResponse, &[u8]),
+    ) -> Result<HttpResponse, Error> {
+        let url = request.url();
+        let port = url
+            .port_or_default()
+            .ok_or_else(|| Error::UnexpectedInput(format!("unknown port for {}", url)))?;
+        // IPv6アドレスの角括弧はURL上の表記なので取り除く
+        let host = url.host();
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+
+        let mut request = request.clone();
+        if !request.has_header("Accept-Encoding") {
+            request = request.with_header("Accept-Encoding", ACCEPT_ENCODING);
+        }
+        // レスポンスの終わりを接続の切断で判断するため、接続を使い回さないように伝える
+        if !request.has_header("Connection") {
+            request = request.with_header("Connection", "close");
+        }
+
+        let proxy = self.proxy.as_ref().filter(|proxy| proxy.is_used_for(&url));
+        let mut receiver = ResponseReceiver::new(request.method(), on_body);
+        if url.scheme() == "https" {
+            let config = self
+                .tls
+                .as_ref()
+                .ok_or_else(|| Error::Network("TLS is not configured".to_string()))?;
+            let url_host = url
+                .host_kind()
+                .ok_or_else(|| Error::UnexpectedInput(format!("no host in {}", url)))?;
+            let connection = match proxy {
+                Some(proxy) => {
+                    let mut connection = self.transport.connect(&proxy.host(), proxy.port())?;
+                    proxy.open_tunnel(&mut connection, &url)?;
+                    connection
+                }
+                None => self.transport.connect(host, port)?,
+            };
+            let mut connection = TlsConnection::connect(connection, &url_host, config)?;
+            exchange(&mut connection, &request.to_bytes()?, &mut receiver)?;
+        } else {
+            match proxy {
+                Some(proxy) => {
+                    let bytes = proxy.forward_request(&request)?;
+                    let mut connection = self.transport.connect(&proxy.host(), proxy.port())?;
+                    exchange(&mut connection, &bytes, &mut receiver)?;
+                }
+                None => {
+                    let bytes = request.to_bytes()?;
+                    let mut connection = self.transport.connect(host, port)?;
+                    exchange(&mut connection, &bytes, &mut receiver)?;
+                }
+            }
+        }
+
+        receiver.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::fetch::{fetch, file::MemoryFileSystem, DEFAULT_REDIRECT_LIMIT};
+    use crate::http::Method;
+    use crate::net::std_net::{StdRandom, StdTransport};
+    use crate::url::Url;
+    use alloc::rc::Rc;
+    use alloc::string::{String, ToString};
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// パスごとに決まったレスポンスを返すテスト用のサーバーを起動し、ポート番号を返します
+    fn start_server(connections: usize) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("failed to bind");
+        let port = listener.local_addr().expect("no local address").port();
+
+        thread::spawn(move || {
+            for stream in listener.incoming().take(connections) {
+                let mut stream = stream.expect("failed to accept");
+                let mut request = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    let n = Read::read(&mut stream, &mut buf).expect("failed to read");
+                    if n == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buf[..n]);
+                }
+
+                let request = String::from_utf8_lossy(&request).to_string();
+                let response = if request.starts_with("GET /old ") {
+                    "HTTP/1.1 301 Moved Permanently\r\nLocation: /new?from=old\r\n\r\n".to_string()
+                } else {
+                    let body = request.lines().next().unwrap_or_default().to_string();
+                    format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nTransfer-Encoding: chunked\r\n\r\n{:x}\r\n{}\r\n0\r\n\r\n",
+                        body.len(),
+                        body
+                    )
+                };
+                Write::write_all(&mut stream, response.as_bytes()).expect("failed to write");
+            }
+        });
+
+        port
+    }
+
+    #[test]
+    fn test_send() {
+        let port = start_server(1);
+        let client = HttpClient::new(StdTransport::new());
+        let url = Url::new(format!("http://127.0.0.1:{}/index.html?q=1", port))
+            .parse()
+            .expect("invalid url");
+        let response = client
+            .send(&HttpRequest::new(Method::Get, url))
+            .expect("failed to send a request");
+        assert_eq!(200, response.status_code());
+        assert_eq!("GET /index.html?q=1 HTTP/1.1", response.body());
+    }
+
+    #[test]
+    fn test_send_streaming() {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("failed to bind");
+        let port = listener.local_addr().expect("no local address").port();
+        let (sender, receiver) = std::sync::mpsc::channel::<()>();
+
+        // 最初のチャンクを送った後、クライアントがそれを受け取るまで残りを送らない
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().expect("failed to accept");
+            let mut request = Vec::new();
+            let mut buf = [0u8; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let n = Read::read(&mut stream, &mut buf).expect("failed to read");
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+            }
+
+            Write::write_all(
+                &mut stream,
+                b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n6\r\n<p>hi\n\r\n",
+            )
+            .expect("failed to write");
+            let rest = match receiver.recv_timeout(std::time::Duration::from_secs(5)) {
+                Ok(()) => "8\r\nstreamed\r\n0\r\n\r\n",
+                Err(_) => "8\r\nbuffered\r\n0\r\n\r\n",
+            };
+            Write::write_all(&mut stream, rest.as_bytes()).expect("failed to write");
+        });
+
+        let client = HttpClient::new(StdTransport::new());
+        let url = Url::new(format!("http://127.0.0.1:{}/", port))
+            .parse()
+            .expect("invalid url");
+        let mut chunks = Vec::new();
+        let response = client
+            .send_streaming(&HttpRequest::new(Method::Get, url), &mut |head, data| {
+                assert_eq!(200, head.status_code());
+                chunks.push(String::from_utf8_lossy(data).to_string());
+                let _ = sender.send(());
+            })
+            .expect("failed to send a request");
+        // サーバーが残りを送る前に最初のチャンクを受け取っていれば、streamedが返ってくる
+        assert_eq!("<p>hi\nstreamed", chunks.concat());
+        assert_eq!("<p>hi\nstreamed", response.body());
+    }
+
+    #[test]
+    fn test_fetch_with_redirect() {
+        let port = start_server(2);
+        let client = HttpClient::new(StdTransport::new());
+        let url = Url::new(format!("http://127.0.0.1:{}/old", port))
+            .parse()
+            .expect("invalid url");
+        let response = fetch(
+            &url,
+            Method::Get,
+            &MemoryFileSystem::new(),
+            DEFAULT_REDIRECT_LIMIT,
+            |method, url| client.send(&HttpRequest::new(method, url.clone())),
+        )
+        .expect("failed to fetch");
+        assert_eq!("GET /new?from=old HTTP/1.1", response.body());
+        assert_eq!(
+            Some(format!("http://127.0.0.1:{}/new?from=old", port)),
+            response.url().map(|url| url.to_string())
+        );
+    }
+
+    /// 受け取ったリクエストのリクエスト行とProxy-Authorizationヘッダを返すテスト用のプロキシを起動し、
+    /// ポート番号を返します。CONNECTリクエストは認証が必要だとして拒否します
+    fn start_proxy(connections: usize) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("failed to bind");
+        let port = listener.local_addr().expect("no local address").port();
+
+        thread::spawn(move || {
+            for stream in listener.incoming().take(connections) {
+                let mut stream = stream.expect("failed to accept");
+                let mut request = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    let n = Read::read(&mut stream, &mut buf).expect("failed to read");
+                    if n == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buf[..n]);
+                }
+
+                let request = String::from_utf8_lossy(&request).to_string();
+                let response = if request.starts_with("CONNECT ") {
+                    "HTTP/1.1 407 Proxy Authentication Required\r\nContent-Length: 0\r\n\r\n"
+                        .to_string()
+                } else {
+                    let authorization = request
+                        .lines()
+                        .find_map(|line| line.strip_prefix("Proxy-Authorization: "))
+                        .unwrap_or_default();
+                    let body = format!(
+                        "{}\n{}",
+                        request.lines().next().unwrap_or_default(),
+                        authorization
+                    );
+                    format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+                        body.len(),
+                        body
+                    )
+                };
+                Write::write_all(&mut stream, response.as_bytes()).expect("failed to write");
+            }
+        });
+
+        port
+    }
+
+    #[test]
+    fn test_send_through_proxy() {
+        let port = start_proxy(1);
+        let client = HttpClient::new(StdTransport::new())
+            .with_proxy(ProxyConfig::new("127.0.0.1", port).with_credentials("user", "pass"));
+        let url = Url::new("http://example.com/index.html?q=1".to_string())
+            .parse()
+            .expect("invalid url");
+        let response = client
+            .send(&HttpRequest::new(Method::Get, url))
+            .expect("failed to send a request");
+        assert_eq!(
+            "GET http://example.com/index.html?q=1 HTTP/1.1\nBasic dXNlcjpwYXNz",
+            response.body()
+        );
+    }
+
+    #[test]
+    fn test_no_proxy() {
+        let port = start_server(1);
+        // 使い終わったポートをプロキシに指定しても、プロキシを使わないホストには接続できる
+        let proxy_port = TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .expect("failed to bind")
+            .port();
+        let client = HttpClient::new(StdTransport::new())
+            .with_proxy(ProxyConfig::new("127.0.0.1", proxy_port).with_no_proxy("127.0.0.1"));
+        let url = Url::new(format!("http://127.0.0.1:{}/", port))
+            .parse()
+            .expect("invalid url");
+        let response = client
+            .send(&HttpRequest::new(Method::Get, url))
+            .expect("failed to send a request");
+        assert_eq!("GET / HTTP/1.1", response.body());
+    }
+
+    #[test]
+    fn test_proxy_refuses_tunnel() {
+        let port = start_proxy(1);
+        let client = HttpClient::new(StdTransport::new())
+            .with_tls(TlsConfig::new(Rc::new(StdRandom::new())))
+            .with_proxy(ProxyConfig::new("127.0.0.1", port));
+        let url = Url::new("https://example.com/".to_string())
+            .parse()
+            .expect("invalid url");
+        match client.send(&HttpRequest::new(Method::Get, url)) {
+            Err(Error::Network(message)) => assert!(message.contains("407"), "{}", message),
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_connection_refused() {
+        // 使い終わったポートには接続できない
+        let port = TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .expect("failed to bind")
+            .port();
+        let client = HttpClient::new(StdTransport::new());
+        let url = Url::new(format!("http://127.0.0.1:{}/", port))
+            .parse()
+            .expect("invalid url");
+        assert!(client.send(&HttpRequest::new(Method::Get, url)).is_err());
+    }
+}
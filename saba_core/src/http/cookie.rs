@@ -0,0 +1,831 @@
+//! Cookieの保存と送信
+//! https://www.rfc-editor.org/rfc/rfc6265
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+mod public_suffix;
+
+use self::public_suffix::PUBLIC_SUFFIX_RULES;
+use super::date::{days_from_civil, month};
+use super::{HttpRequest, HttpResponse};
+use crate::url::host::Host;
+use crate::url::Url;
+
+/// Expires属性がない、またはMax-Ageが非常に大きいCookieの有効期限の上限（9999年12月31日）
+const MAX_EXPIRY_TIME: u64 = 253402300799;
+
+/// パブリックサフィックスリストに規則があるかどうか
+fn has_public_suffix_rule(rule: &str) -> bool {
+    PUBLIC_SUFFIX_RULES.binary_search(&rule).is_ok()
+}
+
+/// パブリックサフィックスリストに従って、ドメインのパブリックサフィックスを返します
+/// https://github.com/publicsuffix/list/wiki/Format#algorithm
+pub fn public_suffix(domain: &str) -> String {
+    let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+    let labels: Vec<&str> = domain.split('.').collect();
+
+    // 例外の規則は他の規則より優先し、その規則から最も左のラベルを除いたものがパブリックサフィックスになる
+    for i in 0..labels.len() {
+        if has_public_suffix_rule(&format!("!{}", labels[i..].join("."))) {
+            return labels[i + 1..].join(".");
+        }
+    }
+
+    // 最も多くのラベルに一致する規則を使い、一致する規則がなければ最後のラベルをパブリックサフィックスとする
+    for i in 0..labels.len() {
+        let suffix = labels[i..].join(".");
+        if has_public_suffix_rule(&suffix)
+            || (i + 1 < labels.len()
+                && has_public_suffix_rule(&format!("*.{}", labels[i + 1..].join("."))))
+        {
+            return suffix;
+        }
+    }
+    labels[labels.len() - 1].to_string()
+}
+
+/// ドメインがパブリックサフィックスかどうか
+/// パブリックサフィックスにはドメインをまたいだCookieを設定できない
+pub fn is_public_suffix(domain: &str) -> bool {
+    let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+    public_suffix(&domain) == domain
+}
+
+/// https://datatracker.ietf.org/doc/html/draft-ietf-httpbis-rfc6265bis#section-4.1.2.7
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+    /// SameSite属性が指定されていない、または値が不明な場合
+    Default,
+}
+
+/// https://www.rfc-editor.org/rfc/rfc6265#section-5.3
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cookie {
+    name: String,
+    value: String,
+    /// ホスト専用のCookieの場合は、設定したURLのホスト
+    domain: String,
+    host_only: bool,
+    path: String,
+    /// 有効期限（1970年1月1日からの秒数）。ブラウザを閉じるまで有効なCookieの場合はNone
+    expiry_time: Option<u64>,
+    secure: bool,
+    http_only: bool,
+    same_site: SameSite,
+    creation_time: u64,
+}
+
+impl Cookie {
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    pub fn value(&self) -> String {
+        self.value.clone()
+    }
+
+    pub fn domain(&self) -> String {
+        self.domain.clone()
+    }
+
+    pub fn host_only(&self) -> bool {
+        self.host_only
+    }
+
+    pub fn path(&self) -> String {
+        self.path.clone()
+    }
+
+    pub fn expiry_time(&self) -> Option<u64> {
+        self.expiry_time
+    }
+
+    pub fn secure(&self) -> bool {
+        self.secure
+    }
+
+    pub fn http_only(&self) -> bool {
+        self.http_only
+    }
+
+    pub fn same_site(&self) -> SameSite {
+        self.same_site
+    }
+
+    fn is_expired(&self, now: u64) -> bool {
+        self.expiry_time.is_some_and(|expiry| expiry <= now)
+    }
+
+    /// https://www.rfc-editor.org/rfc/rfc6265#section-5.4
+    fn matches(&self, url: &Url, host: &str) -> bool {
+        let domain_ok = if self.host_only {
+            self.domain == host
+        } else {
+            domain_match(url, host, &self.domain)
+        };
+        domain_ok && path_match(&url.path(), &self.path) && (!self.secure || is_secure(url))
+    }
+}
+
+/// 1つのSet-Cookieヘッダの値を解析した結果
+/// https://www.rfc-editor.org/rfc/rfc6265#section-5.2
+/// https://datatracker.ietf.org/doc/html/draft-ietf-httpbis-rfc6265bis#section-5.6
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SetCookie {
+    name: String,
+    value: String,
+    /// (属性名を小文字にしたもの, 値)
+    attributes: Vec<(String, String)>,
+}
+
+impl SetCookie {
+    fn parse(header: &str) -> Option<Self> {
+        let mut parts = header.split(';');
+        let name_value_pair = parts.next()?;
+        // "="を含まない場合は、名前が空で値だけのCookieになる
+        let (name, value) = name_value_pair
+            .split_once('=')
+            .unwrap_or(("", name_value_pair));
+        let name = name.trim();
+        if name.is_empty() && value.trim().is_empty() {
+            return None;
+        }
+
+        let attributes = parts
+            .map(|attribute| {
+                let (name, value) = attribute.split_once('=').unwrap_or((attribute, ""));
+                (name.trim().to_ascii_lowercase(), value.trim().to_string())
+            })
+            .collect();
+
+        Some(Self {
+            name: name.to_string(),
+            value: value.trim().to_string(),
+            attributes,
+        })
+    }
+
+    /// 同じ属性が複数ある場合は最後のものを使う
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn has(&self, name: &str) -> bool {
+        self.attributes.iter().any(|(n, _)| n == name)
+    }
+}
+
+fn is_secure(url: &Url) -> bool {
+    url.scheme() == "https"
+}
+
+/// Cookieに使うホスト名。IPv6アドレスは角括弧で囲まれたままにする
+fn canonical_host(url: &Url) -> Option<String> {
+    match url.host_kind()? {
+        Host::Empty => None,
+        _ => Some(url.host().to_ascii_lowercase()),
+    }
+}
+
+/// https://www.rfc-editor.org/rfc/rfc6265#section-5.1.3
+fn domain_match(url: &Url, host: &str, domain: &str) -> bool {
+    if host == domain {
+        return true;
+    }
+    // IPアドレスはサフィックスで一致させない
+    matches!(url.host_kind(), Some(Host::Domain(_)))
+        && host.ends_with(domain)
+        && host[..host.len() - domain.len()].ends_with('.')
+}
+
+/// https://www.rfc-editor.org/rfc/rfc6265#section-5.1.4
+fn default_path(url: &Url) -> String {
+    let path = url.path();
+    if !path.starts_with('/') {
+        return "/".to_string();
+    }
+    match path.rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(i) => path[..i].to_string(),
+    }
+}
+
+/// https://www.rfc-editor.org/rfc/rfc6265#section-5.1.4
+fn path_match(request_path: &str, cookie_path: &str) -> bool {
+    request_path == cookie_path
+        || (request_path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || request_path[cookie_path.len()..].starts_with('/')))
+}
+
+/// Expires属性の日付を解析します。HTTP-dateよりも緩く、様々な形式を受け付けます
+/// https://www.rfc-editor.org/rfc/rfc6265#section-5.1.1
+fn parse_cookie_date(s: &str) -> Option<u64> {
+    let is_delimiter = |c: char| {
+        c == '\x09'
+            || (' '..='/').contains(&c)
+            || (';'..='@').contains(&c)
+            || ('['..='`').contains(&c)
+            || ('{'..='~').contains(&c)
+    };
+    // 先頭の数字をn桁まで読み、続く文字が数字でなければ値を返す
+    let leading_digits = |token: &str, min: usize, max: usize| -> Option<u64> {
+        let len = token.chars().take_while(|c| c.is_ascii_digit()).count();
+        if len < min || len > max {
+            return None;
+        }
+        token[..len].parse::<u64>().ok()
+    };
+
+    let mut time = None;
+    let mut day_of_month = None;
+    let mut mon = None;
+    let mut year = None;
+    for token in s.split(is_delimiter).filter(|t| !t.is_empty()) {
+        if time.is_none() {
+            let fields: Vec<&str> = token.splitn(3, ':').collect();
+            if let [hour, minute, second] = fields.as_slice() {
+                if let (Some(hour), Some(minute), Some(second)) = (
+                    leading_digits(hour, 1, 2).filter(|_| hour.len() <= 2),
+                    leading_digits(minute, 1, 2).filter(|_| minute.len() <= 2),
+                    leading_digits(second, 1, 2),
+                ) {
+                    time = Some((hour, minute, second));
+                    continue;
+                }
+            }
+        }
+        if day_of_month.is_none() {
+            if let Some(day) = leading_digits(token, 1, 2) {
+                day_of_month = Some(day);
+                continue;
+            }
+        }
+        if mon.is_none() && token.len() >= 3 && token.is_char_boundary(3) {
+            if let Some(m) = month(&token[..3]) {
+                mon = Some(m);
+                continue;
+            }
+        }
+        if year.is_none() {
+            if let Some(y) = leading_digits(token, 2, 4) {
+                year = Some(y);
+                continue;
+            }
+        }
+    }
+
+    let (hour, minute, second) = time?;
+    let day = day_of_month?;
+    let mon = mon?;
+    let mut year = year?;
+    if (70..=99).contains(&year) {
+        year += 1900;
+    } else if year <= 69 {
+        year += 2000;
+    }
+    if !(1..=31).contains(&day) || year < 1601 || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+
+    let days = days_from_civil(year as i64, mon, day as u32);
+    if days < 0 {
+        return Some(0);
+    }
+    Some(days as u64 * 86400 + hour * 3600 + minute * 60 + second)
+}
+
+/// ブラウザが保存しているCookieの集まり
+/// https://www.rfc-editor.org/rfc/rfc6265#section-5.3
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CookieJar {
+    cookies: Vec<Cookie>,
+}
+
+impl Default for CookieJar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CookieJar {
+    pub fn new() -> Self {
+        Self {
+            cookies: Vec::new(),
+        }
+    }
+
+    pub fn cookies(&self) -> Vec<Cookie> {
+        self.cookies.clone()
+    }
+
+    pub fn len(&self) -> usize {
+        self.cookies.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cookies.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.cookies.clear();
+    }
+
+    /// レスポンスのすべてのSet-Cookieヘッダを処理します
+    /// nowは現在時刻（1970年1月1日からの秒数）です
+    pub fn store_response_cookies(&mut self, url: &Url, response: &HttpResponse, now: u64) {
+        for header in response.header_values("Set-Cookie") {
+            self.set_cookie(url, &header, now);
+        }
+    }
+
+    /// Set-Cookieヘッダの値を解析し、urlから受け取ったCookieとして保存します
+    /// 保存できないCookieは無視します
+    /// https://www.rfc-editor.org/rfc/rfc6265#section-5.3
+    pub fn set_cookie(&mut self, url: &Url, header: &str, now: u64) {
+        let set_cookie = match SetCookie::parse(header) {
+            Some(set_cookie) => set_cookie,
+            None => return,
+        };
+        let host = match canonical_host(url) {
+            Some(host) => host,
+            None => return,
+        };
+
+        // Max-AgeはExpiresより優先する
+        let expiry_time = match set_cookie.attribute("max-age") {
+            Some(max_age) if is_max_age(max_age) => Some(match max_age.parse::<i64>() {
+                Ok(seconds) if seconds <= 0 => 0,
+                Ok(seconds) => now.saturating_add(seconds as u64).min(MAX_EXPIRY_TIME),
+                // 桁が大きすぎる値
+                Err(_) if max_age.starts_with('-') => 0,
+                Err(_) => MAX_EXPIRY_TIME,
+            }),
+            _ => set_cookie
+                .attribute("expires")
+                .and_then(parse_cookie_date)
+                .map(|expiry| expiry.min(MAX_EXPIRY_TIME)),
+        };
+
+        let domain = set_cookie
+            .attribute("domain")
+            .map(|domain| domain.trim_start_matches('.').to_ascii_lowercase())
+            .filter(|domain| !domain.is_empty());
+        let (domain, host_only) = match domain {
+            Some(domain) => {
+                if is_public_suffix(&domain) {
+                    // ホスト自身がパブリックサフィックスの場合だけホスト専用のCookieとして許す
+                    if domain != host {
+                        return;
+                    }
+                    (host.clone(), true)
+                } else if !domain_match(url, &host, &domain) {
+                    return;
+                } else {
+                    (domain, false)
+                }
+            }
+            None => (host.clone(), true),
+        };
+
+        let path = match set_cookie.attribute("path") {
+            Some(path) if path.starts_with('/') => path.to_string(),
+            _ => default_path(url),
+        };
+
+        let secure = set_cookie.has("secure");
+        // 安全でない接続からSecure属性付きのCookieは設定できない
+        // https://datatracker.ietf.org/doc/html/draft-ietf-httpbis-rfc6265bis#section-5.7
+        if secure && !is_secure(url) {
+            return;
+        }
+
+        let same_site = match set_cookie.attribute("samesite") {
+            Some(value) if value.eq_ignore_ascii_case("strict") => SameSite::Strict,
+            Some(value) if value.eq_ignore_ascii_case("lax") => SameSite::Lax,
+            Some(value) if value.eq_ignore_ascii_case("none") => SameSite::None,
+            _ => SameSite::Default,
+        };
+        // SameSite=NoneのCookieはSecure属性が必要
+        if same_site == SameSite::None && !secure {
+            return;
+        }
+
+        // Cookie名の接頭辞による制約
+        // https://datatracker.ietf.org/doc/html/draft-ietf-httpbis-rfc6265bis#section-4.1.3
+        let http_only = set_cookie.has("httponly");
+        let name = set_cookie.name.clone();
+        if name.starts_with("__Secure-") && !secure {
+            return;
+        }
+        if name.starts_with("__Host-") && (!secure || !host_only || path != "/") {
+            return;
+        }
+        // 名前が空のCookieは"Cookie: 値"として送られるため、値が接頭辞で始まるものは拒否する
+        // https://datatracker.ietf.org/doc/html/draft-ietf-httpbis-rfc6265bis#section-5.7
+        let value = set_cookie.value.to_ascii_lowercase();
+        if name.is_empty() && (value.starts_with("__secure-") || value.starts_with("__host-")) {
+            return;
+        }
+
+        let mut cookie = Cookie {
+            name,
+            value: set_cookie.value,
+            domain,
+            host_only,
+            path,
+            expiry_time,
+            secure,
+            http_only,
+            same_site,
+            creation_time: now,
+        };
+
+        // 同じ名前、ドメイン、パスのCookieを置き換える。作成時刻は古いものを引き継ぐ
+        if let Some(index) = self.cookies.iter().position(|c| {
+            c.name == cookie.name && c.domain == cookie.domain && c.path == cookie.path
+        }) {
+            let old = self.cookies.remove(index);
+            cookie.creation_time = old.creation_time;
+        }
+
+        if !cookie.is_expired(now) {
+            self.cookies.push(cookie);
+        }
+    }
+
+    /// urlへのリクエストに付けるCookieヘッダの値を返します。送るCookieがなければNoneを返します
+    /// same_siteがfalseの場合は、別のサイトから始まったリクエストとしてSameSite=Strictのものを送りません
+    /// https://www.rfc-editor.org/rfc/rfc6265#section-5.4
+    pub fn cookie_header(&mut self, url: &Url, now: u64, same_site: bool) -> Option<String> {
+        self.cookies.retain(|cookie| !cookie.is_expired(now));
+
+        let host = canonical_host(url)?;
+        let mut cookies: Vec<&Cookie> = self
+            .cookies
+            .iter()
+            .filter(|cookie| cookie.matches(url, &host))
+            .filter(|cookie| same_site || cookie.same_site != SameSite::Strict)
+            .collect();
+        if cookies.is_empty() {
+            return None;
+        }
+
+        // パスが長いもの、作成時刻が古いものから順に並べる
+        cookies.sort_by(|a, b| {
+            b.path
+                .len()
+                .cmp(&a.path.len())
+                .then(a.creation_time.cmp(&b.creation_time))
+        });
+        Some(
+            cookies
+                .iter()
+                .map(|cookie| {
+                    if cookie.name.is_empty() {
+                        cookie.value.clone()
+                    } else {
+                        format!("{}={}", cookie.name, cookie.value)
+                    }
+                })
+                .collect::<Vec<String>>()
+                .join("; "),
+        )
+    }
+
+    /// リクエストのURLに送るCookieがあれば、Cookieヘッダを追加したリクエストを返します
+    pub fn add_cookie_header(
+        &mut self,
+        request: HttpRequest,
+        now: u64,
+        same_site: bool,
+    ) -> HttpRequest {
+        if request.has_header("Cookie") {
+            return request;
+        }
+        match self.cookie_header(&request.url(), now, same_site) {
+            Some(cookie) => request.with_header("Cookie", &cookie),
+            None => request,
+        }
+    }
+}
+
+/// https://www.rfc-editor.org/rfc/rfc6265#section-5.2.2
+fn is_max_age(value: &str) -> bool {
+    let digits = value.strip_prefix('-').unwrap_or(value);
+    !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{Header, Method};
+
+    /// 2024年1月1日0時0分0秒(UTC)
+    const NOW: u64 = 1704067200;
+
+    fn url(s: &str) -> Url {
+        Url::new(s.to_string()).parse().expect("invalid url")
+    }
+
+    #[test]
+    fn test_host_only() {
+        let mut jar = CookieJar::new();
+        jar.set_cookie(&url("http://example.com/"), "sid=abc", NOW);
+        assert_eq!(
+            Some("sid=abc".to_string()),
+            jar.cookie_header(&url("http://example.com/a"), NOW, true)
+        );
+        assert_eq!(
+            None,
+            jar.cookie_header(&url("http://www.example.com/"), NOW, true)
+        );
+    }
+
+    #[test]
+    fn test_domain_attribute() {
+        let mut jar = CookieJar::new();
+        jar.set_cookie(
+            &url("http://www.example.com/"),
+            "a=1; Domain=.Example.com",
+            NOW,
+        );
+        // 別のドメインやパブリックサフィックスには設定できない
+        jar.set_cookie(
+            &url("http://www.example.com/"),
+            "b=2; Domain=other.com",
+            NOW,
+        );
+        jar.set_cookie(&url("http://www.example.com/"), "c=3; Domain=com", NOW);
+        jar.set_cookie(&url("http://foo.co.jp/"), "d=4; Domain=co.jp", NOW);
+        assert_eq!(1, jar.len());
+
+        assert_eq!(
+            Some("a=1".to_string()),
+            jar.cookie_header(&url("http://sub.example.com/"), NOW, true)
+        );
+        assert_eq!(
+            None,
+            jar.cookie_header(&url("http://notexample.com/"), NOW, true)
+        );
+    }
+
+    #[test]
+    fn test_public_suffix() {
+        assert_eq!("com", public_suffix("example.com"));
+        assert_eq!("co.jp", public_suffix("www.example.CO.JP."));
+        assert_eq!("blogspot.com", public_suffix("bob.blogspot.com"));
+        assert_eq!("s3.amazonaws.com", public_suffix("bucket.s3.amazonaws.com"));
+        // リストにないトップレベルドメインもパブリックサフィックスとして扱う
+        assert_eq!("test", public_suffix("example.test"));
+        assert_eq!("localhost", public_suffix("localhost"));
+        // "*.ck"の規則と、その例外の"!www.ck"
+        assert_eq!("example.ck", public_suffix("a.example.ck"));
+        assert_eq!("ck", public_suffix("www.ck"));
+        assert_eq!("ck", public_suffix("a.www.ck"));
+        // 国際化ドメイン名の規則はPunycodeで一致する
+        assert_eq!("xn--55qx5d.cn", public_suffix("example.xn--55qx5d.cn"));
+
+        for domain in ["co.in", "com.mx", "blogspot.com", "s3.amazonaws.com"] {
+            assert!(!is_public_suffix(&format!("example.{}", domain)));
+            assert!(is_public_suffix(domain));
+        }
+        assert!(is_public_suffix("foo.kawasaki.jp"));
+        assert!(!is_public_suffix("city.kawasaki.jp"));
+    }
+
+    #[test]
+    fn test_public_suffix_domain_attribute() {
+        let mut jar = CookieJar::new();
+        for (host, domain) in [
+            ("bob.blogspot.com", "blogspot.com"),
+            ("alice.co.in", "co.in"),
+            ("alice.com.mx", "com.mx"),
+            ("bucket.s3.amazonaws.com", "s3.amazonaws.com"),
+        ] {
+            jar.set_cookie(
+                &url(&format!("http://{}/", host)),
+                &format!("a=1; Domain={}", domain),
+                NOW,
+            );
+        }
+        assert!(jar.is_empty());
+
+        // ホスト自身がパブリックサフィックスなら、そのホストだけに送るCookieになる
+        jar.set_cookie(
+            &url("http://blogspot.com/"),
+            "a=1; Domain=blogspot.com",
+            NOW,
+        );
+        assert_eq!(
+            None,
+            jar.cookie_header(&url("http://bob.blogspot.com/"), NOW, true)
+        );
+        assert_eq!(
+            Some("a=1".to_string()),
+            jar.cookie_header(&url("http://blogspot.com/"), NOW, true)
+        );
+    }
+
+    #[test]
+    fn test_ip_address() {
+        let mut jar = CookieJar::new();
+        jar.set_cookie(&url("http://127.0.0.1/"), "a=1; Domain=0.0.1", NOW);
+        assert!(jar.is_empty());
+        jar.set_cookie(&url("http://127.0.0.1/"), "a=1", NOW);
+        assert_eq!(
+            Some("a=1".to_string()),
+            jar.cookie_header(&url("http://127.0.0.1:8000/"), NOW, true)
+        );
+    }
+
+    #[test]
+    fn test_path() {
+        let mut jar = CookieJar::new();
+        jar.set_cookie(&url("http://example.com/docs/index.html"), "a=1", NOW);
+        jar.set_cookie(&url("http://example.com/"), "b=2; Path=/docs/api", NOW);
+        jar.set_cookie(&url("http://example.com/"), "c=3; Path=/", NOW);
+
+        assert_eq!("/docs", jar.cookies()[0].path());
+        assert_eq!(
+            Some("b=2; a=1; c=3".to_string()),
+            jar.cookie_header(&url("http://example.com/docs/api/v1"), NOW, true)
+        );
+        assert_eq!(
+            Some("c=3".to_string()),
+            jar.cookie_header(&url("http://example.com/docsearch"), NOW, true)
+        );
+    }
+
+    #[test]
+    fn test_expiry() {
+        let mut jar = CookieJar::new();
+        let u = url("http://example.com/");
+        jar.set_cookie(
+            &u,
+            "a=1; Max-Age=60; Expires=Thu, 01 Jan 1970 00:00:00 GMT",
+            NOW,
+        );
+        jar.set_cookie(&u, "b=2; Expires=Mon, 01-Jan-2024 00:10:00 GMT", NOW);
+        jar.set_cookie(&u, "c=3; Expires=Sun, 31 Dec 2023 00:00:00 GMT", NOW);
+        assert_eq!(Some(NOW + 600), jar.cookies()[1].expiry_time());
+        assert_eq!(2, jar.len());
+
+        assert_eq!(
+            Some("a=1; b=2".to_string()),
+            jar.cookie_header(&u, NOW + 59, true)
+        );
+        assert_eq!(
+            Some("b=2".to_string()),
+            jar.cookie_header(&u, NOW + 60, true)
+        );
+        assert_eq!(None, jar.cookie_header(&u, NOW + 600, true));
+        assert!(jar.is_empty());
+    }
+
+    #[test]
+    fn test_replace_and_delete() {
+        let mut jar = CookieJar::new();
+        let u = url("http://example.com/");
+        jar.set_cookie(&u, "a=1", NOW);
+        jar.set_cookie(&u, "a=2", NOW + 1);
+        assert_eq!(1, jar.len());
+        assert_eq!("2", jar.cookies()[0].value());
+
+        jar.set_cookie(&u, "a=; Max-Age=0", NOW + 2);
+        assert!(jar.is_empty());
+    }
+
+    #[test]
+    fn test_nameless_cookie() {
+        let mut jar = CookieJar::new();
+        let u = url("http://example.com/");
+        jar.set_cookie(&u, "value", NOW);
+        jar.set_cookie(&u, "a=1", NOW + 1);
+        assert_eq!(
+            Some("value; a=1".to_string()),
+            jar.cookie_header(&u, NOW + 2, true)
+        );
+
+        // "=value"も名前が空のCookieで、同じ名前として置き換える
+        jar.set_cookie(&u, "=other", NOW + 3);
+        assert_eq!(2, jar.len());
+        assert_eq!(
+            Some("other; a=1".to_string()),
+            jar.cookie_header(&u, NOW + 4, true)
+        );
+
+        // 名前も値も空のものと、値が接頭辞で始まるものは保存しない
+        jar.set_cookie(&u, "=; Path=/", NOW + 5);
+        jar.set_cookie(&u, "__Secure-b=2", NOW + 5);
+        jar.set_cookie(&u, "__Host-c", NOW + 5);
+        assert_eq!(
+            Some("other; a=1".to_string()),
+            jar.cookie_header(&u, NOW + 6, true)
+        );
+    }
+
+    #[test]
+    fn test_secure_and_same_site() {
+        let mut jar = CookieJar::new();
+        let u = url("http://example.com/");
+        // 安全でない接続からはSecure属性付きのCookieを設定できない
+        jar.set_cookie(&u, "a=1; Secure", NOW);
+        jar.set_cookie(&u, "b=2; SameSite=None", NOW);
+        jar.set_cookie(&u, "__Secure-c=3", NOW);
+        jar.set_cookie(&u, "__Host-d=4; Path=/", NOW);
+        assert!(jar.is_empty());
+
+        jar.set_cookie(&u, "a=1; HttpOnly", NOW);
+        jar.set_cookie(&u, "b=2; SameSite=Strict", NOW);
+        assert!(jar.cookies()[0].http_only());
+        assert_eq!(SameSite::Strict, jar.cookies()[1].same_site());
+
+        assert_eq!(
+            Some("a=1; b=2".to_string()),
+            jar.cookie_header(&u, NOW, true)
+        );
+        assert_eq!(Some("a=1".to_string()), jar.cookie_header(&u, NOW, false));
+    }
+
+    #[test]
+    fn test_secure() {
+        let mut jar = CookieJar::new();
+        let secure = url("https://example.com/");
+        jar.set_cookie(&secure, "a=1; Secure", NOW);
+        jar.set_cookie(&secure, "__Secure-b=2; Secure; Domain=example.com", NOW);
+        jar.set_cookie(&secure, "__Host-c=3; Secure; Path=/", NOW);
+        jar.set_cookie(&secure, "d=4; Secure; SameSite=None", NOW);
+        // __Host-はDomain属性を指定できない
+        jar.set_cookie(
+            &secure,
+            "__Host-e=5; Secure; Path=/; Domain=example.com",
+            NOW,
+        );
+        assert_eq!(4, jar.len());
+        assert!(jar.cookies().iter().all(|cookie| cookie.secure()));
+
+        // Secure属性付きのCookieは安全な接続にだけ送る
+        assert_eq!(
+            Some("a=1; __Secure-b=2; __Host-c=3; d=4".to_string()),
+            jar.cookie_header(&secure, NOW, true)
+        );
+        assert_eq!(
+            None,
+            jar.cookie_header(&url("http://example.com/"), NOW, true)
+        );
+        assert_eq!(
+            Some("__Secure-b=2".to_string()),
+            jar.cookie_header(&url("https://www.example.com/"), NOW, true)
+        );
+    }
+
+    #[test]
+    fn test_cookie_date() {
+        let expected = Some(784111777);
+        assert_eq!(expected, parse_cookie_date("Sun, 06 Nov 1994 08:49:37 GMT"));
+        assert_eq!(
+            expected,
+            parse_cookie_date("Sunday, 06-Nov-94 08:49:37 GMT")
+        );
+        assert_eq!(expected, parse_cookie_date("Sun Nov  6 08:49:37 1994"));
+        assert_eq!(expected, parse_cookie_date("6 november 1994 8:49:37"));
+        assert_eq!(None, parse_cookie_date("Sun, 06 Nov 1994"));
+        assert_eq!(None, parse_cookie_date("Sun, 32 Nov 1994 08:49:37 GMT"));
+    }
+
+    #[test]
+    fn test_response_and_request() {
+        let mut jar = CookieJar::new();
+        let u = url("http://example.com/login");
+        let response = HttpResponse::from_parts(
+            200,
+            "OK",
+            alloc::vec![
+                Header::new("Set-Cookie".to_string(), "sid=abc; Path=/".to_string()),
+                Header::new("Set-Cookie".to_string(), "lang=ja; Path=/".to_string()),
+            ],
+            Vec::new(),
+        );
+        jar.store_response_cookies(&u, &response, NOW);
+
+        let request = jar.add_cookie_header(
+            HttpRequest::new(Method::Get, url("http://example.com/")),
+            NOW,
+            true,
+        );
+        assert_eq!(
+            Some("sid=abc; lang=ja".to_string()),
+            request.header_value("Cookie")
+        );
+    }
+}
@@ -0,0 +1,104 @@
+//! HTTP-dateの解析
+//! https://www.rfc-editor.org/rfc/rfc9110#section-5.6.7
+
+use alloc::vec::Vec;
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+pub(crate) fn month(name: &str) -> Option<u32> {
+    MONTHS
+        .iter()
+        .position(|m| m.eq_ignore_ascii_case(name))
+        .map(|i| i as u32 + 1)
+}
+
+/// "08:49:37"のような時刻を(時, 分, 秒)に分解します
+fn time_of_day(s: &str) -> Option<(u64, u64, u64)> {
+    let mut parts = s.split(':').map(|p| p.parse::<u64>().ok());
+    let hour = parts.next()??;
+    let minute = parts.next()??;
+    let second = parts.next()??;
+    if parts.next().is_some() || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+    Some((hour, minute, second))
+}
+
+/// 1970年1月1日からの日数を返します
+/// http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+pub(crate) fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// HTTP-dateを1970年1月1日0時0分0秒(UTC)からの秒数に変換します
+/// IMF-fixdateに加えて、廃止された形式のRFC 850とasctimeの日付も受け付けます
+pub fn parse_http_date(s: &str) -> Option<u64> {
+    let tokens: Vec<&str> = s
+        .split(|c: char| c == ' ' || c == ',' || c == '-')
+        .filter(|t| !t.is_empty())
+        .collect();
+
+    let (day, mon, year, time) = match tokens.as_slice() {
+        // asctime-date: "Sun Nov  6 08:49:37 1994"
+        [_, mon, day, time, year] if month(mon).is_some() => (*day, *mon, *year, *time),
+        // IMF-fixdate: "Sun, 06 Nov 1994 08:49:37 GMT"
+        // rfc850-date: "Sunday, 06-Nov-94 08:49:37 GMT"
+        [_, day, mon, year, time, "GMT"] => (*day, *mon, *year, *time),
+        _ => return None,
+    };
+
+    let day = day.parse::<u32>().ok().filter(|d| (1..=31).contains(d))?;
+    let month = month(mon)?;
+    let mut year = year.parse::<i64>().ok()?;
+    if year < 100 {
+        // 2桁の年は50以上を1900年代、それ以外を2000年代とみなす
+        // https://www.rfc-editor.org/rfc/rfc9110#section-5.6.7
+        year += if year >= 50 { 1900 } else { 2000 };
+    }
+    let (hour, minute, second) = time_of_day(time)?;
+
+    let days = days_from_civil(year, month, day);
+    if days < 0 {
+        return None;
+    }
+    Some(days as u64 * 86400 + hour * 3600 + minute * 60 + second)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_formats() {
+        let expected = Some(784111777);
+        assert_eq!(expected, parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"));
+        assert_eq!(expected, parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT"));
+        assert_eq!(expected, parse_http_date("Sun Nov  6 08:49:37 1994"));
+    }
+
+    #[test]
+    fn test_leap_year() {
+        assert_eq!(
+            Some(1709164800),
+            parse_http_date("Thu, 29 Feb 2024 00:00:00 GMT")
+        );
+        assert_eq!(Some(0), parse_http_date("Thu, 01 Jan 1970 00:00:00 GMT"));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(None, parse_http_date("0"));
+        assert_eq!(None, parse_http_date("Sun, 06 Nov 1994 08:49:37 JST"));
+        assert_eq!(None, parse_http_date("Sun, 06 Foo 1994 08:49:37 GMT"));
+        assert_eq!(None, parse_http_date("Sun, 06 Nov 1994 25:49:37 GMT"));
+    }
+}
@@ -0,0 +1,516 @@
+//! deflate形式で圧縮されたデータの展開
+//! https://www.rfc-editor.org/rfc/rfc1951
+//! zlib形式（https://www.rfc-editor.org/rfc/rfc1950）と
+//! gzip形式（https://www.rfc-editor.org/rfc/rfc1952）のヘッダとチェックサムも扱います
+
+use alloc::format;
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use super::HttpParseError;
+use crate::error::Error;
+
+fn invalid(reason: &str) -> Error {
+    Error::Http(HttpParseError::InvalidCompressedBody(reason.to_string()))
+}
+
+/// 長さの符号257から285に対応する基本の長さと追加ビット数
+/// https://www.rfc-editor.org/rfc/rfc1951#section-3.2.5
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+
+/// 距離の符号0から29に対応する基本の距離と追加ビット数
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// 動的ハフマン符号の符号長を送る順番
+/// https://www.rfc-editor.org/rfc/rfc1951#section-3.2.7
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+const MAX_BITS: usize = 15;
+
+/// 下位ビットから順にビットを読み出すリーダー
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bit_buffer: u32,
+    bit_count: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+            bit_buffer: 0,
+            bit_count: 0,
+        }
+    }
+
+    fn bits(&mut self, count: u32) -> Result<u32, Error> {
+        while self.bit_count < count {
+            let byte = *self
+                .data
+                .get(self.pos)
+                .ok_or_else(|| invalid("unexpected end of deflate stream"))?;
+            self.pos += 1;
+            self.bit_buffer |= (byte as u32) << self.bit_count;
+            self.bit_count += 8;
+        }
+        let value = self.bit_buffer & ((1u64 << count) - 1) as u32;
+        self.bit_buffer >>= count;
+        self.bit_count -= count;
+        Ok(value)
+    }
+
+    /// 読みかけのバイトの残りのビットを捨てます
+    fn align_to_byte(&mut self) {
+        self.bit_buffer = 0;
+        self.bit_count = 0;
+    }
+
+    /// これまでに読み込んだバイト数
+    fn consumed(&self) -> usize {
+        self.pos
+    }
+}
+
+/// 正規ハフマン符号の復号表
+/// https://www.rfc-editor.org/rfc/rfc1951#section-3.2.2
+struct Huffman {
+    /// 符号長ごとの符号の数
+    counts: [u16; MAX_BITS + 1],
+    /// 符号の順に並べたシンボル
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Self, Error> {
+        let mut counts = [0u16; MAX_BITS + 1];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+
+        // 符号長の組み合わせが多すぎないかを確認する
+        let mut left: i32 = 1;
+        for &count in &counts[1..] {
+            left = (left << 1) - count as i32;
+            if left < 0 {
+                return Err(invalid("over-subscribed huffman code"));
+            }
+        }
+
+        let mut offsets = [0u16; MAX_BITS + 2];
+        for length in 1..=MAX_BITS {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+
+        let mut symbols = vec![0u16; lengths.len()];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                symbols[offsets[length as usize] as usize] = symbol as u16;
+                offsets[length as usize] += 1;
+            }
+        }
+
+        Ok(Self { counts, symbols })
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u16, Error> {
+        // 符号は上位ビットから送られてくるため1ビットずつ読む
+        let mut code: i32 = 0;
+        let mut first: i32 = 0;
+        let mut index: i32 = 0;
+        for length in 1..=MAX_BITS {
+            code |= reader.bits(1)? as i32;
+            let count = self.counts[length] as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(invalid("invalid huffman code"))
+    }
+}
+
+/// 固定ハフマン符号の復号表を返します
+/// https://www.rfc-editor.org/rfc/rfc1951#section-3.2.6
+fn fixed_huffman() -> Result<(Huffman, Huffman), Error> {
+    let mut lengths = [0u8; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+    Ok((Huffman::new(&lengths)?, Huffman::new(&[5u8; 30])?))
+}
+
+/// 動的ハフマン符号の復号表をブロックの先頭から読み込みます
+/// https://www.rfc-editor.org/rfc/rfc1951#section-3.2.7
+fn dynamic_huffman(reader: &mut BitReader) -> Result<(Huffman, Huffman), Error> {
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_length_count = reader.bits(4)? as usize + 4;
+    if literal_count > 286 || distance_count > 30 {
+        return Err(invalid("too many huffman codes"));
+    }
+
+    let mut code_lengths = [0u8; 19];
+    for &i in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_lengths[i] = reader.bits(3)? as u8;
+    }
+    let code_length_huffman = Huffman::new(&code_lengths)?;
+
+    let mut lengths = vec![0u8; literal_count + distance_count];
+    let mut i = 0;
+    while i < lengths.len() {
+        let symbol = code_length_huffman.decode(reader)?;
+        let (length, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                if i == 0 {
+                    return Err(invalid("repeat with no previous length"));
+                }
+                (lengths[i - 1], 3 + reader.bits(2)? as usize)
+            }
+            17 => (0, 3 + reader.bits(3)? as usize),
+            _ => (0, 11 + reader.bits(7)? as usize),
+        };
+        if i + repeat > lengths.len() {
+            return Err(invalid("too many code lengths"));
+        }
+        lengths[i..i + repeat].fill(length);
+        i += repeat;
+    }
+
+    if lengths[256] == 0 {
+        return Err(invalid("missing end-of-block code"));
+    }
+
+    Ok((
+        Huffman::new(&lengths[..literal_count])?,
+        Huffman::new(&lengths[literal_count..])?,
+    ))
+}
+
+fn inflate_block(
+    reader: &mut BitReader,
+    output: &mut Vec<u8>,
+    literal: &Huffman,
+    distance: &Huffman,
+) -> Result<(), Error> {
+    loop {
+        let symbol = literal.decode(reader)? as usize;
+        match symbol {
+            0..=255 => output.push(symbol as u8),
+            256 => return Ok(()),
+            257..=285 => {
+                let i = symbol - 257;
+                let length =
+                    LENGTH_BASE[i] as usize + reader.bits(LENGTH_EXTRA[i] as u32)? as usize;
+
+                let i = distance.decode(reader)? as usize;
+                if i >= DISTANCE_BASE.len() {
+                    return Err(invalid("invalid distance code"));
+                }
+                let distance =
+                    DISTANCE_BASE[i] as usize + reader.bits(DISTANCE_EXTRA[i] as u32)? as usize;
+                if distance > output.len() {
+                    return Err(invalid("distance too far back"));
+                }
+
+                // 長さが距離より長い場合はコピーしたばかりのバイトを繰り返すため、1バイトずつコピーする
+                let start = output.len() - distance;
+                for j in 0..length {
+                    output.push(output[start + j]);
+                }
+            }
+            _ => return Err(invalid("invalid literal/length code")),
+        }
+    }
+}
+
+/// deflate形式のデータを展開し、展開したデータと読み込んだバイト数を返します
+/// https://www.rfc-editor.org/rfc/rfc1951#section-3.2.3
+pub fn inflate(data: &[u8]) -> Result<(Vec<u8>, usize), Error> {
+    let mut reader = BitReader::new(data);
+    let mut output = Vec::new();
+
+    loop {
+        let is_final = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            // 非圧縮ブロック
+            0 => {
+                reader.align_to_byte();
+                let header = data
+                    .get(reader.pos..reader.pos + 4)
+                    .ok_or_else(|| invalid("unexpected end of deflate stream"))?;
+                let length = u16::from_le_bytes([header[0], header[1]]);
+                let nlength = u16::from_le_bytes([header[2], header[3]]);
+                if length != !nlength {
+                    return Err(invalid("stored block length mismatch"));
+                }
+                let start = reader.pos + 4;
+                let block = data
+                    .get(start..start + length as usize)
+                    .ok_or_else(|| invalid("unexpected end of deflate stream"))?;
+                output.extend_from_slice(block);
+                reader.pos = start + length as usize;
+            }
+            1 => {
+                let (literal, distance) = fixed_huffman()?;
+                inflate_block(&mut reader, &mut output, &literal, &distance)?;
+            }
+            2 => {
+                let (literal, distance) = dynamic_huffman(&mut reader)?;
+                inflate_block(&mut reader, &mut output, &literal, &distance)?;
+            }
+            _ => return Err(invalid("invalid block type")),
+        }
+
+        if is_final {
+            return Ok((output, reader.consumed()));
+        }
+    }
+}
+
+/// https://www.rfc-editor.org/rfc/rfc1950#section-9
+fn adler32(data: &[u8]) -> u32 {
+    const MOD_ADLER: u32 = 65521;
+    let mut a: u32 = 1;
+    let mut b: u32 = 0;
+    // オーバーフローしない範囲でまとめて剰余をとる
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= MOD_ADLER;
+        b %= MOD_ADLER;
+    }
+    (b << 16) | a
+}
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 {
+                0xEDB88320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+}
+
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+/// https://www.rfc-editor.org/rfc/rfc1952#section-8
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &byte| {
+        CRC32_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+/// zlib形式のデータを展開します
+/// https://www.rfc-editor.org/rfc/rfc1950#section-2.2
+pub fn zlib_decompress(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let (cmf, flg) = match data {
+        [cmf, flg, ..] => (*cmf, *flg),
+        _ => return Err(invalid("zlib header is too short")),
+    };
+    if cmf & 0x0F != 8 || ((cmf as u16) << 8 | flg as u16) % 31 != 0 {
+        return Err(invalid("invalid zlib header"));
+    }
+    if flg & 0x20 != 0 {
+        return Err(invalid("zlib preset dictionary is not supported"));
+    }
+
+    let (output, consumed) = inflate(&data[2..])?;
+    let trailer = data
+        .get(2 + consumed..2 + consumed + 4)
+        .ok_or_else(|| invalid("missing adler-32 checksum"))?;
+    let checksum = u32::from_be_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+    if checksum != adler32(&output) {
+        return Err(invalid("adler-32 checksum mismatch"));
+    }
+    Ok(output)
+}
+
+/// gzip形式のデータを展開します。複数のメンバーが連結されている場合はすべて展開します
+/// https://www.rfc-editor.org/rfc/rfc1952#section-2.3
+pub fn gzip_decompress(data: &[u8]) -> Result<Vec<u8>, Error> {
+    const FHCRC: u8 = 0x02;
+    const FEXTRA: u8 = 0x04;
+    const FNAME: u8 = 0x08;
+    const FCOMMENT: u8 = 0x10;
+
+    let mut output = Vec::new();
+    let mut pos = 0;
+
+    while pos < data.len() {
+        let member = &data[pos..];
+        if member.len() < 10 || member[0] != 0x1F || member[1] != 0x8B || member[2] != 8 {
+            return Err(invalid("invalid gzip header"));
+        }
+        let flags = member[3];
+        let mut i = 10;
+
+        let too_short = || invalid("gzip header is too short");
+        if flags & FEXTRA != 0 {
+            let xlen = member.get(i..i + 2).ok_or_else(too_short)?;
+            i += 2 + u16::from_le_bytes([xlen[0], xlen[1]]) as usize;
+        }
+        for flag in [FNAME, FCOMMENT] {
+            if flags & flag != 0 {
+                // ゼロ終端の文字列を読み飛ばす
+                i += member
+                    .get(i..)
+                    .and_then(|rest| rest.iter().position(|&b| b == 0))
+                    .ok_or_else(too_short)?
+                    + 1;
+            }
+        }
+        if flags & FHCRC != 0 {
+            i += 2;
+        }
+
+        let (inflated, consumed) = inflate(member.get(i..).ok_or_else(too_short)?)?;
+        i += consumed;
+
+        let trailer = member
+            .get(i..i + 8)
+            .ok_or_else(|| invalid("missing gzip trailer"))?;
+        let checksum = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+        let size = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+        if checksum != crc32(&inflated) {
+            return Err(invalid("crc-32 checksum mismatch"));
+        }
+        if size != inflated.len() as u32 {
+            return Err(invalid(&format!(
+                "gzip size mismatch: expected {}, got {}",
+                size,
+                inflated.len()
+            )));
+        }
+
+        output.extend_from_slice(&inflated);
+        pos += i + 8;
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::String;
+
+    const DYNAMIC: &[u8] = b"\
+        \x7d\xd3\x3d\x0a\x02\x41\x0c\x80\xd1\xde\x53\xcc\x0d\x34\x89\xbf\
+        \x30\x78\x9b\x5d\x1c\x58\xb1\x70\xee\x8f\x36\x76\xf2\xda\x7c\x55\
+        \x1e\x49\xdf\xc6\x7d\xcc\xe5\xd9\x0e\xed\xb5\xb6\xf9\x58\xda\x36\
+        \xde\xb3\xef\xbf\xe3\x5d\xff\xb5\x40\x4b\xb4\x42\x3b\xa2\x9d\xd0\
+        \xce\x68\x17\xb4\x2b\xda\x4d\xbb\x13\x46\x32\x21\x9a\x90\x4d\x08\
+        \x27\xa4\x13\xe2\x09\xf9\x84\x80\x42\x42\x29\xa1\xe4\xed\x48\x28\
+        \x25\x94\x12\x4a\x09\xa5\x84\x52\x42\x29\xa1\x94\x50\x49\xa8\x24\
+        \x54\x7c\x2f\x09\x95\x84\x4a\x42\x25\xa1\x92\x50\x49\xa8\xfe\x08\
+        \x7d\x00";
+
+    const GZIP: &[u8] = b"\x1f\x8b\x08\x08\x00\x00\x00\x00\x02\xff\x69\x6e\x64\x65\x78\x2e\x68\x74\x6d\x6c\x00\xb3\x29\xb0\x4b\xaf\xca\x2c\xb0\xd1\x2f\xb0\x03\x00\xec\x4c\xe0\x7a\x0b\x00\x00\x00";
+
+    #[test]
+    fn test_stored_block() {
+        let (output, consumed) = inflate(b"\x01\x06\x00\xf9\xffstored").expect("failed to inflate");
+        assert_eq!(b"stored".to_vec(), output);
+        assert_eq!(11, consumed);
+    }
+
+    #[test]
+    fn test_fixed_huffman() {
+        let (output, _) = inflate(b"\xcb\x48\xcd\xc9\xc9\x57\xc8\xc0\x20\x8b\x13\x93\x12\x15\x01")
+            .expect("failed to inflate");
+        assert_eq!(b"hello hello hello hello saba!".to_vec(), output);
+    }
+
+    #[test]
+    fn test_dynamic_huffman() {
+        let mut expected = String::new();
+        for i in 0..40 {
+            expected.push_str(&format!("<li>item {} of the list</li>\n", i));
+        }
+        let (output, _) = inflate(DYNAMIC).expect("failed to inflate");
+        assert_eq!(expected.into_bytes(), output);
+    }
+
+    #[test]
+    fn test_zlib() {
+        let output = zlib_decompress(
+            b"\x78\x9c\xab\xca\xc9\x4c\x52\x48\x49\x2c\x49\x54\xa8\x82\xb1\x00\x46\x63\x06\xf7",
+        )
+        .expect("failed to decompress");
+        assert_eq!(b"zlib data zlib data".to_vec(), output);
+
+        assert!(zlib_decompress(
+            b"\x78\x9c\xab\xca\xc9\x4c\x52\x48\x49\x2c\x49\x54\xa8\x82\xb1\x00\x46\x63\x06\xf8"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_gzip() {
+        assert_eq!(
+            b"<p>gzip</p>".to_vec(),
+            gzip_decompress(GZIP).expect("failed to decompress")
+        );
+
+        // 連結された複数のメンバー
+        let mut members = GZIP.to_vec();
+        members.extend_from_slice(GZIP);
+        assert_eq!(
+            b"<p>gzip</p><p>gzip</p>".to_vec(),
+            gzip_decompress(&members).expect("failed to decompress")
+        );
+    }
+
+    #[test]
+    fn test_gzip_crc_mismatch() {
+        let mut corrupted = GZIP.to_vec();
+        let len = corrupted.len();
+        corrupted[len - 8] ^= 0xFF;
+        assert_eq!(
+            Err(invalid("crc-32 checksum mismatch")),
+            gzip_decompress(&corrupted)
+        );
+    }
+
+    #[test]
+    fn test_truncated() {
+        assert!(inflate(b"\xcb\x48\xcd").is_err());
+        assert!(gzip_decompress(&GZIP[..20]).is_err());
+    }
+}
@@ -2,11 +2,18 @@
 
 extern crate alloc;
 
+pub mod base64;
 pub mod browser;
 pub mod constants;
+pub mod crypto;
 pub mod display_item;
+pub mod encoding;
 pub mod error;
+pub mod error_page;
+pub mod fetch;
 pub mod http;
+pub mod mime;
+pub mod net;
 pub mod renderer;
 pub mod url;
 pub mod utils;
@@ -0,0 +1,260 @@
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt::{Display, Formatter};
+
+/// https://mimesniff.spec.whatwg.org/#mime-type-representation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MimeType {
+    type_: String,
+    subtype: String,
+    parameters: Vec<(String, String)>,
+}
+
+/// https://fetch.spec.whatwg.org/#http-whitespace
+fn is_http_whitespace(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\r' | ' ')
+}
+
+/// https://mimesniff.spec.whatwg.org/#http-token-code-point
+fn is_http_token_code_point(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c)
+}
+
+/// https://mimesniff.spec.whatwg.org/#http-quoted-string-token-code-point
+fn is_http_quoted_string_token_code_point(c: char) -> bool {
+    c == '\t' || (' '..='~').contains(&c) || ('\u{80}'..='\u{ff}').contains(&c)
+}
+
+fn is_http_token(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(is_http_token_code_point)
+}
+
+impl MimeType {
+    pub fn new(type_: &str, subtype: &str) -> Self {
+        Self {
+            type_: type_.to_ascii_lowercase(),
+            subtype: subtype.to_ascii_lowercase(),
+            parameters: Vec::new(),
+        }
+    }
+
+    /// https://mimesniff.spec.whatwg.org/#parse-a-mime-type
+    pub fn parse(input: &str) -> Option<Self> {
+        let input: Vec<char> = input.trim_matches(is_http_whitespace).chars().collect();
+        let mut pos = 0;
+
+        let collect_until = |pos: &mut usize, stop: &dyn Fn(char) -> bool| -> String {
+            let start = *pos;
+            while *pos < input.len() && !stop(input[*pos]) {
+                *pos += 1;
+            }
+            input[start..*pos].iter().collect()
+        };
+
+        let type_ = collect_until(&mut pos, &|c| c == '/');
+        if !is_http_token(&type_) || pos >= input.len() {
+            return None;
+        }
+        pos += 1;
+
+        let subtype = collect_until(&mut pos, &|c| c == ';');
+        let subtype = subtype.trim_end_matches(is_http_whitespace);
+        if !is_http_token(subtype) {
+            return None;
+        }
+
+        let mut mime_type = MimeType::new(&type_, subtype);
+
+        while pos < input.len() {
+            // ';'を読み飛ばす
+            pos += 1;
+            while pos < input.len() && is_http_whitespace(input[pos]) {
+                pos += 1;
+            }
+
+            let name = collect_until(&mut pos, &|c| c == ';' || c == '=').to_ascii_lowercase();
+            if pos < input.len() {
+                if input[pos] == ';' {
+                    continue;
+                }
+                pos += 1;
+            }
+            if pos >= input.len() {
+                break;
+            }
+
+            let value = if input[pos] == '"' {
+                let value = Self::collect_quoted_string(&input, &mut pos);
+                collect_until(&mut pos, &|c| c == ';');
+                value
+            } else {
+                let value = collect_until(&mut pos, &|c| c == ';');
+                let value = value.trim_end_matches(is_http_whitespace).to_string();
+                if value.is_empty() {
+                    continue;
+                }
+                value
+            };
+
+            if is_http_token(&name)
+                && value.chars().all(is_http_quoted_string_token_code_point)
+                && mime_type.parameter(&name).is_none()
+            {
+                mime_type.parameters.push((name, value));
+            }
+        }
+
+        Some(mime_type)
+    }
+
+    /// https://fetch.spec.whatwg.org/#collect-an-http-quoted-string
+    fn collect_quoted_string(input: &[char], pos: &mut usize) -> String {
+        let mut value = String::new();
+        // 開始の'"'を読み飛ばす
+        *pos += 1;
+        while *pos < input.len() {
+            let c = input[*pos];
+            *pos += 1;
+            match c {
+                '"' => break,
+                '\\' => {
+                    if *pos >= input.len() {
+                        value.push('\\');
+                        break;
+                    }
+                    value.push(input[*pos]);
+                    *pos += 1;
+                }
+                _ => value.push(c),
+            }
+        }
+        value
+    }
+
+    pub fn type_(&self) -> String {
+        self.type_.clone()
+    }
+
+    pub fn subtype(&self) -> String {
+        self.subtype.clone()
+    }
+
+    /// https://mimesniff.spec.whatwg.org/#mime-type-essence
+    pub fn essence(&self) -> String {
+        let mut essence = self.type_.clone();
+        essence.push('/');
+        essence.push_str(&self.subtype);
+        essence
+    }
+
+    pub fn parameter(&self, name: &str) -> Option<String> {
+        self.parameters
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.clone())
+    }
+
+    pub fn set_parameter(&mut self, name: &str, value: &str) {
+        let name = name.to_ascii_lowercase();
+        match self.parameters.iter_mut().find(|(n, _)| *n == name) {
+            Some(parameter) => parameter.1 = value.to_string(),
+            None => self.parameters.push((name, value.to_string())),
+        }
+    }
+
+    /// https://mimesniff.spec.whatwg.org/#html-mime-type
+    pub fn is_html(&self) -> bool {
+        self.essence() == "text/html"
+    }
+
+    /// https://mimesniff.spec.whatwg.org/#javascript-mime-type
+    pub fn is_javascript(&self) -> bool {
+        matches!(
+            self.essence().as_str(),
+            "application/ecmascript"
+                | "application/javascript"
+                | "application/x-ecmascript"
+                | "application/x-javascript"
+                | "text/ecmascript"
+                | "text/javascript"
+        )
+    }
+
+    /// https://mimesniff.spec.whatwg.org/#json-mime-type
+    pub fn is_json(&self) -> bool {
+        self.subtype.ends_with("+json")
+            || matches!(self.essence().as_str(), "application/json" | "text/json")
+    }
+}
+
+impl Display for MimeType {
+    /// https://mimesniff.spec.whatwg.org/#serialize-a-mime-type
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        write!(f, "{}", self.essence())?;
+        for (name, value) in &self.parameters {
+            write!(f, ";{}=", name)?;
+            if is_http_token(value) {
+                write!(f, "{}", value)?;
+            } else {
+                write!(f, "\"")?;
+                for c in value.chars() {
+                    if c == '"' || c == '\\' {
+                        write!(f, "\\")?;
+                    }
+                    write!(f, "{}", c)?;
+                }
+                write!(f, "\"")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let mime = MimeType::parse(" Text/HTML ; Charset=\"Shift_JIS\" ;foo=bar")
+            .expect("failed to parse a mime type");
+        assert_eq!("text/html", mime.essence());
+        assert_eq!(Some("Shift_JIS".to_string()), mime.parameter("charset"));
+        assert_eq!(Some("bar".to_string()), mime.parameter("foo"));
+        assert!(mime.is_html());
+    }
+
+    #[test]
+    fn test_javascript_and_json() {
+        let parse = |s| MimeType::parse(s).expect("failed to parse a mime type");
+        assert!(parse("application/javascript; charset=utf-8").is_javascript());
+        assert!(!parse("application/json").is_javascript());
+        assert!(parse("application/json").is_json());
+        assert!(parse("application/ld+json").is_json());
+        assert!(!parse("text/plain").is_json());
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(None, MimeType::parse("text"));
+        assert_eq!(None, MimeType::parse("text/"));
+        assert_eq!(None, MimeType::parse("te xt/plain"));
+    }
+
+    #[test]
+    fn test_first_parameter_wins() {
+        let mime = MimeType::parse("text/plain;charset=utf-8;charset=ascii;empty=")
+            .expect("failed to parse a mime type");
+        assert_eq!(Some("utf-8".to_string()), mime.parameter("charset"));
+        assert_eq!(None, mime.parameter("empty"));
+    }
+
+    #[test]
+    fn test_serialize() {
+        let mime = MimeType::parse("text/plain;a=\"b c\";d=\"e\\\"\"")
+            .expect("failed to parse a mime type");
+        assert_eq!("text/plain;a=\"b c\";d=\"e\\\"\"", mime.to_string());
+    }
+}
@@ -0,0 +1,38 @@
+//! ネットワーク接続の抽象化
+//! 実行環境ごとのソケットAPIの違いをトレイトで隠し、その上のHTTPの処理を共通化します
+
+#[cfg(any(test, feature = "std"))]
+pub mod std_net;
+pub mod tls;
+
+use crate::error::Error;
+use alloc::string::ToString;
+
+/// TCPなどの双方向のバイトストリーム
+pub trait Connection {
+    /// バッファの内容を送信し、送信したバイト数を返します
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error>;
+
+    /// 受信したデータをバッファに書き込み、そのバイト数を返します。0は接続が閉じられたことを表します
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error>;
+
+    fn write_all(&mut self, mut buf: &[u8]) -> Result<(), Error> {
+        while !buf.is_empty() {
+            let written = self.write(buf)?;
+            if written == 0 {
+                return Err(Error::Network(
+                    "connection closed while writing".to_string(),
+                ));
+            }
+            buf = &buf[written..];
+        }
+        Ok(())
+    }
+}
+
+/// ホスト名とポート番号を指定して接続を確立する手段
+pub trait Transport {
+    type Connection: Connection;
+
+    fn connect(&self, host: &str, port: u16) -> Result<Self::Connection, Error>;
+}
@@ -0,0 +1,59 @@
+//! std::netを使った接続の実装
+//! ホストOS上でテストやツールからHTTPの処理を動かすために使います
+
+extern crate std;
+
+use alloc::format;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+
+use super::tls::RandomSource;
+use super::{Connection, Transport};
+use crate::error::Error;
+
+impl Connection for TcpStream {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        Write::write(self, buf).map_err(|e| Error::Network(format!("failed to write: {}", e)))
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        Read::read(self, buf).map_err(|e| Error::Network(format!("failed to read: {}", e)))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StdTransport;
+
+impl StdTransport {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Transport for StdTransport {
+    type Connection = TcpStream;
+
+    fn connect(&self, host: &str, port: u16) -> Result<TcpStream, Error> {
+        TcpStream::connect((host, port))
+            .map_err(|e| Error::Network(format!("failed to connect to {}:{}: {}", host, port, e)))
+    }
+}
+
+/// OSの乱数生成器（/dev/urandom）から乱数を読み取ります
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StdRandom;
+
+impl StdRandom {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl RandomSource for StdRandom {
+    fn fill_bytes(&self, buf: &mut [u8]) -> Result<(), Error> {
+        File::open("/dev/urandom")
+            .and_then(|mut file| file.read_exact(buf))
+            .map_err(|e| Error::Other(format!("failed to read random bytes: {}", e)))
+    }
+}
@@ -0,0 +1,711 @@
+//! TLS 1.3のクライアント
+//! https://www.rfc-editor.org/rfc/rfc8446
+//!
+//! 暗号スイートはTLS_AES_128_GCM_SHA256、TLS_AES_256_GCM_SHA384、TLS_CHACHA20_POLY1305_SHA256に対応します
+//! 鍵交換はX25519とsecp256r1に対応し、secp256r1はサーバーがHelloRetryRequestで選んだ場合だけ使います
+//! TLS 1.2には対応しません。暗号スイートや鍵交換の組み合わせが多く、CBCモードのパディングオラクルのような
+//! 既知の攻撃への対策も必要になるためです。TLS 1.3に対応していないサーバーとの接続はハンドシェイクの失敗になります
+
+pub mod handshake;
+pub mod key_schedule;
+pub mod record;
+pub mod roots;
+pub mod x509;
+
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{Debug, Formatter};
+
+use self::handshake::{handshake_message, KEY_UPDATE, NEW_SESSION_TICKET};
+use self::key_schedule::next_traffic_secret;
+use self::record::{
+    RecordLayer, ALERT_BAD_CERTIFICATE, ALERT_BAD_RECORD_MAC, ALERT_HANDSHAKE_FAILURE,
+    APPLICATION_DATA, HANDSHAKE, MAX_FRAGMENT_LENGTH,
+};
+use self::roots::ROOT_CERTIFICATES;
+use self::x509::Certificate;
+use super::Connection;
+use crate::error::Error;
+use crate::url::host::Host;
+
+/// TLSの接続の失敗の理由
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TlsError {
+    /// サーバーから致命的なアラートを受け取った
+    /// https://www.rfc-editor.org/rfc/rfc8446#section-6
+    Alert(u8),
+    /// サーバー証明書を信頼できない
+    BadCertificate(String),
+    /// ハンドシェイクのメッセージが不正か、対応していない方式をサーバーが選んだ
+    HandshakeFailure(String),
+    /// 受信したレコードを復号できなかった
+    DecryptError,
+}
+
+/// 暗号論的に安全な乱数の供給源
+pub trait RandomSource {
+    /// バッファを乱数で埋めます
+    fn fill_bytes(&self, buf: &mut [u8]) -> Result<(), Error>;
+}
+
+/// TLSの接続の設定
+#[derive(Clone)]
+pub struct TlsConfig {
+    roots: Rc<Vec<Certificate>>,
+    /// 証明書の有効期間の確認に使う現在時刻（1970年1月1日からの秒数）
+    /// 分からない場合は、x509::MIN_VALIDATION_TIMEの時点で期限が切れていないことだけを確認する
+    time: Option<u64>,
+    random: Rc<dyn RandomSource>,
+}
+
+impl TlsConfig {
+    /// 同梱するルート証明書を信頼する設定を作ります
+    pub fn new(random: Rc<dyn RandomSource>) -> Self {
+        let roots = ROOT_CERTIFICATES
+            .iter()
+            .filter_map(|der| Certificate::from_der(der).ok())
+            .collect();
+        Self {
+            roots: Rc::new(roots),
+            time: None,
+            random,
+        }
+    }
+
+    /// 信頼するルート証明書をDERでエンコードされた証明書のリストに置き換えます
+    pub fn with_root_certificates(mut self, roots: &[&[u8]]) -> Result<Self, Error> {
+        let roots = roots
+            .iter()
+            .map(|der| Certificate::from_der(der))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.roots = Rc::new(roots);
+        Ok(self)
+    }
+
+    /// 証明書の有効期間を確認する現在時刻を設定します
+    /// 設定しない場合は、x509::MIN_VALIDATION_TIMEの時点で期限が切れていないことだけを確認します
+    pub fn with_time(mut self, time: u64) -> Self {
+        self.time = Some(time);
+        self
+    }
+}
+
+impl Debug for TlsConfig {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        f.debug_struct("TlsConfig")
+            .field("roots", &self.roots.len())
+            .field("time", &self.time)
+            .finish()
+    }
+}
+
+/// エラーの原因をサーバーに伝えるアラートの種類
+fn alert_description(error: &Error) -> Option<u8> {
+    match error {
+        Error::Tls(TlsError::BadCertificate(_)) => Some(ALERT_BAD_CERTIFICATE),
+        Error::Tls(TlsError::HandshakeFailure(_)) => Some(ALERT_HANDSHAKE_FAILURE),
+        Error::Tls(TlsError::DecryptError) => Some(ALERT_BAD_RECORD_MAC),
+        _ => None,
+    }
+}
+
+/// 接続の上にTLSで暗号化した通信路を確立したもの
+pub struct TlsConnection<C: Connection> {
+    record: RecordLayer<C>,
+    client_secret: Vec<u8>,
+    server_secret: Vec<u8>,
+    /// 復号したがまだ読み出していないアプリケーションデータ
+    plaintext: Vec<u8>,
+    closed: bool,
+}
+
+impl<C: Connection> TlsConnection<C> {
+    /// hostのサーバーとのconnectionの上でハンドシェイクを行い、暗号化された接続を確立します
+    pub fn connect(connection: C, host: &Host, config: &TlsConfig) -> Result<Self, Error> {
+        let mut record = RecordLayer::new(connection);
+        match handshake::client_handshake(&mut record, host, config) {
+            Ok((client_secret, server_secret)) => Ok(Self {
+                record,
+                client_secret,
+                server_secret,
+                plaintext: Vec::new(),
+                closed: false,
+            }),
+            Err(e) => {
+                // 失敗の理由をサーバーに伝える。アラートを送れなくても元のエラーを返す
+                if let Some(description) = alert_description(&e) {
+                    let _ = record.write_alert(description);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// ハンドシェイクの後にサーバーから送られるメッセージを処理します
+    /// https://www.rfc-editor.org/rfc/rfc8446#section-4.6
+    fn handle_post_handshake_message(&mut self, message: &[u8]) -> Result<(), Error> {
+        match message[0] {
+            // セッションの再開には対応しないため、チケットは使わない
+            NEW_SESSION_TICKET => Ok(()),
+            // https://www.rfc-editor.org/rfc/rfc8446#section-4.6.3
+            KEY_UPDATE => {
+                let update_requested = match message[4..] {
+                    [0] => false,
+                    [1] => true,
+                    _ => {
+                        return Err(Error::Tls(TlsError::HandshakeFailure(
+                            "invalid KeyUpdate".into(),
+                        )))
+                    }
+                };
+                let suite = self.record.cipher_suite();
+                self.server_secret = next_traffic_secret(suite, &self.server_secret);
+                self.record.set_read_secret(&self.server_secret)?;
+                if update_requested {
+                    self.record
+                        .write_record(HANDSHAKE, &handshake_message(KEY_UPDATE, &[0]))?;
+                    self.client_secret = next_traffic_secret(suite, &self.client_secret);
+                    self.record.set_write_secret(&self.client_secret)?;
+                }
+                Ok(())
+            }
+            msg_type => Err(Error::Tls(TlsError::HandshakeFailure(alloc::format!(
+                "unexpected handshake message {}",
+                msg_type
+            )))),
+        }
+    }
+}
+
+impl<C: Connection> Connection for TlsConnection<C> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        let len = buf.len().min(MAX_FRAGMENT_LENGTH);
+        self.record.write_record(APPLICATION_DATA, &buf[..len])?;
+        Ok(len)
+    }
+
+    /// close_notifyを受け取ると0を返します
+    /// close_notifyを受け取る前に下の接続が閉じられた場合は、レスポンスを途中で切り捨てられた可能性があるためエラーを返します
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        while self.plaintext.is_empty() {
+            if self.closed {
+                return Ok(0);
+            }
+            match self.record.read_record()? {
+                None => self.closed = true,
+                Some(record) if record.content_type() == APPLICATION_DATA => {
+                    self.plaintext = record.into_fragment()
+                }
+                Some(record) if record.content_type() == HANDSHAKE => {
+                    self.record.push_handshake_data(&record.into_fragment());
+                    while let Some(message) = self.record.next_handshake_message()? {
+                        self.handle_post_handshake_message(&message)?;
+                    }
+                }
+                Some(record) => {
+                    return Err(Error::Tls(TlsError::HandshakeFailure(alloc::format!(
+                        "unexpected record type {}",
+                        record.content_type()
+                    ))))
+                }
+            }
+        }
+        let len = buf.len().min(self.plaintext.len());
+        buf[..len].copy_from_slice(&self.plaintext[..len]);
+        self.plaintext.drain(..len);
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::handshake::*;
+    use super::key_schedule::*;
+    use super::*;
+    use crate::crypto::ecdsa::{self, Curve};
+    use crate::crypto::sha2::Sha256;
+    use crate::crypto::{from_hex, x25519, Hash};
+    use crate::http::client::HttpClient;
+    use crate::http::proxy::ProxyConfig;
+    use crate::http::{HttpRequest, Method};
+    use crate::net::std_net::{StdRandom, StdTransport};
+    use crate::url::Url;
+    use alloc::format;
+    use alloc::string::ToString;
+    use alloc::vec;
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    const TEST_ROOT_CA: &[u8] = include_bytes!("tls/testdata/test_root_ca.der");
+    const TEST_INTERMEDIATE_CA: &[u8] = include_bytes!("tls/testdata/test_intermediate_ca.der");
+    /// localhost、*.saba.test、127.0.0.1、::1に対して発行したサーバー証明書
+    const TEST_SERVER: &[u8] = include_bytes!("tls/testdata/test_server.der");
+    /// TEST_SERVERのP-256の秘密鍵
+    const TEST_SERVER_KEY: &str =
+        "42af01d8905fbc9a23556de3e2b0207f6108262eaa0937dbd9ff91e7c8601d6a";
+
+    fn config() -> TlsConfig {
+        TlsConfig::new(Rc::new(StdRandom::new()))
+            .with_root_certificates(&[TEST_ROOT_CA])
+            .expect("invalid root certificate")
+    }
+
+    fn send_message(
+        record: &mut RecordLayer<TcpStream>,
+        transcript: &mut Vec<u8>,
+        msg_type: u8,
+        body: &[u8],
+    ) -> Result<(), Error> {
+        let message = handshake_message(msg_type, body);
+        transcript.extend_from_slice(&message);
+        record.write_record(HANDSHAKE, &message)
+    }
+
+    /// テスト用のサーバーの振る舞い
+    #[derive(Debug, Clone)]
+    struct ServerOptions {
+        /// レスポンスの本文のうち、リクエスト行の後に続けるバイト数
+        body_size: usize,
+        /// falseの場合は、レスポンスを送った後にclose_notifyを送らずに接続を閉じる
+        close_notify: bool,
+        cipher_suite: u16,
+        /// Someの場合は、最初のClientHelloにHelloRetryRequestを返し、このグループとcookieを求める
+        hello_retry_group: Option<u16>,
+    }
+
+    impl ServerOptions {
+        fn new(body_size: usize) -> Self {
+            Self {
+                body_size,
+                close_notify: true,
+                cipher_suite: TLS_AES_128_GCM_SHA256,
+                hello_retry_group: None,
+            }
+        }
+    }
+
+    /// ClientHelloのうち、テスト用のサーバーが使う値
+    struct ClientHello {
+        session_id: Vec<u8>,
+        cipher_suites: Vec<u16>,
+        key_shares: Vec<(u16, Vec<u8>)>,
+        cookie: Option<Vec<u8>>,
+    }
+
+    fn parse_client_hello(message: &[u8]) -> Result<ClientHello, Error> {
+        let mut reader = Reader::new(&message[4..]);
+        reader.bytes(2 + 32)?;
+        let session_id = reader.vector8()?.to_vec();
+        let mut suites = Reader::new(reader.vector16()?);
+        let mut cipher_suites = Vec::new();
+        while !suites.is_empty() {
+            cipher_suites.push(suites.u16()?);
+        }
+        reader.vector8()?;
+        let mut extensions = Reader::new(reader.vector16()?);
+        let mut key_shares = Vec::new();
+        let mut cookie = None;
+        while !extensions.is_empty() {
+            let extension_type = extensions.u16()?;
+            let data = extensions.vector16()?;
+            if extension_type == EXTENSION_KEY_SHARE {
+                let mut shares = Reader::new(Reader::new(data).vector16()?);
+                while !shares.is_empty() {
+                    key_shares.push((shares.u16()?, shares.vector16()?.to_vec()));
+                }
+            } else if extension_type == EXTENSION_COOKIE {
+                cookie = Some(data.to_vec());
+            }
+        }
+        Ok(ClientHello {
+            session_id,
+            cipher_suites,
+            key_shares,
+            cookie,
+        })
+    }
+
+    fn server_hello(
+        random: &[u8],
+        session_id: &[u8],
+        cipher_suite: u16,
+        extensions: &[u8],
+    ) -> Vec<u8> {
+        let mut body = vec![0x03, 0x03];
+        body.extend_from_slice(random);
+        push_vector(&mut body, 1, session_id);
+        body.extend_from_slice(&cipher_suite.to_be_bytes());
+        body.push(0);
+        let mut all_extensions = Vec::new();
+        push_extension(
+            &mut all_extensions,
+            EXTENSION_SUPPORTED_VERSIONS,
+            &[0x03, 0x04],
+        );
+        all_extensions.extend_from_slice(extensions);
+        push_vector(&mut body, 2, &all_extensions);
+        body
+    }
+
+    /// テスト用のTLS 1.3のサーバーとしてハンドシェイクを行い、HTTPのリクエストを受け取ると
+    /// リクエスト行にoptions.body_sizeバイトを続けた本文を持つレスポンスを返します
+    fn serve(stream: TcpStream, options: &ServerOptions) -> Result<(), Error> {
+        let suite = CipherSuite::from_u16(options.cipher_suite).expect("unsupported cipher suite");
+        let mut record = RecordLayer::new(stream);
+        let mut message = record.read_handshake_message()?;
+        let mut client_hello = parse_client_hello(&message)?;
+        let mut transcript = Vec::new();
+        if let Some(group) = options.hello_retry_group {
+            let mut cookie = Vec::new();
+            push_vector(&mut cookie, 2, b"saba");
+            let mut extensions = Vec::new();
+            push_extension(&mut extensions, EXTENSION_KEY_SHARE, &group.to_be_bytes());
+            push_extension(&mut extensions, EXTENSION_COOKIE, &cookie);
+            let body = server_hello(
+                &HELLO_RETRY_REQUEST_RANDOM,
+                &client_hello.session_id,
+                options.cipher_suite,
+                &extensions,
+            );
+            transcript = handshake_message(MESSAGE_HASH, &suite.hash().digest(&message));
+            send_message(&mut record, &mut transcript, SERVER_HELLO, &body)?;
+            message = record.read_handshake_message()?;
+            client_hello = parse_client_hello(&message)?;
+            if client_hello.cookie != Some(cookie) {
+                return Err(Error::Other("cookie was not echoed".to_string()));
+            }
+        }
+        transcript.extend_from_slice(&message);
+        if !client_hello.cipher_suites.contains(&options.cipher_suite) {
+            return Err(Error::Other("cipher suite was not offered".to_string()));
+        }
+
+        let group = options.hello_retry_group.unwrap_or(X25519);
+        let client_key = client_hello
+            .key_shares
+            .iter()
+            .find(|(g, _)| *g == group)
+            .map(|(_, key)| key.clone())
+            .ok_or_else(|| Error::Other("no key share".to_string()))?;
+        let private_key = [0x42; 32];
+        let (public_key, shared_secret) = if group == X25519 {
+            let client_key = client_key.try_into().expect("invalid X25519 key");
+            (
+                x25519::public_key(&private_key).to_vec(),
+                x25519::x25519(&private_key, &client_key).to_vec(),
+            )
+        } else {
+            (
+                ecdsa::public_key(Curve::P256, &private_key)?,
+                ecdsa::ecdh(Curve::P256, &private_key, &client_key)?,
+            )
+        };
+        let mut key_share = group.to_be_bytes().to_vec();
+        push_vector(&mut key_share, 2, &public_key);
+        let mut extensions = Vec::new();
+        push_extension(&mut extensions, EXTENSION_KEY_SHARE, &key_share);
+        let body = server_hello(
+            &[0x07; 32],
+            &client_hello.session_id,
+            options.cipher_suite,
+            &extensions,
+        );
+        send_message(&mut record, &mut transcript, SERVER_HELLO, &body)?;
+        record.write_change_cipher_spec()?;
+        record.set_cipher_suite(suite);
+
+        let handshake_secret = handshake_secret(suite, &shared_secret);
+        let hash = suite.hash().digest(&transcript);
+        let client_handshake_secret =
+            derive_secret(suite, &handshake_secret, "c hs traffic", &hash);
+        let server_handshake_secret =
+            derive_secret(suite, &handshake_secret, "s hs traffic", &hash);
+        record.set_read_secret(&client_handshake_secret)?;
+        record.set_write_secret(&server_handshake_secret)?;
+
+        send_message(&mut record, &mut transcript, ENCRYPTED_EXTENSIONS, &[0, 0])?;
+        let mut certificates = Vec::new();
+        for der in [TEST_SERVER, TEST_INTERMEDIATE_CA] {
+            push_vector(&mut certificates, 3, der);
+            push_vector(&mut certificates, 2, &[]);
+        }
+        let mut body = vec![0];
+        push_vector(&mut body, 3, &certificates);
+        send_message(&mut record, &mut transcript, CERTIFICATE, &body)?;
+
+        let hash = suite.hash().digest(&transcript);
+        let digest = Sha256::digest(&certificate_verify_content(&hash));
+        let signature = ecdsa::sign(
+            Curve::P256,
+            &from_hex(TEST_SERVER_KEY),
+            &digest,
+            &Sha256::digest(&hash),
+        );
+        let mut body = ECDSA_SECP256R1_SHA256.to_be_bytes().to_vec();
+        push_vector(&mut body, 2, &signature);
+        send_message(&mut record, &mut transcript, CERTIFICATE_VERIFY, &body)?;
+        let verify_data = finished_verify_data(
+            suite,
+            &server_handshake_secret,
+            &suite.hash().digest(&transcript),
+        );
+        send_message(&mut record, &mut transcript, FINISHED, &verify_data)?;
+
+        let master_secret = master_secret(suite, &handshake_secret);
+        let hash = suite.hash().digest(&transcript);
+        let client_secret = derive_secret(suite, &master_secret, "c ap traffic", &hash);
+        let server_secret = derive_secret(suite, &master_secret, "s ap traffic", &hash);
+        let verify_data = finished_verify_data(suite, &client_handshake_secret, &hash);
+        if record.read_handshake_message()? != handshake_message(FINISHED, &verify_data) {
+            return Err(Error::Other("invalid client Finished".to_string()));
+        }
+        record.set_read_secret(&client_secret)?;
+        record.set_write_secret(&server_secret)?;
+        record.finish_handshake();
+
+        let mut request = Vec::new();
+        while !request.ends_with(b"\r\n\r\n") {
+            match record.read_record()? {
+                Some(data) => request.extend_from_slice(&data.into_fragment()),
+                None => return Ok(()),
+            }
+        }
+
+        // セッションチケットとKeyUpdateを送り、クライアントが無視するか鍵を更新することを確かめる
+        let ticket = [0, 0, 0, 60, 0, 0, 0, 0, 1, 0, 0, 1, 0xff, 0, 0];
+        record.write_record(HANDSHAKE, &handshake_message(NEW_SESSION_TICKET, &ticket))?;
+        record.write_record(HANDSHAKE, &handshake_message(KEY_UPDATE, &[1]))?;
+        record.set_write_secret(&next_traffic_secret(suite, &server_secret))?;
+        // 応答のKeyUpdateを読まずに接続を閉じると、TCPのリセットでクライアントが応答を受信できないことがある
+        if record.read_handshake_message()? != handshake_message(KEY_UPDATE, &[0]) {
+            return Err(Error::Other("no KeyUpdate from the client".to_string()));
+        }
+
+        let request = String::from_utf8_lossy(&request).to_string();
+        let mut response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n",
+            request.lines().next().unwrap_or_default().len() + options.body_size
+        )
+        .into_bytes();
+        response.extend_from_slice(request.lines().next().unwrap_or_default().as_bytes());
+        response.resize(response.len() + options.body_size, b'a');
+        for chunk in response.chunks(MAX_FRAGMENT_LENGTH) {
+            record.write_record(APPLICATION_DATA, chunk)?;
+        }
+        if !options.close_notify {
+            return Ok(());
+        }
+        record.write_alert(record::ALERT_CLOSE_NOTIFY)
+    }
+
+    /// テスト用のTLSのサーバーを起動し、ポート番号を返します
+    fn start_server(connections: usize, body_size: usize) -> u16 {
+        start_server_with_options(connections, ServerOptions::new(body_size))
+    }
+
+    fn start_server_with_options(connections: usize, options: ServerOptions) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("failed to bind");
+        let port = listener.local_addr().expect("no local address").port();
+        thread::spawn(move || {
+            for stream in listener.incoming().take(connections) {
+                // クライアントがハンドシェイクを中断するテストでは失敗する
+                let _ = serve(stream.expect("failed to accept"), &options);
+            }
+        });
+        port
+    }
+
+    fn get(client: &HttpClient<StdTransport>, url: &str) -> Result<String, Error> {
+        let url = Url::new(url.to_string()).parse().expect("invalid url");
+        client
+            .send(&HttpRequest::new(Method::Get, url))
+            .map(|response| response.body())
+    }
+
+    #[test]
+    fn test_https() {
+        let port = start_server(2, 0);
+        let client = HttpClient::new(StdTransport::new()).with_tls(config().with_time(1893456000));
+        assert_eq!(
+            Ok("GET /index.html HTTP/1.1".to_string()),
+            get(&client, &format!("https://127.0.0.1:{}/index.html", port))
+        );
+        assert_eq!(
+            Ok("GET / HTTP/1.1".to_string()),
+            get(&client, &format!("https://localhost:{}/", port))
+        );
+    }
+
+    #[test]
+    fn test_large_response() {
+        let port = start_server(1, 50000);
+        let client = HttpClient::new(StdTransport::new()).with_tls(config());
+        let body = get(&client, &format!("https://127.0.0.1:{}/large", port)).expect("failed");
+        assert_eq!(50000 + "GET /large HTTP/1.1".len(), body.len());
+    }
+
+    #[test]
+    fn test_cipher_suites() {
+        for cipher_suite in CIPHER_SUITES {
+            let options = ServerOptions {
+                cipher_suite,
+                ..ServerOptions::new(20000)
+            };
+            let port = start_server_with_options(1, options);
+            let client = HttpClient::new(StdTransport::new()).with_tls(config());
+            let body = get(&client, &format!("https://127.0.0.1:{}/", port)).expect("failed");
+            assert_eq!(20000 + "GET / HTTP/1.1".len(), body.len());
+        }
+    }
+
+    #[test]
+    fn test_hello_retry_request() {
+        // X25519に対応していないサーバーが、HelloRetryRequestでsecp256r1とcookieを求める
+        let options = ServerOptions {
+            cipher_suite: TLS_AES_256_GCM_SHA384,
+            hello_retry_group: Some(SECP256R1),
+            ..ServerOptions::new(0)
+        };
+        let port = start_server_with_options(1, options);
+        let client = HttpClient::new(StdTransport::new()).with_tls(config());
+        assert_eq!(
+            Ok("GET / HTTP/1.1".to_string()),
+            get(&client, &format!("https://127.0.0.1:{}/", port))
+        );
+
+        // 送った鍵と同じグループを求めるHelloRetryRequestは不正
+        let options = ServerOptions {
+            hello_retry_group: Some(X25519),
+            ..ServerOptions::new(0)
+        };
+        let port = start_server_with_options(1, options);
+        assert!(matches!(
+            get(&client, &format!("https://127.0.0.1:{}/", port)),
+            Err(Error::Tls(TlsError::HandshakeFailure(_)))
+        ));
+    }
+
+    #[test]
+    fn test_invalid_certificate() {
+        let port = start_server(3, 0);
+        let url = format!("https://127.0.0.1:{}/", port);
+
+        // テスト用のルート証明書を信頼していない
+        let client = HttpClient::new(StdTransport::new())
+            .with_tls(TlsConfig::new(Rc::new(StdRandom::new())));
+        assert!(matches!(
+            get(&client, &url),
+            Err(Error::Tls(TlsError::BadCertificate(_)))
+        ));
+
+        // 有効期間が過ぎている
+        let client = HttpClient::new(StdTransport::new()).with_tls(config().with_time(u64::MAX));
+        assert!(matches!(
+            get(&client, &url),
+            Err(Error::Tls(TlsError::BadCertificate(_)))
+        ));
+
+        // 証明書のホスト名と一致しない
+        let stream = TcpStream::connect(("127.0.0.1", port)).expect("failed to connect");
+        let result = TlsConnection::connect(stream, &Host::Domain("example.com".into()), &config());
+        assert!(matches!(
+            result,
+            Err(Error::Tls(TlsError::BadCertificate(_)))
+        ));
+    }
+
+    #[test]
+    fn test_truncated_response() {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("failed to bind");
+        let port = listener.local_addr().expect("no local address").port();
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().expect("failed to accept");
+            let options = ServerOptions {
+                close_notify: false,
+                ..ServerOptions::new(100)
+            };
+            let _ = serve(stream, &options);
+        });
+
+        // close_notifyを受け取らずに接続が閉じられたら、受信したデータが揃っていてもエラーにする
+        let stream = TcpStream::connect(("127.0.0.1", port)).expect("failed to connect");
+        let mut connection =
+            TlsConnection::connect(stream, &Host::Domain("localhost".into()), &config())
+                .expect("failed to connect");
+        connection
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .expect("failed to write");
+        let mut buf = [0u8; 1024];
+        let mut received = 0;
+        let result = loop {
+            match connection.read(&mut buf) {
+                Ok(0) => break Ok(()),
+                Ok(len) => received += len,
+                Err(e) => break Err(e),
+            }
+        };
+        assert!(received > 100);
+        assert!(matches!(result, Err(Error::Network(_))));
+    }
+
+    #[test]
+    fn test_response_without_close_notify() {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("failed to bind");
+        let port = listener.local_addr().expect("no local address").port();
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().expect("failed to accept");
+            let options = ServerOptions {
+                close_notify: false,
+                ..ServerOptions::new(100)
+            };
+            let _ = serve(stream, &options);
+        });
+
+        // Content-Lengthの長さのボディを受信し終えていれば、close_notifyがなくてもエラーにしない
+        let client = HttpClient::new(StdTransport::new()).with_tls(config());
+        let body = get(&client, &format!("https://127.0.0.1:{}/", port)).expect("failed");
+        assert_eq!(100 + "GET / HTTP/1.1".len(), body.len());
+    }
+
+    #[test]
+    fn test_https_through_proxy() {
+        // CONNECTリクエストを受け取ったら、同じ接続でテスト用のTLSのサーバーとして振る舞うプロキシ
+        let listener = TcpListener::bind("127.0.0.1:0").expect("failed to bind");
+        let port = listener.local_addr().expect("no local address").port();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().expect("failed to accept");
+            let mut request = Vec::new();
+            let mut buf = [0u8; 1];
+            while !request.ends_with(b"\r\n\r\n") {
+                stream.read_exact(&mut buf).expect("failed to read");
+                request.push(buf[0]);
+            }
+            let expected = b"CONNECT www.saba.test:443 HTTP/1.1\r\nHost: www.saba.test:443\r\nProxy-Authorization: Basic dXNlcjpwYXNz\r\n\r\n";
+            if request != expected {
+                let _ = Write::write_all(&mut stream, b"HTTP/1.1 400 Bad Request\r\n\r\n");
+                return;
+            }
+            Write::write_all(&mut stream, b"HTTP/1.1 200 Connection Established\r\n\r\n")
+                .expect("failed to write");
+            let _ = serve(stream, &ServerOptions::new(0));
+        });
+
+        let client = HttpClient::new(StdTransport::new())
+            .with_tls(config())
+            .with_proxy(ProxyConfig::new("127.0.0.1", port).with_credentials("user", "pass"));
+        assert_eq!(
+            Ok("GET /secure HTTP/1.1".to_string()),
+            get(&client, "https://www.saba.test/secure")
+        );
+    }
+
+    #[test]
+    fn test_https_without_tls_config() {
+        let client = HttpClient::new(StdTransport::new());
+        assert!(get(&client, "https://127.0.0.1:1/").is_err());
+    }
+}
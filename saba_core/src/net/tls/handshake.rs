@@ -0,0 +1,621 @@
+//! TLS 1.3のクライアントのハンドシェイク
+//! https://www.rfc-editor.org/rfc/rfc8446#section-4
+
+use alloc::format;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+use super::key_schedule::{
+    derive_secret, finished_verify_data, handshake_secret, master_secret, CipherSuite,
+    CIPHER_SUITES,
+};
+use super::record::{RecordLayer, HANDSHAKE};
+use super::x509::{self, Certificate, PublicKey, SignatureAlgorithm};
+use super::{RandomSource, TlsConfig, TlsError};
+use crate::crypto::ecdsa::{self, Curve};
+use crate::crypto::x25519;
+use crate::crypto::{constant_time_eq, HashAlgorithm};
+use crate::error::Error;
+use crate::net::Connection;
+use crate::url::host::Host;
+
+/// https://www.rfc-editor.org/rfc/rfc8446#appendix-B.3
+pub const CLIENT_HELLO: u8 = 1;
+pub const SERVER_HELLO: u8 = 2;
+pub const NEW_SESSION_TICKET: u8 = 4;
+pub const ENCRYPTED_EXTENSIONS: u8 = 8;
+pub const CERTIFICATE: u8 = 11;
+pub const CERTIFICATE_REQUEST: u8 = 13;
+pub const CERTIFICATE_VERIFY: u8 = 15;
+pub const FINISHED: u8 = 20;
+pub const KEY_UPDATE: u8 = 24;
+/// HelloRetryRequestを受け取ったときに、最初のClientHelloの代わりにハッシュ値をトランスクリプトに含める
+/// https://www.rfc-editor.org/rfc/rfc8446#section-4.4.1
+pub const MESSAGE_HASH: u8 = 254;
+
+/// https://www.rfc-editor.org/rfc/rfc8446#section-4.2
+pub const EXTENSION_SERVER_NAME: u16 = 0;
+pub const EXTENSION_SUPPORTED_GROUPS: u16 = 10;
+pub const EXTENSION_SIGNATURE_ALGORITHMS: u16 = 13;
+pub const EXTENSION_SUPPORTED_VERSIONS: u16 = 43;
+pub const EXTENSION_COOKIE: u16 = 44;
+pub const EXTENSION_KEY_SHARE: u16 = 51;
+
+/// ClientHelloとServerHelloのlegacy_versionには互換性のためTLS 1.2の値を使う
+const LEGACY_VERSION: u16 = 0x0303;
+const TLS_1_3: u16 = 0x0304;
+/// https://www.rfc-editor.org/rfc/rfc8446#section-4.2.7
+pub const SECP256R1: u16 = 0x0017;
+pub const X25519: u16 = 0x001d;
+/// 対応する鍵交換のグループ。最初のClientHelloではX25519の公開鍵だけを送り、
+/// サーバーがX25519に対応していなければHelloRetryRequestでsecp256r1を選んでもらう
+const SUPPORTED_GROUPS: [u16; 2] = [X25519, SECP256R1];
+
+/// https://www.rfc-editor.org/rfc/rfc8446#section-4.2.3
+pub const ECDSA_SECP256R1_SHA256: u16 = 0x0403;
+pub const ECDSA_SECP384R1_SHA384: u16 = 0x0503;
+pub const RSA_PSS_RSAE_SHA256: u16 = 0x0804;
+pub const RSA_PSS_RSAE_SHA384: u16 = 0x0805;
+pub const RSA_PSS_RSAE_SHA512: u16 = 0x0806;
+const RSA_PKCS1_SHA256: u16 = 0x0401;
+const RSA_PKCS1_SHA384: u16 = 0x0501;
+const RSA_PKCS1_SHA512: u16 = 0x0601;
+
+/// 対応する署名方式。RSASSA-PKCS1-v1_5は証明書の署名の検証にだけ使う
+const SIGNATURE_SCHEMES: [u16; 8] = [
+    ECDSA_SECP256R1_SHA256,
+    ECDSA_SECP384R1_SHA384,
+    RSA_PSS_RSAE_SHA256,
+    RSA_PSS_RSAE_SHA384,
+    RSA_PSS_RSAE_SHA512,
+    RSA_PKCS1_SHA256,
+    RSA_PKCS1_SHA384,
+    RSA_PKCS1_SHA512,
+];
+
+/// HelloRetryRequestであることを示すServerHelloのrandomの値（"HelloRetryRequest"のSHA-256）
+/// https://www.rfc-editor.org/rfc/rfc8446#section-4.1.3
+pub const HELLO_RETRY_REQUEST_RANDOM: [u8; 32] = [
+    0xcf, 0x21, 0xad, 0x74, 0xe5, 0x9a, 0x61, 0x11, 0xbe, 0x1d, 0x8c, 0x02, 0x1e, 0x65, 0xb8, 0x91,
+    0xc2, 0xa2, 0x11, 0x16, 0x7a, 0xbb, 0x8c, 0x5e, 0x07, 0x9e, 0x09, 0xe2, 0xc8, 0xa8, 0x33, 0x9c,
+];
+
+fn handshake_failure(message: &str) -> Error {
+    Error::Tls(TlsError::HandshakeFailure(message.to_string()))
+}
+
+fn decode_error() -> Error {
+    handshake_failure("malformed handshake message")
+}
+
+/// ハンドシェイクのメッセージの中身を先頭から順に読み取ります
+/// https://www.rfc-editor.org/rfc/rfc8446#section-3
+#[derive(Debug, Clone)]
+pub struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    pub fn bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or_else(decode_error)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    /// lenバイトのビッグエンディアンの整数を読み取ります
+    fn number(&mut self, len: usize) -> Result<usize, Error> {
+        Ok(self
+            .bytes(len)?
+            .iter()
+            .fold(0usize, |acc, &b| (acc << 8) | b as usize))
+    }
+
+    pub fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.number(1)? as u8)
+    }
+
+    pub fn u16(&mut self) -> Result<u16, Error> {
+        Ok(self.number(2)? as u16)
+    }
+
+    /// 長さが1バイトで前置きされた可変長のベクタを読み取ります
+    pub fn vector8(&mut self) -> Result<&'a [u8], Error> {
+        let len = self.number(1)?;
+        self.bytes(len)
+    }
+
+    pub fn vector16(&mut self) -> Result<&'a [u8], Error> {
+        let len = self.number(2)?;
+        self.bytes(len)
+    }
+
+    pub fn vector24(&mut self) -> Result<&'a [u8], Error> {
+        let len = self.number(3)?;
+        self.bytes(len)
+    }
+
+    /// すべて読み終えたことを確認します
+    pub fn finish(&self) -> Result<(), Error> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(decode_error())
+        }
+    }
+}
+
+/// length_sizeバイトの長さを前置きしてdataを書き込みます
+pub fn push_vector(buf: &mut Vec<u8>, length_size: usize, data: &[u8]) {
+    buf.extend_from_slice(&data.len().to_be_bytes()[core::mem::size_of::<usize>() - length_size..]);
+    buf.extend_from_slice(data);
+}
+
+/// 拡張を書き込みます
+/// https://www.rfc-editor.org/rfc/rfc8446#section-4.2
+pub fn push_extension(buf: &mut Vec<u8>, extension_type: u16, data: &[u8]) {
+    buf.extend_from_slice(&extension_type.to_be_bytes());
+    push_vector(buf, 2, data);
+}
+
+/// 種類と長さのヘッダをつけたハンドシェイクのメッセージを返します
+pub fn handshake_message(msg_type: u8, body: &[u8]) -> Vec<u8> {
+    let mut message = alloc::vec![msg_type];
+    push_vector(&mut message, 3, body);
+    message
+}
+
+/// CertificateVerifyで署名する内容
+/// https://www.rfc-editor.org/rfc/rfc8446#section-4.4.3
+pub fn certificate_verify_content(transcript_hash: &[u8]) -> Vec<u8> {
+    let mut content = alloc::vec![0x20; 64];
+    content.extend_from_slice(b"TLS 1.3, server CertificateVerify\0");
+    content.extend_from_slice(transcript_hash);
+    content
+}
+
+/// 鍵交換に使う一時的な秘密鍵
+/// https://www.rfc-editor.org/rfc/rfc8446#section-4.2.8
+enum KeyShare {
+    X25519([u8; x25519::KEY_SIZE]),
+    Secp256r1(Vec<u8>),
+}
+
+impl KeyShare {
+    fn generate(group: u16, random: &dyn RandomSource) -> Result<Self, Error> {
+        match group {
+            X25519 => {
+                let mut private_key = [0; x25519::KEY_SIZE];
+                random.fill_bytes(&mut private_key)?;
+                Ok(KeyShare::X25519(private_key))
+            }
+            SECP256R1 => {
+                let mut private_key = alloc::vec![0; Curve::P256.field_size()];
+                random.fill_bytes(&mut private_key)?;
+                Ok(KeyShare::Secp256r1(private_key))
+            }
+            _ => Err(handshake_failure(&format!(
+                "unsupported key exchange group: {:#06x}",
+                group
+            ))),
+        }
+    }
+
+    fn group(&self) -> u16 {
+        match self {
+            KeyShare::X25519(_) => X25519,
+            KeyShare::Secp256r1(_) => SECP256R1,
+        }
+    }
+
+    fn public_key(&self) -> Result<Vec<u8>, Error> {
+        match self {
+            KeyShare::X25519(private_key) => Ok(x25519::public_key(private_key).to_vec()),
+            KeyShare::Secp256r1(private_key) => ecdsa::public_key(Curve::P256, private_key),
+        }
+    }
+
+    /// サーバーの公開鍵と共有する値を計算します
+    /// https://www.rfc-editor.org/rfc/rfc8446#section-7.4
+    fn shared_secret(&self, server_key: &[u8]) -> Result<Vec<u8>, Error> {
+        match self {
+            KeyShare::X25519(private_key) => {
+                let server_key = server_key
+                    .try_into()
+                    .map_err(|_| handshake_failure("invalid key share"))?;
+                let shared_secret = x25519::x25519(private_key, &server_key);
+                // https://www.rfc-editor.org/rfc/rfc8446#section-7.4.2
+                if shared_secret.iter().all(|&b| b == 0) {
+                    return Err(handshake_failure("invalid key share"));
+                }
+                Ok(shared_secret.to_vec())
+            }
+            KeyShare::Secp256r1(private_key) => ecdsa::ecdh(Curve::P256, private_key, server_key)
+                .map_err(|_| handshake_failure("invalid key share")),
+        }
+    }
+}
+
+/// https://www.rfc-editor.org/rfc/rfc8446#section-4.1.2
+fn client_hello(
+    random: &[u8; 32],
+    session_id: &[u8; 32],
+    key_share: &KeyShare,
+    cookie: Option<&[u8]>,
+    host: &Host,
+) -> Result<Vec<u8>, Error> {
+    let mut extensions = Vec::new();
+    // IPアドレスはServer Name Indicationに含めない
+    // https://www.rfc-editor.org/rfc/rfc6066#section-3
+    if let Host::Domain(name) = host {
+        let mut server_name = alloc::vec![0];
+        push_vector(&mut server_name, 2, name.as_bytes());
+        let mut server_name_list = Vec::new();
+        push_vector(&mut server_name_list, 2, &server_name);
+        push_extension(&mut extensions, EXTENSION_SERVER_NAME, &server_name_list);
+    }
+    let mut versions = Vec::new();
+    push_vector(&mut versions, 1, &TLS_1_3.to_be_bytes());
+    push_extension(&mut extensions, EXTENSION_SUPPORTED_VERSIONS, &versions);
+    let groups: Vec<u8> = SUPPORTED_GROUPS
+        .iter()
+        .flat_map(|group| group.to_be_bytes())
+        .collect();
+    let mut supported_groups = Vec::new();
+    push_vector(&mut supported_groups, 2, &groups);
+    push_extension(
+        &mut extensions,
+        EXTENSION_SUPPORTED_GROUPS,
+        &supported_groups,
+    );
+    let schemes: Vec<u8> = SIGNATURE_SCHEMES
+        .iter()
+        .flat_map(|scheme| scheme.to_be_bytes())
+        .collect();
+    let mut signature_algorithms = Vec::new();
+    push_vector(&mut signature_algorithms, 2, &schemes);
+    push_extension(
+        &mut extensions,
+        EXTENSION_SIGNATURE_ALGORITHMS,
+        &signature_algorithms,
+    );
+    let mut entry = key_share.group().to_be_bytes().to_vec();
+    push_vector(&mut entry, 2, &key_share.public_key()?);
+    let mut client_shares = Vec::new();
+    push_vector(&mut client_shares, 2, &entry);
+    push_extension(&mut extensions, EXTENSION_KEY_SHARE, &client_shares);
+    // HelloRetryRequestで受け取ったcookieはそのまま返す
+    // https://www.rfc-editor.org/rfc/rfc8446#section-4.2.2
+    if let Some(cookie) = cookie {
+        push_extension(&mut extensions, EXTENSION_COOKIE, cookie);
+    }
+
+    let mut body = LEGACY_VERSION.to_be_bytes().to_vec();
+    body.extend_from_slice(random);
+    push_vector(&mut body, 1, session_id);
+    let cipher_suites: Vec<u8> = CIPHER_SUITES
+        .iter()
+        .flat_map(|suite| suite.to_be_bytes())
+        .collect();
+    push_vector(&mut body, 2, &cipher_suites);
+    // legacy_compression_methodsはnullだけ
+    push_vector(&mut body, 1, &[0]);
+    push_vector(&mut body, 2, &extensions);
+    Ok(handshake_message(CLIENT_HELLO, &body))
+}
+
+/// ServerHelloまたはHelloRetryRequestの内容
+struct ServerHello<'a> {
+    is_hello_retry_request: bool,
+    cipher_suite: CipherSuite,
+    /// ServerHelloではサーバーの公開鍵、HelloRetryRequestでは空
+    key_share: Option<(u16, &'a [u8])>,
+    cookie: Option<&'a [u8]>,
+}
+
+/// ServerHelloとHelloRetryRequestの共通の形式を確認して読み取ります
+/// https://www.rfc-editor.org/rfc/rfc8446#section-4.1.3
+/// https://www.rfc-editor.org/rfc/rfc8446#section-4.1.4
+fn parse_server_hello<'a>(message: &'a [u8], session_id: &[u8]) -> Result<ServerHello<'a>, Error> {
+    let mut reader = Reader::new(&message[4..]);
+    if reader.u16()? != LEGACY_VERSION {
+        return Err(handshake_failure("server does not support TLS 1.3"));
+    }
+    let is_hello_retry_request = reader.bytes(32)? == HELLO_RETRY_REQUEST_RANDOM;
+    if reader.vector8()? != session_id {
+        return Err(handshake_failure("session id mismatch"));
+    }
+    let suite = reader.u16()?;
+    let cipher_suite = CipherSuite::from_u16(suite)
+        .ok_or_else(|| handshake_failure(&format!("unsupported cipher suite: {:#06x}", suite)))?;
+    if reader.u8()? != 0 {
+        return Err(handshake_failure("unsupported compression method"));
+    }
+
+    let mut extensions = Reader::new(reader.vector16()?);
+    reader.finish()?;
+    let mut version = None;
+    let mut key_share = None;
+    let mut cookie = None;
+    while !extensions.is_empty() {
+        let extension_type = extensions.u16()?;
+        let data = extensions.vector16()?;
+        let mut reader = Reader::new(data);
+        match extension_type {
+            EXTENSION_SUPPORTED_VERSIONS => version = Some(reader.u16()?),
+            // HelloRetryRequestのkey_shareにはサーバーが選んだグループだけが入る
+            EXTENSION_KEY_SHARE if is_hello_retry_request => {
+                key_share = Some((reader.u16()?, &[][..]))
+            }
+            EXTENSION_KEY_SHARE => key_share = Some((reader.u16()?, reader.vector16()?)),
+            EXTENSION_COOKIE if is_hello_retry_request => {
+                reader.vector16()?;
+                cookie = Some(data);
+            }
+            _ => continue,
+        }
+        reader.finish()?;
+    }
+
+    // TLS 1.2以前のサーバーはsupported_versionsを返さない
+    if version != Some(TLS_1_3) {
+        return Err(handshake_failure("server does not support TLS 1.3"));
+    }
+    Ok(ServerHello {
+        is_hello_retry_request,
+        cipher_suite,
+        key_share,
+        cookie,
+    })
+}
+
+/// Certificateメッセージから証明書のリストを取り出します
+/// サーバー証明書以外の解析できない証明書は、検証に使わないので無視します
+/// https://www.rfc-editor.org/rfc/rfc8446#section-4.4.2
+fn parse_certificate(message: &[u8]) -> Result<Vec<Certificate>, Error> {
+    let mut reader = Reader::new(&message[4..]);
+    if !reader.vector8()?.is_empty() {
+        return Err(decode_error());
+    }
+    let mut entries = Reader::new(reader.vector24()?);
+    reader.finish()?;
+
+    let mut chain = Vec::new();
+    while !entries.is_empty() {
+        let der = entries.vector24()?;
+        entries.vector16()?;
+        if chain.is_empty() {
+            chain.push(Certificate::from_der(der)?);
+        } else if let Ok(certificate) = Certificate::from_der(der) {
+            chain.push(certificate);
+        }
+    }
+    Ok(chain)
+}
+
+/// CertificateVerifyの署名を、サーバー証明書の公開鍵で検証します
+/// https://www.rfc-editor.org/rfc/rfc8446#section-4.4.3
+fn verify_certificate_verify(
+    message: &[u8],
+    public_key: &PublicKey,
+    transcript_hash: &[u8],
+) -> Result<(), Error> {
+    let mut reader = Reader::new(&message[4..]);
+    let scheme = reader.u16()?;
+    let signature = reader.vector16()?;
+    reader.finish()?;
+
+    let algorithm = match (scheme, public_key) {
+        (ECDSA_SECP256R1_SHA256, PublicKey::Ec(Curve::P256, _)) => {
+            SignatureAlgorithm::Ecdsa(HashAlgorithm::Sha256)
+        }
+        (ECDSA_SECP384R1_SHA384, PublicKey::Ec(Curve::P384, _)) => {
+            SignatureAlgorithm::Ecdsa(HashAlgorithm::Sha384)
+        }
+        (RSA_PSS_RSAE_SHA256, PublicKey::Rsa(_)) => {
+            SignatureAlgorithm::RsaPss(HashAlgorithm::Sha256)
+        }
+        (RSA_PSS_RSAE_SHA384, PublicKey::Rsa(_)) => {
+            SignatureAlgorithm::RsaPss(HashAlgorithm::Sha384)
+        }
+        (RSA_PSS_RSAE_SHA512, PublicKey::Rsa(_)) => {
+            SignatureAlgorithm::RsaPss(HashAlgorithm::Sha512)
+        }
+        _ => {
+            return Err(handshake_failure(&format!(
+                "unsupported signature scheme: {:#06x}",
+                scheme
+            )))
+        }
+    };
+    public_key
+        .verify(
+            algorithm,
+            &certificate_verify_content(transcript_hash),
+            signature,
+        )
+        .map_err(|_| handshake_failure("invalid CertificateVerify signature"))
+}
+
+/// 指定した種類のハンドシェイクのメッセージを受信します
+fn read_message<C: Connection>(
+    record: &mut RecordLayer<C>,
+    msg_type: u8,
+) -> Result<Vec<u8>, Error> {
+    let message = record.read_handshake_message()?;
+    if message[0] != msg_type {
+        return Err(handshake_failure(&format!(
+            "expected handshake message {} but received {}",
+            msg_type, message[0]
+        )));
+    }
+    Ok(message)
+}
+
+/// サーバーとハンドシェイクを行い、アプリケーションデータの送受信に使う鍵をrecordに設定します
+/// (クライアントのシークレット, サーバーのシークレット)を返します
+/// https://www.rfc-editor.org/rfc/rfc8446#section-2
+pub fn client_handshake<C: Connection>(
+    record: &mut RecordLayer<C>,
+    host: &Host,
+    config: &TlsConfig,
+) -> Result<(Vec<u8>, Vec<u8>), Error> {
+    let mut random = [0; 32];
+    config.random.fill_bytes(&mut random)?;
+    // 空でないセッションIDで、TLS 1.2のセッションの再開に見せかける
+    // https://www.rfc-editor.org/rfc/rfc8446#appendix-D.4
+    let mut session_id = [0; 32];
+    config.random.fill_bytes(&mut session_id)?;
+    let mut key_share = KeyShare::generate(X25519, config.random.as_ref())?;
+
+    // 暗号スイートが決まるまでハッシュ関数が分からないため、メッセージを連結したものを保持する
+    let mut transcript = client_hello(&random, &session_id, &key_share, None, host)?;
+    record.write_record(HANDSHAKE, &transcript)?;
+
+    // サーバーが別の鍵交換のグループを選んだ場合やcookieを求めた場合は、ClientHelloを送り直す
+    // https://www.rfc-editor.org/rfc/rfc8446#section-4.1.4
+    let first_message = read_message(record, SERVER_HELLO)?;
+    let hello_retry_request = parse_server_hello(&first_message, &session_id)?;
+    let mut retry_cipher_suite = None;
+    let server_hello_message = if hello_retry_request.is_hello_retry_request {
+        match hello_retry_request.key_share {
+            Some((group, _)) if group == key_share.group() => {
+                return Err(handshake_failure(
+                    "HelloRetryRequest selected the key share already sent",
+                ))
+            }
+            Some((group, _)) => key_share = KeyShare::generate(group, config.random.as_ref())?,
+            None if hello_retry_request.cookie.is_none() => {
+                return Err(handshake_failure(
+                    "HelloRetryRequest does not change the ClientHello",
+                ))
+            }
+            None => {}
+        }
+        let cipher_suite = hello_retry_request.cipher_suite;
+        transcript = handshake_message(MESSAGE_HASH, &cipher_suite.hash().digest(&transcript));
+        transcript.extend_from_slice(&first_message);
+        let client_hello = client_hello(
+            &random,
+            &session_id,
+            &key_share,
+            hello_retry_request.cookie,
+            host,
+        )?;
+        transcript.extend_from_slice(&client_hello);
+        record.write_record(HANDSHAKE, &client_hello)?;
+        retry_cipher_suite = Some(cipher_suite);
+        read_message(record, SERVER_HELLO)?
+    } else {
+        first_message
+    };
+
+    let server_hello = parse_server_hello(&server_hello_message, &session_id)?;
+    if server_hello.is_hello_retry_request {
+        return Err(handshake_failure("received a second HelloRetryRequest"));
+    }
+    let suite = server_hello.cipher_suite;
+    if retry_cipher_suite.is_some_and(|retry| retry != suite) {
+        return Err(handshake_failure(
+            "cipher suite changed after HelloRetryRequest",
+        ));
+    }
+    let shared_secret = match server_hello.key_share {
+        Some((group, server_key)) if group == key_share.group() => {
+            key_share.shared_secret(server_key)?
+        }
+        Some((group, _)) => {
+            return Err(handshake_failure(&format!(
+                "unexpected key exchange group: {:#06x}",
+                group
+            )))
+        }
+        None => return Err(handshake_failure("missing key share")),
+    };
+    transcript.extend_from_slice(&server_hello_message);
+    record.set_cipher_suite(suite);
+    let hash = suite.hash();
+
+    let handshake_secret = handshake_secret(suite, &shared_secret);
+    let transcript_hash = hash.digest(&transcript);
+    let client_handshake_secret =
+        derive_secret(suite, &handshake_secret, "c hs traffic", &transcript_hash);
+    let server_handshake_secret =
+        derive_secret(suite, &handshake_secret, "s hs traffic", &transcript_hash);
+    record.set_read_secret(&server_handshake_secret)?;
+
+    // https://www.rfc-editor.org/rfc/rfc8446#section-4.3.1
+    let encrypted_extensions = read_message(record, ENCRYPTED_EXTENSIONS)?;
+    let mut reader = Reader::new(&encrypted_extensions[4..]);
+    reader.vector16()?;
+    reader.finish()?;
+    transcript.extend_from_slice(&encrypted_extensions);
+
+    // クライアント証明書を求められた場合は、空のCertificateを返す
+    // https://www.rfc-editor.org/rfc/rfc8446#section-4.3.2
+    let mut message = record.read_handshake_message()?;
+    let mut certificate_request_context = None;
+    if message[0] == CERTIFICATE_REQUEST {
+        let mut reader = Reader::new(&message[4..]);
+        certificate_request_context = Some(reader.vector8()?.to_vec());
+        reader.vector16()?;
+        reader.finish()?;
+        transcript.extend_from_slice(&message);
+        message = read_message(record, CERTIFICATE)?;
+    } else if message[0] != CERTIFICATE {
+        return Err(handshake_failure("expected a Certificate message"));
+    }
+    let chain = parse_certificate(&message)?;
+    x509::verify_chain(&chain, &config.roots, host, config.time)?;
+    transcript.extend_from_slice(&message);
+
+    let certificate_verify = read_message(record, CERTIFICATE_VERIFY)?;
+    verify_certificate_verify(
+        &certificate_verify,
+        &chain[0].public_key(),
+        &hash.digest(&transcript),
+    )?;
+    transcript.extend_from_slice(&certificate_verify);
+
+    let finished = read_message(record, FINISHED)?;
+    let expected = finished_verify_data(suite, &server_handshake_secret, &hash.digest(&transcript));
+    if !constant_time_eq(&finished[4..], &expected) {
+        return Err(handshake_failure("invalid Finished"));
+    }
+    transcript.extend_from_slice(&finished);
+
+    let master_secret = master_secret(suite, &handshake_secret);
+    let transcript_hash = hash.digest(&transcript);
+    let client_secret = derive_secret(suite, &master_secret, "c ap traffic", &transcript_hash);
+    let server_secret = derive_secret(suite, &master_secret, "s ap traffic", &transcript_hash);
+    record.set_read_secret(&server_secret)?;
+
+    record.write_change_cipher_spec()?;
+    record.set_write_secret(&client_handshake_secret)?;
+    if let Some(context) = certificate_request_context {
+        let mut body = Vec::new();
+        push_vector(&mut body, 1, &context);
+        push_vector(&mut body, 3, &[]);
+        let certificate = handshake_message(CERTIFICATE, &body);
+        transcript.extend_from_slice(&certificate);
+        record.write_record(HANDSHAKE, &certificate)?;
+    }
+    let verify_data =
+        finished_verify_data(suite, &client_handshake_secret, &hash.digest(&transcript));
+    record.write_record(HANDSHAKE, &handshake_message(FINISHED, &verify_data))?;
+    record.set_write_secret(&client_secret)?;
+    record.finish_handshake();
+
+    Ok((client_secret, server_secret))
+}
@@ -0,0 +1,194 @@
+//! TLS 1.3の鍵スケジュール
+//! ハッシュ関数には、ハンドシェイクで選んだ暗号スイートのものを使います
+//! https://www.rfc-editor.org/rfc/rfc8446#section-7.1
+
+use alloc::vec::Vec;
+
+use crate::crypto::hmac;
+use crate::crypto::sha2::{Sha256, Sha384, Sha512};
+use crate::crypto::HashAlgorithm;
+
+/// https://www.rfc-editor.org/rfc/rfc8446#appendix-B.4
+pub const TLS_AES_128_GCM_SHA256: u16 = 0x1301;
+pub const TLS_AES_256_GCM_SHA384: u16 = 0x1302;
+pub const TLS_CHACHA20_POLY1305_SHA256: u16 = 0x1303;
+
+/// 対応する暗号スイート。ClientHelloではこの順に優先します
+pub const CIPHER_SUITES: [u16; 3] = [
+    TLS_AES_128_GCM_SHA256,
+    TLS_AES_256_GCM_SHA384,
+    TLS_CHACHA20_POLY1305_SHA256,
+];
+
+/// 暗号スイートごとの、レコードの暗号化の方式と鍵スケジュールで使うハッシュ関数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CipherSuite {
+    Aes128GcmSha256,
+    Aes256GcmSha384,
+    ChaCha20Poly1305Sha256,
+}
+
+impl CipherSuite {
+    pub fn from_u16(value: u16) -> Option<Self> {
+        match value {
+            TLS_AES_128_GCM_SHA256 => Some(CipherSuite::Aes128GcmSha256),
+            TLS_AES_256_GCM_SHA384 => Some(CipherSuite::Aes256GcmSha384),
+            TLS_CHACHA20_POLY1305_SHA256 => Some(CipherSuite::ChaCha20Poly1305Sha256),
+            _ => None,
+        }
+    }
+
+    pub fn hash(&self) -> HashAlgorithm {
+        match self {
+            CipherSuite::Aes128GcmSha256 | CipherSuite::ChaCha20Poly1305Sha256 => {
+                HashAlgorithm::Sha256
+            }
+            CipherSuite::Aes256GcmSha384 => HashAlgorithm::Sha384,
+        }
+    }
+
+    /// レコードの暗号化に使う鍵のバイト数
+    pub fn key_size(&self) -> usize {
+        match self {
+            CipherSuite::Aes128GcmSha256 => 16,
+            CipherSuite::Aes256GcmSha384 | CipherSuite::ChaCha20Poly1305Sha256 => 32,
+        }
+    }
+}
+
+fn hkdf_extract(hash: HashAlgorithm, salt: &[u8], ikm: &[u8]) -> Vec<u8> {
+    match hash {
+        HashAlgorithm::Sha256 => hmac::hkdf_extract::<Sha256>(salt, ikm),
+        HashAlgorithm::Sha384 => hmac::hkdf_extract::<Sha384>(salt, ikm),
+        HashAlgorithm::Sha512 => hmac::hkdf_extract::<Sha512>(salt, ikm),
+    }
+}
+
+/// HKDF-Expand-Label
+/// https://www.rfc-editor.org/rfc/rfc8446#section-7.1
+pub fn hkdf_expand_label(
+    suite: CipherSuite,
+    secret: &[u8],
+    label: &str,
+    context: &[u8],
+    length: usize,
+) -> Vec<u8> {
+    let label = [b"tls13 ", label.as_bytes()].concat();
+    let mut info = Vec::with_capacity(4 + label.len() + context.len());
+    info.extend_from_slice(&(length as u16).to_be_bytes());
+    info.push(label.len() as u8);
+    info.extend_from_slice(&label);
+    info.push(context.len() as u8);
+    info.extend_from_slice(context);
+    match suite.hash() {
+        HashAlgorithm::Sha256 => hmac::hkdf_expand::<Sha256>(secret, &info, length),
+        HashAlgorithm::Sha384 => hmac::hkdf_expand::<Sha384>(secret, &info, length),
+        HashAlgorithm::Sha512 => hmac::hkdf_expand::<Sha512>(secret, &info, length),
+    }
+}
+
+/// Derive-Secret。transcript_hashはそれまでのハンドシェイクのメッセージのハッシュ値です
+pub fn derive_secret(
+    suite: CipherSuite,
+    secret: &[u8],
+    label: &str,
+    transcript_hash: &[u8],
+) -> Vec<u8> {
+    hkdf_expand_label(
+        suite,
+        secret,
+        label,
+        transcript_hash,
+        suite.hash().output_size(),
+    )
+}
+
+/// 事前共有鍵を使わない場合のHandshake Secretを、鍵交換で共有した値から求めます
+pub fn handshake_secret(suite: CipherSuite, shared_secret: &[u8]) -> Vec<u8> {
+    let hash = suite.hash();
+    let zeros = alloc::vec![0; hash.output_size()];
+    let early_secret = hkdf_extract(hash, &zeros, &zeros);
+    let derived = derive_secret(suite, &early_secret, "derived", &hash.digest(&[]));
+    hkdf_extract(hash, &derived, shared_secret)
+}
+
+pub fn master_secret(suite: CipherSuite, handshake_secret: &[u8]) -> Vec<u8> {
+    let hash = suite.hash();
+    let derived = derive_secret(suite, handshake_secret, "derived", &hash.digest(&[]));
+    hkdf_extract(hash, &derived, &alloc::vec![0; hash.output_size()])
+}
+
+/// Finishedメッセージのverify_data
+/// https://www.rfc-editor.org/rfc/rfc8446#section-4.4.4
+pub fn finished_verify_data(
+    suite: CipherSuite,
+    traffic_secret: &[u8],
+    transcript_hash: &[u8],
+) -> Vec<u8> {
+    let finished_key = hkdf_expand_label(
+        suite,
+        traffic_secret,
+        "finished",
+        &[],
+        suite.hash().output_size(),
+    );
+    match suite.hash() {
+        HashAlgorithm::Sha256 => hmac::hmac::<Sha256>(&finished_key, transcript_hash),
+        HashAlgorithm::Sha384 => hmac::hmac::<Sha384>(&finished_key, transcript_hash),
+        HashAlgorithm::Sha512 => hmac::hmac::<Sha512>(&finished_key, transcript_hash),
+    }
+}
+
+/// KeyUpdateで切り替える次のアプリケーションデータ用のシークレット
+/// https://www.rfc-editor.org/rfc/rfc8446#section-7.2
+pub fn next_traffic_secret(suite: CipherSuite, traffic_secret: &[u8]) -> Vec<u8> {
+    hkdf_expand_label(
+        suite,
+        traffic_secret,
+        "traffic upd",
+        &[],
+        suite.hash().output_size(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{from_hex, to_hex};
+
+    /// RFC 8448の「Simple 1-RTT Handshake」の値
+    /// https://www.rfc-editor.org/rfc/rfc8448#section-3
+    #[test]
+    fn test_key_schedule() {
+        let shared_secret =
+            from_hex("8bd4054fb55b9d63fdfbacf9f04b9f0d35e6d63f537563efd46272900f89492d");
+        let suite = CipherSuite::Aes128GcmSha256;
+        let handshake_secret = handshake_secret(suite, &shared_secret);
+        assert_eq!(
+            "1dc826e93606aa6fdc0aadc12f741b01046aa6b99f691ed221a9f0ca043fbeac",
+            to_hex(&handshake_secret)
+        );
+
+        let transcript_hash =
+            from_hex("860c06edc07858ee8e78f0e7428c58edd6b43f2ca3e6e95f02ed063cf0e1cad8");
+        let client_secret =
+            derive_secret(suite, &handshake_secret, "c hs traffic", &transcript_hash);
+        assert_eq!(
+            "b3eddb126e067f35a780b3abf45e2d8f3b1a950738f52e9600746a0e27a55a21",
+            to_hex(&client_secret)
+        );
+        assert_eq!(
+            "dbfaa693d1762c5b666af5d950258d01",
+            to_hex(&hkdf_expand_label(suite, &client_secret, "key", &[], 16))
+        );
+        assert_eq!(
+            "5bd3c71b836e0b76bb73265f",
+            to_hex(&hkdf_expand_label(suite, &client_secret, "iv", &[], 12))
+        );
+
+        assert_eq!(
+            "18df06843d13a08bf2a449844c5f8a478001bc4d4c627984d5a41da8d0402919",
+            to_hex(&master_secret(suite, &handshake_secret))
+        );
+    }
+}
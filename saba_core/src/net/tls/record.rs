@@ -0,0 +1,377 @@
+//! TLS 1.3のレコード層
+//! https://www.rfc-editor.org/rfc/rfc8446#section-5
+
+use alloc::format;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+use super::key_schedule::{hkdf_expand_label, CipherSuite};
+use super::TlsError;
+use crate::crypto::chacha20poly1305::ChaCha20Poly1305;
+use crate::crypto::gcm::{AesGcm, NONCE_SIZE, TAG_SIZE};
+use crate::error::Error;
+use crate::net::Connection;
+
+/// https://www.rfc-editor.org/rfc/rfc8446#appendix-B.1
+pub const CHANGE_CIPHER_SPEC: u8 = 20;
+pub const ALERT: u8 = 21;
+pub const HANDSHAKE: u8 = 22;
+pub const APPLICATION_DATA: u8 = 23;
+
+/// https://www.rfc-editor.org/rfc/rfc8446#appendix-B.2
+pub const ALERT_CLOSE_NOTIFY: u8 = 0;
+pub const ALERT_BAD_RECORD_MAC: u8 = 20;
+pub const ALERT_HANDSHAKE_FAILURE: u8 = 40;
+pub const ALERT_BAD_CERTIFICATE: u8 = 42;
+
+/// 1つのレコードに入れられる平文の最大のバイト数
+/// https://www.rfc-editor.org/rfc/rfc8446#section-5.1
+pub const MAX_FRAGMENT_LENGTH: usize = 1 << 14;
+/// 暗号化したレコードの最大のバイト数
+/// https://www.rfc-editor.org/rfc/rfc8446#section-5.2
+const MAX_CIPHERTEXT_LENGTH: usize = MAX_FRAGMENT_LENGTH + 256;
+/// 受け付けるハンドシェイクのメッセージの最大のバイト数
+const MAX_HANDSHAKE_MESSAGE_LENGTH: usize = 1 << 18;
+const HEADER_SIZE: usize = 5;
+/// TLS 1.3でもレコードのバージョンには互換性のためTLS 1.2の値を使う
+const LEGACY_RECORD_VERSION: [u8; 2] = [0x03, 0x03];
+
+fn unexpected_message(message: &str) -> Error {
+    Error::Tls(TlsError::HandshakeFailure(message.to_string()))
+}
+
+/// 暗号スイートで選んだ認証付き暗号。どちらもノンスは12バイト、認証タグは16バイト
+enum Aead {
+    AesGcm(AesGcm),
+    ChaCha20Poly1305(ChaCha20Poly1305),
+}
+
+impl Aead {
+    fn seal(&self, nonce: &[u8; NONCE_SIZE], aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+        match self {
+            Aead::AesGcm(aead) => aead.seal(nonce, aad, plaintext),
+            Aead::ChaCha20Poly1305(aead) => aead.seal(nonce, aad, plaintext),
+        }
+    }
+
+    fn open(
+        &self,
+        nonce: &[u8; NONCE_SIZE],
+        aad: &[u8],
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        match self {
+            Aead::AesGcm(aead) => aead.open(nonce, aad, ciphertext),
+            Aead::ChaCha20Poly1305(aead) => aead.open(nonce, aad, ciphertext),
+        }
+    }
+}
+
+/// 一方向の通信の暗号化に使う鍵とシーケンス番号
+/// https://www.rfc-editor.org/rfc/rfc8446#section-5.3
+struct CipherState {
+    aead: Aead,
+    iv: [u8; NONCE_SIZE],
+    sequence_number: u64,
+}
+
+impl CipherState {
+    fn new(suite: CipherSuite, traffic_secret: &[u8]) -> Result<Self, Error> {
+        let key = hkdf_expand_label(suite, traffic_secret, "key", &[], suite.key_size());
+        let mut iv = [0; NONCE_SIZE];
+        iv.copy_from_slice(&hkdf_expand_label(
+            suite,
+            traffic_secret,
+            "iv",
+            &[],
+            NONCE_SIZE,
+        ));
+        let aead = match suite {
+            CipherSuite::Aes128GcmSha256 | CipherSuite::Aes256GcmSha384 => {
+                Aead::AesGcm(AesGcm::new(&key)?)
+            }
+            CipherSuite::ChaCha20Poly1305Sha256 => {
+                Aead::ChaCha20Poly1305(ChaCha20Poly1305::new(&key)?)
+            }
+        };
+        Ok(Self {
+            aead,
+            iv,
+            sequence_number: 0,
+        })
+    }
+
+    /// シーケンス番号とIVの排他的論理和をノンスとして返し、シーケンス番号を進めます
+    fn next_nonce(&mut self) -> [u8; NONCE_SIZE] {
+        let mut nonce = self.iv;
+        for (n, s) in nonce[NONCE_SIZE - 8..]
+            .iter_mut()
+            .zip(self.sequence_number.to_be_bytes())
+        {
+            *n ^= s;
+        }
+        self.sequence_number += 1;
+        nonce
+    }
+}
+
+/// 受信したレコード
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    content_type: u8,
+    fragment: Vec<u8>,
+}
+
+impl Record {
+    pub fn content_type(&self) -> u8 {
+        self.content_type
+    }
+
+    pub fn fragment(&self) -> Vec<u8> {
+        self.fragment.clone()
+    }
+
+    pub fn into_fragment(self) -> Vec<u8> {
+        self.fragment
+    }
+}
+
+/// 接続の上でレコードを読み書きし、鍵が設定されていれば暗号化と復号を行います
+pub struct RecordLayer<C: Connection> {
+    connection: C,
+    /// 受信したがまだレコードとして処理していないバイト列
+    received: Vec<u8>,
+    /// ServerHelloで決まるまではTLS_AES_128_GCM_SHA256として扱う
+    cipher_suite: CipherSuite,
+    read_state: Option<CipherState>,
+    write_state: Option<CipherState>,
+    /// 受信したがまだメッセージとして取り出していないハンドシェイクのバイト列
+    handshake_buffer: Vec<u8>,
+    /// ハンドシェイクが終わるまでは、互換性のために送られるChangeCipherSpecを読み飛ばす
+    /// https://www.rfc-editor.org/rfc/rfc8446#section-5
+    ignore_change_cipher_spec: bool,
+}
+
+impl<C: Connection> RecordLayer<C> {
+    pub fn new(connection: C) -> Self {
+        Self {
+            connection,
+            received: Vec::new(),
+            cipher_suite: CipherSuite::Aes128GcmSha256,
+            read_state: None,
+            write_state: None,
+            handshake_buffer: Vec::new(),
+            ignore_change_cipher_spec: true,
+        }
+    }
+
+    pub fn cipher_suite(&self) -> CipherSuite {
+        self.cipher_suite
+    }
+
+    /// 以降に設定するシークレットから鍵を導出するときの暗号スイートを設定します
+    pub fn set_cipher_suite(&mut self, cipher_suite: CipherSuite) {
+        self.cipher_suite = cipher_suite;
+    }
+
+    /// 受信するレコードの復号に使うシークレットを設定します
+    pub fn set_read_secret(&mut self, traffic_secret: &[u8]) -> Result<(), Error> {
+        if !self.handshake_buffer.is_empty() {
+            // 鍵を切り替える前のメッセージが残っていてはならない
+            return Err(unexpected_message("handshake message spans a key change"));
+        }
+        self.read_state = Some(CipherState::new(self.cipher_suite, traffic_secret)?);
+        Ok(())
+    }
+
+    /// 送信するレコードの暗号化に使うシークレットを設定します
+    pub fn set_write_secret(&mut self, traffic_secret: &[u8]) -> Result<(), Error> {
+        self.write_state = Some(CipherState::new(self.cipher_suite, traffic_secret)?);
+        Ok(())
+    }
+
+    pub fn finish_handshake(&mut self) {
+        self.ignore_change_cipher_spec = false;
+    }
+
+    /// 受信したバイト列がlenバイト以上になるまで読み取ります。接続が閉じられたらfalseを返します
+    fn fill(&mut self, len: usize) -> Result<bool, Error> {
+        let mut buf = [0u8; 4096];
+        while self.received.len() < len {
+            let bytes_read = self.connection.read(&mut buf)?;
+            if bytes_read == 0 {
+                return Ok(false);
+            }
+            self.received.extend_from_slice(&buf[..bytes_read]);
+        }
+        Ok(true)
+    }
+
+    /// レコードを1つ読み取って復号します
+    /// close_notifyのアラートを受け取った場合はNoneを返します
+    /// close_notifyを受け取る前に接続が閉じられた場合は、データを途中で切り捨てられた可能性があるためエラーを返します
+    /// https://www.rfc-editor.org/rfc/rfc8446#section-6.1
+    pub fn read_record(&mut self) -> Result<Option<Record>, Error> {
+        loop {
+            if !self.fill(HEADER_SIZE)? {
+                if self.received.is_empty() {
+                    return Err(Error::Network(
+                        "connection closed without close_notify".to_string(),
+                    ));
+                }
+                return Err(Error::Network(
+                    "connection closed in a TLS record".to_string(),
+                ));
+            }
+            let length = u16::from_be_bytes([self.received[3], self.received[4]]) as usize;
+            if length > MAX_CIPHERTEXT_LENGTH {
+                return Err(unexpected_message("record is too large"));
+            }
+            if !self.fill(HEADER_SIZE + length)? {
+                return Err(Error::Network(
+                    "connection closed in a TLS record".to_string(),
+                ));
+            }
+            let record: Vec<u8> = self.received.drain(..HEADER_SIZE + length).collect();
+            let (header, fragment) = record.split_at(HEADER_SIZE);
+            let content_type = header[0];
+
+            if content_type == CHANGE_CIPHER_SPEC {
+                if self.ignore_change_cipher_spec && fragment == [1] {
+                    continue;
+                }
+                return Err(unexpected_message("unexpected change_cipher_spec"));
+            }
+
+            let (content_type, fragment) = match &mut self.read_state {
+                None => (content_type, fragment.to_vec()),
+                Some(state) => {
+                    if content_type != APPLICATION_DATA {
+                        return Err(unexpected_message("unexpected plaintext record"));
+                    }
+                    let nonce = state.next_nonce();
+                    let mut plaintext = state
+                        .aead
+                        .open(&nonce, header, fragment)
+                        .map_err(|_| Error::Tls(TlsError::DecryptError))?;
+                    // TLSInnerPlaintextの末尾の0の詰め物を取り除き、本来のContentTypeを取り出す
+                    // https://www.rfc-editor.org/rfc/rfc8446#section-5.4
+                    while plaintext.last() == Some(&0) {
+                        plaintext.pop();
+                    }
+                    let content_type = plaintext
+                        .pop()
+                        .ok_or_else(|| unexpected_message("record has no content type"))?;
+                    (content_type, plaintext)
+                }
+            };
+            if fragment.len() > MAX_FRAGMENT_LENGTH {
+                return Err(unexpected_message("record is too large"));
+            }
+
+            if content_type == ALERT {
+                // https://www.rfc-editor.org/rfc/rfc8446#section-6
+                return match fragment[..] {
+                    [_, ALERT_CLOSE_NOTIFY] => Ok(None),
+                    [_, description] => Err(Error::Tls(TlsError::Alert(description))),
+                    _ => Err(unexpected_message("invalid alert")),
+                };
+            }
+            return Ok(Some(Record {
+                content_type,
+                fragment,
+            }));
+        }
+    }
+
+    /// dataを1つのレコードとして送信します。dataはMAX_FRAGMENT_LENGTHバイト以下でなければなりません
+    pub fn write_record(&mut self, content_type: u8, data: &[u8]) -> Result<(), Error> {
+        debug_assert!(data.len() <= MAX_FRAGMENT_LENGTH);
+        let mut record = Vec::with_capacity(HEADER_SIZE + data.len() + 1 + TAG_SIZE);
+        match &mut self.write_state {
+            None => {
+                record.push(content_type);
+                record.extend_from_slice(&LEGACY_RECORD_VERSION);
+                record.extend_from_slice(&(data.len() as u16).to_be_bytes());
+                record.extend_from_slice(data);
+            }
+            Some(state) => {
+                let mut inner = data.to_vec();
+                inner.push(content_type);
+                record.push(APPLICATION_DATA);
+                record.extend_from_slice(&LEGACY_RECORD_VERSION);
+                record.extend_from_slice(&((inner.len() + TAG_SIZE) as u16).to_be_bytes());
+                let nonce = state.next_nonce();
+                let ciphertext = state.aead.seal(&nonce, &record, &inner);
+                record.extend_from_slice(&ciphertext);
+            }
+        }
+        self.connection.write_all(&record)
+    }
+
+    /// 致命的なアラートを送信します
+    pub fn write_alert(&mut self, description: u8) -> Result<(), Error> {
+        // levelは2（fatal）。close_notifyは1（warning）を使う
+        let level = if description == ALERT_CLOSE_NOTIFY {
+            1
+        } else {
+            2
+        };
+        self.write_record(ALERT, &[level, description])
+    }
+
+    /// 互換性のためのChangeCipherSpecを送信します
+    /// https://www.rfc-editor.org/rfc/rfc8446#appendix-D.4
+    pub fn write_change_cipher_spec(&mut self) -> Result<(), Error> {
+        let record = [CHANGE_CIPHER_SPEC, 0x03, 0x03, 0x00, 0x01, 0x01];
+        self.connection.write_all(&record)
+    }
+
+    /// 受信したハンドシェイクのレコードの中身を追加します
+    pub fn push_handshake_data(&mut self, data: &[u8]) {
+        self.handshake_buffer.extend_from_slice(data);
+    }
+
+    /// 受信済みのバイト列から、ヘッダを含むハンドシェイクのメッセージを1つ取り出します
+    pub fn next_handshake_message(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        if self.handshake_buffer.len() < 4 {
+            return Ok(None);
+        }
+        let length = u32::from_be_bytes([
+            0,
+            self.handshake_buffer[1],
+            self.handshake_buffer[2],
+            self.handshake_buffer[3],
+        ]) as usize;
+        if length > MAX_HANDSHAKE_MESSAGE_LENGTH {
+            return Err(unexpected_message(&format!(
+                "handshake message is too large: {} bytes",
+                length
+            )));
+        }
+        if self.handshake_buffer.len() < 4 + length {
+            return Ok(None);
+        }
+        Ok(Some(self.handshake_buffer.drain(..4 + length).collect()))
+    }
+
+    /// ハンドシェイクのメッセージを1つ受信します
+    pub fn read_handshake_message(&mut self) -> Result<Vec<u8>, Error> {
+        loop {
+            if let Some(message) = self.next_handshake_message()? {
+                return Ok(message);
+            }
+            match self.read_record()? {
+                Some(record) if record.content_type == HANDSHAKE => {
+                    self.push_handshake_data(&record.fragment)
+                }
+                Some(_) => return Err(unexpected_message("expected a handshake message")),
+                None => {
+                    return Err(Error::Network(
+                        "connection closed during the TLS handshake".to_string(),
+                    ))
+                }
+            }
+        }
+    }
+}
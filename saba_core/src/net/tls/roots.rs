@@ -0,0 +1,56 @@
+//! サーバー証明書の検証で信頼するルート証明書
+//! 主要な認証局のルート証明書をDER形式で同梱します
+
+/// 同梱するルート証明書
+pub static ROOT_CERTIFICATES: &[&[u8]] = &[
+    // Amazon Root CA 1
+    include_bytes!("roots/amazon_root_ca_1.der"),
+    // Amazon Root CA 2
+    include_bytes!("roots/amazon_root_ca_2.der"),
+    // Amazon Root CA 3
+    include_bytes!("roots/amazon_root_ca_3.der"),
+    // Amazon Root CA 4
+    include_bytes!("roots/amazon_root_ca_4.der"),
+    // AAA Certificate Services
+    include_bytes!("roots/comodo_aaa_services_root.der"),
+    // DigiCert Global Root CA
+    include_bytes!("roots/digicert_global_root_ca.der"),
+    // DigiCert Global Root G2
+    include_bytes!("roots/digicert_global_root_g2.der"),
+    // DigiCert Global Root G3
+    include_bytes!("roots/digicert_global_root_g3.der"),
+    // Entrust Root Certification Authority - G2
+    include_bytes!("roots/entrust_root_certification_authority_g2.der"),
+    // GlobalSign Root CA
+    include_bytes!("roots/globalsign_root_ca.der"),
+    // GlobalSign
+    include_bytes!("roots/globalsign_root_ca_r3.der"),
+    // Go Daddy Root Certificate Authority - G2
+    include_bytes!("roots/go_daddy_root_certificate_authority_g2.der"),
+    // GTS Root R1
+    include_bytes!("roots/gts_root_r1.der"),
+    // GTS Root R2
+    include_bytes!("roots/gts_root_r2.der"),
+    // GTS Root R3
+    include_bytes!("roots/gts_root_r3.der"),
+    // GTS Root R4
+    include_bytes!("roots/gts_root_r4.der"),
+    // ISRG Root X1
+    include_bytes!("roots/isrg_root_x1.der"),
+    // ISRG Root X2
+    include_bytes!("roots/isrg_root_x2.der"),
+    // Microsoft ECC Root Certificate Authority 2017
+    include_bytes!("roots/microsoft_ecc_root_certificate_authority_2017.der"),
+    // Microsoft RSA Root Certificate Authority 2017
+    include_bytes!("roots/microsoft_rsa_root_certificate_authority_2017.der"),
+    // Sectigo Public Server Authentication Root E46
+    include_bytes!("roots/sectigo_public_server_authentication_root_e46.der"),
+    // Sectigo Public Server Authentication Root R46
+    include_bytes!("roots/sectigo_public_server_authentication_root_r46.der"),
+    // Starfield Root Certificate Authority - G2
+    include_bytes!("roots/starfield_root_certificate_authority_g2.der"),
+    // USERTrust ECC Certification Authority
+    include_bytes!("roots/usertrust_ecc_certification_authority.der"),
+    // USERTrust RSA Certification Authority
+    include_bytes!("roots/usertrust_rsa_certification_authority.der"),
+];
@@ -0,0 +1,658 @@
+//! X.509証明書の解析と、サーバー証明書のチェーンの検証
+//! https://www.rfc-editor.org/rfc/rfc5280
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use super::TlsError;
+use crate::crypto::der::{
+    context_specific, Der, DerReader, TAG_BOOLEAN, TAG_GENERALIZED_TIME, TAG_INTEGER, TAG_NULL,
+    TAG_OCTET_STRING, TAG_SEQUENCE, TAG_UTC_TIME,
+};
+use crate::crypto::ecdsa::{self, Curve};
+use crate::crypto::rsa::RsaPublicKey;
+use crate::crypto::HashAlgorithm;
+use crate::error::Error;
+use crate::http::date::days_from_civil;
+use crate::url::host::Host;
+
+/// サーバー証明書からルート証明書までにたどる証明書の数の上限
+const MAX_CHAIN_DEPTH: usize = 8;
+
+/// 証明書の有効期間の確認に使う現在時刻の下限で、saba_coreをビルドした時刻です（build.rsを参照）
+/// 実行するときにビルドより前の時刻にはならないため、時計が進んでいなくてもビルドの時点で期限が切れている証明書は拒否できます
+/// 時計が分からないときは、ビルドの後に期限が切れた証明書を受け入れてしまいます。下限はビルドし直すたびに進みます
+pub const MIN_VALIDATION_TIME: u64 = parse_decimal(env!("SABA_BUILD_TIME"));
+
+/// 10進数の文字列を、定数の初期化に使えるように変換します
+const fn parse_decimal(s: &str) -> u64 {
+    let bytes = s.as_bytes();
+    let mut value = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        assert!(bytes[i].is_ascii_digit(), "not a decimal number");
+        value = value * 10 + (bytes[i] - b'0') as u64;
+        i += 1;
+    }
+    value
+}
+
+const OID_RSA_ENCRYPTION: &str = "1.2.840.113549.1.1.1";
+const OID_SHA256_WITH_RSA: &str = "1.2.840.113549.1.1.11";
+const OID_SHA384_WITH_RSA: &str = "1.2.840.113549.1.1.12";
+const OID_SHA512_WITH_RSA: &str = "1.2.840.113549.1.1.13";
+const OID_EC_PUBLIC_KEY: &str = "1.2.840.10045.2.1";
+const OID_ECDSA_WITH_SHA256: &str = "1.2.840.10045.4.3.2";
+const OID_ECDSA_WITH_SHA384: &str = "1.2.840.10045.4.3.3";
+const OID_ECDSA_WITH_SHA512: &str = "1.2.840.10045.4.3.4";
+const OID_P256: &str = "1.2.840.10045.3.1.7";
+const OID_P384: &str = "1.3.132.0.34";
+
+/// https://www.rfc-editor.org/rfc/rfc5280#section-4.2.1
+const OID_KEY_USAGE: &str = "2.5.29.15";
+const OID_SUBJECT_ALT_NAME: &str = "2.5.29.17";
+const OID_BASIC_CONSTRAINTS: &str = "2.5.29.19";
+const OID_EXTENDED_KEY_USAGE: &str = "2.5.29.37";
+const OID_ANY_EXTENDED_KEY_USAGE: &str = "2.5.29.37.0";
+const OID_SERVER_AUTH: &str = "1.3.6.1.5.5.7.3.1";
+
+/// KeyUsageのdigitalSignature（ビット0）
+const KEY_USAGE_DIGITAL_SIGNATURE: u16 = 0x8000;
+/// KeyUsageのkeyCertSign（ビット5）
+const KEY_USAGE_KEY_CERT_SIGN: u16 = 0x0400;
+
+fn bad_certificate(message: &str) -> Error {
+    Error::Tls(TlsError::BadCertificate(message.to_string()))
+}
+
+/// 証明書の署名アルゴリズム
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureAlgorithm {
+    /// https://www.rfc-editor.org/rfc/rfc8017#section-8.2
+    RsaPkcs1(HashAlgorithm),
+    /// https://www.rfc-editor.org/rfc/rfc8017#section-8.1
+    RsaPss(HashAlgorithm),
+    /// https://www.rfc-editor.org/rfc/rfc5758#section-3.2
+    Ecdsa(HashAlgorithm),
+}
+
+impl SignatureAlgorithm {
+    /// AlgorithmIdentifierを読み取ります。SHA-1などの対応していないアルゴリズムの場合はNoneを返します
+    fn from_der(der: &Der) -> Result<Option<Self>, Error> {
+        let mut reader = der.reader();
+        let algorithm = match reader.read()?.oid()?.as_str() {
+            OID_SHA256_WITH_RSA => Self::RsaPkcs1(HashAlgorithm::Sha256),
+            OID_SHA384_WITH_RSA => Self::RsaPkcs1(HashAlgorithm::Sha384),
+            OID_SHA512_WITH_RSA => Self::RsaPkcs1(HashAlgorithm::Sha512),
+            OID_ECDSA_WITH_SHA256 => Self::Ecdsa(HashAlgorithm::Sha256),
+            OID_ECDSA_WITH_SHA384 => Self::Ecdsa(HashAlgorithm::Sha384),
+            OID_ECDSA_WITH_SHA512 => Self::Ecdsa(HashAlgorithm::Sha512),
+            _ => return Ok(None),
+        };
+        // RSAのパラメーターはNULLで、ECDSAにはパラメーターがない
+        reader.read_optional(TAG_NULL)?;
+        reader.finish()?;
+        Ok(Some(algorithm))
+    }
+}
+
+/// 証明書の公開鍵
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PublicKey {
+    Rsa(RsaPublicKey),
+    /// 曲線と非圧縮形式の点
+    Ec(Curve, Vec<u8>),
+}
+
+impl PublicKey {
+    /// SubjectPublicKeyInfoを読み取ります
+    /// https://www.rfc-editor.org/rfc/rfc5280#section-4.1.2.7
+    fn from_der(der: &Der) -> Result<Self, Error> {
+        let mut reader = der.reader();
+        let mut algorithm = reader.read_tag(TAG_SEQUENCE)?.reader();
+        let key = reader.read()?.bit_string()?;
+        reader.finish()?;
+
+        match algorithm.read()?.oid()?.as_str() {
+            OID_RSA_ENCRYPTION => Ok(Self::Rsa(RsaPublicKey::from_der(key)?)),
+            OID_EC_PUBLIC_KEY => {
+                let curve = match algorithm.read()?.oid()?.as_str() {
+                    OID_P256 => Curve::P256,
+                    OID_P384 => Curve::P384,
+                    oid => return Err(bad_certificate(&format!("unsupported curve: {}", oid))),
+                };
+                if key.len() != 1 + 2 * curve.field_size() || key[0] != 0x04 {
+                    return Err(bad_certificate("unsupported EC public key format"));
+                }
+                Ok(Self::Ec(curve, key.to_vec()))
+            }
+            oid => Err(bad_certificate(&format!(
+                "unsupported public key algorithm: {}",
+                oid
+            ))),
+        }
+    }
+
+    /// この公開鍵でmessageに対するsignatureを検証します
+    pub fn verify(
+        &self,
+        algorithm: SignatureAlgorithm,
+        message: &[u8],
+        signature: &[u8],
+    ) -> Result<(), Error> {
+        match (self, algorithm) {
+            (Self::Rsa(key), SignatureAlgorithm::RsaPkcs1(hash)) => {
+                key.verify_pkcs1_v15(hash, message, signature)
+            }
+            (Self::Rsa(key), SignatureAlgorithm::RsaPss(hash)) => {
+                key.verify_pss(hash, message, signature)
+            }
+            (Self::Ec(curve, point), SignatureAlgorithm::Ecdsa(hash)) => {
+                ecdsa::verify(*curve, point, &hash.digest(message), signature)
+            }
+            _ => Err(Error::Other(
+                "signature algorithm does not match the key".to_string(),
+            )),
+        }
+    }
+}
+
+/// 10進数の数字の列を数値にします
+fn parse_digits(digits: &[u8]) -> Option<u32> {
+    digits.iter().try_fold(0u32, |acc, &b| {
+        b.is_ascii_digit().then(|| acc * 10 + (b - b'0') as u32)
+    })
+}
+
+/// UTCTimeまたはGeneralizedTimeを1970年1月1日0時0分0秒(UTC)からの秒数にします
+/// https://www.rfc-editor.org/rfc/rfc5280#section-4.1.2.5
+fn parse_time(der: &Der) -> Result<u64, Error> {
+    let invalid = || bad_certificate("invalid validity time");
+    let contents = der.contents();
+    let (year, rest) = match der.tag() {
+        // YYMMDDHHMMSSZ。YYが50以上なら19YY、そうでなければ20YY
+        TAG_UTC_TIME if contents.len() == 13 => {
+            let year = parse_digits(&contents[..2]).ok_or_else(invalid)?;
+            (
+                if year >= 50 { 1900 + year } else { 2000 + year },
+                &contents[2..],
+            )
+        }
+        // YYYYMMDDHHMMSSZ
+        TAG_GENERALIZED_TIME if contents.len() == 15 => (
+            parse_digits(&contents[..4]).ok_or_else(invalid)?,
+            &contents[4..],
+        ),
+        _ => return Err(invalid()),
+    };
+    if rest[10] != b'Z' {
+        return Err(invalid());
+    }
+    let field = |i: usize| parse_digits(&rest[i..i + 2]).ok_or_else(invalid);
+    let (month, day, hour, minute, second) =
+        (field(0)?, field(2)?, field(4)?, field(6)?, field(8)?);
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 59
+    {
+        return Err(invalid());
+    }
+    let days = days_from_civil(year as i64, month, day);
+    let seconds = days * 86400 + (hour * 3600 + minute * 60 + second) as i64;
+    Ok(seconds.max(0) as u64)
+}
+
+/// ホスト名がdNSNameに一致するかを調べます
+/// ワイルドカードは最も左のラベル全体にだけ使うことができ、1つのラベルに一致します
+/// https://www.rfc-editor.org/rfc/rfc6125#section-6.4.3
+fn matches_dns_name(pattern: &str, domain: &str) -> bool {
+    let pattern = pattern.to_ascii_lowercase();
+    let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+    match pattern.strip_prefix("*.") {
+        // "*.com"のように、ワイルドカードがトップレベルドメイン全体に一致することは認めない
+        Some(suffix) if suffix.contains('.') => match domain.split_once('.') {
+            Some((label, rest)) => !label.is_empty() && rest == suffix,
+            None => false,
+        },
+        Some(_) => false,
+        None => pattern == domain,
+    }
+}
+
+/// 解析したX.509証明書
+/// https://www.rfc-editor.org/rfc/rfc5280#section-4.1
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Certificate {
+    /// 署名の対象のTBSCertificateのバイト列
+    tbs_certificate: Vec<u8>,
+    /// 対応していない署名アルゴリズムの場合はNone
+    /// ルート証明書自身の署名は検証しないため、SHA-1で自己署名されたルート証明書も使える
+    signature_algorithm: Option<SignatureAlgorithm>,
+    signature: Vec<u8>,
+    /// 発行者と主体の名前は、DERのバイト列のまま比較する
+    issuer: Vec<u8>,
+    subject: Vec<u8>,
+    not_before: u64,
+    not_after: u64,
+    public_key: PublicKey,
+    is_ca: bool,
+    path_length: Option<u64>,
+    key_usage: Option<u16>,
+    extended_key_usage: Option<Vec<String>>,
+    dns_names: Vec<String>,
+    ip_addresses: Vec<Vec<u8>>,
+}
+
+impl Certificate {
+    /// DERでエンコードされた証明書を解析します
+    pub fn from_der(der: &[u8]) -> Result<Self, Error> {
+        Self::parse(der).map_err(|e| match e {
+            Error::Tls(_) => e,
+            e => bad_certificate(&format!("malformed certificate: {:?}", e)),
+        })
+    }
+
+    fn parse(der: &[u8]) -> Result<Self, Error> {
+        let mut reader = DerReader::new(der);
+        let certificate = reader.read_tag(TAG_SEQUENCE)?;
+        reader.finish()?;
+
+        let mut reader = certificate.reader();
+        let tbs_certificate = reader.read_tag(TAG_SEQUENCE)?;
+        let signature_algorithm = reader.read_tag(TAG_SEQUENCE)?;
+        let signature = reader.read()?.bit_string()?;
+        reader.finish()?;
+
+        // https://www.rfc-editor.org/rfc/rfc5280#section-4.1.2
+        let mut tbs = tbs_certificate.reader();
+        let version = match tbs.read_optional(context_specific(0))? {
+            Some(version) => {
+                let mut reader = version.reader();
+                let version = reader.read()?.small_integer()?;
+                reader.finish()?;
+                version
+            }
+            None => 0,
+        };
+        tbs.read_tag(TAG_INTEGER)?;
+        // TBSCertificateの中の署名アルゴリズムは外側のものと一致しなければならない
+        if tbs.read_tag(TAG_SEQUENCE)?.raw() != signature_algorithm.raw() {
+            return Err(bad_certificate("signature algorithm mismatch"));
+        }
+        let issuer = tbs.read_tag(TAG_SEQUENCE)?.raw().to_vec();
+        let mut validity = tbs.read_tag(TAG_SEQUENCE)?.reader();
+        let not_before = parse_time(&validity.read()?)?;
+        let not_after = parse_time(&validity.read()?)?;
+        validity.finish()?;
+        let subject = tbs.read_tag(TAG_SEQUENCE)?.raw().to_vec();
+        let public_key = PublicKey::from_der(&tbs.read_tag(TAG_SEQUENCE)?)?;
+        // issuerUniqueIDとsubjectUniqueIDは使わない
+        tbs.read_optional(0x81)?;
+        tbs.read_optional(0x82)?;
+
+        let mut certificate = Self {
+            tbs_certificate: tbs_certificate.raw().to_vec(),
+            signature_algorithm: SignatureAlgorithm::from_der(&signature_algorithm)?,
+            signature: signature.to_vec(),
+            issuer,
+            subject,
+            not_before,
+            not_after,
+            public_key,
+            is_ca: false,
+            path_length: None,
+            key_usage: None,
+            extended_key_usage: None,
+            dns_names: Vec::new(),
+            ip_addresses: Vec::new(),
+        };
+
+        if let Some(extensions) = tbs.read_optional(context_specific(3))? {
+            if version != 2 {
+                return Err(bad_certificate("extensions in a non-v3 certificate"));
+            }
+            let mut reader = extensions.reader();
+            let extensions = reader.read_tag(TAG_SEQUENCE)?;
+            reader.finish()?;
+            let mut reader = extensions.reader();
+            while !reader.is_empty() {
+                certificate.parse_extension(&reader.read_tag(TAG_SEQUENCE)?)?;
+            }
+        }
+        tbs.finish()?;
+        Ok(certificate)
+    }
+
+    /// https://www.rfc-editor.org/rfc/rfc5280#section-4.2
+    fn parse_extension(&mut self, extension: &Der) -> Result<(), Error> {
+        let mut reader = extension.reader();
+        let id = reader.read()?.oid()?;
+        let critical = match reader.read_optional(TAG_BOOLEAN)? {
+            Some(critical) => critical.boolean()?,
+            None => false,
+        };
+        let value = reader.read_tag(TAG_OCTET_STRING)?;
+        reader.finish()?;
+        let mut reader = DerReader::new(value.contents());
+        let value = reader.read()?;
+        reader.finish()?;
+
+        match id.as_str() {
+            // https://www.rfc-editor.org/rfc/rfc5280#section-4.2.1.9
+            OID_BASIC_CONSTRAINTS => {
+                value.expect(TAG_SEQUENCE)?;
+                let mut reader = value.reader();
+                if let Some(ca) = reader.read_optional(TAG_BOOLEAN)? {
+                    self.is_ca = ca.boolean()?;
+                }
+                if let Some(path_length) = reader.read_optional(TAG_INTEGER)? {
+                    self.path_length = Some(path_length.small_integer()?);
+                }
+                reader.finish()?;
+            }
+            // https://www.rfc-editor.org/rfc/rfc5280#section-4.2.1.3
+            OID_KEY_USAGE => {
+                // 名前付きビットのリストは末尾の0のビットを省略するため、未使用ビットを含むBIT STRINGになる
+                let bits = match value.contents() {
+                    [unused, bytes @ ..] if *unused < 8 && bytes.len() <= 2 => bytes,
+                    _ => return Err(bad_certificate("invalid key usage")),
+                };
+                let usage = bits
+                    .iter()
+                    .chain([0, 0].iter())
+                    .take(2)
+                    .fold(0u16, |acc, &b| (acc << 8) | b as u16);
+                self.key_usage = Some(usage);
+            }
+            // https://www.rfc-editor.org/rfc/rfc5280#section-4.2.1.12
+            OID_EXTENDED_KEY_USAGE => {
+                value.expect(TAG_SEQUENCE)?;
+                let mut reader = value.reader();
+                let mut purposes = Vec::new();
+                while !reader.is_empty() {
+                    purposes.push(reader.read()?.oid()?);
+                }
+                self.extended_key_usage = Some(purposes);
+            }
+            // https://www.rfc-editor.org/rfc/rfc5280#section-4.2.1.6
+            OID_SUBJECT_ALT_NAME => {
+                value.expect(TAG_SEQUENCE)?;
+                let mut reader = value.reader();
+                while !reader.is_empty() {
+                    let name = reader.read()?;
+                    match name.tag() {
+                        // dNSName [2] IA5String
+                        0x82 => {
+                            let dns_name = core::str::from_utf8(name.contents())
+                                .map_err(|_| bad_certificate("invalid dNSName"))?;
+                            self.dns_names.push(dns_name.to_string());
+                        }
+                        // iPAddress [7] OCTET STRING
+                        0x87 => self.ip_addresses.push(name.contents().to_vec()),
+                        _ => {}
+                    }
+                }
+            }
+            // 理解できない重要な拡張を含む証明書は拒否しなければならない
+            id if critical => {
+                return Err(bad_certificate(&format!(
+                    "unsupported critical extension: {}",
+                    id
+                )))
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        self.public_key.clone()
+    }
+
+    pub fn dns_names(&self) -> Vec<String> {
+        self.dns_names.clone()
+    }
+
+    pub fn not_before(&self) -> u64 {
+        self.not_before
+    }
+
+    pub fn not_after(&self) -> u64 {
+        self.not_after
+    }
+
+    /// 証明書がホストに対して発行されたものかを、subjectAltNameで調べます
+    /// https://www.rfc-editor.org/rfc/rfc6125#section-6
+    pub fn matches_host(&self, host: &Host) -> bool {
+        match host {
+            Host::Domain(domain) => self
+                .dns_names
+                .iter()
+                .any(|pattern| matches_dns_name(pattern, domain)),
+            Host::Ipv4(address) => self
+                .ip_addresses
+                .iter()
+                .any(|ip| ip[..] == address.to_be_bytes()),
+            Host::Ipv6(pieces) => {
+                let address: Vec<u8> = pieces.iter().flat_map(|p| p.to_be_bytes()).collect();
+                self.ip_addresses.contains(&address)
+            }
+            Host::Opaque(_) | Host::Empty => false,
+        }
+    }
+
+    fn check_validity(&self, time: Option<u64>) -> Result<(), Error> {
+        // 下限より前の時刻は時計が合っていないものとみなし、現在時刻が分からない場合と同じに扱う
+        match time.filter(|time| *time >= MIN_VALIDATION_TIME) {
+            Some(time) if time < self.not_before => {
+                Err(bad_certificate("certificate is not yet valid"))
+            }
+            Some(time) if time > self.not_after => Err(bad_certificate("certificate has expired")),
+            // 現在時刻が分からなくても、下限の時点で期限が切れている証明書は受け入れない
+            None if MIN_VALIDATION_TIME > self.not_after => {
+                Err(bad_certificate("certificate has expired"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// この証明書がissuerの鍵で署名されているかを調べます
+    fn is_issued_by(&self, issuer: &Certificate) -> bool {
+        self.issuer == issuer.subject
+            && self.signature_algorithm.is_some_and(|algorithm| {
+                issuer
+                    .public_key
+                    .verify(algorithm, &self.tbs_certificate, &self.signature)
+                    .is_ok()
+            })
+    }
+
+    /// この証明書が、後ろにintermediates個の中間CA証明書が続くチェーンのCA証明書として使えるかを調べます
+    fn can_issue(&self, intermediates: usize) -> bool {
+        self.is_ca
+            && self
+                .key_usage
+                .map_or(true, |usage| usage & KEY_USAGE_KEY_CERT_SIGN != 0)
+            && self
+                .path_length
+                .map_or(true, |length| length >= intermediates as u64)
+    }
+
+    /// TLSのサーバーの認証に使える証明書かを調べます
+    fn can_authenticate_server(&self) -> bool {
+        self.key_usage
+            .map_or(true, |usage| usage & KEY_USAGE_DIGITAL_SIGNATURE != 0)
+            && self.extended_key_usage.as_ref().map_or(true, |purposes| {
+                purposes
+                    .iter()
+                    .any(|p| p == OID_SERVER_AUTH || p == OID_ANY_EXTENDED_KEY_USAGE)
+            })
+    }
+}
+
+/// サーバーから受け取った証明書のリスト（先頭がサーバーの証明書）を、信頼するルート証明書までたどって検証します
+/// timeは現在時刻（1970年1月1日からの秒数）です
+/// Noneの場合は、MIN_VALIDATION_TIMEの時点で期限が切れていないことだけを確認します
+/// https://www.rfc-editor.org/rfc/rfc5280#section-6
+pub fn verify_chain(
+    chain: &[Certificate],
+    roots: &[Certificate],
+    host: &Host,
+    time: Option<u64>,
+) -> Result<(), Error> {
+    let leaf = chain
+        .first()
+        .ok_or_else(|| bad_certificate("no certificate"))?;
+    if !leaf.matches_host(host) {
+        return Err(bad_certificate(&format!(
+            "certificate is not valid for {}",
+            host
+        )));
+    }
+    if !leaf.can_authenticate_server() {
+        return Err(bad_certificate(
+            "certificate is not for server authentication",
+        ));
+    }
+
+    let mut current = leaf;
+    for intermediates in 0..MAX_CHAIN_DEPTH {
+        current.check_validity(time)?;
+        if roots.iter().any(|root| current.is_issued_by(root)) {
+            return Ok(());
+        }
+        // サーバーが送ってきた中間CA証明書から発行者を探す
+        current = chain[1..]
+            .iter()
+            .find(|ca| ca.can_issue(intermediates) && current.is_issued_by(ca))
+            .ok_or_else(|| bad_certificate("certificate is not issued by a trusted authority"))?;
+    }
+    Err(bad_certificate("certificate chain is too long"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::tls::roots::ROOT_CERTIFICATES;
+    use alloc::vec;
+
+    const TEST_ROOT_CA: &[u8] = include_bytes!("testdata/test_root_ca.der");
+    const TEST_INTERMEDIATE_CA: &[u8] = include_bytes!("testdata/test_intermediate_ca.der");
+    const TEST_SERVER: &[u8] = include_bytes!("testdata/test_server.der");
+
+    /// 2030年1月1日
+    const TIME: u64 = 1893456000;
+
+    fn test_chain() -> (Vec<Certificate>, Vec<Certificate>) {
+        let chain = vec![
+            Certificate::from_der(TEST_SERVER).expect("invalid server certificate"),
+            Certificate::from_der(TEST_INTERMEDIATE_CA).expect("invalid intermediate"),
+        ];
+        let roots = vec![Certificate::from_der(TEST_ROOT_CA).expect("invalid root")];
+        (chain, roots)
+    }
+
+    fn domain(name: &str) -> Host {
+        Host::Domain(name.to_string())
+    }
+
+    #[test]
+    fn test_parse() {
+        let (chain, roots) = test_chain();
+        assert_eq!(
+            vec!["localhost".to_string(), "*.saba.test".to_string()],
+            chain[0].dns_names()
+        );
+        assert!(matches!(
+            chain[0].public_key(),
+            PublicKey::Ec(Curve::P256, _)
+        ));
+        assert!(matches!(roots[0].public_key(), PublicKey::Rsa(_)));
+        assert!(chain[1].can_issue(0));
+        assert!(!chain[1].can_issue(1));
+        assert!(!chain[0].can_issue(0));
+        assert!(chain[0].not_before() < chain[0].not_after());
+    }
+
+    #[test]
+    fn test_verify_chain() {
+        let (chain, roots) = test_chain();
+        for host in [
+            domain("localhost"),
+            domain("www.saba.test"),
+            Host::Ipv4(0x7f000001),
+            Host::Ipv6([0, 0, 0, 0, 0, 0, 0, 1]),
+        ] {
+            assert_eq!(Ok(()), verify_chain(&chain, &roots, &host, Some(TIME)));
+        }
+        // 現在時刻が分からないときは、下限の時点で期限が切れていないことだけを確認する
+        assert_eq!(
+            Ok(()),
+            verify_chain(&chain, &roots, &domain("localhost"), None)
+        );
+    }
+
+    #[test]
+    fn test_verify_chain_failures() {
+        let (chain, roots) = test_chain();
+        let localhost = domain("localhost");
+        for host in [
+            domain("example.com"),
+            domain("saba.test"),
+            domain("a.b.saba.test"),
+            Host::Ipv4(0x7f000002),
+        ] {
+            assert!(verify_chain(&chain, &roots, &host, Some(TIME)).is_err());
+        }
+        // 信頼していないルート証明書
+        assert!(verify_chain(&chain, &[], &localhost, Some(TIME)).is_err());
+        // 中間CA証明書が送られてこない
+        assert!(verify_chain(&chain[..1], &roots, &localhost, Some(TIME)).is_err());
+        // 有効期間の前後
+        let time = TIME.max(MIN_VALIDATION_TIME);
+        let mut not_yet_valid = chain.clone();
+        not_yet_valid[0].not_before = time + 1;
+        assert!(verify_chain(&not_yet_valid, &roots, &localhost, Some(time)).is_err());
+        assert!(verify_chain(&chain, &roots, &localhost, Some(u64::MAX)).is_err());
+        // サーバー証明書をCA証明書として使うことはできない
+        let forged = vec![chain[0].clone(), chain[0].clone()];
+        assert!(verify_chain(&forged, &roots, &localhost, Some(TIME)).is_err());
+    }
+
+    #[test]
+    fn test_min_validation_time() {
+        let (chain, _) = test_chain();
+        let mut expired = chain[0].clone();
+        expired.not_after = MIN_VALIDATION_TIME - 1;
+        // 時計が0や下限より前の時刻でも、下限の時点で期限が切れている証明書は受け入れない
+        for time in [None, Some(0), Some(MIN_VALIDATION_TIME - 2)] {
+            assert!(expired.check_validity(time).is_err());
+        }
+        // 下限より前の時刻では、まだ有効期間が始まっていないことは確認できない
+        assert_eq!(Ok(()), chain[0].check_validity(Some(0)));
+    }
+
+    #[test]
+    fn test_matches_dns_name() {
+        assert!(matches_dns_name("example.com", "EXAMPLE.com."));
+        assert!(matches_dns_name("*.example.com", "www.example.com"));
+        assert!(!matches_dns_name("*.example.com", "example.com"));
+        assert!(!matches_dns_name("*.com", "example.com"));
+        assert!(!matches_dns_name("w*.example.com", "www.example.com"));
+    }
+
+    #[test]
+    fn test_root_certificates() {
+        for der in ROOT_CERTIFICATES {
+            let root = Certificate::from_der(der).expect("invalid root certificate");
+            // ルート証明書は自己署名されている
+            if root.signature_algorithm.is_some() {
+                assert!(root.is_issued_by(&root));
+            }
+            assert!(root.can_issue(0));
+        }
+    }
+}
@@ -1,12 +1,11 @@
 use core::cell::RefCell;
 
 use alloc::{
-    rc::Rc,
+    rc::{Rc, Weak},
     string::{String, ToString},
-    vec::Vec,
 };
 
-use crate::renderer::dom::node::{Element, NodeKind};
+use crate::renderer::dom::node::NodeKind;
 
 use super::node::{ElementKind, Node};
 
@@ -15,9 +14,7 @@ pub fn get_target_element_node(
     element_kind: ElementKind,
 ) -> Option<Rc<RefCell<Node>>> {
     let root = root?;
-    if root.borrow().kind()
-        == NodeKind::Element(Element::new(&element_kind.to_string(), Vec::new()))
-    {
+    if root.borrow().element_kind() == Some(element_kind) {
         return Some(root.clone());
     }
 
@@ -37,3 +34,65 @@ pub fn get_style_content(root: Rc<RefCell<Node>>) -> String {
         })
         .unwrap_or("".to_string())
 }
+
+/// ノードを親ノードの子供から取り除きます。親ノードがなければ何もしません
+/// https://dom.spec.whatwg.org/#concept-node-remove
+pub fn remove_from_parent(node: &Rc<RefCell<Node>>) {
+    let parent = match node.borrow().parent().upgrade() {
+        Some(parent) => parent,
+        None => return,
+    };
+    let previous = node.borrow().previous_sibling();
+    let next = node.borrow().next_sibling();
+
+    match previous.upgrade() {
+        Some(previous) => previous.borrow_mut().set_next_sibling(next.clone()),
+        None => parent.borrow_mut().set_first_child(next.clone()),
+    }
+    match next {
+        Some(next) => next.borrow_mut().set_previous_sibling(previous),
+        None => parent.borrow_mut().set_last_child(previous),
+    }
+
+    let mut node = node.borrow_mut();
+    node.set_parent(Weak::new());
+    node.set_previous_sibling(Weak::new());
+    node.set_next_sibling(None);
+}
+
+/// 親ノードの子供のうち、基準となるノードの直前にノードを追加します
+/// 基準となるノードがなければ最後尾に追加します。ノードがすでに別の親を持つときは、そこから取り除きます
+/// https://dom.spec.whatwg.org/#concept-node-insert
+pub fn insert_before(
+    parent: &Rc<RefCell<Node>>,
+    child: Rc<RefCell<Node>>,
+    reference: Option<&Rc<RefCell<Node>>>,
+) {
+    remove_from_parent(&child);
+
+    let previous = match reference {
+        Some(reference) => reference.borrow().previous_sibling(),
+        None => parent.borrow().last_child(),
+    };
+    match previous.upgrade() {
+        Some(previous) => previous.borrow_mut().set_next_sibling(Some(child.clone())),
+        None => parent.borrow_mut().set_first_child(Some(child.clone())),
+    }
+    match reference {
+        Some(reference) => reference
+            .borrow_mut()
+            .set_previous_sibling(Rc::downgrade(&child)),
+        None => parent.borrow_mut().set_last_child(Rc::downgrade(&child)),
+    }
+
+    let mut node = child.borrow_mut();
+    node.set_parent(Rc::downgrade(parent));
+    node.set_previous_sibling(previous);
+    node.set_next_sibling(reference.cloned());
+}
+
+/// 親ノードの子供の最後尾にノードを追加します
+/// https://dom.spec.whatwg.org/#concept-node-append
+pub fn append_child(parent: &Rc<RefCell<Node>>, child: Rc<RefCell<Node>>) {
+    insert_before(parent, child, None);
+}
@@ -1,13 +1,9 @@
 use alloc::format;
-use core::{
-    cell::RefCell,
-    fmt::{Display, Formatter},
-    str::FromStr,
-};
+use core::{cell::RefCell, str::FromStr};
 
 use alloc::{
     rc::{Rc, Weak},
-    string::String,
+    string::{String, ToString},
     vec::Vec,
 };
 
@@ -40,6 +36,9 @@ impl Window {
 #[derive(Debug, Clone)]
 pub struct Node {
     pub kind: NodeKind,
+    /// Documentノードのモード。Document以外のノードでは使われません
+    /// https://dom.spec.whatwg.org/#concept-document-mode
+    document_mode: DocumentMode,
     window: Weak<RefCell<Window>>,
     parent: Weak<RefCell<Node>>,
     first_child: Option<Rc<RefCell<Node>>>,
@@ -52,6 +51,7 @@ impl Node {
     pub fn new(kind: NodeKind) -> Self {
         Self {
             kind,
+            document_mode: DocumentMode::NoQuirks,
             window: Weak::new(),
             parent: Weak::new(),
             first_child: None,
@@ -61,6 +61,14 @@ impl Node {
         }
     }
 
+    pub fn set_document_mode(&mut self, mode: DocumentMode) {
+        self.document_mode = mode;
+    }
+
+    pub fn document_mode(&self) -> DocumentMode {
+        self.document_mode
+    }
+
     pub fn set_window(&mut self, window: Weak<RefCell<Window>>) {
         self.window = window;
     }
@@ -109,16 +117,33 @@ impl Node {
         self.kind.clone()
     }
 
+    /// 要素がまだ持っていない名前の属性だけを追加します。要素でないノードでは何もしません
+    pub fn add_missing_attributes(&mut self, attributes: &[Attribute]) {
+        if let NodeKind::Element(ref mut element) = self.kind {
+            for attribute in attributes {
+                if element.get_attr(&attribute.name()).is_none() {
+                    element.attributes.push(attribute.clone());
+                }
+            }
+        }
+    }
+
     pub fn get_element(&self) -> Option<Element> {
         match self.kind {
-            NodeKind::Document | NodeKind::Text(_) => None,
+            NodeKind::Document
+            | NodeKind::DocumentType(_)
+            | NodeKind::Text(_)
+            | NodeKind::Comment(_) => None,
             NodeKind::Element(ref e) => Some(e.clone()),
         }
     }
 
     pub fn element_kind(&self) -> Option<ElementKind> {
         match self.kind {
-            NodeKind::Document | NodeKind::Text(_) => None,
+            NodeKind::Document
+            | NodeKind::DocumentType(_)
+            | NodeKind::Text(_)
+            | NodeKind::Comment(_) => None,
             NodeKind::Element(ref e) => Some(e.kind()),
         }
     }
@@ -128,24 +153,38 @@ impl Node {
 pub enum NodeKind {
     /// https://dom.spec.whatwg.org/#interface-document
     Document,
+    /// https://dom.spec.whatwg.org/#interface-documenttype
+    DocumentType(DocumentType),
     /// https://dom.spec.whatwg.org/#interface-element
     Element(Element),
     /// https://dom.spec.whatwg.org/#interface-text
     Text(String),
+    /// https://dom.spec.whatwg.org/#interface-comment
+    Comment(String),
 }
 
 impl PartialEq for NodeKind {
     fn eq(&self, other: &Self) -> bool {
         match &self {
             NodeKind::Document => matches!(other, NodeKind::Document),
+            NodeKind::DocumentType(d1) => match &other {
+                NodeKind::DocumentType(d2) => d1 == d2,
+                _ => false,
+            },
             NodeKind::Element(e1) => match &other {
-                NodeKind::Element(e2) => e1.kind == e2.kind,
+                NodeKind::Element(e2) => {
+                    e1.local_name == e2.local_name && e1.namespace == e2.namespace
+                }
                 _ => false,
             },
             NodeKind::Text(t1) => match &other {
                 NodeKind::Text(t2) => t1 == t2,
                 _ => false,
             },
+            NodeKind::Comment(c1) => match &other {
+                NodeKind::Comment(c2) => c1 == c2,
+                _ => false,
+            },
         }
     }
 }
@@ -156,18 +195,96 @@ impl PartialEq for Node {
     }
 }
 
+/// https://dom.spec.whatwg.org/#concept-document-mode
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DocumentMode {
+    /// https://dom.spec.whatwg.org/#concept-document-no-quirks
+    NoQuirks,
+    /// https://dom.spec.whatwg.org/#concept-document-quirks
+    Quirks,
+    /// https://dom.spec.whatwg.org/#concept-document-limited-quirks
+    LimitedQuirks,
+}
+
+/// https://dom.spec.whatwg.org/#interface-documenttype
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocumentType {
+    name: String,
+    public_id: String,
+    system_id: String,
+}
+
+impl DocumentType {
+    pub fn new(name: &str, public_id: &str, system_id: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            public_id: public_id.to_string(),
+            system_id: system_id.to_string(),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    pub fn public_id(&self) -> String {
+        self.public_id.clone()
+    }
+
+    pub fn system_id(&self) -> String {
+        self.system_id.clone()
+    }
+}
+
+/// https://infra.spec.whatwg.org/#namespaces
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Namespace {
+    Html,
+    MathMl,
+    Svg,
+}
+
+impl Namespace {
+    pub fn url(&self) -> &'static str {
+        match self {
+            Namespace::Html => "http://www.w3.org/1999/xhtml",
+            Namespace::MathMl => "http://www.w3.org/1998/Math/MathML",
+            Namespace::Svg => "http://www.w3.org/2000/svg",
+        }
+    }
+}
+
 /// https://dom.spec.whatwg.org/#interface-element
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Element {
     kind: ElementKind,
+    /// https://dom.spec.whatwg.org/#concept-element-local-name
+    local_name: String,
+    /// https://dom.spec.whatwg.org/#concept-element-namespace
+    namespace: Namespace,
     attributes: Vec<Attribute>,
 }
 
 impl Element {
-    pub fn new(element_name: &str, attributes: Vec<Attribute>) -> Self {
+    /// HTML名前空間の要素を作ります
+    pub fn new(local_name: &str, attributes: Vec<Attribute>) -> Self {
+        Self::new_with_namespace(local_name, Namespace::Html, attributes)
+    }
+
+    pub fn new_with_namespace(
+        local_name: &str,
+        namespace: Namespace,
+        attributes: Vec<Attribute>,
+    ) -> Self {
+        // 個別に扱う要素かどうかは、作るときに一度だけ判定しておく
+        let kind = match namespace {
+            Namespace::Html => ElementKind::from_str(local_name).unwrap_or(ElementKind::Other),
+            _ => ElementKind::Other,
+        };
         Self {
-            kind: ElementKind::from_str(element_name)
-                .expect("failed to convert string to ElementKind"),
+            kind,
+            local_name: local_name.to_string(),
+            namespace,
             attributes,
         }
     }
@@ -176,6 +293,19 @@ impl Element {
         self.kind
     }
 
+    pub fn local_name(&self) -> String {
+        self.local_name.clone()
+    }
+
+    pub fn namespace(&self) -> Namespace {
+        self.namespace
+    }
+
+    /// HTML名前空間の、指定した名前の要素かどうか
+    pub fn is_html(&self, local_name: &str) -> bool {
+        self.namespace == Namespace::Html && self.local_name == local_name
+    }
+
     pub fn attributes(&self) -> Vec<Attribute> {
         self.attributes.clone()
     }
@@ -189,11 +319,78 @@ impl Element {
         None
     }
 
+    /// https://html.spec.whatwg.org/multipage/rendering.html#flow-content-3
     pub fn is_block(&self) -> bool {
-        match self.kind {
-            ElementKind::Body | ElementKind::H1 | ElementKind::H2 | ElementKind::P => true,
-            _ => false,
-        }
+        self.namespace == Namespace::Html
+            && matches!(
+                self.local_name.as_str(),
+                "address"
+                    | "article"
+                    | "aside"
+                    | "blockquote"
+                    | "body"
+                    | "center"
+                    | "dd"
+                    | "details"
+                    | "dialog"
+                    | "dir"
+                    | "div"
+                    | "dl"
+                    | "dt"
+                    | "fieldset"
+                    | "figcaption"
+                    | "figure"
+                    | "footer"
+                    | "form"
+                    | "h1"
+                    | "h2"
+                    | "h3"
+                    | "h4"
+                    | "h5"
+                    | "h6"
+                    | "header"
+                    | "hgroup"
+                    | "hr"
+                    | "html"
+                    | "legend"
+                    | "li"
+                    | "listing"
+                    | "main"
+                    | "menu"
+                    | "nav"
+                    | "ol"
+                    | "p"
+                    | "plaintext"
+                    | "pre"
+                    | "search"
+                    | "section"
+                    | "summary"
+                    | "ul"
+                    | "xmp"
+            )
+    }
+
+    /// https://html.spec.whatwg.org/multipage/rendering.html#hidden-elements
+    pub fn is_hidden(&self) -> bool {
+        self.namespace == Namespace::Html
+            && matches!(
+                self.local_name.as_str(),
+                "area"
+                    | "base"
+                    | "basefont"
+                    | "datalist"
+                    | "head"
+                    | "link"
+                    | "meta"
+                    | "noembed"
+                    | "noframes"
+                    | "param"
+                    | "rp"
+                    | "script"
+                    | "style"
+                    | "template"
+                    | "title"
+            )
     }
 }
 
@@ -204,10 +401,16 @@ pub enum ElementKind {
     Html,
     /// https://html.spec.whatwg.org/multipage/semantics.html#the-head-element
     Head,
+    /// https://html.spec.whatwg.org/multipage/semantics.html#the-title-element
+    Title,
+    /// https://html.spec.whatwg.org/multipage/semantics.html#the-base-element
+    Base,
     /// https://html.spec.whatwg.org/multipage/semantics.html#the-style-element
     Style,
     /// https://html.spec.whatwg.org/multipage/scripting.html#the-script-element
     Script,
+    /// https://html.spec.whatwg.org/multipage/scripting.html#the-noscript-element
+    Noscript,
     /// https://html.spec.whatwg.org/multipage/sections.html#the-body-element
     Body,
     /// https://html.spec.whatwg.org/multipage/grouping-content.html#the-p-element
@@ -217,6 +420,20 @@ pub enum ElementKind {
     H2,
     /// https://html.spec.whatwg.org/multipage/text-level-semantics.html#the-a-element
     A,
+    /// https://html.spec.whatwg.org/multipage/form-elements.html#the-textarea-element
+    Textarea,
+    /// https://html.spec.whatwg.org/multipage/iframe-embed-object.html#the-iframe-element
+    Iframe,
+    /// https://html.spec.whatwg.org/multipage/obsolete.html#xmp
+    Xmp,
+    /// https://html.spec.whatwg.org/multipage/obsolete.html#noembed
+    Noembed,
+    /// https://html.spec.whatwg.org/multipage/obsolete.html#noframes
+    Noframes,
+    /// https://html.spec.whatwg.org/multipage/obsolete.html#plaintext
+    Plaintext,
+    /// 上記以外の要素。要素の名前はElement::local_nameで分かります
+    Other,
 }
 
 impl FromStr for ElementKind {
@@ -226,31 +443,23 @@ impl FromStr for ElementKind {
         match s {
             "html" => Ok(ElementKind::Html),
             "head" => Ok(ElementKind::Head),
+            "title" => Ok(ElementKind::Title),
+            "base" => Ok(ElementKind::Base),
             "style" => Ok(ElementKind::Style),
             "script" => Ok(ElementKind::Script),
+            "noscript" => Ok(ElementKind::Noscript),
             "body" => Ok(ElementKind::Body),
             "p" => Ok(ElementKind::P),
             "h1" => Ok(ElementKind::H1),
             "h2" => Ok(ElementKind::H2),
             "a" => Ok(ElementKind::A),
+            "textarea" => Ok(ElementKind::Textarea),
+            "iframe" => Ok(ElementKind::Iframe),
+            "xmp" => Ok(ElementKind::Xmp),
+            "noembed" => Ok(ElementKind::Noembed),
+            "noframes" => Ok(ElementKind::Noframes),
+            "plaintext" => Ok(ElementKind::Plaintext),
             _ => Err(format!("unimplemented element name {:?}", s)),
         }
     }
 }
-
-impl Display for ElementKind {
-    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
-        let s = match self {
-            ElementKind::Html => "html",
-            ElementKind::Head => "head",
-            ElementKind::Style => "style",
-            ElementKind::Script => "script",
-            ElementKind::Body => "body",
-            ElementKind::P => "p",
-            ElementKind::H1 => "h1",
-            ElementKind::H2 => "h2",
-            ElementKind::A => "a",
-        };
-        write!(f, "{}", s)
-    }
-}
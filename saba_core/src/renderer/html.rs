@@ -1,3 +1,5 @@
 pub mod attribute;
+pub mod character_reference;
+pub mod encoding_sniffing;
 pub mod parser;
 pub mod token;
@@ -0,0 +1,347 @@
+//! HTMLドキュメントの文字エンコーディングの判定
+//! https://html.spec.whatwg.org/multipage/parsing.html#encoding-sniffing-algorithm
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::encoding::Encoding;
+use crate::mime::MimeType;
+
+/// プレスキャンで調べるバイト数
+pub const PRESCAN_LIMIT: usize = 1024;
+
+fn is_whitespace(b: u8) -> bool {
+    matches!(b, 0x09 | 0x0A | 0x0C | 0x0D | 0x20)
+}
+
+/// ドキュメントの文字エンコーディングを、BOM、Content-Typeヘッダのcharsetパラメータ、
+/// <meta>要素のプレスキャンの順に判定します
+/// いずれでも判定できない場合は、UTF-8として正しいバイト列であればUTF-8、そうでなければShift_JISとします
+/// https://html.spec.whatwg.org/multipage/parsing.html#encoding-sniffing-algorithm
+pub fn determine_encoding(bytes: &[u8], content_type: Option<&str>) -> Encoding {
+    if let Some(encoding) = Encoding::sniff_bom(bytes) {
+        return encoding;
+    }
+
+    if let Some(encoding) = content_type
+        .and_then(MimeType::parse)
+        .and_then(|mime_type| mime_type.parameter("charset"))
+        .and_then(|charset| Encoding::for_label(&charset))
+    {
+        return encoding;
+    }
+
+    if let Some(encoding) = prescan(&bytes[..bytes.len().min(PRESCAN_LIMIT)]) {
+        return encoding;
+    }
+
+    // 日本語のロケールでは既定のエンコーディングとしてShift_JISが推奨されている
+    // https://html.spec.whatwg.org/multipage/parsing.html#determining-the-character-encoding
+    // 受信途中のバイト列では、末尾で途切れた文字はUTF-8として不正とはみなさない
+    match core::str::from_utf8(bytes) {
+        Ok(_) => Encoding::Utf8,
+        Err(e) if e.error_len().is_none() => Encoding::Utf8,
+        Err(_) => Encoding::ShiftJis,
+    }
+}
+
+/// 先頭のバイト列から<meta charset>や<meta http-equiv="Content-Type">を探してエンコーディングを判定します
+/// https://html.spec.whatwg.org/multipage/parsing.html#prescan-a-byte-stream-to-determine-its-encoding
+pub fn prescan(bytes: &[u8]) -> Option<Encoding> {
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        let rest = &bytes[pos..];
+
+        if rest.starts_with(b"<!--") {
+            // "<!-->"のように開始と終了のハイフンが重なっていてもよい
+            match find(&bytes[pos + 2..], b"-->") {
+                Some(end) => pos += 2 + end + 3,
+                None => return None,
+            }
+            continue;
+        }
+
+        if starts_with_ignore_case(rest, b"<meta")
+            && rest.get(5).is_some_and(|&b| is_whitespace(b) || b == b'/')
+        {
+            pos += 6;
+            match prescan_meta(bytes, &mut pos) {
+                Ok(Some(encoding)) => return Some(encoding),
+                Ok(None) => continue,
+                Err(()) => return None,
+            }
+        }
+
+        let is_tag = match rest {
+            [b'<', b, ..] if b.is_ascii_alphabetic() => Some(1),
+            [b'<', b'/', b, ..] if b.is_ascii_alphabetic() => Some(2),
+            _ => None,
+        };
+        if let Some(len) = is_tag {
+            // タグ名を読み飛ばし、属性もすべて読み飛ばす
+            pos += len;
+            while pos < bytes.len() && !is_whitespace(bytes[pos]) && bytes[pos] != b'>' {
+                pos += 1;
+            }
+            loop {
+                match get_attribute(bytes, &mut pos) {
+                    Ok(Some(_)) => continue,
+                    Ok(None) => break,
+                    Err(()) => return None,
+                }
+            }
+            pos += 1;
+            continue;
+        }
+
+        if rest.starts_with(b"<!") || rest.starts_with(b"</") || rest.starts_with(b"<?") {
+            match find(rest, b">") {
+                Some(end) => pos += end + 1,
+                None => return None,
+            }
+            continue;
+        }
+
+        pos += 1;
+    }
+
+    None
+}
+
+/// <meta要素の属性を調べてエンコーディングを返します
+/// 入力の終わりに達した場合はErrを返します
+fn prescan_meta(bytes: &[u8], pos: &mut usize) -> Result<Option<Encoding>, ()> {
+    let mut attribute_names: Vec<String> = Vec::new();
+    let mut got_pragma = false;
+    let mut need_pragma = None;
+    let mut charset = None;
+
+    while let Some((name, value)) = get_attribute(bytes, pos)? {
+        if attribute_names.contains(&name) {
+            continue;
+        }
+
+        match name.as_str() {
+            "http-equiv" => {
+                if value == "content-type" {
+                    got_pragma = true;
+                }
+            }
+            "content" => {
+                if charset.is_none() {
+                    if let Some(encoding) = extract_encoding_from_meta(&value) {
+                        charset = Some(encoding);
+                        need_pragma = Some(true);
+                    }
+                }
+            }
+            "charset" => {
+                charset = Encoding::for_label(&value);
+                need_pragma = Some(false);
+            }
+            _ => {}
+        }
+        attribute_names.push(name);
+    }
+    // '>'を読み飛ばす
+    *pos += 1;
+
+    match (need_pragma, charset) {
+        (None, _) | (_, None) => Ok(None),
+        (Some(true), Some(_)) if !got_pragma => Ok(None),
+        // UTF-16は<meta>で指定できないため、UTF-8とみなす
+        (_, Some(Encoding::Utf16Be | Encoding::Utf16Le)) => Ok(Some(Encoding::Utf8)),
+        (_, Some(encoding)) => Ok(Some(encoding)),
+    }
+}
+
+/// 属性を1つ読み取り、(小文字にした名前, 小文字にした値)を返します
+/// タグの終わりに達した場合はOk(None)を、入力の終わりに達した場合はErrを返します
+/// https://html.spec.whatwg.org/multipage/parsing.html#concept-get-attributes-when-sniffing
+fn get_attribute(bytes: &[u8], pos: &mut usize) -> Result<Option<(String, String)>, ()> {
+    let byte_at = |pos: usize| bytes.get(pos).copied().ok_or(());
+
+    while is_whitespace(byte_at(*pos)?) || byte_at(*pos)? == b'/' {
+        *pos += 1;
+    }
+    if byte_at(*pos)? == b'>' {
+        return Ok(None);
+    }
+
+    let mut name = String::new();
+    let mut value = String::new();
+
+    loop {
+        let b = byte_at(*pos)?;
+        match b {
+            b'=' if !name.is_empty() => break,
+            b if is_whitespace(b) => {
+                while is_whitespace(byte_at(*pos)?) {
+                    *pos += 1;
+                }
+                if byte_at(*pos)? != b'=' {
+                    return Ok(Some((name, value)));
+                }
+                break;
+            }
+            b'/' | b'>' => return Ok(Some((name, value))),
+            _ => name.push(b.to_ascii_lowercase() as char),
+        }
+        *pos += 1;
+    }
+
+    // '='を読み飛ばす
+    *pos += 1;
+    while is_whitespace(byte_at(*pos)?) {
+        *pos += 1;
+    }
+
+    let b = byte_at(*pos)?;
+    if b == b'"' || b == b'\'' {
+        loop {
+            *pos += 1;
+            let c = byte_at(*pos)?;
+            if c == b {
+                *pos += 1;
+                return Ok(Some((name, value)));
+            }
+            value.push(c.to_ascii_lowercase() as char);
+        }
+    }
+    if b == b'>' {
+        return Ok(Some((name, value)));
+    }
+
+    loop {
+        let c = byte_at(*pos)?;
+        if is_whitespace(c) || c == b'>' {
+            return Ok(Some((name, value)));
+        }
+        value.push(c.to_ascii_lowercase() as char);
+        *pos += 1;
+    }
+}
+
+/// content属性の値（"text/html; charset=Shift_JIS"など）からエンコーディングを取り出します
+/// https://html.spec.whatwg.org/multipage/urls-and-fetching.html#algorithm-for-extracting-a-character-encoding-from-a-meta-element
+fn extract_encoding_from_meta(content: &str) -> Option<Encoding> {
+    let bytes = content.as_bytes();
+    let mut pos = 0;
+
+    loop {
+        pos += find_ignore_case(&bytes[pos..], b"charset")? + "charset".len();
+        while bytes.get(pos).is_some_and(|&b| is_whitespace(b)) {
+            pos += 1;
+        }
+        if bytes.get(pos) == Some(&b'=') {
+            break;
+        }
+    }
+
+    pos += 1;
+    while bytes.get(pos).is_some_and(|&b| is_whitespace(b)) {
+        pos += 1;
+    }
+
+    let rest = &content[pos..];
+    let value = match rest.as_bytes().first()? {
+        quote @ (b'"' | b'\'') => {
+            let end = rest[1..].find(*quote as char)?;
+            &rest[1..end + 1]
+        }
+        _ => {
+            let end = rest
+                .find(|c: char| c == ';' || (c.is_ascii() && is_whitespace(c as u8)))
+                .unwrap_or(rest.len());
+            &rest[..end]
+        }
+    };
+    Encoding::for_label(value)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn find_ignore_case(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle))
+}
+
+fn starts_with_ignore_case(bytes: &[u8], prefix: &[u8]) -> bool {
+    bytes.len() >= prefix.len() && bytes[..prefix.len()].eq_ignore_ascii_case(prefix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_meta_charset() {
+        assert_eq!(
+            Some(Encoding::ShiftJis),
+            prescan(b"<!DOCTYPE html><html><head><META CharSet=\"shift_jis\">")
+        );
+        assert_eq!(
+            Some(Encoding::EucJp),
+            prescan(b"<head><meta name=x charset=euc-jp>")
+        );
+    }
+
+    #[test]
+    fn test_meta_http_equiv() {
+        assert_eq!(
+            Some(Encoding::ShiftJis),
+            prescan(b"<meta content='text/html; charset=\"Shift_JIS\"' http-equiv=Content-Type>")
+        );
+        // http-equivがない場合はcontent属性を使わない
+        assert_eq!(
+            None,
+            prescan(b"<meta content=\"text/html; charset=Shift_JIS\"><p>")
+        );
+    }
+
+    #[test]
+    fn test_skip_comments_and_attributes() {
+        assert_eq!(
+            Some(Encoding::EucJp),
+            prescan(b"<!-- <meta charset=shift_jis> --><p title='<meta charset=utf-8>'><meta charset=euc-jp>")
+        );
+    }
+
+    #[test]
+    fn test_utf16_in_meta() {
+        assert_eq!(Some(Encoding::Utf8), prescan(b"<meta charset=utf-16>"));
+    }
+
+    #[test]
+    fn test_determine_encoding() {
+        let html = b"<meta charset=euc-jp><p>\x93\xfa</p>";
+        assert_eq!(
+            Encoding::Utf16Le,
+            determine_encoding(b"\xFF\xFE<\x00", Some("text/html; charset=shift_jis"))
+        );
+        assert_eq!(
+            Encoding::ShiftJis,
+            determine_encoding(html, Some("text/html; charset=shift_jis"))
+        );
+        assert_eq!(Encoding::EucJp, determine_encoding(html, Some("text/html")));
+        assert_eq!(
+            Encoding::ShiftJis,
+            determine_encoding(b"<p>\x93\xfa</p>", None)
+        );
+        assert_eq!(
+            Encoding::Utf8,
+            determine_encoding("<p>日本語</p>".as_bytes(), None)
+        );
+        // 受信途中で文字が途切れていてもUTF-8と判定する
+        assert_eq!(
+            Encoding::Utf8,
+            determine_encoding(&"<p>日本語".as_bytes()[..8], None)
+        );
+    }
+}
@@ -1,10 +1,19 @@
-use core::{cell::RefCell, str::FromStr};
+use core::{cell::RefCell, mem};
 
-use alloc::{rc::Rc, string::String, vec::Vec};
+use alloc::{
+    rc::Rc,
+    string::{String, ToString},
+    vec::Vec,
+};
 
 use crate::renderer::{
-    dom::node::{Element, ElementKind, Node, NodeKind, Window},
-    html::token::HtmlToken,
+    dom::{
+        api::{append_child, insert_before, remove_from_parent},
+        node::{
+            DocumentMode, DocumentType, Element, ElementKind, Namespace, Node, NodeKind, Window,
+        },
+    },
+    html::token::{HtmlToken, State},
 };
 
 use super::{attribute::Attribute, token::HtmlTokenizer};
@@ -12,6 +21,330 @@ use super::{attribute::Attribute, token::HtmlTokenizer};
 const SPACE: char = ' ';
 const LINE_FEED: char = '\n';
 
+/// 公開識別子がこれらのいずれかで始まるDOCTYPEの文書はquirksモードで扱う
+/// https://html.spec.whatwg.org/multipage/parsing.html#the-initial-insertion-mode
+const QUIRKS_PUBLIC_ID_PREFIXES: &[&str] = &[
+    "+//Silmaril//dtd html Pro v0r11 19970101//",
+    "-//AS//DTD HTML 3.0 asWedit + extensions//",
+    "-//AdvaSoft Ltd//DTD HTML 3.0 asWedit + extensions//",
+    "-//IETF//DTD HTML 2.0 Level 1//",
+    "-//IETF//DTD HTML 2.0 Level 2//",
+    "-//IETF//DTD HTML 2.0 Strict Level 1//",
+    "-//IETF//DTD HTML 2.0 Strict Level 2//",
+    "-//IETF//DTD HTML 2.0 Strict//",
+    "-//IETF//DTD HTML 2.0//",
+    "-//IETF//DTD HTML 2.1E//",
+    "-//IETF//DTD HTML 3.0//",
+    "-//IETF//DTD HTML 3.2 Final//",
+    "-//IETF//DTD HTML 3.2//",
+    "-//IETF//DTD HTML 3//",
+    "-//IETF//DTD HTML Level 0//",
+    "-//IETF//DTD HTML Level 1//",
+    "-//IETF//DTD HTML Level 2//",
+    "-//IETF//DTD HTML Level 3//",
+    "-//IETF//DTD HTML Strict Level 0//",
+    "-//IETF//DTD HTML Strict Level 1//",
+    "-//IETF//DTD HTML Strict Level 2//",
+    "-//IETF//DTD HTML Strict Level 3//",
+    "-//IETF//DTD HTML Strict//",
+    "-//IETF//DTD HTML//",
+    "-//Metrius//DTD Metrius Presentational//",
+    "-//Microsoft//DTD Internet Explorer 2.0 HTML Strict//",
+    "-//Microsoft//DTD Internet Explorer 2.0 HTML//",
+    "-//Microsoft//DTD Internet Explorer 2.0 Tables//",
+    "-//Microsoft//DTD Internet Explorer 3.0 HTML Strict//",
+    "-//Microsoft//DTD Internet Explorer 3.0 HTML//",
+    "-//Microsoft//DTD Internet Explorer 3.0 Tables//",
+    "-//Netscape Comm. Corp.//DTD HTML//",
+    "-//Netscape Comm. Corp.//DTD Strict HTML//",
+    "-//O'Reilly and Associates//DTD HTML 2.0//",
+    "-//O'Reilly and Associates//DTD HTML Extended 1.0//",
+    "-//O'Reilly and Associates//DTD HTML Extended Relaxed 1.0//",
+    "-//SQ//DTD HTML 2.0 HoTMetaL + extensions//",
+    "-//SoftQuad Software//DTD HoTMetaL PRO 6.0::19990601::extensions to HTML 4.0//",
+    "-//SoftQuad//DTD HoTMetaL PRO 4.0::19971010::extensions to HTML 4.0//",
+    "-//Spyglass//DTD HTML 2.0 Extended//",
+    "-//Sun Microsystems Corp.//DTD HotJava HTML//",
+    "-//Sun Microsystems Corp.//DTD HotJava Strict HTML//",
+    "-//W3C//DTD HTML 3 1995-03-24//",
+    "-//W3C//DTD HTML 3.2 Draft//",
+    "-//W3C//DTD HTML 3.2 Final//",
+    "-//W3C//DTD HTML 3.2//",
+    "-//W3C//DTD HTML 3.2S Draft//",
+    "-//W3C//DTD HTML 4.0 Frameset//",
+    "-//W3C//DTD HTML 4.0 Transitional//",
+    "-//W3C//DTD HTML Experimental 19960712//",
+    "-//W3C//DTD HTML Experimental 970421//",
+    "-//W3C//DTD W3 HTML//",
+    "-//W3O//DTD W3 HTML 3.0//",
+    "-//WebTechs//DTD Mozilla HTML 2.0//",
+    "-//WebTechs//DTD Mozilla HTML//",
+];
+
+/// 公開識別子がこれらと一致するDOCTYPEの文書はquirksモードで扱う
+const QUIRKS_PUBLIC_IDS: &[&str] = &[
+    "-//W3O//DTD W3 HTML Strict 3.0//EN//",
+    "-/W3C/DTD HTML 4.0 Transitional/EN",
+    "HTML",
+];
+
+/// システム識別子があるかどうかで、quirksモードとlimited-quirksモードのどちらになるかが変わる公開識別子
+const HTML401_PUBLIC_ID_PREFIXES: &[&str] = &[
+    "-//W3C//DTD HTML 4.01 Frameset//",
+    "-//W3C//DTD HTML 4.01 Transitional//",
+];
+
+/// 公開識別子がこれらのいずれかで始まるDOCTYPEの文書はlimited-quirksモードで扱う
+const LIMITED_QUIRKS_PUBLIC_ID_PREFIXES: &[&str] = &[
+    "-//W3C//DTD XHTML 1.0 Frameset//",
+    "-//W3C//DTD XHTML 1.0 Transitional//",
+];
+
+fn starts_with_ignore_ascii_case(s: &str, prefix: &str) -> bool {
+    s.len() >= prefix.len() && s.as_bytes()[..prefix.len()].eq_ignore_ascii_case(prefix.as_bytes())
+}
+
+/// DOCTYPEトークンからDocumentのモードを決めます
+/// https://html.spec.whatwg.org/multipage/parsing.html#the-initial-insertion-mode
+fn document_mode(
+    name: &Option<String>,
+    public_id: &Option<String>,
+    system_id: &Option<String>,
+    force_quirks: bool,
+) -> DocumentMode {
+    let public = public_id.as_deref().unwrap_or("");
+    let system = system_id.as_deref().unwrap_or("");
+    let has_prefix = |prefixes: &[&str]| {
+        prefixes
+            .iter()
+            .any(|prefix| starts_with_ignore_ascii_case(public, prefix))
+    };
+
+    if force_quirks
+        || name.as_deref() != Some("html")
+        || QUIRKS_PUBLIC_IDS
+            .iter()
+            .any(|id| public.eq_ignore_ascii_case(id))
+        || system.eq_ignore_ascii_case("http://www.ibm.com/data/dtd/v11/ibmxhtml1-transitional.dtd")
+        || has_prefix(QUIRKS_PUBLIC_ID_PREFIXES)
+        || (system_id.is_none() && has_prefix(HTML401_PUBLIC_ID_PREFIXES))
+    {
+        return DocumentMode::Quirks;
+    }
+
+    if has_prefix(LIMITED_QUIRKS_PUBLIC_ID_PREFIXES)
+        || (system_id.is_some() && has_prefix(HTML401_PUBLIC_ID_PREFIXES))
+    {
+        return DocumentMode::LimitedQuirks;
+    }
+
+    DocumentMode::NoQuirks
+}
+
+/// 開始タグや終了タグの処理で、他の要素と区別して扱うHTMLの名前空間の要素
+/// https://html.spec.whatwg.org/multipage/parsing.html#special
+const SPECIAL_ELEMENTS: &[&str] = &[
+    "address",
+    "applet",
+    "area",
+    "article",
+    "aside",
+    "base",
+    "basefont",
+    "bgsound",
+    "blockquote",
+    "body",
+    "br",
+    "button",
+    "caption",
+    "center",
+    "col",
+    "colgroup",
+    "dd",
+    "details",
+    "dir",
+    "div",
+    "dl",
+    "dt",
+    "embed",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "frame",
+    "frameset",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "head",
+    "header",
+    "hgroup",
+    "hr",
+    "html",
+    "iframe",
+    "img",
+    "input",
+    "keygen",
+    "li",
+    "link",
+    "listing",
+    "main",
+    "marquee",
+    "menu",
+    "meta",
+    "nav",
+    "noembed",
+    "noframes",
+    "noscript",
+    "object",
+    "ol",
+    "p",
+    "param",
+    "plaintext",
+    "pre",
+    "script",
+    "search",
+    "section",
+    "select",
+    "source",
+    "style",
+    "summary",
+    "table",
+    "tbody",
+    "td",
+    "template",
+    "textarea",
+    "tfoot",
+    "th",
+    "thead",
+    "title",
+    "tr",
+    "track",
+    "ul",
+    "wbr",
+    "xmp",
+];
+
+/// 終了タグがなくても、後に続くタグによって閉じられる要素
+/// https://html.spec.whatwg.org/multipage/parsing.html#generate-implied-end-tags
+const IMPLIED_END_TAG_ELEMENTS: &[&str] = &[
+    "dd", "dt", "li", "optgroup", "option", "p", "rb", "rp", "rt", "rtc",
+];
+
+const HEADING_ELEMENTS: &[&str] = &["h1", "h2", "h3", "h4", "h5", "h6"];
+
+/// 要素が範囲内にあるかを調べるときに、どの要素で探索を打ち切るか
+/// https://html.spec.whatwg.org/multipage/parsing.html#has-an-element-in-the-specific-scope
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Scope {
+    Default,
+    ListItem,
+    Button,
+    Table,
+}
+
+impl Scope {
+    fn is_boundary(&self, element: &Element) -> bool {
+        let local_name = element.local_name();
+        let local_name = local_name.as_str();
+        match element.namespace() {
+            Namespace::Html => match self {
+                Scope::Table => matches!(local_name, "html" | "table" | "template"),
+                _ => {
+                    matches!(
+                        local_name,
+                        "applet"
+                            | "caption"
+                            | "html"
+                            | "table"
+                            | "td"
+                            | "th"
+                            | "marquee"
+                            | "object"
+                            | "template"
+                    ) || (*self == Scope::ListItem && matches!(local_name, "ol" | "ul"))
+                        || (*self == Scope::Button && local_name == "button")
+                }
+            },
+            Namespace::MathMl => {
+                *self != Scope::Table
+                    && matches!(
+                        local_name,
+                        "mi" | "mo" | "mn" | "ms" | "mtext" | "annotation-xml"
+                    )
+            }
+            Namespace::Svg => {
+                *self != Scope::Table && matches!(local_name, "foreignObject" | "desc" | "title")
+            }
+        }
+    }
+}
+
+/// https://html.spec.whatwg.org/multipage/parsing.html#list-of-active-formatting-elements
+#[derive(Debug, Clone)]
+enum ActiveFormattingElement {
+    /// <applet>や<object>などに入ったことを表す印。印より前の書式要素は、その中で作り直さない
+    Marker,
+    Element(Rc<RefCell<Node>>),
+}
+
+/// ノードの要素を返します。要素でないときは空の<span>として扱います
+fn element_of(node: &Rc<RefCell<Node>>) -> Element {
+    node.borrow()
+        .get_element()
+        .unwrap_or_else(|| Element::new("span", Vec::new()))
+}
+
+fn local_name_of(node: &Rc<RefCell<Node>>) -> String {
+    node.borrow()
+        .get_element()
+        .map(|element| element.local_name())
+        .unwrap_or_default()
+}
+
+/// ノードが、いずれかの名前を持つHTMLの名前空間の要素かどうかを返します
+fn is_html_element_in(node: &Rc<RefCell<Node>>, local_names: &[&str]) -> bool {
+    node.borrow().get_element().is_some_and(|element| {
+        element.namespace() == Namespace::Html
+            && local_names.contains(&element.local_name().as_str())
+    })
+}
+
+/// https://html.spec.whatwg.org/multipage/parsing.html#special
+fn is_special(node: &Rc<RefCell<Node>>) -> bool {
+    let element = match node.borrow().get_element() {
+        Some(element) => element,
+        None => return false,
+    };
+    let local_name = element.local_name();
+    match element.namespace() {
+        Namespace::Html => SPECIAL_ELEMENTS.contains(&local_name.as_str()),
+        Namespace::MathMl => matches!(
+            local_name.as_str(),
+            "mi" | "mo" | "mn" | "ms" | "mtext" | "annotation-xml"
+        ),
+        Namespace::Svg => matches!(local_name.as_str(), "foreignObject" | "desc" | "title"),
+    }
+}
+
+/// 2つの要素が同じ名前と、順序を問わず同じ属性を持つかどうかを返します
+fn is_same_element(a: &Rc<RefCell<Node>>, b: &Rc<RefCell<Node>>) -> bool {
+    match (a.borrow().get_element(), b.borrow().get_element()) {
+        (Some(a), Some(b)) => {
+            let attributes = b.attributes();
+            a.local_name() == b.local_name()
+                && a.namespace() == b.namespace()
+                && a.attributes().len() == attributes.len()
+                && a.attributes().iter().all(|attr| attributes.contains(attr))
+        }
+        _ => false,
+    }
+}
+
 /// https://html.spec.whatwg.org/multipage/parsing.html#the-insertion-mode
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum InsertionMode {
@@ -19,13 +352,46 @@ pub enum InsertionMode {
     BeforeHtml,
     BeforeHead,
     InHead,
+    InHeadNoscript,
     AfterHead,
     InBody,
     Text,
+    InTable,
+    InTableText,
+    InCaption,
+    InColumnGroup,
+    InTableBody,
+    InRow,
+    InCell,
     AfterBody,
     AfterAfterBody,
 }
 
+/// 仕様に従っていない入力が見つかった位置
+/// ツリー構築は止めずに、仕様の定める方法で回復して続ける
+/// https://html.spec.whatwg.org/multipage/parsing.html#parse-errors
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    position: usize,
+    mode: InsertionMode,
+}
+
+impl ParseError {
+    pub fn new(position: usize, mode: InsertionMode) -> Self {
+        Self { position, mode }
+    }
+
+    /// エラーとなったトークンが始まる、入力の先頭からの文字数
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// エラーが見つかったときの挿入モード
+    pub fn mode(&self) -> InsertionMode {
+        self.mode
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct HtmlParser {
     window: Rc<RefCell<Window>>,
@@ -37,6 +403,30 @@ pub struct HtmlParser {
     // 開いているタグのスタック
     stack_of_open_elements: Vec<Rc<RefCell<Node>>>,
     t: HtmlTokenizer,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#scripting-flag
+    scripting: bool,
+    /// 次の文字トークンが改行なら無視するかどうか
+    ignore_line_feed: bool,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#list-of-active-formatting-elements
+    // 閉じられていない<b>や<a>などの書式要素のリスト
+    active_formatting_elements: Vec<ActiveFormattingElement>,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#head-element-pointer
+    head_element: Option<Rc<RefCell<Node>>>,
+    /// 閉じられていない<form>要素。<form>の中の<form>を無視するために使います
+    /// https://html.spec.whatwg.org/multipage/parsing.html#form-element-pointer
+    form_element: Option<Rc<RefCell<Node>>>,
+    /// 表の中に置けないノードを、表の直前に追加するかどうか
+    /// https://html.spec.whatwg.org/multipage/parsing.html#foster-parent
+    foster_parenting: bool,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#concept-pending-table-char-tokens
+    pending_table_characters: Vec<char>,
+    /// 入力の終わりまでツリー構築を終えたかどうか
+    /// https://html.spec.whatwg.org/multipage/parsing.html#stop-parsing
+    stopped: bool,
+    parse_errors: Vec<ParseError>,
+    /// 断片を解析するときに、その断片を子供として持つ要素
+    /// https://html.spec.whatwg.org/multipage/parsing.html#concept-frag-parse-context
+    context_element: Option<Rc<RefCell<Node>>>,
 }
 
 impl HtmlParser {
@@ -47,36 +437,77 @@ impl HtmlParser {
             original_insertion_mode: InsertionMode::Initial,
             stack_of_open_elements: Vec::new(),
             t,
+            scripting: false,
+            ignore_line_feed: false,
+            active_formatting_elements: Vec::new(),
+            head_element: None,
+            form_element: None,
+            foster_parenting: false,
+            pending_table_characters: Vec::new(),
+            stopped: false,
+            parse_errors: Vec::new(),
+            context_element: None,
         }
     }
 
-    fn contains_in_stack(&mut self, element_kind: ElementKind) -> bool {
-        for i in 0..self.stack_of_open_elements.len() {
-            if self.stack_of_open_elements[i].borrow().element_kind() == Some(element_kind) {
-                return true;
+    /// 文脈の要素の子供としてHTMLの断片を解析し、作られた子ノードの一覧を返します
+    /// innerHTMLのように、要素の中身だけを解析するときに使います
+    /// https://html.spec.whatwg.org/multipage/parsing.html#parsing-html-fragments
+    pub fn parse_fragment(
+        context_element: &Rc<RefCell<Node>>,
+        markup: &str,
+    ) -> Vec<Rc<RefCell<Node>>> {
+        let mut parser = Self::new(HtmlTokenizer::new(markup.to_string()));
+        parser.context_element = Some(context_element.clone());
+
+        // 文脈の要素の中身を読むときと同じ状態からトークナイザを始める
+        let is_html_element = context_element
+            .borrow()
+            .get_element()
+            .is_some_and(|element| element.namespace() == Namespace::Html);
+        if is_html_element {
+            match local_name_of(context_element).as_str() {
+                "title" | "textarea" => parser.t.switch_to(State::Rcdata),
+                "style" | "xmp" | "iframe" | "noembed" | "noframes" => {
+                    parser.t.switch_to(State::Rawtext)
+                }
+                "script" => parser.t.switch_to(State::ScriptData),
+                "plaintext" => parser.t.switch_to(State::Plaintext),
+                _ => {}
             }
         }
 
-        false
-    }
-
-    fn pop_until(&mut self, element_kind: ElementKind) {
-        assert!(
-            self.contains_in_stack(element_kind),
-            "stack doesn't have an element {:?}",
-            element_kind,
-        );
-
-        loop {
-            let current = match self.stack_of_open_elements.pop() {
-                Some(node) => node,
-                None => return,
-            };
-
-            if current.borrow().element_kind() == Some(element_kind) {
-                return;
+        // 断片のノードは、文書に追加した<html>要素の子供として作る
+        let root = Rc::new(RefCell::new(parser.create_element("html", Vec::new())));
+        append_child(&parser.window.borrow().document(), root.clone());
+        parser.stack_of_open_elements.push(root.clone());
+        parser.reset_insertion_mode();
+        // 文脈の要素かその祖先の<form>を、開いている<form>とする
+        let mut node = Some(context_element.clone());
+        while let Some(current) = node {
+            if is_html_element_in(&current, &["form"]) {
+                parser.form_element = Some(current);
+                break;
             }
+            node = current.borrow().parent().upgrade();
+        }
+        parser.construct_tree();
+
+        let mut children = Vec::new();
+        let mut child = root.borrow().first_child();
+        while let Some(node) = child {
+            child = node.borrow().next_sibling();
+            remove_from_parent(&node);
+            children.push(node);
         }
+        children
+    }
+
+    /// スクリプトが有効な文書として解析するかどうかを指定します
+    /// 有効なときは<noscript>の中身をテキストとして読みます
+    pub fn with_scripting(mut self, scripting: bool) -> Self {
+        self.scripting = scripting;
+        self
     }
 
     fn pop_current_node(&mut self, element_kind: ElementKind) -> bool {
@@ -93,52 +524,520 @@ impl HtmlParser {
         false
     }
 
-    /// 親ノードの持つ子供の最後尾に新しいノードを追加します
-    fn insert_node(&mut self, parent: Rc<RefCell<Node>>, new_node: Node) {
-        // if HtmlParser::has_child(&current) {
-        //     // last_childと等価?
-        //     let mut last_sibling = current.borrow().first_child();
-        //     loop {
-        //         last_sibling = match last_sibling {
-        //             Some(ref node) => {
-        //                 if node.borrow().next_sibling().is_some() {
-        //                     node.borrow().next_sibling()
-        //                 } else {
-        //                     break;
-        //                 }
-        //             }
-        //             None => unimplemented!("last_sibiling shoud be Some"),
-        //         }
-        //     }
-        //     let last_sibling = current.borrow_mut().last_child();
-        //     last_sibling
-        //         .upgrade()
-        //         .unwrap()
-        //         .borrow_mut()
-        //         .set_next_sibling(Some(new_node.clone()));
-        //     new_node.borrow_mut().set_previous_sibling(Rc::downgrade(
-        //         &last_sibling.upgrade().expect("last_sibling should be Some"),
-        //     ))
-        // } else {
-        //     current.borrow_mut().set_first_child(Some(new_node.clone()));
-        // }
+    /// 指定した名前のいずれかを持つ要素が取り除かれるまで、スタックから要素を取り除きます
+    fn pop_until_one_of(&mut self, local_names: &[&str]) {
+        while let Some(node) = self.stack_of_open_elements.pop() {
+            if is_html_element_in(&node, local_names) {
+                return;
+            }
+        }
+    }
 
-        let new_node = Rc::new(RefCell::new(new_node));
-        let mut current_node = parent.borrow_mut();
-        match current_node.last_child().upgrade() {
-            Some(last_child) => {
-                last_child
-                    .borrow_mut()
-                    .set_next_sibling(Some(new_node.clone()));
+    /// スタックを上から順に調べ、範囲の境界となる要素より先に条件に合う要素があるかを返します
+    /// https://html.spec.whatwg.org/multipage/parsing.html#has-an-element-in-the-specific-scope
+    fn has_in_scope(&self, scope: Scope, is_target: impl Fn(&Rc<RefCell<Node>>) -> bool) -> bool {
+        for node in self.stack_of_open_elements.iter().rev() {
+            if is_target(node) {
+                return true;
+            }
+            if node
+                .borrow()
+                .get_element()
+                .is_some_and(|element| scope.is_boundary(&element))
+            {
+                return false;
+            }
+        }
+
+        false
+    }
+
+    /// 指定した名前のいずれかを持つ要素が範囲内にあるかを返します
+    fn has_element_in_scope(&self, local_names: &[&str], scope: Scope) -> bool {
+        self.has_in_scope(scope, |node| is_html_element_in(node, local_names))
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#generate-implied-end-tags
+    fn generate_implied_end_tags(&mut self, except: Option<&str>) {
+        while let Some(node) = self.stack_of_open_elements.last() {
+            if !is_html_element_in(node, IMPLIED_END_TAG_ELEMENTS)
+                || except.is_some_and(|local_name| is_html_element_in(node, &[local_name]))
+            {
+                return;
+            }
+            self.stack_of_open_elements.pop();
+        }
+    }
+
+    /// 範囲内にある、指定した名前のいずれかを持つ要素までをスタックから取り除きます
+    /// 範囲内に要素がないときや、終了タグのない要素も一緒に閉じたときはfalseを返します
+    fn close_element_in_scope(
+        &mut self,
+        local_names: &[&str],
+        scope: Scope,
+        except: Option<&str>,
+    ) -> bool {
+        if !self.has_element_in_scope(local_names, scope) {
+            return false;
+        }
+
+        self.generate_implied_end_tags(except);
+        let is_current = self
+            .stack_of_open_elements
+            .last()
+            .is_some_and(|node| is_html_element_in(node, local_names));
+        self.pop_until_one_of(local_names);
+        is_current
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#close-a-p-element
+    fn close_p_element(&mut self) {
+        if !self.close_element_in_scope(&["p"], Scope::Button, Some("p")) {
+            self.parse_error();
+        }
+    }
+
+    /// ブロックの開始タグの前に、開いている<p>があれば閉じます
+    fn close_p_element_in_button_scope(&mut self) {
+        if self.has_element_in_scope(&["p"], Scope::Button) {
+            self.close_p_element();
+        }
+    }
+
+    /// <li>や<dd>、<dt>の開始タグの前に、閉じられていない同じ種類の要素を閉じます
+    fn close_list_item(&mut self, local_names: &[&str]) {
+        for node in self.stack_of_open_elements.clone().iter().rev() {
+            if is_html_element_in(node, local_names) {
+                let local_name = local_name_of(node);
+                self.generate_implied_end_tags(Some(&local_name));
+                self.pop_until_one_of(&[&local_name]);
+                return;
+            }
+            if is_special(node) && !is_html_element_in(node, &["address", "div", "p"]) {
+                return;
+            }
+        }
+    }
+
+    /// <body>の中の規則で、個別の規則のない終了タグを処理します
+    /// 同じ名前の要素より先に特別な要素が見つかったときは、終了タグを無視します
+    fn close_element_for_end_tag(&mut self, tag: &str) {
+        for i in (0..self.stack_of_open_elements.len()).rev() {
+            let node = self.stack_of_open_elements[i].clone();
+            if is_html_element_in(&node, &[tag]) {
+                self.generate_implied_end_tags(Some(tag));
+                if self.stack_of_open_elements.len() != i + 1 {
+                    self.parse_error();
+                }
+                self.stack_of_open_elements.truncate(i);
+                return;
+            }
+            if is_special(&node) {
+                self.parse_error();
+                return;
+            }
+        }
+    }
+
+    /// 読んでいるトークンを、仕様に従っていない入力として記録します
+    fn parse_error(&mut self) {
+        self.parse_errors
+            .push(ParseError::new(self.t.token_position(), self.mode));
+    }
+
+    /// 現在のノードがいずれかの名前を持つ要素になるまで、スタックから要素を取り除きます
+    /// https://html.spec.whatwg.org/multipage/parsing.html#clear-the-stack-back-to-a-table-context
+    fn clear_stack_back_to(&mut self, local_names: &[&str]) {
+        while let Some(node) = self.stack_of_open_elements.last() {
+            if is_html_element_in(node, local_names) {
+                return;
+            }
+            self.stack_of_open_elements.pop();
+        }
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#close-the-cell
+    fn close_cell(&mut self) {
+        self.generate_implied_end_tags(None);
+        if !self
+            .stack_of_open_elements
+            .last()
+            .is_some_and(|node| is_html_element_in(node, &["td", "th"]))
+        {
+            self.parse_error();
+        }
+        self.pop_until_one_of(&["td", "th"]);
+        self.clear_active_formatting_elements_up_to_last_marker();
+        self.mode = InsertionMode::InRow;
+    }
+
+    /// スタックに残っている要素から、挿入モードを決め直します
+    /// https://html.spec.whatwg.org/multipage/parsing.html#reset-the-insertion-mode-appropriately
+    fn reset_insertion_mode(&mut self) {
+        for (i, node) in self.stack_of_open_elements.iter().enumerate().rev() {
+            let last = i == 0;
+            // 断片を解析するときは、スタックの最初の要素の代わりに文脈の要素を見る
+            let node = match self.context_element {
+                Some(ref context) if last => context,
+                _ => node,
+            };
+            let local_name = local_name_of(node);
+            self.mode = match local_name.as_str() {
+                "td" | "th" if !last => InsertionMode::InCell,
+                "tr" => InsertionMode::InRow,
+                "tbody" | "thead" | "tfoot" => InsertionMode::InTableBody,
+                "caption" => InsertionMode::InCaption,
+                "colgroup" => InsertionMode::InColumnGroup,
+                "table" => InsertionMode::InTable,
+                "head" if !last => InsertionMode::InHead,
+                "body" => InsertionMode::InBody,
+                "html" => match self.head_element {
+                    Some(_) => InsertionMode::AfterHead,
+                    None => InsertionMode::BeforeHead,
+                },
+                _ if last => InsertionMode::InBody,
+                _ => continue,
+            };
+            return;
+        }
+    }
+
+    fn has_template_in_stack(&self) -> bool {
+        self.stack_of_open_elements
+            .iter()
+            .any(|node| is_html_element_in(node, &["template"]))
+    }
+
+    fn is_in_stack(&self, node: &Rc<RefCell<Node>>) -> bool {
+        self.stack_of_open_elements
+            .iter()
+            .any(|open| Rc::ptr_eq(open, node))
+    }
+
+    /// 書式要素のリストの中でのノードの位置を返します
+    fn active_formatting_element_index(&self, node: &Rc<RefCell<Node>>) -> Option<usize> {
+        self.active_formatting_elements.iter().position(
+            |entry| matches!(entry, ActiveFormattingElement::Element(e) if Rc::ptr_eq(e, node)),
+        )
+    }
+
+    /// 最後の印より後にある、指定した名前の書式要素のうち最後のものを返します
+    fn last_active_formatting_element(&self, local_name: &str) -> Option<Rc<RefCell<Node>>> {
+        for entry in self.active_formatting_elements.iter().rev() {
+            match entry {
+                ActiveFormattingElement::Marker => return None,
+                ActiveFormattingElement::Element(node) => {
+                    if is_html_element_in(node, &[local_name]) {
+                        return Some(node.clone());
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#push-onto-the-list-of-active-formatting-elements
+    fn push_active_formatting_element(&mut self, node: Rc<RefCell<Node>>) {
+        // 最後の印より後に同じ名前と属性を持つ要素がすでに3つあれば、最も古いものを取り除く
+        let mut same_elements = Vec::new();
+        for (i, entry) in self.active_formatting_elements.iter().enumerate().rev() {
+            match entry {
+                ActiveFormattingElement::Marker => break,
+                ActiveFormattingElement::Element(other) => {
+                    if is_same_element(&node, other) {
+                        same_elements.push(i);
+                    }
+                }
+            }
+        }
+        if same_elements.len() >= 3 {
+            self.active_formatting_elements
+                .remove(same_elements[same_elements.len() - 1]);
+        }
+
+        self.active_formatting_elements
+            .push(ActiveFormattingElement::Element(node));
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#clear-the-list-of-active-formatting-elements-up-to-the-last-marker
+    fn clear_active_formatting_elements_up_to_last_marker(&mut self) {
+        while let Some(entry) = self.active_formatting_elements.pop() {
+            if let ActiveFormattingElement::Marker = entry {
+                return;
+            }
+        }
+    }
+
+    /// 印であるか、スタックに残っている書式要素かどうか
+    fn is_open_or_marker(&self, entry: &ActiveFormattingElement) -> bool {
+        match entry {
+            ActiveFormattingElement::Marker => true,
+            ActiveFormattingElement::Element(node) => self.is_in_stack(node),
+        }
+    }
+
+    /// 閉じられた書式要素を現在のノードの中に作り直し、後に続く文字や要素にも書式が適用されるようにします
+    /// https://html.spec.whatwg.org/multipage/parsing.html#reconstruct-the-active-formatting-elements
+    fn reconstruct_active_formatting_elements(&mut self) {
+        let mut i = match self.active_formatting_elements.len() {
+            0 => return,
+            len => len - 1,
+        };
+        if self.is_open_or_marker(&self.active_formatting_elements[i]) {
+            return;
+        }
+
+        // 印かスタックに残っている要素の直後まで戻り、そこから最後までを作り直す
+        while i > 0 && !self.is_open_or_marker(&self.active_formatting_elements[i - 1]) {
+            i -= 1;
+        }
+        for j in i..self.active_formatting_elements.len() {
+            let element = match &self.active_formatting_elements[j] {
+                ActiveFormattingElement::Element(node) => node.borrow().get_element(),
+                ActiveFormattingElement::Marker => None,
+            };
+            if let Some(element) = element {
+                let node = self.insert_element_node(Node::new(NodeKind::Element(element)));
+                self.active_formatting_elements[j] = ActiveFormattingElement::Element(node);
+            }
+        }
+    }
+
+    /// 書式要素の終了タグを処理し、入れ子が崩れた書式要素を組み直します
+    /// 対応する書式要素がなく、その他の終了タグとして処理すべきときはfalseを返します
+    /// https://html.spec.whatwg.org/multipage/parsing.html#adoption-agency-algorithm
+    fn run_adoption_agency(&mut self, subject: &str) -> bool {
+        if let Some(current) = self.stack_of_open_elements.last() {
+            if is_html_element_in(current, &[subject])
+                && self.active_formatting_element_index(current).is_none()
+            {
+                self.stack_of_open_elements.pop();
+                return true;
+            }
+        }
+
+        for _ in 0..8 {
+            let formatting_element = match self.last_active_formatting_element(subject) {
+                Some(node) => node,
+                None => return false,
+            };
+            let formatting_index = match self
+                .stack_of_open_elements
+                .iter()
+                .position(|node| Rc::ptr_eq(node, &formatting_element))
+            {
+                Some(i) => i,
+                None => {
+                    self.active_formatting_elements
+                        .retain(|entry| !matches!(entry, ActiveFormattingElement::Element(e) if Rc::ptr_eq(e, &formatting_element)));
+                    return true;
+                }
+            };
+            if !self.has_in_scope(Scope::Default, |node| Rc::ptr_eq(node, &formatting_element)) {
+                return true;
+            }
+
+            // 書式要素の中で開いている、最も外側の特別な要素
+            let furthest_block = match self.stack_of_open_elements[formatting_index + 1..]
+                .iter()
+                .find(|node| is_special(node))
+            {
+                Some(node) => node.clone(),
+                None => {
+                    self.stack_of_open_elements.truncate(formatting_index);
+                    self.active_formatting_elements
+                        .retain(|entry| !matches!(entry, ActiveFormattingElement::Element(e) if Rc::ptr_eq(e, &formatting_element)));
+                    return true;
+                }
+            };
+            let common_ancestor = match formatting_index.checked_sub(1) {
+                Some(i) => self.stack_of_open_elements[i].clone(),
+                None => return true,
+            };
+
+            // 書式要素の新しい要素を、リストのどこに置くか
+            // Noneのときは元の書式要素の位置に、Someのときはそのノードの直後に置く
+            let mut bookmark: Option<Rc<RefCell<Node>>> = None;
+            let mut node_index = self
+                .stack_of_open_elements
+                .iter()
+                .position(|node| Rc::ptr_eq(node, &furthest_block))
+                .unwrap_or(formatting_index + 1);
+            let mut last_node = furthest_block.clone();
+            let mut inner_loop_counter = 0;
+            loop {
+                inner_loop_counter += 1;
+                node_index -= 1;
+                let node = self.stack_of_open_elements[node_index].clone();
+                if Rc::ptr_eq(&node, &formatting_element) {
+                    break;
+                }
+
+                let mut list_index = self.active_formatting_element_index(&node);
+                if inner_loop_counter > 3 {
+                    if let Some(i) = list_index.take() {
+                        self.active_formatting_elements.remove(i);
+                    }
+                }
+                let list_index = match list_index {
+                    Some(i) => i,
+                    None => {
+                        self.stack_of_open_elements.remove(node_index);
+                        continue;
+                    }
+                };
+
+                // 書式要素の中にある書式要素は、同じ名前と属性を持つ要素を作り直して置き換える
+                let new_node = Rc::new(RefCell::new(Node::new(NodeKind::Element(element_of(
+                    &node,
+                )))));
+                self.active_formatting_elements[list_index] =
+                    ActiveFormattingElement::Element(new_node.clone());
+                self.stack_of_open_elements[node_index] = new_node.clone();
+                if Rc::ptr_eq(&last_node, &furthest_block) {
+                    bookmark = Some(new_node.clone());
+                }
+                append_child(&new_node, last_node);
+                last_node = new_node;
+            }
+
+            let (parent, reference) = self.appropriate_place_for_inserting(Some(common_ancestor));
+            insert_before(&parent, last_node, reference.as_ref());
+
+            // 特別な要素の子供を、作り直した書式要素の中に移す
+            let new_node = Rc::new(RefCell::new(Node::new(NodeKind::Element(element_of(
+                &formatting_element,
+            )))));
+            loop {
+                let child = furthest_block.borrow().first_child();
+                match child {
+                    Some(child) => append_child(&new_node, child),
+                    None => break,
+                }
+            }
+            append_child(&furthest_block, new_node.clone());
+
+            match bookmark.and_then(|node| self.active_formatting_element_index(&node)) {
+                Some(i) => {
+                    self.active_formatting_elements
+                        .insert(i + 1, ActiveFormattingElement::Element(new_node.clone()));
+                    self.active_formatting_elements
+                        .retain(|entry| !matches!(entry, ActiveFormattingElement::Element(e) if Rc::ptr_eq(e, &formatting_element)));
+                }
+                None => {
+                    if let Some(i) = self.active_formatting_element_index(&formatting_element) {
+                        self.active_formatting_elements[i] =
+                            ActiveFormattingElement::Element(new_node.clone());
+                    }
+                }
+            }
+
+            self.stack_of_open_elements
+                .retain(|node| !Rc::ptr_eq(node, &formatting_element));
+            if let Some(i) = self
+                .stack_of_open_elements
+                .iter()
+                .position(|node| Rc::ptr_eq(node, &furthest_block))
+            {
+                self.stack_of_open_elements.insert(i + 1, new_node);
             }
-            None => {
-                current_node.set_first_child(Some(new_node.clone()));
+        }
+
+        true
+    }
+
+    /// <head>の中の規則で中身をテキストとして読む要素について、中身を読むトークナイザのステートを返します
+    fn text_element_state(&self, tag: &str) -> Option<State> {
+        match tag {
+            "title" => Some(State::Rcdata),
+            "style" | "noframes" => Some(State::Rawtext),
+            "noscript" if self.scripting => Some(State::Rawtext),
+            "script" => Some(State::ScriptData),
+            _ => None,
+        }
+    }
+
+    /// 要素を追加し、その中身をテキストとして読むようにトークナイザを切り替えます
+    /// https://html.spec.whatwg.org/multipage/parsing.html#generic-raw-text-element-parsing-algorithm
+    /// https://html.spec.whatwg.org/multipage/parsing.html#generic-rcdata-element-parsing-algorithm
+    fn insert_text_element(&mut self, tag: &str, attributes: Vec<Attribute>, state: State) {
+        self.insert_element(tag, attributes);
+        self.t.switch_to(state);
+        self.original_insertion_mode = self.mode;
+        self.mode = InsertionMode::Text;
+    }
+
+    /// ノードを追加する親ノードと、その子供のうち追加したノードの直後に来るノードを返します
+    /// 表の中に置けないノードは、表の直前に追加する(foster parenting)
+    /// https://html.spec.whatwg.org/multipage/parsing.html#appropriate-place-for-inserting-a-node
+    fn appropriate_place_for_inserting(
+        &self,
+        override_target: Option<Rc<RefCell<Node>>>,
+    ) -> (Rc<RefCell<Node>>, Option<Rc<RefCell<Node>>>) {
+        let target = override_target.unwrap_or_else(|| self.current_node());
+        if !self.foster_parenting
+            || !is_html_element_in(&target, &["table", "tbody", "tfoot", "thead", "tr"])
+        {
+            return (target, None);
+        }
+
+        let last_table = self
+            .stack_of_open_elements
+            .iter()
+            .rposition(|node| is_html_element_in(node, &["table"]));
+        match last_table {
+            Some(i) => {
+                let table = self.stack_of_open_elements[i].clone();
+                let parent = table.borrow().parent().upgrade();
+                match parent {
+                    Some(parent) => (parent, Some(table)),
+                    None => (
+                        self.stack_of_open_elements[i.saturating_sub(1)].clone(),
+                        None,
+                    ),
+                }
             }
+            None => (self.stack_of_open_elements[0].clone(), None),
         }
-        current_node.set_last_child(Rc::downgrade(&new_node));
-        new_node.borrow_mut().set_parent(Rc::downgrade(&parent));
+    }
+
+    /// 親ノードの持つ子供の最後尾に新しいノードを追加します
+    fn append_child(&self, parent: Rc<RefCell<Node>>, new_node: Node) -> Rc<RefCell<Node>> {
+        let new_node = Rc::new(RefCell::new(new_node));
+        append_child(&parent, new_node.clone());
+        new_node
+    }
+
+    /// 現在のノードの最後の子としてコメントを追加します
+    /// https://html.spec.whatwg.org/multipage/parsing.html#insert-a-comment
+    fn insert_comment(&mut self, data: &str) {
+        let (parent, reference) = self.appropriate_place_for_inserting(None);
+        let comment = Rc::new(RefCell::new(Node::new(NodeKind::Comment(data.to_string()))));
+        insert_before(&parent, comment, reference.as_ref());
+    }
+
+    /// html要素の最後の子としてコメントを追加します
+    fn insert_comment_in_html_element(&mut self, data: &str) {
+        let parent = match self.stack_of_open_elements.first() {
+            Some(node) => node.clone(),
+            None => self.window.borrow().document(),
+        };
+        self.append_child(parent, Node::new(NodeKind::Comment(data.to_string())));
+    }
+
+    /// Documentの最後の子としてコメントを追加します
+    fn insert_comment_in_document(&mut self, data: &str) {
+        let document = self.window.borrow().document();
+        self.append_child(document, Node::new(NodeKind::Comment(data.to_string())));
+    }
 
-        self.stack_of_open_elements.push(new_node);
+    fn set_quirks_mode(&self) {
+        self.window
+            .borrow()
+            .document()
+            .borrow_mut()
+            .set_document_mode(DocumentMode::Quirks);
     }
 
     fn create_char(&self, c: char) -> Node {
@@ -147,61 +1046,202 @@ impl HtmlParser {
         Node::new(NodeKind::Text(s))
     }
 
-    /// 現在のノードによって以下の2つの処理を行います
-    /// 現在のノードがTextノードのとき, テキストの最後にに文字を挿入します
-    /// 現在のノードが上記以外のとき, 最後の子ノードの次のノードとしてTextノードを追加します
+    /// 文字を追加する位置の直前のノードによって以下の2つの処理を行います
+    /// 直前のノードがTextノードのとき, テキストの最後に文字を挿入します
+    /// 直前のノードが上記以外のとき, その次のノードとしてTextノードを追加します
     ///   ただし、引数の文字が無視されるべき文字のときは、Textノードを追加しません
+    /// https://html.spec.whatwg.org/multipage/parsing.html#insert-a-character
     fn insert_char(&mut self, c: char) {
-        let current = match self.stack_of_open_elements.last() {
-            Some(node) => node.clone(),
-            None => return,
-        };
-
-        if let NodeKind::Text(ref mut s) = current.borrow_mut().kind {
-            s.push(c);
+        let (parent, reference) = self.appropriate_place_for_inserting(None);
+        if matches!(parent.borrow().kind, NodeKind::Document) {
             return;
         }
 
+        let previous = match reference {
+            Some(ref reference) => reference.borrow().previous_sibling().upgrade(),
+            None => parent.borrow().last_child().upgrade(),
+        };
+        if let Some(previous) = previous {
+            if let NodeKind::Text(ref mut s) = previous.borrow_mut().kind {
+                s.push(c);
+                return;
+            }
+        }
+
         if c == SPACE || c == LINE_FEED {
             return;
         }
 
-        // let node = Rc::new(RefCell::new(self.create_char(c)));
-        self.insert_node(current, self.create_char(c));
+        let text = Rc::new(RefCell::new(self.create_char(c)));
+        insert_before(&parent, text, reference.as_ref());
     }
 
     fn create_element(&self, tag: &str, attributes: Vec<Attribute>) -> Node {
         Node::new(NodeKind::Element(Element::new(tag, attributes)))
     }
 
-    fn insert_element(&mut self, tag: &str, attributes: Vec<Attribute>) {
-        let current = match self.stack_of_open_elements.last() {
+    /// https://html.spec.whatwg.org/multipage/parsing.html#current-node
+    fn current_node(&self) -> Rc<RefCell<Node>> {
+        match self.stack_of_open_elements.last() {
             Some(node) => node.clone(),
             // Documentが最初にスタックに積まれているという仕様
             None => self.window.borrow().document(),
-        };
+        }
+    }
 
-        // let new_node = Rc::new(RefCell::new(self.create_element(tag, attributes)));
-        self.insert_node(current, self.create_element(tag, attributes));
+    fn insert_element(&mut self, tag: &str, attributes: Vec<Attribute>) -> Rc<RefCell<Node>> {
+        self.insert_element_node(self.create_element(tag, attributes))
     }
 
-    pub fn construct_tree(&mut self) -> Rc<RefCell<Window>> {
-        let mut token = self.t.next();
+    /// 現在のノードの最後の子として要素を追加し、スタックに積みます
+    /// https://html.spec.whatwg.org/multipage/parsing.html#insert-an-html-element
+    fn insert_element_node(&mut self, element: Node) -> Rc<RefCell<Node>> {
+        let (parent, reference) = self.appropriate_place_for_inserting(None);
+        let element = Rc::new(RefCell::new(element));
+        insert_before(&parent, element.clone(), reference.as_ref());
+        self.stack_of_open_elements.push(element.clone());
+        element
+    }
 
-        while token.is_some() {
-            match self.mode {
-                InsertionMode::Initial => {
-                    // <!doctype html>のようなトークンは文字トークンになり、文字トークンは無視する
-                    if let Some(HtmlToken::Char(_)) = token {
-                        token = self.t.next();
-                        continue;
-                    }
+    /// 入力の続きを渡し、トークナイザが読み込めたところまでDOMツリーを構築します
+    pub fn feed(&mut self, input: &str) {
+        self.t.feed(input);
+        self.process_tokens();
+    }
+
+    /// テキストファイルを表示するドキュメントとして解析を始めます
+    /// 以降に渡した入力は、タグや文字参照として解釈せずにすべて<pre>要素の中のテキストになります
+    /// https://html.spec.whatwg.org/multipage/document-lifecycle.html#read-text
+    pub fn start_text_document(&mut self) {
+        self.process(Some(HtmlToken::StartTag {
+            tag: "pre".to_string(),
+            self_closing: false,
+            attributes: Vec::new(),
+        }));
+        // テキストの先頭の改行は<pre>の直後の改行として読み飛ばさない
+        self.ignore_line_feed = false;
+        self.t.switch_to(State::Plaintext);
+    }
+
+    /// 残りの入力を最後まで読み込んでDOMツリーを構築します
+    pub fn construct_tree(&mut self) -> Rc<RefCell<Window>> {
+        self.t.end();
+        self.process_tokens();
+        // トークナイザがEOFトークンを返さずに終わったときも、入力の終わりとして処理する
+        if !self.stopped {
+            self.process(Some(HtmlToken::Eof));
+        }
+        self.window.clone()
+    }
+
+    /// ツリー構築中に見つかった、仕様に従っていない入力の一覧を返します
+    pub fn parse_errors(&self) -> Vec<ParseError> {
+        self.parse_errors.clone()
+    }
+
+    /// 構築中のDOMツリーを持つウィンドウを返します
+    pub fn window(&self) -> Rc<RefCell<Window>> {
+        self.window.clone()
+    }
+
+    pub fn insertion_mode(&self) -> InsertionMode {
+        self.mode
+    }
+
+    /// トークナイザが返すトークンを順に処理します
+    /// トークナイザが入力の続きを待っているときは、次の入力が渡されるまで処理を中断します
+    fn process_tokens(&mut self) {
+        let token = self.t.next();
+        self.process(token);
+    }
+
+    /// 渡されたトークンから順に処理します
+    fn process(&mut self, token: Option<HtmlToken>) {
+        let mut token = token;
+        // 他の挿入モードの規則でトークンを処理するときに、その挿入モードを入れておく
+        // https://html.spec.whatwg.org/multipage/parsing.html#using-the-rules-for
+        let mut using_rules = None;
+
+        while token.is_some() {
+            let mode = match using_rules.take() {
+                Some(mode) => mode,
+                None => {
+                    self.foster_parenting = false;
+                    self.mode
+                }
+            };
+            match mode {
+                InsertionMode::Initial => {
+                    match token {
+                        Some(HtmlToken::Char(c)) => {
+                            if c == SPACE || c == LINE_FEED {
+                                token = self.t.next();
+                                continue;
+                            }
+                        }
+                        Some(HtmlToken::Comment(ref data)) => {
+                            self.insert_comment_in_document(data);
+                            token = self.t.next();
+                            continue;
+                        }
+                        Some(HtmlToken::Doctype {
+                            ref name,
+                            ref public_id,
+                            ref system_id,
+                            force_quirks,
+                        }) => {
+                            // <!DOCTYPE html>以外のDOCTYPEは、古い文書との互換のためにだけ許される
+                            if name.as_deref() != Some("html")
+                                || public_id.is_some()
+                                || system_id
+                                    .as_deref()
+                                    .is_some_and(|id| id != "about:legacy-compat")
+                            {
+                                self.parse_error();
+                            }
+                            let document = self.window.borrow().document();
+                            let doctype = DocumentType::new(
+                                name.as_deref().unwrap_or(""),
+                                public_id.as_deref().unwrap_or(""),
+                                system_id.as_deref().unwrap_or(""),
+                            );
+                            self.append_child(
+                                document.clone(),
+                                Node::new(NodeKind::DocumentType(doctype)),
+                            );
+                            document.borrow_mut().set_document_mode(document_mode(
+                                name,
+                                public_id,
+                                system_id,
+                                force_quirks,
+                            ));
+                            self.mode = InsertionMode::BeforeHtml;
+                            token = self.t.next();
+                            continue;
+                        }
+                        _ => {}
+                    }
 
+                    // DOCTYPEのない文書や空の文書はquirksモードで扱う
+                    self.parse_error();
+                    self.set_quirks_mode();
                     self.mode = InsertionMode::BeforeHtml;
                     continue;
                 }
+                // https://html.spec.whatwg.org/multipage/parsing.html#the-before-html-insertion-mode
                 InsertionMode::BeforeHtml => {
                     match token {
+                        Some(HtmlToken::Comment(ref data)) => {
+                            self.insert_comment(data);
+                            token = self.t.next();
+                            continue;
+                        }
+                        // DOCTYPEはInitialモードでのみ意味を持ち、それ以外では無視する
+                        Some(HtmlToken::Doctype { .. }) => {
+                            self.parse_error();
+                            token = self.t.next();
+                            continue;
+                        }
                         Some(HtmlToken::Char(c)) => {
                             if c == SPACE || c == LINE_FEED {
                                 token = self.t.next();
@@ -220,229 +1260,1264 @@ impl HtmlParser {
                                 continue;
                             }
                         }
-                        Some(HtmlToken::Eof) | None => return self.window.clone(),
-                        _ => {}
+                        Some(HtmlToken::EndTag { ref tag }) => {
+                            if !matches!(tag.as_str(), "head" | "body" | "html" | "br") {
+                                self.parse_error();
+                                token = self.t.next();
+                                continue;
+                            }
+                        }
+                        // 中身のない文書には要素を作らない
+                        Some(HtmlToken::Eof) => {
+                            self.stopped = true;
+                            return;
+                        }
+                        None => return,
                     }
 
-                    self.insert_element("head", Vec::new());
+                    // <html>タグが存在しない場合に自動挿入
+                    self.insert_element("html", Vec::new());
+                    self.mode = InsertionMode::BeforeHead;
+                    continue;
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#the-before-head-insertion-mode
+                InsertionMode::BeforeHead => {
+                    match token {
+                        Some(HtmlToken::Comment(ref data)) => {
+                            self.insert_comment(data);
+                            token = self.t.next();
+                            continue;
+                        }
+                        // DOCTYPEはInitialモードでのみ意味を持ち、それ以外では無視する
+                        Some(HtmlToken::Doctype { .. }) => {
+                            self.parse_error();
+                            token = self.t.next();
+                            continue;
+                        }
+                        Some(HtmlToken::Char(c)) => {
+                            if c == SPACE || c == LINE_FEED {
+                                token = self.t.next();
+                                continue;
+                            }
+                        }
+                        Some(HtmlToken::StartTag {
+                            ref tag,
+                            ref attributes,
+                            ..
+                        }) => {
+                            if tag == "head" {
+                                let head = self.insert_element(tag, attributes.to_vec());
+                                self.head_element = Some(head);
+                                self.mode = InsertionMode::InHead;
+                                token = self.t.next();
+                                continue;
+                            }
+                            // 2つ目の<html>は<body>の中の規則で属性を追加する
+                            if tag == "html" {
+                                using_rules = Some(InsertionMode::InBody);
+                                continue;
+                            }
+                        }
+                        Some(HtmlToken::EndTag { ref tag }) => {
+                            if !matches!(tag.as_str(), "head" | "body" | "html" | "br") {
+                                self.parse_error();
+                                token = self.t.next();
+                                continue;
+                            }
+                        }
+                        Some(HtmlToken::Eof) => {}
+                        None => return,
+                    }
+
+                    // <head>タグが存在しない場合に自動挿入
+                    let head = self.insert_element("head", Vec::new());
+                    self.head_element = Some(head);
                     self.mode = InsertionMode::InHead;
                     continue;
                 }
-                InsertionMode::BeforeHead => match token {
-                    Some(HtmlToken::Char(c)) => {
-                        if c == SPACE || c == LINE_FEED {
+                // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-inhead
+                InsertionMode::InHead => {
+                    match token {
+                        Some(HtmlToken::Comment(ref data)) => {
+                            self.insert_comment(data);
                             token = self.t.next();
                             continue;
                         }
+                        // DOCTYPEはInitialモードでのみ意味を持ち、それ以外では無視する
+                        Some(HtmlToken::Doctype { .. }) => {
+                            self.parse_error();
+                            token = self.t.next();
+                            continue;
+                        }
+                        Some(HtmlToken::Char(c)) => {
+                            if c == SPACE || c == LINE_FEED {
+                                token = self.t.next();
+                                continue;
+                            }
+                        }
+                        Some(HtmlToken::StartTag {
+                            ref tag,
+                            ref attributes,
+                            ..
+                        }) => {
+                            // <base>や<meta>は空要素なので、挿入した直後にスタックから取り除く
+                            if matches!(
+                                tag.as_str(),
+                                "base" | "basefont" | "bgsound" | "link" | "meta"
+                            ) {
+                                self.insert_element(tag, attributes.to_vec());
+                                self.stack_of_open_elements.pop();
+                                token = self.t.next();
+                                continue;
+                            }
+                            if let Some(state) = self.text_element_state(tag) {
+                                self.insert_text_element(tag, attributes.to_vec(), state);
+                                token = self.t.next();
+                                continue;
+                            }
+                            // スクリプトが無効なときは<noscript>の中身を<head>の中のタグとして読む
+                            if tag == "noscript" {
+                                self.insert_element(tag, attributes.to_vec());
+                                self.mode = InsertionMode::InHeadNoscript;
+                                token = self.t.next();
+                                continue;
+                            }
+                            if tag == "html" {
+                                using_rules = Some(InsertionMode::InBody);
+                                continue;
+                            }
+                            if tag == "head" {
+                                self.parse_error();
+                                token = self.t.next();
+                                continue;
+                            }
+                        }
+                        Some(HtmlToken::EndTag { ref tag }) => {
+                            if tag == "head" {
+                                self.stack_of_open_elements.pop();
+                                self.mode = InsertionMode::AfterHead;
+                                token = self.t.next();
+                                continue;
+                            }
+                            if !matches!(tag.as_str(), "body" | "html" | "br") {
+                                self.parse_error();
+                                token = self.t.next();
+                                continue;
+                            }
+                        }
+                        Some(HtmlToken::Eof) => {}
+                        None => return,
+                    }
+
+                    // その他のトークンは<head>を閉じてから処理し直す
+                    self.stack_of_open_elements.pop();
+                    self.mode = InsertionMode::AfterHead;
+                    continue;
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-inheadnoscript
+                InsertionMode::InHeadNoscript => {
+                    match token {
+                        Some(HtmlToken::Doctype { .. }) => {
+                            self.parse_error();
+                            token = self.t.next();
+                            continue;
+                        }
+                        Some(HtmlToken::Comment(ref data)) => {
+                            self.insert_comment(data);
+                            token = self.t.next();
+                            continue;
+                        }
+                        Some(HtmlToken::Char(c)) => {
+                            if c == SPACE || c == LINE_FEED {
+                                token = self.t.next();
+                                continue;
+                            }
+                        }
+                        Some(HtmlToken::StartTag {
+                            ref tag,
+                            ref attributes,
+                            ..
+                        }) => {
+                            if matches!(tag.as_str(), "basefont" | "bgsound" | "link" | "meta") {
+                                self.insert_element(tag, attributes.to_vec());
+                                self.stack_of_open_elements.pop();
+                                token = self.t.next();
+                                continue;
+                            }
+                            if tag == "style" || tag == "noframes" {
+                                self.insert_text_element(tag, attributes.to_vec(), State::Rawtext);
+                                token = self.t.next();
+                                continue;
+                            }
+                            if tag == "html" {
+                                using_rules = Some(InsertionMode::InBody);
+                                continue;
+                            }
+                            if tag == "head" || tag == "noscript" {
+                                self.parse_error();
+                                token = self.t.next();
+                                continue;
+                            }
+                        }
+                        Some(HtmlToken::EndTag { ref tag }) => {
+                            if tag == "noscript" {
+                                self.pop_current_node(ElementKind::Noscript);
+                                self.mode = InsertionMode::InHead;
+                                token = self.t.next();
+                                continue;
+                            }
+                            if tag != "br" {
+                                self.parse_error();
+                                token = self.t.next();
+                                continue;
+                            }
+                        }
+                        Some(HtmlToken::Eof) => {}
+                        None => return,
+                    }
+                    // <noscript>を閉じて、<head>の中のトークンとして処理し直す
+                    self.parse_error();
+                    self.pop_current_node(ElementKind::Noscript);
+                    self.mode = InsertionMode::InHead;
+                    continue;
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#the-after-head-insertion-mode
+                InsertionMode::AfterHead => {
+                    match token {
+                        Some(HtmlToken::Comment(ref data)) => {
+                            self.insert_comment(data);
+                            token = self.t.next();
+                            continue;
+                        }
+                        // DOCTYPEはInitialモードでのみ意味を持ち、それ以外では無視する
+                        Some(HtmlToken::Doctype { .. }) => {
+                            self.parse_error();
+                            token = self.t.next();
+                            continue;
+                        }
+                        Some(HtmlToken::Char(c)) => {
+                            if c == SPACE || c == LINE_FEED {
+                                self.insert_char(c);
+                                token = self.t.next();
+                                continue;
+                            }
+                        }
+                        Some(HtmlToken::StartTag {
+                            ref tag,
+                            ref attributes,
+                            ..
+                        }) => {
+                            if tag == "body" {
+                                self.insert_element(tag, attributes.to_vec());
+                                token = self.t.next();
+                                self.mode = InsertionMode::InBody;
+                                continue;
+                            }
+                            // </head>の後に書かれた<head>の中の要素は、<head>の中に追加する
+                            if matches!(
+                                tag.as_str(),
+                                "base"
+                                    | "basefont"
+                                    | "bgsound"
+                                    | "link"
+                                    | "meta"
+                                    | "noframes"
+                                    | "script"
+                                    | "style"
+                                    | "title"
+                            ) {
+                                self.parse_error();
+                                if let Some(head) = self.head_element.clone() {
+                                    self.stack_of_open_elements.push(head.clone());
+                                    match self.text_element_state(tag) {
+                                        Some(state) => self.insert_text_element(
+                                            tag,
+                                            attributes.to_vec(),
+                                            state,
+                                        ),
+                                        None => {
+                                            self.insert_element(tag, attributes.to_vec());
+                                            self.stack_of_open_elements.pop();
+                                        }
+                                    }
+                                    self.stack_of_open_elements
+                                        .retain(|node| !Rc::ptr_eq(node, &head));
+                                }
+                                token = self.t.next();
+                                continue;
+                            }
+                            if tag == "html" {
+                                using_rules = Some(InsertionMode::InBody);
+                                continue;
+                            }
+                            if tag == "head" {
+                                self.parse_error();
+                                token = self.t.next();
+                                continue;
+                            }
+                        }
+                        Some(HtmlToken::EndTag { ref tag }) => {
+                            if !matches!(tag.as_str(), "body" | "html" | "br") {
+                                self.parse_error();
+                                token = self.t.next();
+                                continue;
+                            }
+                        }
+                        Some(HtmlToken::Eof) => {}
+                        None => return,
+                    }
+                    // bodyタグが存在しない場合に自動挿入
+                    self.insert_element("body", Vec::new());
+                    self.mode = InsertionMode::InBody;
+                    continue;
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-inbody
+                InsertionMode::InBody => match token {
+                    Some(HtmlToken::Comment(ref data)) => {
+                        self.ignore_line_feed = false;
+                        self.insert_comment(data);
+                        token = self.t.next();
+                        continue;
+                    }
+                    // DOCTYPEはInitialモードでのみ意味を持ち、それ以外では無視する
+                    Some(HtmlToken::Doctype { .. }) => {
+                        self.ignore_line_feed = false;
+                        self.parse_error();
+                        token = self.t.next();
+                        continue;
                     }
                     Some(HtmlToken::StartTag {
                         ref tag,
                         ref attributes,
                         ..
                     }) => {
-                        if tag == "head" {
-                            self.insert_element(tag, attributes.to_vec());
-                            self.mode = InsertionMode::InHead;
+                        self.ignore_line_feed = false;
+                        match tag.as_str() {
+                            // 2つ目の<html>や<body>は、最初の要素が持っていない属性だけを追加する
+                            "html" => {
+                                self.parse_error();
+                                if !self.has_template_in_stack() {
+                                    if let Some(html) = self.stack_of_open_elements.first() {
+                                        html.borrow_mut().add_missing_attributes(attributes);
+                                    }
+                                }
+                            }
+                            "body" => {
+                                self.parse_error();
+                                let body = self
+                                    .stack_of_open_elements
+                                    .get(1)
+                                    .filter(|node| is_html_element_in(node, &["body"]))
+                                    .cloned();
+                                if let Some(body) = body {
+                                    if !self.has_template_in_stack() {
+                                        body.borrow_mut().add_missing_attributes(attributes);
+                                    }
+                                }
+                            }
+                            // <body>の中では意味を持たないタグは無視する
+                            "head" | "frameset" | "frame" | "caption" | "col" | "colgroup"
+                            | "tbody" | "td" | "tfoot" | "th" | "thead" | "tr" => {
+                                self.parse_error()
+                            }
+                            "address" | "article" | "aside" | "blockquote" | "center"
+                            | "details" | "dialog" | "dir" | "div" | "dl" | "fieldset"
+                            | "figcaption" | "figure" | "footer" | "header" | "hgroup" | "main"
+                            | "menu" | "nav" | "ol" | "p" | "search" | "section" | "summary"
+                            | "ul" => {
+                                self.close_p_element_in_button_scope();
+                                self.insert_element(tag, attributes.to_vec());
+                            }
+                            // 開いている<form>の中の<form>は無視する
+                            "form" => {
+                                let has_template = self.has_template_in_stack();
+                                if self.form_element.is_some() && !has_template {
+                                    self.parse_error();
+                                } else {
+                                    self.close_p_element_in_button_scope();
+                                    let form = self.insert_element(tag, attributes.to_vec());
+                                    if !has_template {
+                                        self.form_element = Some(form);
+                                    }
+                                }
+                            }
+                            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                                self.close_p_element_in_button_scope();
+                                // 見出しの中に見出しは入れられない
+                                if self
+                                    .stack_of_open_elements
+                                    .last()
+                                    .is_some_and(|node| is_html_element_in(node, HEADING_ELEMENTS))
+                                {
+                                    self.parse_error();
+                                    self.stack_of_open_elements.pop();
+                                }
+                                self.insert_element(tag, attributes.to_vec());
+                            }
+                            "pre" | "listing" => {
+                                self.close_p_element_in_button_scope();
+                                self.insert_element(tag, attributes.to_vec());
+                                // 開始タグの直後の改行は、中身に含めない
+                                self.ignore_line_feed = true;
+                            }
+                            "li" => {
+                                self.close_list_item(&["li"]);
+                                self.close_p_element_in_button_scope();
+                                self.insert_element(tag, attributes.to_vec());
+                            }
+                            "dd" | "dt" => {
+                                self.close_list_item(&["dd", "dt"]);
+                                self.close_p_element_in_button_scope();
+                                self.insert_element(tag, attributes.to_vec());
+                            }
+                            // <plaintext>の後はすべて文字として読み、終了タグもない
+                            "plaintext" => {
+                                self.close_p_element_in_button_scope();
+                                self.insert_element(tag, attributes.to_vec());
+                                self.t.switch_to(State::Plaintext);
+                            }
+                            "button" => {
+                                if self.has_element_in_scope(&["button"], Scope::Default) {
+                                    self.parse_error();
+                                    self.generate_implied_end_tags(None);
+                                    self.pop_until_one_of(&["button"]);
+                                }
+                                self.reconstruct_active_formatting_elements();
+                                self.insert_element(tag, attributes.to_vec());
+                            }
+                            "a" => {
+                                // 閉じられていない<a>があれば、先に閉じる
+                                if let Some(node) = self.last_active_formatting_element("a") {
+                                    self.parse_error();
+                                    self.run_adoption_agency("a");
+                                    self.active_formatting_elements.retain(|entry| {
+                                        !matches!(entry, ActiveFormattingElement::Element(e) if Rc::ptr_eq(e, &node))
+                                    });
+                                    self.stack_of_open_elements
+                                        .retain(|open| !Rc::ptr_eq(open, &node));
+                                }
+                                self.reconstruct_active_formatting_elements();
+                                let node = self.insert_element(tag, attributes.to_vec());
+                                self.push_active_formatting_element(node);
+                            }
+                            "b" | "big" | "code" | "em" | "font" | "i" | "s" | "small"
+                            | "strike" | "strong" | "tt" | "u" => {
+                                self.reconstruct_active_formatting_elements();
+                                let node = self.insert_element(tag, attributes.to_vec());
+                                self.push_active_formatting_element(node);
+                            }
+                            "nobr" => {
+                                self.reconstruct_active_formatting_elements();
+                                if self.has_element_in_scope(&["nobr"], Scope::Default) {
+                                    self.parse_error();
+                                    self.run_adoption_agency("nobr");
+                                    self.reconstruct_active_formatting_elements();
+                                }
+                                let node = self.insert_element(tag, attributes.to_vec());
+                                self.push_active_formatting_element(node);
+                            }
+                            "applet" | "marquee" | "object" => {
+                                self.reconstruct_active_formatting_elements();
+                                self.insert_element(tag, attributes.to_vec());
+                                self.active_formatting_elements
+                                    .push(ActiveFormattingElement::Marker);
+                            }
+                            "table" => {
+                                let document_mode =
+                                    self.window.borrow().document().borrow().document_mode();
+                                if document_mode != DocumentMode::Quirks {
+                                    self.close_p_element_in_button_scope();
+                                }
+                                self.insert_element(tag, attributes.to_vec());
+                                self.mode = InsertionMode::InTable;
+                            }
+                            // 中身を持たない要素は、挿入した直後にスタックから取り除く
+                            "area" | "br" | "embed" | "img" | "keygen" | "wbr" | "input" => {
+                                self.reconstruct_active_formatting_elements();
+                                self.insert_element(tag, attributes.to_vec());
+                                self.stack_of_open_elements.pop();
+                            }
+                            // <image>は<img>の誤りとして扱う
+                            "image" => {
+                                self.parse_error();
+                                self.reconstruct_active_formatting_elements();
+                                self.insert_element("img", attributes.to_vec());
+                                self.stack_of_open_elements.pop();
+                            }
+                            "param" | "source" | "track" | "base" | "basefont" | "bgsound"
+                            | "link" | "meta" => {
+                                self.insert_element(tag, attributes.to_vec());
+                                self.stack_of_open_elements.pop();
+                            }
+                            "hr" => {
+                                self.close_p_element_in_button_scope();
+                                self.insert_element(tag, attributes.to_vec());
+                                self.stack_of_open_elements.pop();
+                            }
+                            "textarea" => {
+                                self.insert_text_element(tag, attributes.to_vec(), State::Rcdata);
+                                // 開始タグの直後の改行は、中身に含めない
+                                self.ignore_line_feed = true;
+                            }
+                            "xmp" => {
+                                self.close_p_element_in_button_scope();
+                                self.reconstruct_active_formatting_elements();
+                                self.insert_text_element(tag, attributes.to_vec(), State::Rawtext);
+                            }
+                            "iframe" | "noembed" => {
+                                self.insert_text_element(tag, attributes.to_vec(), State::Rawtext);
+                            }
+                            "optgroup" | "option" => {
+                                if self
+                                    .stack_of_open_elements
+                                    .last()
+                                    .is_some_and(|node| is_html_element_in(node, &["option"]))
+                                {
+                                    self.stack_of_open_elements.pop();
+                                }
+                                self.reconstruct_active_formatting_elements();
+                                self.insert_element(tag, attributes.to_vec());
+                            }
+                            "rb" | "rtc" | "rp" | "rt" => {
+                                if self.has_element_in_scope(&["ruby"], Scope::Default) {
+                                    let except = match tag.as_str() {
+                                        "rp" | "rt" => Some("rtc"),
+                                        _ => None,
+                                    };
+                                    self.generate_implied_end_tags(except);
+                                }
+                                self.insert_element(tag, attributes.to_vec());
+                            }
+                            _ => {
+                                // <body>の中に書かれた<style>や<title>なども、<head>の中と同じように読む
+                                match self.text_element_state(tag) {
+                                    Some(state) => {
+                                        self.insert_text_element(tag, attributes.to_vec(), state)
+                                    }
+                                    None => {
+                                        self.reconstruct_active_formatting_elements();
+                                        self.insert_element(tag, attributes.to_vec());
+                                    }
+                                }
+                            }
+                        }
+                        token = self.t.next();
+                        continue;
+                    }
+                    Some(HtmlToken::EndTag { ref tag }) => {
+                        self.ignore_line_feed = false;
+                        match tag.as_str() {
+                            "body" => {
+                                if self.has_element_in_scope(&["body"], Scope::Default) {
+                                    self.mode = InsertionMode::AfterBody;
+                                } else {
+                                    self.parse_error();
+                                }
+                            }
+                            "html" => {
+                                // <body>を閉じてから、AfterBodyモードで処理し直す
+                                if self.has_element_in_scope(&["body"], Scope::Default) {
+                                    self.mode = InsertionMode::AfterBody;
+                                    continue;
+                                }
+                                self.parse_error();
+                            }
+                            "address" | "article" | "aside" | "blockquote" | "button"
+                            | "center" | "details" | "dialog" | "dir" | "div" | "dl"
+                            | "fieldset" | "figcaption" | "figure" | "footer" | "header"
+                            | "hgroup" | "listing" | "main" | "menu" | "nav" | "ol" | "pre"
+                            | "search" | "section" | "summary" | "ul" => {
+                                if !self.close_element_in_scope(&[tag], Scope::Default, None) {
+                                    self.parse_error();
+                                }
+                            }
+                            "form" if self.has_template_in_stack() => {
+                                if !self.close_element_in_scope(&["form"], Scope::Default, None) {
+                                    self.parse_error();
+                                }
+                            }
+                            // form要素ポインタの指す<form>だけを、中の要素を閉じずにスタックから取り除く
+                            "form" => match self.form_element.take() {
+                                Some(form)
+                                    if self.has_in_scope(Scope::Default, |node| {
+                                        Rc::ptr_eq(node, &form)
+                                    }) =>
+                                {
+                                    self.generate_implied_end_tags(None);
+                                    let is_current = self
+                                        .stack_of_open_elements
+                                        .last()
+                                        .is_some_and(|node| Rc::ptr_eq(node, &form));
+                                    if !is_current {
+                                        self.parse_error();
+                                    }
+                                    self.stack_of_open_elements
+                                        .retain(|node| !Rc::ptr_eq(node, &form));
+                                }
+                                _ => self.parse_error(),
+                            },
+                            "p" => {
+                                // 対応する開始タグのない</p>は、空の<p>として扱う
+                                if !self.has_element_in_scope(&["p"], Scope::Button) {
+                                    self.parse_error();
+                                    self.insert_element("p", Vec::new());
+                                }
+                                self.close_p_element();
+                            }
+                            "li" => {
+                                if !self.close_element_in_scope(
+                                    &["li"],
+                                    Scope::ListItem,
+                                    Some("li"),
+                                ) {
+                                    self.parse_error();
+                                }
+                            }
+                            "dd" | "dt" => {
+                                if !self.close_element_in_scope(&[tag], Scope::Default, Some(tag)) {
+                                    self.parse_error();
+                                }
+                            }
+                            // 見出しは、開始タグと異なる見出しの終了タグでも閉じる
+                            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                                let is_current = self
+                                    .stack_of_open_elements
+                                    .last()
+                                    .is_some_and(|node| is_html_element_in(node, &[tag]));
+                                if !self.close_element_in_scope(
+                                    HEADING_ELEMENTS,
+                                    Scope::Default,
+                                    None,
+                                ) || !is_current
+                                {
+                                    self.parse_error();
+                                }
+                            }
+                            "a" | "b" | "big" | "code" | "em" | "font" | "i" | "nobr" | "s"
+                            | "small" | "strike" | "strong" | "tt" | "u" => {
+                                // 開いている書式要素が現在のノードでなければ、入れ子が崩れている
+                                let is_current = self
+                                    .stack_of_open_elements
+                                    .last()
+                                    .is_some_and(|node| is_html_element_in(node, &[tag]));
+                                if !self.run_adoption_agency(tag) {
+                                    self.close_element_for_end_tag(tag);
+                                } else if !is_current {
+                                    self.parse_error();
+                                }
+                            }
+                            "applet" | "marquee" | "object" => {
+                                if self.has_element_in_scope(&[tag], Scope::Default) {
+                                    if !self.close_element_in_scope(&[tag], Scope::Default, None) {
+                                        self.parse_error();
+                                    }
+                                    self.clear_active_formatting_elements_up_to_last_marker();
+                                } else {
+                                    self.parse_error();
+                                }
+                            }
+                            // </br>は<br>として扱う
+                            "br" => {
+                                self.parse_error();
+                                self.reconstruct_active_formatting_elements();
+                                self.insert_element("br", Vec::new());
+                                self.stack_of_open_elements.pop();
+                            }
+                            _ => self.close_element_for_end_tag(tag),
+                        }
+                        token = self.t.next();
+                        continue;
+                    }
+                    Some(HtmlToken::Eof) => {
+                        // 終了タグを省略できない要素が閉じられていなければ、仕様に従っていない
+                        if self.stack_of_open_elements.iter().any(|node| {
+                            !is_html_element_in(node, IMPLIED_END_TAG_ELEMENTS)
+                                && !is_html_element_in(
+                                    node,
+                                    &["tbody", "td", "tfoot", "th", "thead", "tr", "body", "html"],
+                                )
+                        }) {
+                            self.parse_error();
+                        }
+                        self.stopped = true;
+                        return;
+                    }
+                    None => return,
+                    Some(HtmlToken::Char(c)) => {
+                        let ignore_line_feed = mem::take(&mut self.ignore_line_feed);
+                        if c == '\0' {
+                            self.parse_error();
+                        } else if !(ignore_line_feed && c == LINE_FEED) {
+                            self.reconstruct_active_formatting_elements();
+                            self.insert_char(c);
+                        }
+                        token = self.t.next();
+                        continue;
+                    }
+                },
+                InsertionMode::Text => match token {
+                    // トークナイザは要素を開いたタグと同じ名前の終了タグしか返さない
+                    Some(HtmlToken::EndTag { .. }) => {
+                        self.ignore_line_feed = false;
+                        self.stack_of_open_elements.pop();
+                        self.mode = self.original_insertion_mode;
+                        token = self.t.next();
+                        continue;
+                    }
+                    Some(HtmlToken::Char(c)) => {
+                        if !(mem::take(&mut self.ignore_line_feed) && c == LINE_FEED) {
+                            self.insert_char(c);
+                        }
+                        token = self.t.next();
+                        continue;
+                    }
+                    // 終了タグのないまま入力が終わったときは、要素を閉じて元の挿入モードで処理し直す
+                    Some(HtmlToken::Eof) => {
+                        self.parse_error();
+                        self.stack_of_open_elements.pop();
+                        self.mode = self.original_insertion_mode;
+                        continue;
+                    }
+                    None => return,
+                    _ => {
+                        token = self.t.next();
+                        continue;
+                    }
+                },
+                // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-intable
+                InsertionMode::InTable => {
+                    match token {
+                        Some(HtmlToken::Char(_)) => {
+                            // 表の構造の中に直接書かれた文字は、まとめてから置き場所を決める
+                            if self.stack_of_open_elements.last().is_some_and(|node| {
+                                is_html_element_in(
+                                    node,
+                                    &["table", "tbody", "template", "tfoot", "thead", "tr"],
+                                )
+                            }) {
+                                self.pending_table_characters = Vec::new();
+                                self.original_insertion_mode = self.mode;
+                                self.mode = InsertionMode::InTableText;
+                                continue;
+                            }
+                        }
+                        Some(HtmlToken::Comment(ref data)) => {
+                            self.insert_comment(data);
+                            token = self.t.next();
+                            continue;
+                        }
+                        // DOCTYPEはInitialモードでのみ意味を持ち、それ以外では無視する
+                        Some(HtmlToken::Doctype { .. }) => {
+                            self.parse_error();
                             token = self.t.next();
                             continue;
                         }
+                        Some(HtmlToken::StartTag {
+                            ref tag,
+                            ref attributes,
+                            ..
+                        }) => match tag.as_str() {
+                            "caption" => {
+                                self.clear_stack_back_to(&["table", "template", "html"]);
+                                self.active_formatting_elements
+                                    .push(ActiveFormattingElement::Marker);
+                                self.insert_element(tag, attributes.to_vec());
+                                self.mode = InsertionMode::InCaption;
+                                token = self.t.next();
+                                continue;
+                            }
+                            "colgroup" => {
+                                self.clear_stack_back_to(&["table", "template", "html"]);
+                                self.insert_element(tag, attributes.to_vec());
+                                self.mode = InsertionMode::InColumnGroup;
+                                token = self.t.next();
+                                continue;
+                            }
+                            // <colgroup>のない<col>は、<colgroup>を補ってから処理し直す
+                            "col" => {
+                                self.clear_stack_back_to(&["table", "template", "html"]);
+                                self.insert_element("colgroup", Vec::new());
+                                self.mode = InsertionMode::InColumnGroup;
+                                continue;
+                            }
+                            "tbody" | "tfoot" | "thead" => {
+                                self.clear_stack_back_to(&["table", "template", "html"]);
+                                self.insert_element(tag, attributes.to_vec());
+                                self.mode = InsertionMode::InTableBody;
+                                token = self.t.next();
+                                continue;
+                            }
+                            // <tbody>のない行やセルは、<tbody>を補ってから処理し直す
+                            "td" | "th" | "tr" => {
+                                self.clear_stack_back_to(&["table", "template", "html"]);
+                                self.insert_element("tbody", Vec::new());
+                                self.mode = InsertionMode::InTableBody;
+                                continue;
+                            }
+                            // 表の中の<table>は、開いている表を閉じてから処理し直す
+                            "table" => {
+                                self.parse_error();
+                                if self.has_element_in_scope(&["table"], Scope::Table) {
+                                    self.pop_until_one_of(&["table"]);
+                                    self.reset_insertion_mode();
+                                    continue;
+                                }
+                                token = self.t.next();
+                                continue;
+                            }
+                            "style" | "script" => {
+                                if let Some(state) = self.text_element_state(tag) {
+                                    self.insert_text_element(tag, attributes.to_vec(), state);
+                                }
+                                token = self.t.next();
+                                continue;
+                            }
+                            // 見えない<input>と<form>は、表の中にそのまま置く
+                            "input"
+                                if attributes.iter().any(|attr| {
+                                    attr.name() == "type"
+                                        && attr.value().eq_ignore_ascii_case("hidden")
+                                }) =>
+                            {
+                                self.parse_error();
+                                self.insert_element(tag, attributes.to_vec());
+                                self.stack_of_open_elements.pop();
+                                token = self.t.next();
+                                continue;
+                            }
+                            "form" => {
+                                self.parse_error();
+                                if self.form_element.is_none() && !self.has_template_in_stack() {
+                                    let form = self.insert_element(tag, attributes.to_vec());
+                                    self.form_element = Some(form);
+                                    self.stack_of_open_elements.pop();
+                                }
+                                token = self.t.next();
+                                continue;
+                            }
+                            _ => {}
+                        },
+                        Some(HtmlToken::EndTag { ref tag }) => match tag.as_str() {
+                            "table" => {
+                                if self.has_element_in_scope(&["table"], Scope::Table) {
+                                    self.pop_until_one_of(&["table"]);
+                                    self.reset_insertion_mode();
+                                } else {
+                                    self.parse_error();
+                                }
+                                token = self.t.next();
+                                continue;
+                            }
+                            "body" | "caption" | "col" | "colgroup" | "html" | "tbody" | "td"
+                            | "tfoot" | "th" | "thead" | "tr" => {
+                                self.parse_error();
+                                token = self.t.next();
+                                continue;
+                            }
+                            _ => {}
+                        },
+                        Some(HtmlToken::Eof) => {
+                            using_rules = Some(InsertionMode::InBody);
+                            continue;
+                        }
+                        None => return,
                     }
-                    Some(HtmlToken::Eof) | None => {
-                        break;
+                    // その他のトークンは<body>の中の規則で処理し、表の中に置けないノードは表の直前に追加する
+                    self.parse_error();
+                    self.foster_parenting = true;
+                    using_rules = Some(InsertionMode::InBody);
+                    continue;
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-intabletext
+                InsertionMode::InTableText => match token {
+                    Some(HtmlToken::Char(c)) => {
+                        if c == '\0' {
+                            self.parse_error();
+                        } else {
+                            self.pending_table_characters.push(c);
+                        }
+                        token = self.t.next();
+                        continue;
+                    }
+                    None => return,
+                    _ => {
+                        // 空白だけなら表の中に、それ以外の文字を含むなら表の直前に追加する
+                        let characters = mem::take(&mut self.pending_table_characters);
+                        let foster_parenting = characters.iter().any(|c| !c.is_ascii_whitespace());
+                        if foster_parenting {
+                            self.parse_error();
+                        }
+                        self.foster_parenting = foster_parenting;
+                        for c in characters {
+                            if foster_parenting {
+                                self.reconstruct_active_formatting_elements();
+                            }
+                            self.insert_char(c);
+                        }
+                        self.foster_parenting = false;
+                        self.mode = self.original_insertion_mode;
+                        continue;
                     }
-                    _ => {}
                 },
-                // <head>タグ内では<style>タグ, <script>タグのみサポート
-                InsertionMode::InHead => {
+                // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-incaption
+                InsertionMode::InCaption => {
+                    let (closes_caption, reprocess) = match token {
+                        Some(HtmlToken::EndTag { ref tag }) => match tag.as_str() {
+                            "caption" => (true, false),
+                            "table" => (true, true),
+                            "body" | "col" | "colgroup" | "html" | "tbody" | "td" | "tfoot"
+                            | "th" | "thead" | "tr" => {
+                                self.parse_error();
+                                token = self.t.next();
+                                continue;
+                            }
+                            _ => (false, false),
+                        },
+                        Some(HtmlToken::StartTag { ref tag, .. }) => (
+                            matches!(
+                                tag.as_str(),
+                                "caption"
+                                    | "col"
+                                    | "colgroup"
+                                    | "tbody"
+                                    | "td"
+                                    | "tfoot"
+                                    | "th"
+                                    | "thead"
+                                    | "tr"
+                            ),
+                            true,
+                        ),
+                        None => return,
+                        _ => (false, false),
+                    };
+                    if !closes_caption {
+                        using_rules = Some(InsertionMode::InBody);
+                        continue;
+                    }
+
+                    // <caption>を閉じて表の中に戻る
+                    if !self.close_element_in_scope(&["caption"], Scope::Table, None) {
+                        self.parse_error();
+                        if !self.has_element_in_scope(&["caption"], Scope::Table) {
+                            token = self.t.next();
+                            continue;
+                        }
+                    }
+                    self.clear_active_formatting_elements_up_to_last_marker();
+                    self.mode = InsertionMode::InTable;
+                    if !reprocess {
+                        token = self.t.next();
+                    }
+                    continue;
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-incolgroup
+                InsertionMode::InColumnGroup => {
                     match token {
                         Some(HtmlToken::Char(c)) => {
                             if c == SPACE || c == LINE_FEED {
+                                self.insert_char(c);
                                 token = self.t.next();
                                 continue;
                             }
                         }
+                        Some(HtmlToken::Comment(ref data)) => {
+                            self.insert_comment(data);
+                            token = self.t.next();
+                            continue;
+                        }
+                        // DOCTYPEはInitialモードでのみ意味を持ち、それ以外では無視する
+                        Some(HtmlToken::Doctype { .. }) => {
+                            self.parse_error();
+                            token = self.t.next();
+                            continue;
+                        }
                         Some(HtmlToken::StartTag {
                             ref tag,
                             ref attributes,
                             ..
                         }) => {
-                            if tag == "style" || tag == "script" {
+                            if tag == "html" {
+                                using_rules = Some(InsertionMode::InBody);
+                                continue;
+                            }
+                            if tag == "col" {
                                 self.insert_element(tag, attributes.to_vec());
-                                self.original_insertion_mode = self.mode;
-                                self.mode = InsertionMode::Text;
+                                self.stack_of_open_elements.pop();
                                 token = self.t.next();
                                 continue;
                             }
-                            // 仕様外の挙動
-                            // <head>が省略されているHTML文書で無限ループが起きてしまうことへの対応
-                            if tag == "body" {
-                                self.pop_until(ElementKind::Head);
-                                self.mode = InsertionMode::AfterHead;
+                        }
+                        Some(HtmlToken::EndTag { ref tag }) => {
+                            if tag == "col" {
+                                self.parse_error();
+                                token = self.t.next();
                                 continue;
                             }
-                            // サポートしているその他のタグ(?)
-                            if let Ok(_element_kind) = ElementKind::from_str(tag) {
-                                self.pop_until(ElementKind::Head);
-                                self.mode = InsertionMode::AfterHead;
+                        }
+                        Some(HtmlToken::Eof) => {
+                            using_rules = Some(InsertionMode::InBody);
+                            continue;
+                        }
+                        None => return,
+                    }
+
+                    // <colgroup>を閉じて表の中に戻る
+                    let is_colgroup = self
+                        .stack_of_open_elements
+                        .last()
+                        .is_some_and(|node| is_html_element_in(node, &["colgroup"]));
+                    if !is_colgroup {
+                        self.parse_error();
+                        token = self.t.next();
+                        continue;
+                    }
+                    self.stack_of_open_elements.pop();
+                    self.mode = InsertionMode::InTable;
+                    // </colgroup>はここで処理を終える
+                    if matches!(token, Some(HtmlToken::EndTag { ref tag }) if tag == "colgroup") {
+                        token = self.t.next();
+                    }
+                    continue;
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-intbody
+                InsertionMode::InTableBody => {
+                    match token {
+                        Some(HtmlToken::StartTag {
+                            ref tag,
+                            ref attributes,
+                            ..
+                        }) => match tag.as_str() {
+                            "tr" => {
+                                self.clear_stack_back_to(&[
+                                    "tbody", "tfoot", "thead", "template", "html",
+                                ]);
+                                self.insert_element(tag, attributes.to_vec());
+                                self.mode = InsertionMode::InRow;
+                                token = self.t.next();
                                 continue;
                             }
-                        }
-                        Some(HtmlToken::EndTag { ref tag }) => {
-                            if tag == "head" {
-                                self.mode = InsertionMode::AfterHead;
+                            // <tr>のないセルは、<tr>を補ってから処理し直す
+                            "th" | "td" => {
+                                self.parse_error();
+                                self.clear_stack_back_to(&[
+                                    "tbody", "tfoot", "thead", "template", "html",
+                                ]);
+                                self.insert_element("tr", Vec::new());
+                                self.mode = InsertionMode::InRow;
+                                continue;
+                            }
+                            "caption" | "col" | "colgroup" | "tbody" | "tfoot" | "thead" => {
+                                if !self.has_element_in_scope(
+                                    &["tbody", "thead", "tfoot"],
+                                    Scope::Table,
+                                ) {
+                                    self.parse_error();
+                                    token = self.t.next();
+                                    continue;
+                                }
+                                self.clear_stack_back_to(&[
+                                    "tbody", "tfoot", "thead", "template", "html",
+                                ]);
+                                self.stack_of_open_elements.pop();
+                                self.mode = InsertionMode::InTable;
+                                continue;
+                            }
+                            _ => {}
+                        },
+                        Some(HtmlToken::EndTag { ref tag }) => match tag.as_str() {
+                            "tbody" | "tfoot" | "thead" => {
+                                if !self.has_element_in_scope(&[tag], Scope::Table) {
+                                    self.parse_error();
+                                    token = self.t.next();
+                                    continue;
+                                }
+                                self.clear_stack_back_to(&[
+                                    "tbody", "tfoot", "thead", "template", "html",
+                                ]);
+                                self.stack_of_open_elements.pop();
+                                self.mode = InsertionMode::InTable;
+                                token = self.t.next();
+                                continue;
+                            }
+                            "table" => {
+                                if !self.has_element_in_scope(
+                                    &["tbody", "thead", "tfoot"],
+                                    Scope::Table,
+                                ) {
+                                    self.parse_error();
+                                    token = self.t.next();
+                                    continue;
+                                }
+                                self.clear_stack_back_to(&[
+                                    "tbody", "tfoot", "thead", "template", "html",
+                                ]);
+                                self.stack_of_open_elements.pop();
+                                self.mode = InsertionMode::InTable;
+                                continue;
+                            }
+                            "body" | "caption" | "col" | "colgroup" | "html" | "td" | "th"
+                            | "tr" => {
+                                self.parse_error();
+                                token = self.t.next();
+                                continue;
+                            }
+                            _ => {}
+                        },
+                        None => return,
+                        _ => {}
+                    }
+                    using_rules = Some(InsertionMode::InTable);
+                    continue;
+                }
+                // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-intr
+                InsertionMode::InRow => {
+                    // 行を閉じるかどうかと、閉じた後にトークンを処理し直すかどうか
+                    let (closes_row, reprocess) = match token {
+                        Some(HtmlToken::StartTag {
+                            ref tag,
+                            ref attributes,
+                            ..
+                        }) => match tag.as_str() {
+                            "th" | "td" => {
+                                self.clear_stack_back_to(&["tr", "template", "html"]);
+                                self.insert_element(tag, attributes.to_vec());
+                                self.mode = InsertionMode::InCell;
+                                self.active_formatting_elements
+                                    .push(ActiveFormattingElement::Marker);
+                                token = self.t.next();
+                                continue;
+                            }
+                            "caption" | "col" | "colgroup" | "tbody" | "tfoot" | "thead" | "tr" => {
+                                (true, true)
+                            }
+                            _ => (false, false),
+                        },
+                        Some(HtmlToken::EndTag { ref tag }) => match tag.as_str() {
+                            "tr" => (true, false),
+                            "table" => (true, true),
+                            "tbody" | "tfoot" | "thead" => {
+                                if !self.has_element_in_scope(&[tag], Scope::Table) {
+                                    self.parse_error();
+                                    token = self.t.next();
+                                    continue;
+                                }
+                                (true, true)
+                            }
+                            "body" | "caption" | "col" | "colgroup" | "html" | "td" | "th" => {
+                                self.parse_error();
                                 token = self.t.next();
-                                self.pop_until(ElementKind::Head);
                                 continue;
                             }
-                        }
-                        Some(HtmlToken::Eof) | None => {
-                            return self.window.clone();
-                        }
+                            _ => (false, false),
+                        },
+                        None => return,
+                        _ => (false, false),
+                    };
+                    if !closes_row {
+                        using_rules = Some(InsertionMode::InTable);
+                        continue;
+                    }
+
+                    // <tr>を閉じて<tbody>の中に戻る
+                    if !self.has_element_in_scope(&["tr"], Scope::Table) {
+                        self.parse_error();
+                        token = self.t.next();
+                        continue;
+                    }
+                    self.clear_stack_back_to(&["tr", "template", "html"]);
+                    self.stack_of_open_elements.pop();
+                    self.mode = InsertionMode::InTableBody;
+                    if !reprocess {
+                        token = self.t.next();
                     }
-                    // サポートしていないタグは無視する
-                    token = self.t.next();
                     continue;
                 }
-                InsertionMode::AfterHead => {
+                // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-intd
+                InsertionMode::InCell => {
                     match token {
-                        Some(HtmlToken::Char(c)) => {
-                            if c == SPACE || c == LINE_FEED {
-                                self.insert_char(c);
+                        Some(HtmlToken::EndTag { ref tag }) => match tag.as_str() {
+                            "td" | "th" => {
+                                if !self.close_element_in_scope(&[tag], Scope::Table, None) {
+                                    self.parse_error();
+                                    if !self.has_element_in_scope(&[tag], Scope::Table) {
+                                        token = self.t.next();
+                                        continue;
+                                    }
+                                }
+                                self.clear_active_formatting_elements_up_to_last_marker();
+                                self.mode = InsertionMode::InRow;
                                 token = self.t.next();
                                 continue;
                             }
-                        }
-                        Some(HtmlToken::StartTag {
-                            ref tag,
-                            ref attributes,
-                            ..
-                        }) => {
-                            if tag == "body" {
-                                self.insert_element(tag, attributes.to_vec());
+                            "body" | "caption" | "col" | "colgroup" | "html" => {
+                                self.parse_error();
                                 token = self.t.next();
-                                self.mode = InsertionMode::InBody;
+                                continue;
+                            }
+                            // セルを閉じてから、行の中の規則で処理し直す
+                            "table" | "tbody" | "tfoot" | "thead" | "tr" => {
+                                if !self.has_element_in_scope(&[tag], Scope::Table) {
+                                    self.parse_error();
+                                    token = self.t.next();
+                                    continue;
+                                }
+                                self.close_cell();
+                                continue;
+                            }
+                            _ => {}
+                        },
+                        Some(HtmlToken::StartTag { ref tag, .. }) => {
+                            if matches!(
+                                tag.as_str(),
+                                "caption"
+                                    | "col"
+                                    | "colgroup"
+                                    | "tbody"
+                                    | "td"
+                                    | "tfoot"
+                                    | "th"
+                                    | "thead"
+                                    | "tr"
+                            ) {
+                                if !self.has_element_in_scope(&["td", "th"], Scope::Table) {
+                                    self.parse_error();
+                                    token = self.t.next();
+                                    continue;
+                                }
+                                self.close_cell();
                                 continue;
                             }
                         }
-                        Some(HtmlToken::Eof) | None => {
-                            return self.window.clone();
-                        }
+                        None => return,
                         _ => {}
                     }
-                    // bodyタグが存在しない場合に自動挿入
-                    self.insert_element("body", Vec::new());
-                    self.mode = InsertionMode::InBody;
+                    using_rules = Some(InsertionMode::InBody);
                     continue;
                 }
-                InsertionMode::InBody => match token {
-                    Some(HtmlToken::StartTag {
-                        ref tag,
-                        ref attributes,
-                        ..
-                    }) => match tag.as_str() {
-                        "p" => {
-                            self.insert_element(tag, attributes.to_vec());
-                            token = self.t.next();
-                            continue;
-                        }
-                        "h1" | "h2" => {
-                            self.insert_element(tag, attributes.to_vec());
-                            token = self.t.next();
-                            continue;
-                        }
-                        "a" => {
-                            self.insert_element(tag, attributes.to_vec());
-                            token = self.t.next();
-                            continue;
-                        }
-                        _ => {
-                            token = self.t.next();
-                        }
-                    },
-                    Some(HtmlToken::EndTag { ref tag }) => match tag.as_str() {
-                        "body" => {
-                            self.mode = InsertionMode::AfterBody;
-                            token = self.t.next();
-                            if !self.contains_in_stack(ElementKind::Body) {
-                                // すでにbody開始タグがあると失敗
-                                continue;
-                            }
-                            self.pop_until(ElementKind::Body);
-                            continue;
-                        }
-                        "html" => {
-                            if self.pop_current_node(ElementKind::Body) {
-                                self.mode = InsertionMode::AfterBody;
-                                assert!(self.pop_current_node(ElementKind::Html));
-                            } else {
-                                token = self.t.next();
-                            }
-                            continue;
-                        }
-                        "p" => {
-                            let element_kind = ElementKind::from_str(tag)
-                                .expect("failed to convert string to ElementKind");
-                            token = self.t.next();
-                            self.pop_until(element_kind);
-                            continue;
-                        }
-                        "h1" | "h2" => {
-                            let element_kind = ElementKind::from_str(tag)
-                                .expect("failed to convert string to ElementKind");
-                            token = self.t.next();
-                            self.pop_until(element_kind);
-                            continue;
-                        }
-                        "a" => {
-                            let element_kind = ElementKind::from_str(tag)
-                                .expect("failed to convert string to ElementKind");
+                // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-afterbody
+                InsertionMode::AfterBody => {
+                    match token {
+                        Some(HtmlToken::Comment(ref data)) => {
+                            self.insert_comment_in_html_element(data);
                             token = self.t.next();
-                            self.pop_until(element_kind);
                             continue;
                         }
-                        _ => {
-                            token = self.t.next();
-                        }
-                    },
-                    Some(HtmlToken::Eof) | None => {
-                        return self.window.clone();
-                    }
-                    Some(HtmlToken::Char(c)) => {
-                        self.insert_char(c);
-                        token = self.t.next();
-                        continue;
-                    }
-                },
-                InsertionMode::Text => match token {
-                    Some(HtmlToken::EndTag { ref tag }) => match tag.as_str() {
-                        "style" => {
-                            self.pop_until(ElementKind::Style);
-                            self.mode = self.original_insertion_mode;
+                        // DOCTYPEはInitialモードでのみ意味を持ち、それ以外では無視する
+                        Some(HtmlToken::Doctype { .. }) => {
+                            self.parse_error();
                             token = self.t.next();
                             continue;
                         }
-                        "script" => {
-                            self.pop_until(ElementKind::Script);
-                            self.mode = self.original_insertion_mode;
+                        // 空白は<body>の中の規則で処理する
+                        Some(HtmlToken::Char(c)) if c == SPACE || c == LINE_FEED => {
+                            self.reconstruct_active_formatting_elements();
+                            self.insert_char(c);
                             token = self.t.next();
                             continue;
                         }
-                        _ => {}
-                    },
-                    Some(HtmlToken::Char(c)) => {
-                        self.insert_char(c);
-                        token = self.t.next();
-                        continue;
-                    }
-                    Some(HtmlToken::Eof) | None => {
-                        return self.window.clone();
-                    }
-                    _ => {}
-                },
-                InsertionMode::AfterBody => {
-                    match token {
-                        Some(HtmlToken::Char(_c)) => {
-                            token = self.t.next();
+                        Some(HtmlToken::StartTag { ref tag, .. }) if tag == "html" => {
+                            using_rules = Some(InsertionMode::InBody);
                             continue;
                         }
                         Some(HtmlToken::EndTag { ref tag }) => {
@@ -452,28 +2527,55 @@ impl HtmlParser {
                                 continue;
                             }
                         }
-                        Some(HtmlToken::Eof) | None => {
-                            return self.window.clone();
+                        Some(HtmlToken::Eof) => {
+                            self.stopped = true;
+                            return;
                         }
+                        None => return,
                         _ => {}
                     }
                     // パースできないHTMLでもできる限りHTMLとして解釈するように
+                    self.parse_error();
                     self.mode = InsertionMode::InBody;
                 }
+                // https://html.spec.whatwg.org/multipage/parsing.html#the-after-after-body-insertion-mode
                 InsertionMode::AfterAfterBody => match token {
-                    Some(HtmlToken::Char(_c)) => {
+                    Some(HtmlToken::Comment(ref data)) => {
+                        self.insert_comment_in_document(data);
+                        token = self.t.next();
+                        continue;
+                    }
+                    // DOCTYPEはInitialモードでのみ意味を持ち、それ以外では無視する
+                    Some(HtmlToken::Doctype { .. }) => {
+                        self.parse_error();
+                        token = self.t.next();
+                        continue;
+                    }
+                    // 空白は<body>の中の規則で処理する
+                    Some(HtmlToken::Char(c)) if c == SPACE || c == LINE_FEED => {
+                        self.reconstruct_active_formatting_elements();
+                        self.insert_char(c);
                         token = self.t.next();
                         continue;
                     }
-                    Some(HtmlToken::Eof) | None => {
-                        return self.window.clone();
+                    Some(HtmlToken::StartTag { ref tag, .. }) if tag == "html" => {
+                        using_rules = Some(InsertionMode::InBody);
+                        continue;
+                    }
+                    Some(HtmlToken::Eof) => {
+                        self.stopped = true;
+                        return;
+                    }
+                    None => return,
+                    _ => {
+                        // パースできないHTMLでもできる限りHTMLとして解釈するように
+                        self.parse_error();
+                        self.mode = InsertionMode::InBody;
+                        continue;
                     }
-                    _ => {}
                 },
             }
         }
-
-        Rc::clone(&self.window)
     }
 }
 
@@ -481,6 +2583,9 @@ impl HtmlParser {
 mod tests {
     use super::*;
     use crate::alloc::string::ToString;
+    use crate::renderer::dom::api::get_target_element_node;
+    use crate::utils::convert_dom_to_string;
+    use alloc::format;
     use alloc::vec;
 
     #[test]
@@ -680,4 +2785,476 @@ mod tests {
 
         assert!(body.borrow().first_child().is_none());
     }
+
+    #[test]
+    fn test_streaming() {
+        let html = "<html><head><style>p { color: red; }</style></head><body><p><a href=\"/x\">link</a> text</p><h1>title</h1></body></html>";
+        let expected = HtmlParser::new(HtmlTokenizer::new(html.to_string())).construct_tree();
+
+        let mut parser = HtmlParser::new(HtmlTokenizer::new_streaming());
+        let (first, second) = html.split_at(html.find("text").expect("no text"));
+        parser.feed(first);
+        // 届いたところまでのDOMツリーが構築されている
+        let partial = convert_dom_to_string(&Some(parser.window().borrow().document()));
+        assert!(partial.contains("Text(\"link\")"), "{}", partial);
+        assert!(!partial.contains("H1"), "{}", partial);
+
+        for c in second.chars() {
+            parser.feed(&c.to_string());
+        }
+        let window = parser.construct_tree();
+        assert_eq!(
+            convert_dom_to_string(&Some(expected.borrow().document())),
+            convert_dom_to_string(&Some(window.borrow().document()))
+        );
+    }
+
+    #[test]
+    fn test_streaming_stops_in_raw_text() {
+        let mut parser = HtmlParser::new(HtmlTokenizer::new_streaming());
+        parser.feed("<html><head><style>p { col");
+        assert_eq!(InsertionMode::Text, parser.insertion_mode());
+        parser.feed("or: red; }</style></head>");
+        assert_eq!(InsertionMode::AfterHead, parser.insertion_mode());
+    }
+
+    #[test]
+    fn test_doctype_and_comments() {
+        let html = "<!-- before --><!DOCTYPE html><html><!-- in html --><head></head><body><p>a<!-- in p -->b</p></body></html><!-- after -->";
+        let window = HtmlParser::new(HtmlTokenizer::new(html.to_string())).construct_tree();
+        let document = window.borrow().document();
+        assert_eq!(DocumentMode::NoQuirks, document.borrow().document_mode());
+        assert_eq!(
+            r#"
+Document
+  Comment(" before ")
+  DocumentType(DocumentType { name: "html", public_id: "", system_id: "" })
+  Element(Element { kind: Html, local_name: "html", namespace: Html, attributes: [] })
+    Comment(" in html ")
+    Element(Element { kind: Head, local_name: "head", namespace: Html, attributes: [] })
+    Element(Element { kind: Body, local_name: "body", namespace: Html, attributes: [] })
+      Element(Element { kind: P, local_name: "p", namespace: Html, attributes: [] })
+        Text("a")
+        Comment(" in p ")
+        Text("b")
+  Comment(" after ")
+"#,
+            convert_dom_to_string(&Some(document))
+        );
+    }
+
+    fn document_mode_of(html: &str) -> DocumentMode {
+        let window = HtmlParser::new(HtmlTokenizer::new(html.to_string())).construct_tree();
+        let mode = window.borrow().document().borrow().document_mode();
+        mode
+    }
+
+    #[test]
+    fn test_document_mode() {
+        assert_eq!(
+            DocumentMode::NoQuirks,
+            document_mode_of("<!DOCTYPE html><p>a</p>")
+        );
+        assert_eq!(
+            DocumentMode::NoQuirks,
+            document_mode_of("<!DOCTYPE html PUBLIC \"-//W3C//DTD HTML 4.01//EN\">")
+        );
+        assert_eq!(
+            DocumentMode::NoQuirks,
+            document_mode_of("<!DOCTYPE html SYSTEM \"about:legacy-compat\">")
+        );
+        // DOCTYPEがない、または古いDOCTYPEの文書
+        assert_eq!(DocumentMode::Quirks, document_mode_of(""));
+        assert_eq!(DocumentMode::Quirks, document_mode_of("<p>a</p>"));
+        assert_eq!(DocumentMode::Quirks, document_mode_of("<!DOCTYPE>"));
+        assert_eq!(DocumentMode::Quirks, document_mode_of("<!DOCTYPE svg>"));
+        assert_eq!(
+            DocumentMode::Quirks,
+            document_mode_of("<!DOCTYPE HTML PUBLIC \"-//W3C//DTD HTML 3.2 Final//EN\">")
+        );
+        assert_eq!(
+            DocumentMode::Quirks,
+            document_mode_of("<!DOCTYPE html PUBLIC \"-//w3c//dtd html 4.01 transitional//en\">")
+        );
+        assert_eq!(
+            DocumentMode::LimitedQuirks,
+            document_mode_of("<!DOCTYPE html PUBLIC \"-//W3C//DTD HTML 4.01 Transitional//EN\" \"http://www.w3.org/TR/html4/loose.dtd\">")
+        );
+        assert_eq!(
+            DocumentMode::LimitedQuirks,
+            document_mode_of("<!DOCTYPE html PUBLIC \"-//W3C//DTD XHTML 1.0 Transitional//EN\" \"http://www.w3.org/TR/xhtml1/DTD/xhtml1-transitional.dtd\">")
+        );
+    }
+
+    #[test]
+    fn test_text_elements() {
+        let html = "<html><head><title>a &amp; <b></title><style>p > a {}</style><noscript><style>x {}</style></noscript></head><body><textarea>\n<p>&lt;</textarea><xmp><a></xmp><plaintext></plaintext>";
+        let window = HtmlParser::new(HtmlTokenizer::new(html.to_string())).construct_tree();
+        assert_eq!(
+            r#"
+Document
+  Element(Element { kind: Html, local_name: "html", namespace: Html, attributes: [] })
+    Element(Element { kind: Head, local_name: "head", namespace: Html, attributes: [] })
+      Element(Element { kind: Title, local_name: "title", namespace: Html, attributes: [] })
+        Text("a & <b>")
+      Element(Element { kind: Style, local_name: "style", namespace: Html, attributes: [] })
+        Text("p > a {}")
+      Element(Element { kind: Noscript, local_name: "noscript", namespace: Html, attributes: [] })
+        Element(Element { kind: Style, local_name: "style", namespace: Html, attributes: [] })
+          Text("x {}")
+    Element(Element { kind: Body, local_name: "body", namespace: Html, attributes: [] })
+      Element(Element { kind: Textarea, local_name: "textarea", namespace: Html, attributes: [] })
+        Text("<p><")
+      Element(Element { kind: Xmp, local_name: "xmp", namespace: Html, attributes: [] })
+        Text("<a>")
+      Element(Element { kind: Plaintext, local_name: "plaintext", namespace: Html, attributes: [] })
+        Text("</plaintext>")
+"#,
+            convert_dom_to_string(&Some(window.borrow().document()))
+        );
+    }
+
+    #[test]
+    fn test_noscript_with_scripting() {
+        let html = "<html><head></head><body><noscript><p>a</p></noscript></body></html>";
+        let window = HtmlParser::new(HtmlTokenizer::new(html.to_string()))
+            .with_scripting(true)
+            .construct_tree();
+        let dom = convert_dom_to_string(&Some(window.borrow().document()));
+        assert!(dom.contains("Text(\"<p>a</p>\")"), "{}", dom);
+
+        let window = HtmlParser::new(HtmlTokenizer::new(html.to_string())).construct_tree();
+        let dom = convert_dom_to_string(&Some(window.borrow().document()));
+        assert!(dom.contains("Text(\"a\")"), "{}", dom);
+    }
+
+    #[test]
+    fn test_unknown_elements() {
+        let html = "<html><head><meta charset=utf-8><link rel=icon></head><body><div>a <span>b</span><br>c<my-element>d</my-element></div></body></html>";
+        let window = HtmlParser::new(HtmlTokenizer::new(html.to_string())).construct_tree();
+        assert_eq!(
+            r#"
+Document
+  Element(Element { kind: Html, local_name: "html", namespace: Html, attributes: [] })
+    Element(Element { kind: Head, local_name: "head", namespace: Html, attributes: [] })
+      Element(Element { kind: Other, local_name: "meta", namespace: Html, attributes: [Attribute { name: "charset", value: "utf-8" }] })
+      Element(Element { kind: Other, local_name: "link", namespace: Html, attributes: [Attribute { name: "rel", value: "icon" }] })
+    Element(Element { kind: Body, local_name: "body", namespace: Html, attributes: [] })
+      Element(Element { kind: Other, local_name: "div", namespace: Html, attributes: [] })
+        Text("a ")
+        Element(Element { kind: Other, local_name: "span", namespace: Html, attributes: [] })
+          Text("b")
+        Element(Element { kind: Other, local_name: "br", namespace: Html, attributes: [] })
+        Text("c")
+        Element(Element { kind: Other, local_name: "my-element", namespace: Html, attributes: [] })
+          Text("d")
+"#,
+            convert_dom_to_string(&Some(window.borrow().document()))
+        );
+
+        let element = Element::new("my-element", Vec::new());
+        assert_eq!(ElementKind::Other, element.kind());
+        assert_eq!("my-element", element.local_name());
+        assert_eq!(Namespace::Html, element.namespace());
+        assert_eq!(ElementKind::P, Element::new("p", Vec::new()).kind());
+        assert_eq!(
+            ElementKind::Other,
+            Element::new_with_namespace("p", Namespace::Svg, Vec::new()).kind()
+        );
+    }
+
+    /// <body>の中に書いたHTMLのツリーを、要素の名前とテキストだけの文字列にします
+    fn body_tree(body: &str) -> String {
+        body_tree_of(&format!("<html><head></head><body>{}", body))
+    }
+
+    /// HTMLのツリーのうち<body>の中を、要素の名前とテキストだけの文字列にします
+    fn body_tree_of(html: &str) -> String {
+        let window = HtmlParser::new(HtmlTokenizer::new(html.to_string())).construct_tree();
+        let body = get_target_element_node(Some(window.borrow().document()), ElementKind::Body)
+            .expect("failed to get a body element");
+        let mut result = String::new();
+        write_tree(body.borrow().first_child(), 0, &mut result);
+        result
+    }
+
+    fn write_tree(node: Option<Rc<RefCell<Node>>>, depth: usize, result: &mut String) {
+        let mut node = node;
+        while let Some(n) = node {
+            result.push_str(&"  ".repeat(depth));
+            match n.borrow().kind() {
+                NodeKind::Element(element) => result.push_str(&element.local_name()),
+                NodeKind::Text(s) => result.push_str(&format!("{:?}", s)),
+                kind => result.push_str(&format!("{:?}", kind)),
+            }
+            result.push('\n');
+            write_tree(n.borrow().first_child(), depth + 1, result);
+            node = n.borrow().next_sibling();
+        }
+    }
+
+    #[test]
+    fn test_implied_end_tags() {
+        assert_eq!("p\n  \"one\"\np\n  \"two\"\n", body_tree("<p>one<p>two"));
+        assert_eq!(
+            "div\n  p\n    \"a\"\n\"b\"\n",
+            body_tree("<div><p>a</div>b")
+        );
+        assert_eq!(
+            "ul\n  li\n    \"a\"\n  li\n    \"b\"\n",
+            body_tree("<ul><li>a<li>b</ul>")
+        );
+        assert_eq!(
+            "dl\n  dt\n    \"a\"\n  dd\n    \"b\"\n  dt\n    \"c\"\n",
+            body_tree("<dl><dt>a<dd>b<dt>c</dl>")
+        );
+        assert_eq!(
+            "h1\n  \"a\"\nh2\n  \"b\"\n\"c\"\n",
+            body_tree("<h1>a<h2>b</h1>c")
+        );
+        // 対応する開始タグのない</p>は空の<p>になり、</div>は無視される
+        assert_eq!("\"a\"\np\n\"b\"\n", body_tree("a</p></div>b"));
+        assert_eq!("pre\n  \"x\\n\"\n", body_tree("<pre>\nx\n</pre>"));
+    }
+
+    #[test]
+    fn test_active_formatting_elements() {
+        assert_eq!("b\n  i\n", body_tree("<b><i></b></i>"));
+        assert_eq!(
+            "b\n  \"1\"\n  i\n    \"2\"\ni\n  \"3\"\n\"4\"\n",
+            body_tree("<b>1<i>2</b>3</i>4")
+        );
+        assert_eq!(
+            "p\n  b\n    \"1\"\np\n  b\n    \"2\"\n",
+            body_tree("<p><b>1<p>2</b>")
+        );
+        assert_eq!(
+            "a\n  \"x\"\na\n  \"y\"\n",
+            body_tree("<a href=1>x<a href=2>y")
+        );
+    }
+
+    #[test]
+    fn test_adoption_agency() {
+        assert_eq!(
+            "a\n  \"1\"\np\n  a\n    \"2\"\n  \"3\"\n",
+            body_tree("<a>1<p>2</a>3</p>")
+        );
+        assert_eq!(
+            "b\n  \"1\"\np\n  b\n    \"2\"\n    i\n      \"3\"\n  i\n    \"4\"\n  \"5\"\n",
+            body_tree("<b>1<p>2<i>3</b>4</i>5</p>")
+        );
+        assert_eq!(
+            "div\n  b\n    \"1\"\n  div\n    b\n      \"2\"\n    \"3\"\n",
+            body_tree("<div><b>1<div>2</b>3</div></div>")
+        );
+    }
+
+    /// 仕様に従っていない入力が見つかった位置の一覧を返します
+    fn parse_error_positions(html: &str) -> Vec<usize> {
+        let mut parser = HtmlParser::new(HtmlTokenizer::new(html.to_string()));
+        parser.construct_tree();
+        parser
+            .parse_errors()
+            .iter()
+            .map(|error| error.position())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            Vec::<usize>::new(),
+            parse_error_positions("<!DOCTYPE html><p>a<b>b</b></p>")
+        );
+        // 対応する開始タグのない終了タグ
+        assert_eq!(
+            vec![19, 23],
+            parse_error_positions("<!DOCTYPE html><p>a</a></h1>b")
+        );
+        // DOCTYPEがない
+        assert_eq!(vec![0], parse_error_positions("<p>x"));
+        // 終了タグのないまま入力が終わった
+        assert_eq!(vec![23], parse_error_positions("<!DOCTYPE html><title>x"));
+        assert_eq!(vec![21], parse_error_positions("<!DOCTYPE html><div>x"));
+
+        // <html>より前の終了タグは無視する
+        let mut parser = HtmlParser::new(HtmlTokenizer::new("</p>".to_string()));
+        parser.construct_tree();
+        assert_eq!(
+            vec![
+                ParseError::new(0, InsertionMode::Initial),
+                ParseError::new(0, InsertionMode::BeforeHtml),
+            ],
+            parser.parse_errors()
+        );
+    }
+
+    #[test]
+    fn test_malformed_documents() {
+        // <html>と<head>、<body>は省略されていても作られる
+        assert_eq!("p\n  \"a\"\n", body_tree_of("<html></html><p>a"));
+        assert_eq!("\"a\"\n", body_tree_of("a"));
+        assert_eq!(
+            "h1\n  \"x\"\np\n",
+            body_tree_of("<title>t</title><h1>x</h2></p></a></html></body>")
+        );
+        // 閉じられた<body>や<html>の後の内容も<body>の中に追加する
+        assert_eq!("p\n  \"ab\"\n", body_tree_of("<body><p>a</body></html>b"));
+        assert_eq!(
+            "div\n  \"a\"\np\n",
+            body_tree_of("<div>a</div></p c=d></dl></li></object></br x>").replace("br\n", "")
+        );
+        // 終了タグのない<title>は、入力の終わりで閉じる
+        let window =
+            HtmlParser::new(HtmlTokenizer::new("<title>a</b>".to_string())).construct_tree();
+        let title = get_target_element_node(Some(window.borrow().document()), ElementKind::Title)
+            .expect("failed to get a title element");
+        assert_eq!(
+            Some(NodeKind::Text("a</b>".to_string())),
+            title
+                .borrow()
+                .first_child()
+                .map(|node| node.borrow().kind())
+        );
+    }
+
+    #[test]
+    fn test_forms() {
+        // 開いている<form>の中の<form>は無視する
+        assert_eq!(
+            "form\n  \"a\"\n  input\n",
+            body_tree("<form>a<form><input></form></form>")
+        );
+        assert_eq!(
+            "form\n  div\n    table\n",
+            body_tree("<form><div><table><form></table>")
+        );
+        // </form>は中の要素を閉じずに<form>だけを取り除き、その後は新しい<form>を作れる
+        assert_eq!(
+            "form\n  div\n    \"ab\"\n",
+            body_tree("<form><div>a</form>b")
+        );
+        assert_eq!("form\nform\n", body_tree("<form></form><form>"));
+        // 表の中の<form>は、空の要素として表に追加する
+        assert_eq!(
+            "table\n  form\n  tbody\n    tr\n      td\n        \"a\"\n",
+            body_tree("<table><form><tr><td>a</table>")
+        );
+    }
+
+    #[test]
+    fn test_repeated_html_and_body() {
+        let window = HtmlParser::new(HtmlTokenizer::new(
+            "<html a=1><head></head><body b=1><p><html a=2 c=3><body b=2 d=4>".to_string(),
+        ))
+        .construct_tree();
+        let attributes_of = |kind| {
+            get_target_element_node(Some(window.borrow().document()), kind)
+                .and_then(|node| node.borrow().get_element())
+                .map(|element| {
+                    element
+                        .attributes()
+                        .iter()
+                        .map(|attr| format!("{}={}", attr.name(), attr.value()))
+                        .collect::<Vec<String>>()
+                })
+                .unwrap_or_default()
+        };
+        // 2つ目の<html>や<body>は、最初の要素が持っていない属性だけを追加する
+        assert_eq!(vec!["a=1", "c=3"], attributes_of(ElementKind::Html));
+        assert_eq!(vec!["b=1", "d=4"], attributes_of(ElementKind::Body));
+        assert_eq!("p\n", body_tree("<p><body x=1>"));
+    }
+
+    #[test]
+    fn test_tables() {
+        // 省略された<tbody>と<tr>、セルの終了タグは補われる
+        assert_eq!(
+            "table\n  tbody\n    tr\n      td\n        \"a\"\n      td\n        \"b\"\n",
+            body_tree("<table><td>a<td>b</table>")
+        );
+        assert_eq!(
+            "table\n  caption\n    \"c\"\n  colgroup\n    col\n  thead\n    tr\n      th\n        \"h\"\n  tbody\n    tr\n      td\n        \"d\"\n",
+            body_tree("<table><caption>c<col><thead><tr><th>h<tbody><tr><td>d</table>")
+        );
+        // 表の構造の中の空白は、表の前に移さない
+        assert_eq!(
+            "table\n  tbody\n    tr\n      td\n        \"a\"\n",
+            body_tree("<table><tbody> <tr><td>a</td></tr></tbody></table>")
+        );
+        // セルの中の表は入れ子になり、閉じた後は外側のセルに戻る
+        assert_eq!(
+            "table\n  tbody\n    tr\n      td\n        table\n          tbody\n            tr\n              td\n                \"a\"\n        \"b\"\n",
+            body_tree("<table><tr><td><table><tr><td>a</table>b</table>")
+        );
+        // <table>は開いている<p>を閉じるが、後方互換モードでは<p>の中に入れる
+        assert_eq!(
+            "p\n  \"a\"\ntable\n",
+            body_tree_of("<!doctype html><p>a<table></table>")
+        );
+        assert_eq!("p\n  \"a\"\n  table\n", body_tree("<p>a<table></table>"));
+    }
+
+    #[test]
+    fn test_foster_parenting() {
+        // 表の構造の中に置けないテキストや要素は、表の直前に移される
+        assert_eq!(
+            "\"x\"\ntable\n  tbody\n    tr\n",
+            body_tree("<table>x<tr></table>")
+        );
+        assert_eq!(
+            "b\n  \"x\"\ntable\n  tbody\n    tr\n      td\n        \"y\"\n",
+            body_tree("<table><tr><b>x</b><td>y</table>")
+        );
+        assert_eq!(
+            "div\n  p\n  table\n",
+            body_tree("<div><table><p></table></div>")
+        );
+        let mut parser = HtmlParser::new(HtmlTokenizer::new(
+            "<!doctype html><table>x</table>".to_string(),
+        ));
+        parser.construct_tree();
+        assert!(!parser.parse_errors().is_empty());
+    }
+
+    /// 文脈の要素の中で断片を解析し、作られたツリーを要素の名前とテキストだけの文字列にします
+    fn fragment_tree(context: &str, markup: &str) -> String {
+        let context = Rc::new(RefCell::new(Node::new(NodeKind::Element(Element::new(
+            context,
+            Vec::new(),
+        )))));
+        let mut result = String::new();
+        for node in HtmlParser::parse_fragment(&context, markup) {
+            assert!(node.borrow().parent().upgrade().is_none());
+            write_tree(Some(node.clone()), 0, &mut result);
+            assert!(node.borrow().next_sibling().is_none());
+        }
+        result
+    }
+
+    #[test]
+    fn test_parse_fragment() {
+        assert_eq!(
+            "p\n  \"a\"\np\n  b\n    \"b\"\n",
+            fragment_tree("div", "<p>a<p><b>b</b>")
+        );
+        assert_eq!("", fragment_tree("body", ""));
+        // <html>や<body>のタグは作られない
+        assert_eq!(
+            "\"a\"\n",
+            fragment_tree("div", "<html><body>a</body></html>")
+        );
+        // 表の中の文脈では、表の挿入モードから始まる
+        assert_eq!(
+            "tbody\n  tr\n    td\n      \"x\"\n",
+            fragment_tree("table", "<tr><td>x")
+        );
+        assert_eq!("td\n  \"x\"\ntd\n", fragment_tree("tr", "<td>x<td>"));
+        // 文脈の要素の終了タグは無視される
+        assert_eq!("\"ab\"\n", fragment_tree("td", "a</td>b"));
+        // テキストとして読む要素の中身は、タグもテキストになる
+        assert_eq!("\"<b>a</b>\"\n", fragment_tree("title", "<b>a</b>"));
+        assert_eq!("\"a</script>\"\n", fragment_tree("script", "a</script>"));
+    }
 }
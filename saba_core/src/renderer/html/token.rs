@@ -1,6 +1,11 @@
 use crate::renderer::html::attribute::Attribute;
-use alloc::string::String;
+use crate::renderer::html::character_reference::{
+    has_named_reference_with_prefix, named_reference, numeric_reference,
+};
+use alloc::collections::VecDeque;
+use alloc::string::{String, ToString};
 use alloc::vec::Vec;
+use core::mem;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum HtmlToken {
@@ -16,6 +21,16 @@ pub enum HtmlToken {
     },
     // Char
     Char(char),
+    // コメント
+    Comment(String),
+    // DOCTYPE
+    // https://html.spec.whatwg.org/multipage/parsing.html#tokenization
+    Doctype {
+        name: Option<String>,
+        public_id: Option<String>,
+        system_id: Option<String>,
+        force_quirks: bool,
+    },
     // ファイルの終了
     Eof,
 }
@@ -39,13 +54,79 @@ pub enum State {
     AttributeValueUnquoted,
     AfterAttributeValueQuoted,
     SelfClosingStartTag,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#rcdata-state
+    Rcdata,
+    RcdataLessThanSign,
+    RcdataEndTagOpen,
+    RcdataEndTagName,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#rawtext-state
+    Rawtext,
+    RawtextLessThanSign,
+    RawtextEndTagOpen,
+    RawtextEndTagName,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#script-data-state
     ScriptData,
     ScriptDataLessThanSign,
     ScriptDataEndTagOpen,
     ScriptDataEndTagName,
-    TemporaryBuffer,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#plaintext-state
+    Plaintext,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#character-reference-state
+    CharacterReference,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#named-character-reference-state
+    NamedCharacterReference,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#numeric-character-reference-state
+    NumericCharacterReference,
+    HexadecimalCharacterReferenceStart,
+    DecimalCharacterReferenceStart,
+    HexadecimalCharacterReference,
+    DecimalCharacterReference,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#markup-declaration-open-state
+    MarkupDeclarationOpen,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#comment-start-state
+    CommentStart,
+    CommentStartDash,
+    Comment,
+    CommentEndDash,
+    CommentEnd,
+    CommentEndBang,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#bogus-comment-state
+    BogusComment,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#doctype-state
+    Doctype,
+    BeforeDoctypeName,
+    DoctypeName,
+    AfterDoctypeName,
+    AfterDoctypePublicKeyword,
+    BeforeDoctypePublicIdentifier,
+    DoctypePublicIdentifierDoubleQuoted,
+    DoctypePublicIdentifierSingleQuoted,
+    AfterDoctypePublicIdentifier,
+    BetweenDoctypePublicAndSystemIdentifiers,
+    AfterDoctypeSystemKeyword,
+    BeforeDoctypeSystemIdentifier,
+    DoctypeSystemIdentifierDoubleQuoted,
+    DoctypeSystemIdentifierSingleQuoted,
+    AfterDoctypeSystemIdentifier,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#bogus-doctype-state
+    BogusDoctype,
+}
+
+/// https://infra.spec.whatwg.org/#ascii-whitespace
+fn is_whitespace(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\x0C' | '\r' | ' ')
 }
 
+/// 要素の中身として読んだNULL文字をU+FFFDに置き換えます
+fn replace_null(c: char) -> char {
+    match c {
+        '\0' => '\u{FFFD}',
+        _ => c,
+    }
+}
+
+/// HTMLのトークナイザ
+/// 入力は分割して渡すことができ、トークンの途中で入力が途切れた場合は、次の入力が渡されるまで状態を保ちます
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct HtmlTokenizer {
     state: State,
@@ -53,19 +134,85 @@ pub struct HtmlTokenizer {
     reconsume: bool,
     latest_token: Option<HtmlToken>,
     input: Vec<char>,
+    /// 入力がすべて渡されたかどうか
+    end_of_input: bool,
     buf: String,
+    /// 文字参照を読み終えた後に戻るステート
+    /// https://html.spec.whatwg.org/multipage/parsing.html#return-state
+    return_state: State,
+    /// 名前付き文字参照の名前として最も長く一致した、bufの先頭からのバイト数とその文字
+    matched_reference: Option<(usize, &'static str)>,
+    /// 数値文字参照のコードポイント
+    /// https://html.spec.whatwg.org/multipage/parsing.html#character-reference-code
+    character_reference_code: u32,
+    /// 1つの入力文字から複数のトークンが生成されたときに、まだ返していないトークン
+    pending_tokens: VecDeque<HtmlToken>,
+    /// 最後に返した開始タグの名前
+    /// https://html.spec.whatwg.org/multipage/parsing.html#appropriate-end-tag-token
+    last_start_tag: String,
+    /// feedで捨てた、読み終えた入力の文字数
+    discarded: usize,
+    /// 読んでいるトークンが始まる、入力の先頭からの文字数
+    token_start: usize,
 }
 
 impl HtmlTokenizer {
+    /// すべての入力をまとめて渡してトークナイザを作ります
     pub fn new(html: String) -> Self {
+        let mut tokenizer = Self::new_streaming();
+        tokenizer.feed(&html);
+        tokenizer.end();
+        tokenizer
+    }
+
+    /// 入力をfeedで少しずつ渡すトークナイザを作ります
+    /// 入力の終わりはendで伝えます
+    pub fn new_streaming() -> Self {
         Self {
             state: State::Data,
             pos: 0,
             reconsume: false,
             latest_token: None,
-            input: html.chars().collect(),
+            input: Vec::new(),
+            end_of_input: false,
             buf: String::new(),
+            return_state: State::Data,
+            matched_reference: None,
+            character_reference_code: 0,
+            pending_tokens: VecDeque::new(),
+            last_start_tag: String::new(),
+            discarded: 0,
+            token_start: 0,
+        }
+    }
+
+    /// 入力の続きを追加します
+    pub fn feed(&mut self, input: &str) {
+        // 読み終えた入力は捨てる。ただし再消費に備えて最後に読んだ1文字は残す
+        if self.pos > 1 {
+            self.input.drain(..self.pos - 1);
+            self.discarded += self.pos - 1;
+            self.pos = 1;
         }
+        self.input.extend(input.chars());
+    }
+
+    /// 入力がすべて渡されたことを伝えます
+    pub fn end(&mut self) {
+        self.end_of_input = true;
+    }
+
+    /// 最後に返したトークンが始まる、入力の先頭からの文字数を返します
+    /// 1つの文字から複数のトークンが生成されたときは、どれもその文字の位置になります
+    pub fn token_position(&self) -> usize {
+        self.token_start
+    }
+
+    /// トークナイザのステートを切り替えます
+    /// ツリー構築の段階で、<title>や<style>などの要素の中身を読むステートに切り替えるために使います
+    /// https://html.spec.whatwg.org/multipage/parsing.html#parsing-elements-that-contain-only-text
+    pub fn switch_to(&mut self, state: State) {
+        self.state = state;
     }
 
     fn is_eof(&self) -> bool {
@@ -115,9 +262,61 @@ impl HtmlTokenizer {
         self.latest_token = None;
         assert!(self.latest_token.is_none());
 
+        if let Some(HtmlToken::StartTag { ref tag, .. }) = t {
+            self.last_start_tag = tag.clone();
+        }
         t
     }
 
+    /// 終了タグが、最後に返した開始タグと同じ名前かどうか
+    /// https://html.spec.whatwg.org/multipage/parsing.html#appropriate-end-tag-token
+    fn is_appropriate_end_tag(&self) -> bool {
+        match self.latest_token {
+            Some(HtmlToken::EndTag { ref tag }) => {
+                !self.last_start_tag.is_empty() && *tag == self.last_start_tag
+            }
+            _ => false,
+        }
+    }
+
+    /// RCDATA・RAWTEXT・スクリプトデータのうち、現在のステートが属するものの
+    /// 要素の中身、終了タグの開始、終了タグ名のステートを返します
+    fn text_states(&self) -> (State, State, State) {
+        match self.state {
+            State::Rcdata
+            | State::RcdataLessThanSign
+            | State::RcdataEndTagOpen
+            | State::RcdataEndTagName => (
+                State::Rcdata,
+                State::RcdataEndTagOpen,
+                State::RcdataEndTagName,
+            ),
+            State::Rawtext
+            | State::RawtextLessThanSign
+            | State::RawtextEndTagOpen
+            | State::RawtextEndTagName => (
+                State::Rawtext,
+                State::RawtextEndTagOpen,
+                State::RawtextEndTagName,
+            ),
+            _ => (
+                State::ScriptData,
+                State::ScriptDataEndTagOpen,
+                State::ScriptDataEndTagName,
+            ),
+        }
+    }
+
+    /// 終了タグになれなかった"</"と読んだタグ名を文字トークンとして返します
+    fn flush_end_tag_as_text(&mut self) -> Option<HtmlToken> {
+        self.latest_token = None;
+        self.pending_tokens.push_back(HtmlToken::Char('/'));
+        for c in mem::take(&mut self.buf).chars() {
+            self.pending_tokens.push_back(HtmlToken::Char(c));
+        }
+        Some(HtmlToken::Char('<'))
+    }
+
     fn start_new_attribute(&mut self) {
         assert!(self.latest_token.is_some());
 
@@ -126,7 +325,9 @@ impl HtmlTokenizer {
                 HtmlToken::StartTag {
                     ref mut attributes, ..
                 } => attributes.push(Attribute::new()),
-                _ => panic!("`latest_token` should be either StartTag"),
+                // 終了タグの属性は意味を持たないので読み捨てる
+                HtmlToken::EndTag { .. } => {}
+                _ => panic!("`latest_token` should be either StartTag or EndTag"),
             }
         }
     }
@@ -143,7 +344,8 @@ impl HtmlTokenizer {
                     assert!(len > 0);
                     attributes[len - 1].add_char(c, is_name);
                 }
-                _ => panic!("`latest_token` should be either StartTag"),
+                HtmlToken::EndTag { .. } => {}
+                _ => panic!("`latest_token` should be either StartTag or EndTag"),
             }
         }
     }
@@ -157,27 +359,321 @@ impl HtmlTokenizer {
                     ref mut self_closing,
                     ..
                 } => *self_closing = true,
-                _ => panic!("`latest_token` should be either StartTag"),
+                // 終了タグのself-closingフラグは意味を持たないので無視する
+                HtmlToken::EndTag { .. } => {}
+                _ => panic!("`latest_token` should be either StartTag or EndTag"),
+            }
+        }
+    }
+
+    fn create_comment(&mut self) {
+        self.latest_token = Some(HtmlToken::Comment(String::new()));
+    }
+
+    fn append_comment(&mut self, s: &str) {
+        assert!(self.latest_token.is_some());
+
+        if let Some(t) = self.latest_token.as_mut() {
+            match t {
+                HtmlToken::Comment(ref mut data) => data.push_str(s),
+                _ => panic!("`latest_token` should be Comment"),
+            }
+        }
+    }
+
+    fn create_doctype(&mut self) {
+        self.latest_token = Some(HtmlToken::Doctype {
+            name: None,
+            public_id: None,
+            system_id: None,
+            force_quirks: false,
+        });
+    }
+
+    fn append_doctype_name(&mut self, c: char) {
+        assert!(self.latest_token.is_some());
+
+        if let Some(t) = self.latest_token.as_mut() {
+            match t {
+                HtmlToken::Doctype { ref mut name, .. } => {
+                    name.get_or_insert_with(String::new).push(c)
+                }
+                _ => panic!("`latest_token` should be Doctype"),
+            }
+        }
+    }
+
+    /// 公開識別子かシステム識別子を空文字列にします
+    fn start_doctype_identifier(&mut self, is_public: bool) {
+        assert!(self.latest_token.is_some());
+
+        if let Some(t) = self.latest_token.as_mut() {
+            match t {
+                HtmlToken::Doctype {
+                    ref mut public_id,
+                    ref mut system_id,
+                    ..
+                } => match is_public {
+                    true => *public_id = Some(String::new()),
+                    false => *system_id = Some(String::new()),
+                },
+                _ => panic!("`latest_token` should be Doctype"),
+            }
+        }
+    }
+
+    fn append_doctype_identifier(&mut self, c: char, is_public: bool) {
+        assert!(self.latest_token.is_some());
+
+        if let Some(t) = self.latest_token.as_mut() {
+            match t {
+                HtmlToken::Doctype {
+                    ref mut public_id,
+                    ref mut system_id,
+                    ..
+                } => {
+                    let id = match is_public {
+                        true => public_id,
+                        false => system_id,
+                    };
+                    id.get_or_insert_with(String::new).push(c);
+                }
+                _ => panic!("`latest_token` should be Doctype"),
+            }
+        }
+    }
+
+    fn set_force_quirks_flag(&mut self) {
+        assert!(self.latest_token.is_some());
+
+        if let Some(t) = self.latest_token.as_mut() {
+            match t {
+                HtmlToken::Doctype {
+                    ref mut force_quirks,
+                    ..
+                } => *force_quirks = true,
+                _ => panic!("`latest_token` should be Doctype"),
+            }
+        }
+    }
+
+    /// 直前に読んだ文字から始まる入力が、ASCIIの大文字と小文字を区別せずにsと一致するかを返します
+    /// 判断できるだけの入力がまだ届いていなければNoneを返します
+    fn lookahead(&self, s: &str) -> Option<bool> {
+        let start = self.pos - 1;
+        for (i, expected) in s.chars().enumerate() {
+            match self.input.get(start + i) {
+                Some(c) if c.eq_ignore_ascii_case(&expected) => continue,
+                Some(_) => return Some(false),
+                None if self.end_of_input => return Some(false),
+                None => return None,
+            }
+        }
+        Some(true)
+    }
+
+    /// 入力の終わりに達したときに、それまでの入力から決まるトークンを返します
+    /// 入力が途切れた時点では'<'の後に続くのがタグかどうかを判断できないため、入力の終わりで文字として扱う
+    /// https://html.spec.whatwg.org/multipage/parsing.html#tag-open-state
+    fn end_of_file(&mut self) -> Option<HtmlToken> {
+        match self.state {
+            State::TagOpen => {
+                self.state = State::Data;
+                Some(HtmlToken::Char('<'))
+            }
+            State::RcdataLessThanSign
+            | State::RawtextLessThanSign
+            | State::ScriptDataLessThanSign => {
+                self.state = self.text_states().0;
+                Some(HtmlToken::Char('<'))
+            }
+            State::RcdataEndTagOpen | State::RawtextEndTagOpen | State::ScriptDataEndTagOpen => {
+                self.state = self.text_states().0;
+                self.pending_tokens.push_back(HtmlToken::Char('/'));
+                Some(HtmlToken::Char('<'))
+            }
+            State::RcdataEndTagName | State::RawtextEndTagName | State::ScriptDataEndTagName => {
+                self.state = self.text_states().0;
+                self.flush_end_tag_as_text()
+            }
+            State::CharacterReference
+            | State::NumericCharacterReference
+            | State::HexadecimalCharacterReferenceStart
+            | State::DecimalCharacterReferenceStart => {
+                self.flush_temporary_buffer();
+                self.state = self.return_state.clone();
+                self.pending_tokens.pop_front()
+            }
+            State::NamedCharacterReference => {
+                self.finish_named_character_reference(None);
+                self.pending_tokens.pop_front()
+            }
+            State::HexadecimalCharacterReference | State::DecimalCharacterReference => {
+                self.finish_numeric_character_reference();
+                self.pending_tokens.pop_front()
+            }
+            // "<!"で入力が終わった場合は空のコメントになる
+            State::MarkupDeclarationOpen => {
+                self.state = State::Data;
+                Some(HtmlToken::Comment(String::new()))
+            }
+            State::CommentStart
+            | State::CommentStartDash
+            | State::Comment
+            | State::CommentEndDash
+            | State::CommentEnd
+            | State::CommentEndBang
+            | State::BogusComment
+            | State::BogusDoctype => {
+                self.state = State::Data;
+                self.take_latest_token()
+            }
+            State::Doctype | State::BeforeDoctypeName => {
+                self.state = State::Data;
+                self.create_doctype();
+                self.set_force_quirks_flag();
+                self.take_latest_token()
+            }
+            State::DoctypeName
+            | State::AfterDoctypeName
+            | State::AfterDoctypePublicKeyword
+            | State::BeforeDoctypePublicIdentifier
+            | State::DoctypePublicIdentifierDoubleQuoted
+            | State::DoctypePublicIdentifierSingleQuoted
+            | State::AfterDoctypePublicIdentifier
+            | State::BetweenDoctypePublicAndSystemIdentifiers
+            | State::AfterDoctypeSystemKeyword
+            | State::BeforeDoctypeSystemIdentifier
+            | State::DoctypeSystemIdentifierDoubleQuoted
+            | State::DoctypeSystemIdentifierSingleQuoted
+            | State::AfterDoctypeSystemIdentifier => {
+                self.state = State::Data;
+                self.set_force_quirks_flag();
+                self.take_latest_token()
+            }
+            _ => None,
+        }
+    }
+
+    /// 文字参照の後に属性値に戻るかどうか
+    /// https://html.spec.whatwg.org/multipage/parsing.html#charref-in-attribute
+    fn is_character_reference_in_attribute(&self) -> bool {
+        matches!(
+            self.return_state,
+            State::AttributeValueDoubleQuoted
+                | State::AttributeValueSingleQuoted
+                | State::AttributeValueUnquoted
+        )
+    }
+
+    /// 文字参照として読んだ文字を、属性値に追加するか文字トークンとして返します
+    /// https://html.spec.whatwg.org/multipage/parsing.html#flush-code-points-consumed-as-a-character-reference
+    fn flush_code_points(&mut self, s: &str) {
+        for c in s.chars() {
+            if self.is_character_reference_in_attribute() {
+                self.append_attribute(c, false);
+            } else {
+                self.pending_tokens.push_back(HtmlToken::Char(c));
+            }
+        }
+    }
+
+    /// 文字参照にならなかった一時バッファの文字を、そのままの文字として扱います
+    fn flush_temporary_buffer(&mut self) {
+        let buf = mem::take(&mut self.buf);
+        self.flush_code_points(&buf);
+    }
+
+    /// 一時バッファの中で最も長く一致した名前付き文字参照を文字に置き換えます
+    /// nextは一時バッファの後に続く文字で、入力の終わりではNoneです
+    /// https://html.spec.whatwg.org/multipage/parsing.html#named-character-reference-state
+    fn finish_named_character_reference(&mut self, next: Option<char>) {
+        self.state = self.return_state.clone();
+        let (len, value) = match self.matched_reference.take() {
+            Some(matched) => matched,
+            None => {
+                // 一致する名前がなければ文字のまま扱う。続く英数字も同じく文字になる
+                // https://html.spec.whatwg.org/multipage/parsing.html#ambiguous-ampersand-state
+                self.flush_temporary_buffer();
+                return;
             }
+        };
+
+        // 一致した名前より後に読んだ文字は、文字参照の一部ではない
+        let rest = self.buf.split_off(len);
+        let next = rest.chars().next().or(next);
+        if self.is_character_reference_in_attribute()
+            && !self.buf.ends_with(';')
+            && next.is_some_and(|c| c == '=' || c.is_ascii_alphanumeric())
+        {
+            // 歴史的な理由で、属性値の中の"&copy=1"のような文字列は文字参照として扱わない
+            self.buf.push_str(&rest);
+            self.flush_temporary_buffer();
+            return;
         }
+
+        self.buf = String::new();
+        self.flush_code_points(value);
+        self.flush_code_points(&rest);
+    }
+
+    /// 数値文字参照を文字に置き換えます
+    /// https://html.spec.whatwg.org/multipage/parsing.html#numeric-character-reference-end-state
+    fn finish_numeric_character_reference(&mut self) {
+        self.state = self.return_state.clone();
+        self.buf = String::new();
+        let c = numeric_reference(self.character_reference_code);
+        self.flush_code_points(&c.to_string());
+    }
+
+    /// 文字参照の読み始めの'&'を一時バッファに入れ、文字参照を読むステートに移ります
+    fn start_character_reference(&mut self, return_state: State) {
+        self.return_state = return_state;
+        self.buf = String::from("&");
+        self.state = State::CharacterReference;
     }
 }
 
 impl Iterator for HtmlTokenizer {
     type Item = HtmlToken;
 
+    /// 次のトークンを返します
+    /// 入力を読み終えるとNoneを返します。入力の終わりが伝えられていなければ、feedで入力を追加すると続きを読めます
     fn next(&mut self) -> Option<Self::Item> {
-        if self.pos >= self.input.len() {
-            return None;
+        if let Some(token) = self.pending_tokens.pop_front() {
+            return Some(token);
         }
 
         loop {
             let c = match self.reconsume {
                 true => self.reconsume_input(),
+                // 入力の終わりが伝えられるまでは、続きが届いてから読む
+                false if self.pos >= self.input.len() => match self.end_of_input {
+                    true => {
+                        let token = self.end_of_file();
+                        if matches!(token, None | Some(HtmlToken::Eof)) {
+                            self.token_start = self.discarded + self.input.len();
+                        }
+                        return token;
+                    }
+                    false => return None,
+                },
                 false => self.consume_next_input(),
             };
+            // トークンはすべて、文字を読むステートから始まる
+            if matches!(
+                self.state,
+                State::Data | State::Rcdata | State::Rawtext | State::ScriptData | State::Plaintext
+            ) {
+                self.token_start = self.discarded + self.pos - 1;
+            }
             match self.state {
                 State::Data => {
+                    if c == '&' {
+                        self.start_character_reference(State::Data);
+                        continue;
+                    }
+
                     if c == '<' {
                         self.state = State::TagOpen;
                         continue;
@@ -196,6 +692,20 @@ impl Iterator for HtmlTokenizer {
                         continue;
                     }
 
+                    if c == '!' {
+                        // <!-- -->や<!DOCTYPE>のような場合にMarkupDeclarationOpenへ遷移
+                        self.state = State::MarkupDeclarationOpen;
+                        continue;
+                    }
+
+                    if c == '?' {
+                        // <?xml ?>のような処理命令はコメントとして扱う
+                        self.create_comment();
+                        self.reconsume = true;
+                        self.state = State::BogusComment;
+                        continue;
+                    }
+
                     if c.is_ascii_alphabetic() {
                         self.reconsume = true;
                         self.state = State::TagName;
@@ -223,6 +733,17 @@ impl Iterator for HtmlTokenizer {
                         self.create_tag(false);
                         continue;
                     }
+
+                    // "</>"は何も生成せずに読み飛ばす
+                    if c == '>' {
+                        self.state = State::Data;
+                        continue;
+                    }
+
+                    // "</3>"のようなタグ名でない文字の並びはコメントとして扱う
+                    self.create_comment();
+                    self.reconsume = true;
+                    self.state = State::BogusComment;
                 }
                 State::TagName => {
                     if c == ' ' {
@@ -337,6 +858,11 @@ impl Iterator for HtmlTokenizer {
                         continue;
                     }
 
+                    if c == '&' {
+                        self.start_character_reference(State::AttributeValueDoubleQuoted);
+                        continue;
+                    }
+
                     if self.is_eof() {
                         return Some(HtmlToken::Eof);
                     }
@@ -349,6 +875,11 @@ impl Iterator for HtmlTokenizer {
                         continue;
                     }
 
+                    if c == '&' {
+                        self.start_character_reference(State::AttributeValueSingleQuoted);
+                        continue;
+                    }
+
                     if self.is_eof() {
                         return Some(HtmlToken::Eof);
                     }
@@ -366,6 +897,11 @@ impl Iterator for HtmlTokenizer {
                         return self.take_latest_token();
                     }
 
+                    if c == '&' {
+                        self.start_character_reference(State::AttributeValueUnquoted);
+                        continue;
+                    }
+
                     if self.is_eof() {
                         return Some(HtmlToken::Eof);
                     }
@@ -406,45 +942,85 @@ impl Iterator for HtmlTokenizer {
                         return Some(HtmlToken::Eof);
                     }
                 }
-                State::ScriptData => {
+                State::Rcdata => {
+                    if c == '&' {
+                        self.start_character_reference(State::Rcdata);
+                        continue;
+                    }
+
                     if c == '<' {
-                        self.state = State::ScriptDataLessThanSign;
+                        self.state = State::RcdataLessThanSign;
                         continue;
                     }
 
-                    if self.is_eof() {
-                        return Some(HtmlToken::Eof);
+                    return Some(HtmlToken::Char(replace_null(c)));
+                }
+                State::Rawtext => {
+                    if c == '<' {
+                        self.state = State::RawtextLessThanSign;
+                        continue;
                     }
 
-                    return Some(HtmlToken::Char(c));
+                    return Some(HtmlToken::Char(replace_null(c)));
+                }
+                State::ScriptData => {
+                    if c == '<' {
+                        self.state = State::ScriptDataLessThanSign;
+                        continue;
+                    }
+
+                    return Some(HtmlToken::Char(replace_null(c)));
+                }
+                State::Plaintext => {
+                    return Some(HtmlToken::Char(replace_null(c)));
                 }
-                // <script>タグの中で'<'が出現したときに終了タグなのかを判断するステート
-                State::ScriptDataLessThanSign => {
+                // <title>や<style>、<script>の中で'<'が出現したときに終了タグなのかを判断するステート
+                State::RcdataLessThanSign
+                | State::RawtextLessThanSign
+                | State::ScriptDataLessThanSign => {
+                    let (text, end_tag_open, _) = self.text_states();
                     if c == '/' {
                         self.buf = String::new();
-                        self.state = State::ScriptDataEndTagOpen;
+                        self.state = end_tag_open;
                         continue;
                     }
 
                     self.reconsume = true;
-                    self.state = State::ScriptData
+                    self.state = text;
+                    return Some(HtmlToken::Char('<'));
                 }
-                State::ScriptDataEndTagOpen => {
+                State::RcdataEndTagOpen
+                | State::RawtextEndTagOpen
+                | State::ScriptDataEndTagOpen => {
+                    let (text, _, end_tag_name) = self.text_states();
                     if c.is_ascii_alphabetic() {
                         self.reconsume = true;
-                        self.state = State::ScriptDataEndTagName;
+                        self.state = end_tag_name;
                         self.create_tag(false);
                         continue;
                     }
 
                     self.reconsume = true;
-                    self.state = State::ScriptData;
-                    // 仕様上 '<'と'/' のトークンを返すことになっているが、今回の実装では'<'のみを返す
-                    // https://html.spec.whatwg.org/multipage/parsing.html#script-data-end-tag-name-state
+                    self.state = text;
+                    self.pending_tokens.push_back(HtmlToken::Char('/'));
                     return Some(HtmlToken::Char('<'));
                 }
-                State::ScriptDataEndTagName => {
-                    if c == '>' {
+                State::RcdataEndTagName
+                | State::RawtextEndTagName
+                | State::ScriptDataEndTagName => {
+                    // 要素を開いた開始タグと同じ名前の終了タグだけが、要素の中身を終わらせる
+                    let is_appropriate = self.is_appropriate_end_tag();
+                    if is_whitespace(c) && is_appropriate {
+                        self.state = State::BeforeAttributeName;
+                        continue;
+                    }
+
+                    if c == '/' && is_appropriate {
+                        self.state = State::SelfClosingStartTag;
+                        continue;
+                    }
+
+                    if c == '>' && is_appropriate {
                         self.state = State::Data;
                         return self.take_latest_token();
                     }
@@ -455,26 +1031,422 @@ impl Iterator for HtmlTokenizer {
                         continue;
                     }
 
-                    self.state = State::TemporaryBuffer;
-                    self.buf = String::from("</") + &self.buf;
-                    self.buf.push(c);
-                    continue;
+                    // 終了タグでなければ、読んだ文字をそのまま文字として扱う
+                    let (text, _, _) = self.text_states();
+                    self.reconsume = true;
+                    self.state = text;
+                    return self.flush_end_tag_as_text();
+                }
+                State::CharacterReference => {
+                    if c.is_ascii_alphanumeric() {
+                        self.reconsume = true;
+                        self.matched_reference = None;
+                        self.state = State::NamedCharacterReference;
+                        continue;
+                    }
+
+                    if c == '#' {
+                        self.buf.push(c);
+                        self.state = State::NumericCharacterReference;
+                        continue;
+                    }
+
+                    // "& "のように文字参照でない'&'は文字として扱う
+                    self.flush_temporary_buffer();
+                    self.reconsume = true;
+                    self.state = self.return_state.clone();
+                    if let Some(token) = self.pending_tokens.pop_front() {
+                        return Some(token);
+                    }
+                }
+                State::NamedCharacterReference => {
+                    // 名前付き文字参照の名前の先頭と一致する限り読み進め、最も長く一致した名前を覚えておく
+                    let mut name = self.buf[1..].to_string();
+                    name.push(c);
+                    if has_named_reference_with_prefix(&name) {
+                        self.buf.push(c);
+                        if let Some(value) = named_reference(&name) {
+                            self.matched_reference = Some((self.buf.len(), value));
+                        }
+                        continue;
+                    }
+
+                    self.reconsume = true;
+                    self.finish_named_character_reference(Some(c));
+                    if let Some(token) = self.pending_tokens.pop_front() {
+                        return Some(token);
+                    }
                 }
-                State::TemporaryBuffer => {
+                State::NumericCharacterReference => {
+                    self.character_reference_code = 0;
+
+                    if c == 'x' || c == 'X' {
+                        self.buf.push(c);
+                        self.state = State::HexadecimalCharacterReferenceStart;
+                        continue;
+                    }
+
                     self.reconsume = true;
+                    self.state = State::DecimalCharacterReferenceStart;
+                }
+                State::HexadecimalCharacterReferenceStart
+                | State::DecimalCharacterReferenceStart => {
+                    let is_hex = self.state == State::HexadecimalCharacterReferenceStart;
+                    if (is_hex && c.is_ascii_hexdigit()) || (!is_hex && c.is_ascii_digit()) {
+                        self.reconsume = true;
+                        self.state = match is_hex {
+                            true => State::HexadecimalCharacterReference,
+                            false => State::DecimalCharacterReference,
+                        };
+                        continue;
+                    }
 
-                    if self.buf.chars().count() == 0 {
-                        self.state = State::ScriptData;
+                    // "&#"や"&#x"の後に数字がなければ、そのまま文字として扱う
+                    self.flush_temporary_buffer();
+                    self.reconsume = true;
+                    self.state = self.return_state.clone();
+                    if let Some(token) = self.pending_tokens.pop_front() {
+                        return Some(token);
+                    }
+                }
+                State::HexadecimalCharacterReference | State::DecimalCharacterReference => {
+                    let radix = match self.state {
+                        State::HexadecimalCharacterReference => 16,
+                        _ => 10,
+                    };
+                    if let Some(digit) = c.to_digit(radix) {
+                        // 範囲外のコードポイントはどれもU+FFFDになるので、溢れないように上限で止める
+                        self.character_reference_code = self
+                            .character_reference_code
+                            .saturating_mul(radix)
+                            .saturating_add(digit);
                         continue;
                     }
 
-                    let c = self
-                        .buf
-                        .chars()
-                        .nth(0)
-                        .expect("self.buf should have at least 1 char");
-                    self.buf.remove(0);
-                    return Some(HtmlToken::Char(c));
+                    // セミコロンで終わっていなければ、その文字は文字参照の後の文字として読み直す
+                    if c != ';' {
+                        self.reconsume = true;
+                    }
+                    self.finish_numeric_character_reference();
+                    if let Some(token) = self.pending_tokens.pop_front() {
+                        return Some(token);
+                    }
+                }
+                State::MarkupDeclarationOpen => {
+                    let (comment, doctype) = (self.lookahead("--"), self.lookahead("DOCTYPE"));
+                    if comment == Some(true) {
+                        self.pos += "--".len() - 1;
+                        self.create_comment();
+                        self.state = State::CommentStart;
+                        continue;
+                    }
+
+                    if doctype == Some(true) {
+                        self.pos += "DOCTYPE".len() - 1;
+                        self.state = State::Doctype;
+                        continue;
+                    }
+
+                    if comment.is_none() || doctype.is_none() {
+                        // 一致するかを判断できるだけの入力が届くまで待つ
+                        self.reconsume = true;
+                        return None;
+                    }
+
+                    // "<![CDATA["などはコメントとして扱う
+                    self.create_comment();
+                    self.reconsume = true;
+                    self.state = State::BogusComment;
+                }
+                State::CommentStart => {
+                    if c == '-' {
+                        self.state = State::CommentStartDash;
+                        continue;
+                    }
+
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+
+                    self.reconsume = true;
+                    self.state = State::Comment;
+                }
+                State::CommentStartDash => {
+                    if c == '-' {
+                        self.state = State::CommentEnd;
+                        continue;
+                    }
+
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+
+                    self.append_comment("-");
+                    self.reconsume = true;
+                    self.state = State::Comment;
+                }
+                State::Comment => {
+                    if c == '-' {
+                        self.state = State::CommentEndDash;
+                        continue;
+                    }
+
+                    if c == '\0' {
+                        self.append_comment("\u{FFFD}");
+                        continue;
+                    }
+
+                    self.append_comment(c.encode_utf8(&mut [0; 4]));
+                }
+                State::CommentEndDash => {
+                    if c == '-' {
+                        self.state = State::CommentEnd;
+                        continue;
+                    }
+
+                    self.append_comment("-");
+                    self.reconsume = true;
+                    self.state = State::Comment;
+                }
+                State::CommentEnd => {
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+
+                    if c == '!' {
+                        self.state = State::CommentEndBang;
+                        continue;
+                    }
+
+                    if c == '-' {
+                        self.append_comment("-");
+                        continue;
+                    }
+
+                    self.append_comment("--");
+                    self.reconsume = true;
+                    self.state = State::Comment;
+                }
+                State::CommentEndBang => {
+                    if c == '-' {
+                        self.append_comment("--!");
+                        self.state = State::CommentEndDash;
+                        continue;
+                    }
+
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+
+                    self.append_comment("--!");
+                    self.reconsume = true;
+                    self.state = State::Comment;
+                }
+                State::BogusComment => {
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+
+                    if c == '\0' {
+                        self.append_comment("\u{FFFD}");
+                        continue;
+                    }
+
+                    self.append_comment(c.encode_utf8(&mut [0; 4]));
+                }
+                State::Doctype => {
+                    if !is_whitespace(c) {
+                        self.reconsume = true;
+                    }
+                    self.state = State::BeforeDoctypeName;
+                }
+                State::BeforeDoctypeName => {
+                    if is_whitespace(c) {
+                        continue;
+                    }
+
+                    self.create_doctype();
+                    if c == '>' {
+                        self.set_force_quirks_flag();
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+
+                    self.reconsume = true;
+                    self.state = State::DoctypeName;
+                }
+                State::DoctypeName => {
+                    if is_whitespace(c) {
+                        self.state = State::AfterDoctypeName;
+                        continue;
+                    }
+
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+
+                    if c == '\0' {
+                        self.append_doctype_name('\u{FFFD}');
+                        continue;
+                    }
+
+                    self.append_doctype_name(c.to_ascii_lowercase());
+                }
+                State::AfterDoctypeName => {
+                    if is_whitespace(c) {
+                        continue;
+                    }
+
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+
+                    let (public, system) = (self.lookahead("PUBLIC"), self.lookahead("SYSTEM"));
+                    if public.is_none() || system.is_none() {
+                        // 一致するかを判断できるだけの入力が届くまで待つ
+                        self.reconsume = true;
+                        return None;
+                    }
+
+                    if public == Some(true) {
+                        self.pos += "PUBLIC".len() - 1;
+                        self.state = State::AfterDoctypePublicKeyword;
+                        continue;
+                    }
+
+                    if system == Some(true) {
+                        self.pos += "SYSTEM".len() - 1;
+                        self.state = State::AfterDoctypeSystemKeyword;
+                        continue;
+                    }
+
+                    self.set_force_quirks_flag();
+                    self.reconsume = true;
+                    self.state = State::BogusDoctype;
+                }
+                State::AfterDoctypePublicKeyword
+                | State::BeforeDoctypePublicIdentifier
+                | State::AfterDoctypeSystemKeyword
+                | State::BeforeDoctypeSystemIdentifier => {
+                    let is_public = matches!(
+                        self.state,
+                        State::AfterDoctypePublicKeyword | State::BeforeDoctypePublicIdentifier
+                    );
+                    if is_whitespace(c) {
+                        self.state = match self.state {
+                            State::AfterDoctypePublicKeyword => {
+                                State::BeforeDoctypePublicIdentifier
+                            }
+                            State::AfterDoctypeSystemKeyword => {
+                                State::BeforeDoctypeSystemIdentifier
+                            }
+                            _ => self.state.clone(),
+                        };
+                        continue;
+                    }
+
+                    if c == '"' || c == '\'' {
+                        self.start_doctype_identifier(is_public);
+                        self.state = match (is_public, c == '"') {
+                            (true, true) => State::DoctypePublicIdentifierDoubleQuoted,
+                            (true, false) => State::DoctypePublicIdentifierSingleQuoted,
+                            (false, true) => State::DoctypeSystemIdentifierDoubleQuoted,
+                            (false, false) => State::DoctypeSystemIdentifierSingleQuoted,
+                        };
+                        continue;
+                    }
+
+                    self.set_force_quirks_flag();
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+
+                    self.reconsume = true;
+                    self.state = State::BogusDoctype;
+                }
+                State::DoctypePublicIdentifierDoubleQuoted
+                | State::DoctypePublicIdentifierSingleQuoted
+                | State::DoctypeSystemIdentifierDoubleQuoted
+                | State::DoctypeSystemIdentifierSingleQuoted => {
+                    let (is_public, quote) = match self.state {
+                        State::DoctypePublicIdentifierDoubleQuoted => (true, '"'),
+                        State::DoctypePublicIdentifierSingleQuoted => (true, '\''),
+                        State::DoctypeSystemIdentifierDoubleQuoted => (false, '"'),
+                        _ => (false, '\''),
+                    };
+                    if c == quote {
+                        self.state = match is_public {
+                            true => State::AfterDoctypePublicIdentifier,
+                            false => State::AfterDoctypeSystemIdentifier,
+                        };
+                        continue;
+                    }
+
+                    if c == '>' {
+                        self.set_force_quirks_flag();
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+
+                    if c == '\0' {
+                        self.append_doctype_identifier('\u{FFFD}', is_public);
+                        continue;
+                    }
+
+                    self.append_doctype_identifier(c, is_public);
+                }
+                State::AfterDoctypePublicIdentifier
+                | State::BetweenDoctypePublicAndSystemIdentifiers => {
+                    if is_whitespace(c) {
+                        self.state = State::BetweenDoctypePublicAndSystemIdentifiers;
+                        continue;
+                    }
+
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+
+                    if c == '"' || c == '\'' {
+                        self.start_doctype_identifier(false);
+                        self.state = match c {
+                            '"' => State::DoctypeSystemIdentifierDoubleQuoted,
+                            _ => State::DoctypeSystemIdentifierSingleQuoted,
+                        };
+                        continue;
+                    }
+
+                    self.set_force_quirks_flag();
+                    self.reconsume = true;
+                    self.state = State::BogusDoctype;
+                }
+                State::AfterDoctypeSystemIdentifier => {
+                    if is_whitespace(c) {
+                        continue;
+                    }
+
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+
+                    // 識別子の後の余分な文字は無視する。この場合はquirksモードにしない
+                    self.reconsume = true;
+                    self.state = State::BogusDoctype;
+                }
+                State::BogusDoctype => {
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
                 }
             }
         }
@@ -595,6 +1567,275 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_streaming() {
+        let html = "<p class=\"A\">text</p>a<";
+        let expected: Vec<HtmlToken> = HtmlTokenizer::new(html.to_string()).collect();
+
+        // 1文字ずつ渡しても、まとめて渡したときと同じトークンになる
+        let mut tokenizer = HtmlTokenizer::new_streaming();
+        let mut tokens = Vec::new();
+        for c in html.chars() {
+            tokenizer.feed(&c.to_string());
+            tokens.extend(&mut tokenizer);
+        }
+        // 最後の'<'はタグの始まりかもしれないため、入力の終わりが伝えられるまで返さない
+        assert_eq!(Some(&HtmlToken::Char('a')), tokens.last());
+        tokenizer.end();
+        tokens.extend(&mut tokenizer);
+        assert_eq!(Some(&HtmlToken::Char('<')), tokens.last());
+        assert_eq!(expected, tokens);
+    }
+
+    /// トークンと、そのトークンが始まる位置の組を返します
+    fn positions(tokenizer: &mut HtmlTokenizer) -> Vec<(HtmlToken, usize)> {
+        let mut tokens = Vec::new();
+        while let Some(token) = tokenizer.next() {
+            tokens.push((token, tokenizer.token_position()));
+        }
+        tokens
+    }
+
+    #[test]
+    fn test_position() {
+        let html = "<p>a&amp;</p c=d>";
+        let expected = vec![
+            (
+                HtmlToken::StartTag {
+                    tag: "p".to_string(),
+                    self_closing: false,
+                    attributes: Vec::new(),
+                },
+                0,
+            ),
+            (HtmlToken::Char('a'), 3),
+            (HtmlToken::Char('&'), 4),
+            // 終了タグの属性は読み捨てる
+            (
+                HtmlToken::EndTag {
+                    tag: "p".to_string(),
+                },
+                9,
+            ),
+        ];
+        assert_eq!(
+            expected,
+            positions(&mut HtmlTokenizer::new(html.to_string()))
+        );
+
+        let mut tokenizer = HtmlTokenizer::new_streaming();
+        let mut tokens = Vec::new();
+        for c in html.chars() {
+            tokenizer.feed(&c.to_string());
+            tokens.extend(positions(&mut tokenizer));
+        }
+        tokenizer.end();
+        tokens.extend(positions(&mut tokenizer));
+        assert_eq!(expected, tokens);
+    }
+
+    /// 文字トークンをつなげた文字列を返します
+    fn text(html: &str) -> String {
+        HtmlTokenizer::new(html.to_string())
+            .filter_map(|token| match token {
+                HtmlToken::Char(c) => Some(c),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_character_references() {
+        assert_eq!(
+            "& < \u{A0} あ あ ≠",
+            text("&amp; &lt; &nbsp; &#12354; &#x3042; &ne;")
+        );
+        // セミコロンのない古い名前と、最も長く一致する名前
+        assert_eq!("¬it; ∉ & ©2024", text("&notit; &notin; &amp &copy2024"));
+        // 文字参照でない'&'はそのまま残る
+        assert_eq!("&foo; & &#; &#x; &", text("&foo; & &#; &#x; &"));
+        // 数値文字参照の置き換え
+        assert_eq!("€\u{FFFD}\u{FFFD}A", text("&#x80;&#0;&#xD800;&#65"));
+        assert_eq!("\u{FFFD}", text("&#99999999999999999999;"));
+        // 入力の終わりで途切れた文字参照
+        assert_eq!("&", text("&amp"));
+        assert_eq!("A", text("&#x41"));
+        assert_eq!("&#x", text("&#x"));
+    }
+
+    #[test]
+    fn test_character_references_in_attribute() {
+        let mut tokenizer = HtmlTokenizer::new(
+            "<a href=\"?a=1&amp;b=2&copy=3&copy;&notit\" title='&lt;&#x41;' id=x&amp;y>"
+                .to_string(),
+        );
+        match tokenizer.next() {
+            Some(HtmlToken::StartTag { attributes, .. }) => {
+                let values: Vec<String> = attributes.iter().map(|a| a.value()).collect();
+                assert_eq!(vec!["?a=1&b=2&copy=3©&notit", "<A", "x&y"], values);
+            }
+            token => panic!("unexpected token: {:?}", token),
+        }
+    }
+
+    #[test]
+    fn test_streaming_character_references() {
+        let mut tokenizer = HtmlTokenizer::new_streaming();
+        let mut tokens = Vec::new();
+        for chunk in ["a&a", "mp", ";b&no", "tin;&#x30", "42;&am"] {
+            tokenizer.feed(chunk);
+            tokens.extend(&mut tokenizer);
+        }
+        tokenizer.end();
+        tokens.extend(&mut tokenizer);
+        let text: String = tokens
+            .into_iter()
+            .filter_map(|token| match token {
+                HtmlToken::Char(c) => Some(c),
+                _ => None,
+            })
+            .collect();
+        assert_eq!("a&b∉あ&am", text);
+    }
+
+    #[test]
+    fn test_comment() {
+        let tokens: Vec<HtmlToken> =
+            HtmlTokenizer::new("<!-- a -- b -->c<!---->< !--x--!><?xml?></3><!--".to_string())
+                .collect();
+        let expected = vec![
+            HtmlToken::Comment(" a -- b ".to_string()),
+            HtmlToken::Char('c'),
+            HtmlToken::Comment("".to_string()),
+            HtmlToken::Char(' '),
+            HtmlToken::Char('!'),
+            HtmlToken::Char('-'),
+            HtmlToken::Char('-'),
+            HtmlToken::Char('x'),
+            HtmlToken::Char('-'),
+            HtmlToken::Char('-'),
+            HtmlToken::Char('!'),
+            HtmlToken::Char('>'),
+            HtmlToken::Comment("?xml?".to_string()),
+            HtmlToken::Comment("3".to_string()),
+            HtmlToken::Comment("".to_string()),
+        ];
+        assert_eq!(expected, tokens);
+    }
+
+    fn doctype(
+        name: Option<&str>,
+        public_id: Option<&str>,
+        system_id: Option<&str>,
+        force_quirks: bool,
+    ) -> HtmlToken {
+        HtmlToken::Doctype {
+            name: name.map(|s| s.to_string()),
+            public_id: public_id.map(|s| s.to_string()),
+            system_id: system_id.map(|s| s.to_string()),
+            force_quirks,
+        }
+    }
+
+    #[test]
+    fn test_doctype() {
+        let cases = [
+            ("<!DOCTYPE html>", doctype(Some("html"), None, None, false)),
+            ("<!doctype HTML >", doctype(Some("html"), None, None, false)),
+            ("<!DOCTYPE>", doctype(None, None, None, true)),
+            (
+                "<!DOCTYPE html PUBLIC \"-//W3C//DTD HTML 4.01//EN\" 'http://www.w3.org/TR/html4/strict.dtd'>",
+                doctype(
+                    Some("html"),
+                    Some("-//W3C//DTD HTML 4.01//EN"),
+                    Some("http://www.w3.org/TR/html4/strict.dtd"),
+                    false,
+                ),
+            ),
+            (
+                "<!DOCTYPE html system \"about:legacy-compat\">",
+                doctype(Some("html"), None, Some("about:legacy-compat"), false),
+            ),
+            (
+                "<!DOCTYPE html PUBLIC \"abc>",
+                doctype(Some("html"), Some("abc"), None, true),
+            ),
+            ("<!DOCTYPE html foo>", doctype(Some("html"), None, None, true)),
+            ("<!DOCTYPE html", doctype(Some("html"), None, None, true)),
+        ];
+        for (html, expected) in cases {
+            let mut tokenizer = HtmlTokenizer::new(html.to_string());
+            assert_eq!(Some(expected), tokenizer.next(), "{}", html);
+            assert_eq!(None, tokenizer.next(), "{}", html);
+        }
+    }
+
+    #[test]
+    fn test_streaming_comment_and_doctype() {
+        let html = "<!DOCTYPE html PUBLIC \"a\" \"b\"><!--c--><![CDATA[d]]>";
+        let expected: Vec<HtmlToken> = HtmlTokenizer::new(html.to_string()).collect();
+        assert_eq!(
+            vec![
+                doctype(Some("html"), Some("a"), Some("b"), false),
+                HtmlToken::Comment("c".to_string()),
+                HtmlToken::Comment("[CDATA[d]]".to_string()),
+            ],
+            expected
+        );
+
+        let mut tokenizer = HtmlTokenizer::new_streaming();
+        let mut tokens = Vec::new();
+        for c in html.chars() {
+            tokenizer.feed(&c.to_string());
+            tokens.extend(&mut tokenizer);
+        }
+        tokenizer.end();
+        tokens.extend(&mut tokenizer);
+        assert_eq!(expected, tokens);
+    }
+
+    /// 最初の開始タグを読んだ後にステートを切り替え、残りのトークンを返します
+    fn tokens_in(state: State, html: &str) -> Vec<HtmlToken> {
+        let mut tokenizer = HtmlTokenizer::new(html.to_string());
+        assert!(matches!(tokenizer.next(), Some(HtmlToken::StartTag { .. })));
+        tokenizer.switch_to(state);
+        tokenizer.collect()
+    }
+
+    fn chars(s: &str) -> Vec<HtmlToken> {
+        s.chars().map(HtmlToken::Char).collect()
+    }
+
+    #[test]
+    fn test_text_states() {
+        let end_tag = |s: &str| HtmlToken::EndTag { tag: s.to_string() };
+
+        assert_eq!(
+            [chars("a & <b></t<!-- -->"), vec![end_tag("title")]].concat(),
+            tokens_in(State::Rcdata, "<title>a &amp; <b></t<!-- --></TITLE>")
+        );
+        assert_eq!(
+            [
+                chars("a &amp; </styl</p>"),
+                vec![end_tag("style")],
+                chars("x")
+            ]
+            .concat(),
+            tokens_in(State::Rawtext, "<style>a &amp; </styl</p></style >x")
+        );
+        assert_eq!(
+            [chars("if (a<b) {}</p>"), vec![end_tag("script")]].concat(),
+            tokens_in(State::ScriptData, "<script>if (a<b) {}</p></script>")
+        );
+        assert_eq!(
+            chars("</plaintext>&amp;"),
+            tokens_in(State::Plaintext, "<plaintext></plaintext>&amp;")
+        );
+        // 入力の終わりで途切れた終了タグは文字になる
+        assert_eq!(chars("a</sty"), tokens_in(State::Rawtext, "<style>a</sty"));
+        assert_eq!(chars("a</"), tokens_in(State::Rcdata, "<title>a</"));
+    }
+
     #[test]
     fn test_when_newline_exists_betwenn_open_tag_and_close_tag_html_should_be_tokenzed() {
         let html = r#"<head>
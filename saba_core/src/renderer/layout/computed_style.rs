@@ -18,6 +18,7 @@ pub struct ComputedStyle {
     display: Option<DisplayType>,
     font_size: Option<FontSize>,
     text_decoration: Option<TextDecoration>,
+    white_space: Option<WhiteSpace>,
     height: Option<f64>,
     width: Option<f64>,
 }
@@ -30,6 +31,7 @@ impl ComputedStyle {
             display: None,
             font_size: None,
             text_decoration: None,
+            white_space: None,
             height: None,
             width: None,
         }
@@ -53,6 +55,9 @@ impl ComputedStyle {
             {
                 self.text_decoration = Some(parent_style.text_decoration());
             }
+            if self.white_space.is_none() && parent_style.white_space() != WhiteSpace::Normal {
+                self.white_space = Some(parent_style.white_space());
+            }
         }
 
         // 値が設定されていないプロパティについてプロパティごとのデフォルト値を設定する
@@ -71,6 +76,9 @@ impl ComputedStyle {
         if self.text_decoration.is_none() {
             self.text_decoration = Some(TextDecoration::default(node));
         }
+        if self.white_space.is_none() {
+            self.white_space = Some(WhiteSpace::default(node));
+        }
         if self.height.is_none() {
             self.height = Some(0.0);
         }
@@ -118,6 +126,15 @@ impl ComputedStyle {
             .expect("failed to access CSS property: text_decoration")
     }
 
+    pub fn set_white_space(&mut self, white_space: WhiteSpace) {
+        self.white_space = Some(white_space);
+    }
+
+    pub fn white_space(&self) -> WhiteSpace {
+        self.white_space
+            .expect("failed to access CSS property: white_space")
+    }
+
     pub fn set_height(&mut self, height: f64) {
         self.height = Some(height);
     }
@@ -272,13 +289,18 @@ impl DisplayType {
         match node.borrow().kind() {
             NodeKind::Document => DisplayType::Block,
             NodeKind::Element(element) => {
-                if element.is_block() {
+                // iframeの中身は代替のコンテンツではなく、フレームの表示にも対応していないため表示しない
+                if element.is_hidden() || element.kind() == ElementKind::Iframe {
+                    DisplayType::None
+                } else if element.is_block() {
                     DisplayType::Block
                 } else {
                     DisplayType::Inline
                 }
             }
             NodeKind::Text(_) => DisplayType::Inline,
+            // DOCTYPEとコメントは画面に表示しない
+            NodeKind::DocumentType(_) | NodeKind::Comment(_) => DisplayType::None,
         }
     }
 }
@@ -299,6 +321,32 @@ impl TryFrom<&str> for DisplayType {
     }
 }
 
+/// https://drafts.csswg.org/css-text/#white-space-property
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum WhiteSpace {
+    /// 連続する空白と改行を1つの空白にまとめる
+    Normal,
+    /// 空白と改行をそのまま表示する
+    Pre,
+}
+
+impl WhiteSpace {
+    /// https://html.spec.whatwg.org/multipage/rendering.html#flow-content-3
+    fn default(node: &Rc<RefCell<Node>>) -> Self {
+        match node.borrow().kind() {
+            NodeKind::Element(element)
+                if matches!(
+                    element.local_name().as_str(),
+                    "listing" | "plaintext" | "pre" | "xmp"
+                ) =>
+            {
+                WhiteSpace::Pre
+            }
+            _ => WhiteSpace::Normal,
+        }
+    }
+}
+
 /// https://w3c.github.io/csswg-drafts/css-text-decor/#text-decoration-property
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum TextDecoration {
@@ -18,7 +18,7 @@ use crate::{
     },
 };
 
-use super::computed_style::{Color, ComputedStyle, DisplayType, FontSize};
+use super::computed_style::{Color, ComputedStyle, DisplayType, FontSize, WhiteSpace};
 
 /// https://drafts.csswg.org/css-text/#word-break-property
 fn find_index_for_line_break(line: String, max_index: usize) -> usize {
@@ -46,6 +46,28 @@ fn split_text(line: String, char_width: i64) -> Vec<String> {
     result
 }
 
+/// white-space: preのテキストを、改行とコンテンツエリアの幅で区切った行にします
+/// タブは次の8の倍数の桁まで空白で埋めます
+fn split_preformatted_text(text: &str, char_width: i64) -> Vec<String> {
+    let max_chars = (CONTENT_AREA_WIDTH / char_width).max(1) as usize;
+    let mut lines = vec![];
+    for line in text.strip_suffix('\n').unwrap_or(text).split('\n') {
+        let mut chars = vec![];
+        for c in line.strip_suffix('\r').unwrap_or(line).chars() {
+            if c == '\t' {
+                chars.resize(chars.len() + 8 - chars.len() % 8, ' ');
+            } else {
+                chars.push(c);
+            }
+        }
+        if chars.is_empty() {
+            lines.push(String::new());
+        }
+        lines.extend(chars.chunks(max_chars).map(|chunk| chunk.iter().collect()));
+    }
+    lines
+}
+
 /// レオアウトオブジェクトを生成します
 /// ただし、画面に描画されないノードの場合、レイアウトオブジェクトをしません（Noneを返します）
 pub fn create_layout_object(
@@ -141,16 +163,25 @@ impl LayoutObject {
                     FontSize::XLarge => 2,
                     FontSize::XXLarge => 3,
                 };
-                let plain_text = text
-                    .replace("\n", " ")
-                    .split(' ')
-                    .filter(|s| !s.is_empty())
-                    .collect::<Vec<_>>()
-                    .join(" ");
-                let lines = split_text(plain_text, CHAR_WIDTH * ratio);
+                let lines = match self.style.white_space() {
+                    WhiteSpace::Pre => split_preformatted_text(&text, CHAR_WIDTH * ratio),
+                    WhiteSpace::Normal => {
+                        let plain_text = text
+                            .replace("\n", " ")
+                            .split(' ')
+                            .filter(|s| !s.is_empty())
+                            .collect::<Vec<_>>()
+                            .join(" ");
+                        split_text(plain_text, CHAR_WIDTH * ratio)
+                    }
+                };
                 let mut display_items = vec![];
                 let mut i = 0;
                 for line in lines {
+                    if line.is_empty() {
+                        i += 1;
+                        continue;
+                    }
                     let item = DisplayItem::Text {
                         text: line,
                         style: self.style(),
@@ -222,6 +253,18 @@ impl LayoutObject {
                     FontSize::XLarge => 2,
                     FontSize::XXLarge => 3,
                 };
+                if self.style.white_space() == WhiteSpace::Pre {
+                    let lines = split_preformatted_text(&text, CHAR_WIDTH * ratio);
+                    let max_chars = lines
+                        .iter()
+                        .map(|line| line.chars().count())
+                        .max()
+                        .unwrap_or(0);
+                    size.set_width(CHAR_WIDTH * ratio * max_chars as i64);
+                    size.set_height(CHAR_HEIGHT_WITH_PADDING * ratio * lines.len() as i64);
+                    self.size = size;
+                    return;
+                }
                 let width = CHAR_WIDTH * ratio * text.len() as i64;
                 if width > CONTENT_AREA_WIDTH {
                     // テキスト複数行
@@ -284,7 +327,7 @@ impl LayoutObject {
     pub fn is_node_selected(&self, selector: &Selector) -> bool {
         match &self.node_kind() {
             NodeKind::Element(element) => match selector {
-                Selector::TypeSelector(type_name) => element.kind().to_string() == *type_name,
+                Selector::TypeSelector(type_name) => element.local_name() == *type_name,
                 Selector::ClassSelector(class_name) => element
                     .get_attr("class")
                     .map_or(false, |attr| attr.value() == *class_name),
@@ -323,6 +366,15 @@ impl LayoutObject {
                     }
                     _ => {}
                 },
+                "white-space" => match &declaration.value {
+                    ComponentValue::Ident(value) if value == "pre" => {
+                        self.style.set_white_space(WhiteSpace::Pre)
+                    }
+                    ComponentValue::Ident(value) if value == "normal" => {
+                        self.style.set_white_space(WhiteSpace::Normal)
+                    }
+                    _ => {}
+                },
                 "display" => {
                     if let ComponentValue::Ident(value) = declaration.value {
                         let display_type =
@@ -347,6 +399,9 @@ impl LayoutObject {
     pub fn update_kind(&mut self) {
         match self.node_kind() {
             NodeKind::Document => panic!("should not create a layout object for a Document node"),
+            NodeKind::DocumentType(_) | NodeKind::Comment(_) => {
+                panic!("should not create a layout object for a DocumentType or Comment node")
+            }
             NodeKind::Element(_) => {
                 let display = self.style.display();
                 match display {
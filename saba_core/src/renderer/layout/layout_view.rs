@@ -18,35 +18,6 @@ use super::layout_object::{
     create_layout_object, LayoutObject, LayoutObjectKind, LayoutPoint, LayoutSize,
 };
 
-/// 引数で指定されたノードを先頭としたすべての兄弟ノードのレイアウトオブジェクトを生成します
-fn build_layout_siblings(
-    first_node: Option<Rc<RefCell<Node>>>,
-    parent: &mut Option<Rc<RefCell<LayoutObject>>>,
-    cssom: &StyleSheet,
-) {
-    let mut first_node_layout = build_layout_tree(&first_node, &parent, cssom);
-    if first_node.is_some() && first_node_layout.is_none() {
-        let mut candidate_node = first_node
-            .expect("first child shoud exist")
-            .borrow()
-            .next_sibling();
-
-        loop {
-            first_node_layout = build_layout_tree(&candidate_node, &parent, cssom);
-
-            if first_node_layout.is_none() && candidate_node.is_some() {
-                candidate_node = candidate_node
-                    .expect("next sibling should exists")
-                    .borrow()
-                    .next_sibling();
-                continue;
-            }
-
-            break;
-        }
-    }
-}
-
 /// レイアウトツリーを構築します
 /// レイアウトツリーの要素はDOM要素の中から画面に表示される(display: noneでない)要素のみで構成されたものだけになります
 fn build_layout_tree(
@@ -74,14 +45,12 @@ fn build_layout_tree(
     // 最初に画面に表示される子ノードをレイアウトツリー上の子ノードとする
     // （画面表示されない子ノードはスキップ）
     let original_first_child = node.borrow().first_child();
-    let mut first_child_layout = build_layout_tree(&original_first_child, &current_layout, cssom);
-    build_layout_siblings(original_first_child, &mut first_child_layout, cssom);
+    let first_child_layout = build_layout_tree(&original_first_child, &current_layout, cssom);
 
     // 最初に画面に表示される兄弟ノードをレイアウトツリー上の次の兄弟ノードとする
     // （画面表示されない兄弟ノードはスキップ）
     let original_next_sibling = node.borrow().next_sibling();
-    let mut next_sibling_layout = build_layout_tree(&original_next_sibling, &None, cssom);
-    build_layout_siblings(original_next_sibling, &mut next_sibling_layout, cssom);
+    let next_sibling_layout = build_layout_tree(&original_next_sibling, &None, cssom);
 
     {
         let current_layout = current_layout
@@ -404,4 +373,33 @@ mod tests {
             .next_sibling()
             .is_none());
     }
+
+    #[test]
+    fn test_unknown_elements() {
+        let html = "<html><head><style>em{display:block;} span{display:none;}</style></head><body><div>a<span>b</span></div><em>c</em></body></html>".to_string();
+        let layout_view = create_layout_view(html);
+
+        let div = layout_view
+            .root()
+            .expect("root should exist")
+            .borrow()
+            .first_child()
+            .expect("div node should exist");
+        assert_eq!(LayoutObjectKind::Block, div.borrow().kind());
+        assert_eq!(
+            NodeKind::Element(Element::new("div", Vec::new())),
+            div.borrow().node_kind()
+        );
+
+        let text = div.borrow().first_child().expect("text node should exist");
+        assert_eq!(NodeKind::Text("a".to_string()), text.borrow().node_kind());
+        assert!(text.borrow().next_sibling().is_none());
+
+        let em = div.borrow().next_sibling().expect("em node should exist");
+        assert_eq!(LayoutObjectKind::Block, em.borrow().kind());
+        assert_eq!(
+            NodeKind::Element(Element::new("em", Vec::new())),
+            em.borrow().node_kind()
+        );
+    }
 }
@@ -3,13 +3,21 @@ use core::cell::RefCell;
 use alloc::{
     format,
     rc::{Rc, Weak},
-    string::String,
+    string::{String, ToString},
     vec,
     vec::Vec,
 };
 
 use crate::{
-    browser::Browser, display_item::DisplayItem, http::HttpResponse, utils::convert_dom_to_string,
+    browser::Browser,
+    display_item::DisplayItem,
+    encoding::{Decoder, Encoding},
+    error::Error,
+    error_page::{error_page, http_error_page, interrupted_notice, unsupported_content_page},
+    http::HttpResponse,
+    mime::MimeType,
+    url::Url,
+    utils::convert_dom_to_string,
 };
 
 use super::{
@@ -18,30 +26,93 @@ use super::{
         token::CssTokenizer,
     },
     dom::{
-        api::get_style_content,
-        node::{ElementKind, NodeKind, Window},
+        api::{append_child, get_style_content, get_target_element_node},
+        node::{Element, ElementKind, Node, NodeKind, Window},
+    },
+    html::{
+        encoding_sniffing::{determine_encoding, PRESCAN_LIMIT},
+        parser::{HtmlParser, InsertionMode},
+        token::HtmlTokenizer,
     },
-    html::{parser::HtmlParser, token::HtmlTokenizer},
     layout::{layout_object::LayoutObjectKind, layout_view::LayoutView},
 };
 
+/// Content-Typeから決まるドキュメントの表示の仕方
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum DocumentKind {
+    Html,
+    /// 整形済みテキストとしてそのまま表示する
+    Text,
+    /// 表示できない種類。値はMIMEタイプのessence
+    Unsupported(String),
+}
+
+impl DocumentKind {
+    /// Content-Typeがない、または解析できない場合は、これまでどおりHTMLとして表示する
+    /// https://html.spec.whatwg.org/multipage/browsing-the-web.html#loading-a-document
+    fn new(content_type: Option<&str>) -> Self {
+        let mime_type = match content_type.and_then(MimeType::parse) {
+            Some(mime_type) => mime_type,
+            None => return DocumentKind::Html,
+        };
+        // XMLのパーサーはないため、XHTMLもHTMLとして解析する
+        if mime_type.is_html() || mime_type.essence() == "application/xhtml+xml" {
+            DocumentKind::Html
+        } else if mime_type.type_() == "text" || mime_type.is_javascript() || mime_type.is_json() {
+            DocumentKind::Text
+        } else {
+            DocumentKind::Unsupported(mime_type.essence())
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Page {
     browser: Weak<RefCell<Browser>>,
+    /// https://dom.spec.whatwg.org/#concept-document-url
+    url: Option<Url>,
+    /// https://html.spec.whatwg.org/multipage/urls-and-fetching.html#document-base-url
+    base_url: Option<Url>,
+    /// https://dom.spec.whatwg.org/#concept-document-encoding
+    encoding: Encoding,
     frame: Option<Rc<RefCell<Window>>>,
     style: Option<StyleSheet>,
+    /// styleのもとになった<style>要素の内容。内容が変わったときだけスタイルシートを作り直します
+    style_source: Option<String>,
     layout_view: Option<LayoutView>,
     display_items: Vec<DisplayItem>,
+    /// 受信中のドキュメントのパーサー。ドキュメントを受信し終えるとNoneになります
+    parser: Option<HtmlParser>,
+    /// 受信中のドキュメントのデコーダー。エンコーディングが決まるまではNoneになります
+    decoder: Option<Decoder>,
+    /// エンコーディングを判定するために、デコードせずにためているバイト列
+    received: Vec<u8>,
+    /// 受信中のドキュメントのContent-Typeヘッダの値
+    content_type: Option<String>,
+    /// 受信中のドキュメントのうち、パーサーに渡した文字列のバイト数
+    fed_len: usize,
+    /// 最後にレイアウトと描画を更新したときのfed_len
+    rendered_len: usize,
 }
 
 impl Page {
     pub fn new() -> Self {
         Self {
             browser: Weak::new(),
+            url: None,
+            base_url: None,
+            encoding: Encoding::Utf8,
             frame: None,
             style: None,
+            style_source: None,
             layout_view: None,
             display_items: vec![],
+            parser: None,
+            decoder: None,
+            received: Vec::new(),
+            content_type: None,
+            fed_len: 0,
+            rendered_len: 0,
         }
     }
 
@@ -49,23 +120,243 @@ impl Page {
         self.browser = browser;
     }
 
-    pub fn receive_response(&mut self, response: HttpResponse) {
-        self.create_frame(response.body());
+    pub fn receive_response(&mut self, url: Url, response: HttpResponse) {
+        self.url = Some(url.clone());
+
+        // エラーの状態コードの場合は、サーバーが返した内容の代わりにエラーページを表示する
+        if response.status_code() >= 400 {
+            self.encoding = Encoding::Utf8;
+            self.load_html(http_error_page(
+                &url.href(),
+                response.status_code(),
+                &response.reason(),
+            ));
+            return;
+        }
+
+        self.start_loading(url, response.header_value("Content-Type").ok());
+        self.receive_data(&response.body_bytes());
+        self.finish_loading();
+    }
+
+    /// urlのドキュメントの受信を始めます。本文はreceive_dataで受信した順に渡します
+    /// Content-Typeがテキストの場合は整形済みテキストとして表示し、表示できない種類の場合は
+    /// 本文を受信する代わりにエラーページを表示します
+    pub fn start_loading(&mut self, url: Url, content_type: Option<String>) {
+        let href = url.href();
+        self.url = Some(url);
+        self.base_url = None;
+        self.frame = None;
+        self.style = None;
+        self.style_source = None;
+        self.layout_view = None;
+        self.display_items = vec![];
+        self.parser = None;
+        self.decoder = None;
+        self.received = Vec::new();
+        self.fed_len = 0;
+        self.rendered_len = 0;
+
+        match DocumentKind::new(content_type.as_deref()) {
+            DocumentKind::Html => {
+                self.parser = Some(HtmlParser::new(HtmlTokenizer::new_streaming()));
+            }
+            DocumentKind::Text => {
+                let mut parser = HtmlParser::new(HtmlTokenizer::new_streaming());
+                parser.start_text_document();
+                self.parser = Some(parser);
+            }
+            DocumentKind::Unsupported(mime_type) => {
+                self.encoding = Encoding::Utf8;
+                self.load_html(unsupported_content_page(&href, &mime_type));
+            }
+        }
+        self.content_type = content_type;
+    }
+
+    /// 受信したドキュメントの本文の続きを渡します
+    /// 受信したところまでのDOMツリーを構築し、レイアウトと描画を更新した場合はtrueを返します
+    ///
+    /// 更新のたびにドキュメント全体をレイアウトし直すため、細かく届くたびに更新すると
+    /// ドキュメントの長さの2乗に比例する時間がかかります。そこで、前回の更新からパーサーに渡した
+    /// 文字列が倍になったときだけ更新し、残りはfinish_loadingでまとめて更新します
+    pub fn receive_data(&mut self, bytes: &[u8]) -> bool {
+        if self.parser.is_none() {
+            return false;
+        }
+
+        let text = match self.decoder.as_mut() {
+            Some(decoder) => decoder.decode(bytes),
+            None => {
+                // <meta>要素のプレスキャンに必要なバイト数が届くまでは、エンコーディングを決めない
+                self.received.extend_from_slice(bytes);
+                if self.received.len() < PRESCAN_LIMIT {
+                    return false;
+                }
+                self.start_decoding()
+            }
+        };
+        if let Some(parser) = self.parser.as_mut() {
+            parser.feed(&text);
+        }
+        self.fed_len += text.len();
+
+        if self.fed_len == 0 || self.fed_len < self.rendered_len * 2 {
+            return false;
+        }
+        self.rendered_len = self.fed_len;
+        self.update_rendering();
+        true
+    }
+
+    /// ドキュメントの本文をすべて受信したことを伝えます
+    pub fn finish_loading(&mut self) {
+        if self.parser.is_none() {
+            return;
+        }
+        self.finish_parsing();
+        self.update_rendering();
+    }
+
+    /// ドキュメントの本文の受信がerrorで途中で失敗したことを伝えます
+    /// 受信したところまでのページは破棄せずに表示したまま、読み込みが中断されたことを伝える
+    /// 段落を<body>の末尾に追加します
+    pub fn abort_loading(&mut self, error: &Error) {
+        if self.parser.is_none() {
+            return;
+        }
+        self.finish_parsing();
+
+        let body = self.frame.as_ref().and_then(|frame| {
+            get_target_element_node(Some(frame.borrow().document()), ElementKind::Body)
+        });
+        if let Some(body) = body {
+            let notice = Rc::new(RefCell::new(Node::new(NodeKind::Element(Element::new(
+                "p",
+                Vec::new(),
+            )))));
+            append_child(
+                &notice,
+                Rc::new(RefCell::new(Node::new(NodeKind::Text(interrupted_notice(
+                    error,
+                ))))),
+            );
+            append_child(&body, notice);
+        }
+        self.update_rendering();
+    }
+
+    /// デコーダーにためていた残りを含めてパーサーに渡し、DOMツリーを完成させます
+    fn finish_parsing(&mut self) {
+        let mut text = match self.decoder.as_mut() {
+            Some(_) => String::new(),
+            None => self.start_decoding(),
+        };
+        if let Some(decoder) = self.decoder.as_mut() {
+            text.push_str(&decoder.finish());
+        }
+        if let Some(mut parser) = self.parser.take() {
+            parser.feed(&text);
+            self.frame = Some(parser.construct_tree());
+        }
+        self.decoder = None;
+    }
+
+    /// ためていたバイト列からエンコーディングを決めて、デコードできたところまでの文字列を返します
+    fn start_decoding(&mut self) -> String {
+        self.encoding = determine_encoding(&self.received, self.content_type.as_deref());
+        let mut decoder = Decoder::new(self.encoding);
+        let text = decoder.decode(&self.received);
+        self.encoding = decoder.encoding();
+        self.decoder = Some(decoder);
+        self.received = Vec::new();
+        text
+    }
+
+    /// destinationへのナビゲーションがerrorで失敗したことを伝えるエラーページを読み込みます
+    pub fn receive_error(&mut self, destination: &str, error: &Error) {
+        self.url = Url::new(destination.to_string()).parse().ok();
+        self.encoding = Encoding::Utf8;
+        self.load_html(error_page(destination, error));
+    }
+
+    fn load_html(&mut self, html: String) {
+        self.parser = None;
+        self.decoder = None;
+        self.received = Vec::new();
+        let html_tokenizer = HtmlTokenizer::new(html);
+        self.frame = Some(HtmlParser::new(html_tokenizer).construct_tree());
+        self.update_rendering();
+    }
+
+    /// その時点のDOMツリーからスタイルシートとベースURLを求め、レイアウトと描画をやり直します
+    fn update_rendering(&mut self) {
+        if let Some(parser) = self.parser.as_ref() {
+            self.frame = Some(parser.window());
+        }
+        self.set_style();
+        self.set_base_url();
         self.set_layout_view();
         self.paint_tree();
     }
 
-    fn create_frame(&mut self, html: String) {
-        let html_tokenizer = HtmlTokenizer::new(html);
-        let frame = HtmlParser::new(html_tokenizer).construct_tree();
+    fn set_style(&mut self) {
+        // <style>要素の内容を受信している途中では、それまでのスタイルシートを使う
+        if self
+            .parser
+            .as_ref()
+            .is_some_and(|parser| parser.insertion_mode() == InsertionMode::Text)
+        {
+            if self.style.is_none() {
+                self.style = Some(StyleSheet::new());
+            }
+            return;
+        }
 
-        let dom = frame.borrow().document();
+        let dom = match self.frame.as_ref() {
+            Some(frame) => frame.borrow().document(),
+            None => return,
+        };
         let style = get_style_content(dom);
-        let css_tokenizer = CssTokenizer::new(style);
-        let cssom = CssParser::new(css_tokenizer).parse_stylesheet();
+        if self.style.is_some() && self.style_source.as_ref() == Some(&style) {
+            return;
+        }
+        let css_tokenizer = CssTokenizer::new(style.clone());
+        self.style = Some(CssParser::new(css_tokenizer).parse_stylesheet());
+        self.style_source = Some(style);
+    }
+
+    /// ドキュメント内の最初の<base>要素のhref属性をドキュメントのURLに対して解決し、ベースURLとします
+    /// https://html.spec.whatwg.org/multipage/semantics.html#set-the-frozen-base-url
+    fn set_base_url(&mut self) {
+        self.base_url = None;
+
+        let dom = match self.frame.as_ref() {
+            Some(frame) => frame.borrow().document(),
+            None => return,
+        };
+        let url = match self.url.as_ref() {
+            Some(url) => url,
+            None => return,
+        };
+
+        self.base_url = get_target_element_node(Some(dom), ElementKind::Base)
+            .and_then(|node| node.borrow().get_element())
+            .and_then(|element| element.get_attr("href"))
+            .and_then(|href| url.join(&href.value()).ok());
+    }
+
+    pub fn url(&self) -> Option<Url> {
+        self.url.clone()
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
 
-        self.frame = Some(frame);
-        self.style = Some(cssom);
+    /// <base href>が指定されていればそのURLを、なければドキュメントのURLを返します
+    pub fn base_url(&self) -> Option<Url> {
+        self.base_url.clone().or_else(|| self.url.clone())
     }
 
     fn set_layout_view(&mut self) {
@@ -89,6 +380,7 @@ impl Page {
     }
 
     /// 指定された位置に<a>タグが存在するとき、その<a>タグのリンクを返します
+    /// 相対URLのリンクはドキュメントのベースURLに対して解決した絶対URLになります
     pub fn get_link_at(&self, position: (i64, i64)) -> Option<String> {
         let view = self.layout_view.as_ref()?;
         let node = view.find_node_by_position(position)?;
@@ -102,7 +394,10 @@ impl Page {
         } else {
             None
         };
-        link
+        match self.base_url() {
+            Some(base_url) => base_url.join(&link?).ok().map(|url| url.href()),
+            None => link,
+        }
     }
 
     pub fn display_items(&self) -> Vec<DisplayItem> {
@@ -113,3 +408,252 @@ impl Page {
         self.display_items = vec![];
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load_bytes(url: &str, headers: &str, body: &[u8]) -> Page {
+        let url = Url::new(url.to_string()).parse().expect("invalid url");
+        let mut raw = format!("HTTP/1.1 200 OK\r\n{}\r\n", headers).into_bytes();
+        raw.extend_from_slice(body);
+        let response = HttpResponse::new(&raw).expect("failed to parse http response");
+        let mut page = Page::new();
+        page.receive_response(url, response);
+        page
+    }
+
+    fn load(url: &str, html: &str) -> Page {
+        let url = Url::new(url.to_string()).parse().expect("invalid url");
+        let response = HttpResponse::new(format!("HTTP/1.1 200 OK\n\n{}", html).as_bytes())
+            .expect("failed to parse http response");
+        let mut page = Page::new();
+        page.receive_response(url, response);
+        page
+    }
+
+    #[test]
+    fn test_base_url_defaults_to_document_url() {
+        let page = load(
+            "http://example.com/dir/index.html",
+            "<html><head></head><body></body></html>",
+        );
+        assert_eq!(
+            Some("http://example.com/dir/index.html".to_string()),
+            page.base_url().map(|url| url.href())
+        );
+    }
+
+    #[test]
+    fn test_base_element() {
+        let page = load(
+            "http://example.com/dir/index.html",
+            "<html><head><base href=\"/other/\"></head><body></body></html>",
+        );
+        assert_eq!(
+            Some("http://example.com/dir/index.html".to_string()),
+            page.url().map(|url| url.href())
+        );
+        assert_eq!(
+            Some("http://example.com/other/".to_string()),
+            page.base_url().map(|url| url.href())
+        );
+    }
+
+    #[test]
+    fn test_decode_shift_jis_document() {
+        let page = load_bytes(
+            "http://example.com/",
+            "Content-Type: text/html; charset=Shift_JIS\r\n",
+            b"<html><head></head><body><p>\x93\xfa\x96\x7b</p></body></html>",
+        );
+        assert_eq!(Encoding::ShiftJis, page.encoding());
+        assert!(page
+            .display_items()
+            .iter()
+            .any(|item| matches!(item, DisplayItem::Text { text, .. } if text.contains("日本"))));
+    }
+
+    fn has_text(page: &Page, s: &str) -> bool {
+        page.display_items()
+            .iter()
+            .any(|item| matches!(item, DisplayItem::Text { text, .. } if text.contains(s)))
+    }
+
+    #[test]
+    fn test_http_error_page() {
+        let url = Url::new("http://example.com/missing".to_string())
+            .parse()
+            .expect("invalid url");
+        let response = HttpResponse::new(b"HTTP/1.1 404 Not Found\r\n\r\n")
+            .expect("failed to parse http response");
+        let mut page = Page::new();
+        page.receive_response(url, response);
+        assert!(has_text(&page, "Client error"));
+        assert!(has_text(&page, "404"));
+    }
+
+    #[test]
+    fn test_receive_error() {
+        let mut page = Page::new();
+        page.receive_error(
+            "http://example.com/",
+            &Error::Network("connection refused".to_string()),
+        );
+        assert_eq!(
+            Some("http://example.com/".to_string()),
+            page.url().map(|url| url.href())
+        );
+        assert!(has_text(&page, "Network error"));
+        assert!(has_text(&page, "Retry"));
+
+        page.receive_error("foo", &Error::UnexpectedInput("bad url".to_string()));
+        assert_eq!(None, page.url());
+        assert!(has_text(&page, "Invalid request"));
+    }
+
+    #[test]
+    fn test_abort_loading_keeps_partial_page() {
+        let mut page = Page::new();
+        page.start_loading(
+            Url::new("http://example.com/".to_string())
+                .parse()
+                .expect("invalid url"),
+            Some("text/html".to_string()),
+        );
+        page.receive_data(b"<html><body><p>first part</p><p>second");
+        page.abort_loading(&Error::Network("connection reset".to_string()));
+        assert!(has_text(&page, "first part"));
+        assert!(has_text(&page, "second"));
+        assert!(has_text(&page, "could not load the rest of this page"));
+        assert!(has_text(&page, "connection reset"));
+        assert_eq!(
+            Some("http://example.com/".to_string()),
+            page.url().map(|url| url.href())
+        );
+
+        // 中断したあとに届いたデータは無視する
+        assert!(!page.receive_data(b"<p>late</p>"));
+        page.finish_loading();
+        assert!(!has_text(&page, "late"));
+    }
+
+    #[test]
+    fn test_plain_text() {
+        let page = load_bytes(
+            "http://example.com/a.txt",
+            "Content-Type: text/plain\r\n",
+            b"<p>not a tag</p>\n\n  indented &amp;",
+        );
+        let texts: Vec<String> = page
+            .display_items()
+            .iter()
+            .filter_map(|item| match item {
+                DisplayItem::Text { text, .. } => Some(text.clone()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                "<p>not a tag</p>".to_string(),
+                "  indented &amp;".to_string()
+            ],
+            texts
+        );
+
+        // JSONもテキストとして表示する
+        let page = load_bytes(
+            "http://example.com/a.json",
+            "Content-Type: application/json\r\n",
+            b"{\"a\": \"<b>\"}",
+        );
+        assert!(has_text(&page, "{\"a\": \"<b>\"}"));
+    }
+
+    #[test]
+    fn test_unsupported_content() {
+        let page = load_bytes(
+            "http://example.com/a.png",
+            "Content-Type: image/png\r\n",
+            b"\x89PNG\r\n",
+        );
+        assert!(has_text(&page, "Unsupported content"));
+        assert!(has_text(&page, "image/png"));
+        assert_eq!(
+            Some("http://example.com/a.png".to_string()),
+            page.url().map(|url| url.href())
+        );
+    }
+
+    #[test]
+    fn test_streaming() {
+        let url = Url::new("http://example.com/".to_string())
+            .parse()
+            .expect("invalid url");
+        let mut page = Page::new();
+        page.start_loading(url, Some("text/html".to_string()));
+
+        // エンコーディングを判定できるだけのバイト数が届くまでは何も描画しない
+        page.receive_data(b"<html><head></head><body><p>first</p>");
+        assert!(page.display_items().is_empty());
+
+        page.receive_data(format!("<p>{}</p><p>sec", "a".repeat(PRESCAN_LIMIT)).as_bytes());
+        assert!(has_text(&page, "first"));
+        assert!(has_text(&page, "sec"));
+        assert!(!has_text(&page, "second"));
+
+        page.receive_data(b"ond</p></body></html>");
+        page.finish_loading();
+        assert!(has_text(&page, "second"));
+    }
+
+    #[test]
+    fn test_streaming_in_small_chunks() {
+        let body: &[u8] = b"<html><head><style>p { color: red; }</style></head><body><p>\x93\xfa\x96\x7b</p><a href=\"/next\">next</a></body></html>";
+        let content_type = "text/html; charset=Shift_JIS";
+        let expected = load_bytes(
+            "http://example.com/",
+            &format!("Content-Type: {}\r\n", content_type),
+            body,
+        );
+
+        let url = Url::new("http://example.com/".to_string())
+            .parse()
+            .expect("invalid url");
+        let mut page = Page::new();
+        page.start_loading(url, Some(content_type.to_string()));
+        for b in body {
+            page.receive_data(&[*b]);
+        }
+        page.finish_loading();
+        assert_eq!(Encoding::ShiftJis, page.encoding());
+        assert!(has_text(&page, "日本"));
+        assert_eq!(expected.display_items(), page.display_items());
+    }
+
+    #[test]
+    fn test_streaming_throttles_rendering() {
+        let body = format!(
+            "<html><body>{}</body></html>",
+            "<p>text</p>".repeat(PRESCAN_LIMIT / 4)
+        );
+        let expected = load_bytes("http://example.com/", "", body.as_bytes());
+
+        let url = Url::new("http://example.com/".to_string())
+            .parse()
+            .expect("invalid url");
+        let mut page = Page::new();
+        page.start_loading(url, Some("text/html".to_string()));
+        let mut updates = 0;
+        for chunk in body.as_bytes().chunks(16) {
+            if page.receive_data(chunk) {
+                updates += 1;
+            }
+        }
+        // 細かく届いても、パーサーに渡した量が倍になったときだけレイアウトをやり直す
+        assert!(updates > 0);
+        assert!(updates <= 5);
+        page.finish_loading();
+        assert_eq!(expected.display_items(), page.display_items());
+    }
+}
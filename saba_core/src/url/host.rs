@@ -0,0 +1,539 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt::{Display, Formatter};
+
+use super::percent_encoding::{percent_decode, utf8_percent_encode, PercentEncodeSet};
+
+/// https://url.spec.whatwg.org/#concept-host
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Host {
+    /// https://url.spec.whatwg.org/#concept-domain
+    Domain(String),
+    /// https://url.spec.whatwg.org/#concept-ipv4
+    Ipv4(u32),
+    /// https://url.spec.whatwg.org/#concept-ipv6
+    Ipv6([u16; 8]),
+    /// https://url.spec.whatwg.org/#opaque-host
+    Opaque(String),
+    /// https://url.spec.whatwg.org/#empty-host
+    Empty,
+}
+
+impl Host {
+    /// https://url.spec.whatwg.org/#concept-host-parser
+    pub fn parse(input: &str, is_opaque: bool) -> Result<Self, String> {
+        if let Some(rest) = input.strip_prefix('[') {
+            return match rest.strip_suffix(']') {
+                Some(address) => parse_ipv6(address).map(Host::Ipv6),
+                None => Err(format!("invalid IPv6 address: {}", input)),
+            };
+        }
+
+        if is_opaque {
+            return parse_opaque_host(input);
+        }
+
+        let domain = String::from_utf8_lossy(&percent_decode(input.as_bytes())).into_owned();
+        let ascii_domain = domain_to_ascii(&domain)?;
+
+        if ascii_domain.chars().any(is_forbidden_domain_code_point) {
+            return Err(format!("forbidden domain code point in host: {}", input));
+        }
+
+        if ends_in_a_number(&ascii_domain) {
+            return parse_ipv4(&ascii_domain).map(Host::Ipv4);
+        }
+
+        Ok(Host::Domain(ascii_domain))
+    }
+}
+
+impl Display for Host {
+    /// https://url.spec.whatwg.org/#concept-host-serializer
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        match self {
+            Host::Domain(domain) | Host::Opaque(domain) => write!(f, "{}", domain),
+            Host::Ipv4(address) => write!(
+                f,
+                "{}.{}.{}.{}",
+                address >> 24,
+                (address >> 16) & 0xff,
+                (address >> 8) & 0xff,
+                address & 0xff
+            ),
+            Host::Ipv6(address) => write!(f, "[{}]", serialize_ipv6(address)),
+            Host::Empty => Ok(()),
+        }
+    }
+}
+
+/// https://url.spec.whatwg.org/#forbidden-host-code-point
+fn is_forbidden_host_code_point(c: char) -> bool {
+    matches!(
+        c,
+        '\0' | '\t'
+            | '\n'
+            | '\r'
+            | ' '
+            | '#'
+            | '/'
+            | ':'
+            | '<'
+            | '>'
+            | '?'
+            | '@'
+            | '['
+            | '\\'
+            | ']'
+            | '^'
+            | '|'
+    )
+}
+
+/// https://url.spec.whatwg.org/#forbidden-domain-code-point
+fn is_forbidden_domain_code_point(c: char) -> bool {
+    is_forbidden_host_code_point(c) || c.is_ascii_control() || c == '%'
+}
+
+/// https://url.spec.whatwg.org/#concept-opaque-host-parser
+fn parse_opaque_host(input: &str) -> Result<Host, String> {
+    if input.chars().any(is_forbidden_host_code_point) {
+        return Err(format!("forbidden host code point in host: {}", input));
+    }
+    Ok(Host::Opaque(utf8_percent_encode(
+        input,
+        PercentEncodeSet::C0Control,
+    )))
+}
+
+/// https://url.spec.whatwg.org/#concept-domain-to-ascii
+/// UTS #46のマッピング表は持たず、小文字化と非ASCIIラベルのPunycode変換のみを行います
+fn domain_to_ascii(domain: &str) -> Result<String, String> {
+    let mut labels = Vec::new();
+    for label in domain.split('.') {
+        if label.is_ascii() {
+            labels.push(label.to_ascii_lowercase());
+            continue;
+        }
+        let lowered: Vec<char> = label.chars().flat_map(|c| c.to_lowercase()).collect();
+        labels.push(format!("xn--{}", punycode_encode(&lowered)?));
+    }
+
+    let result = labels.join(".");
+    if result.is_empty() {
+        return Err("empty host".to_string());
+    }
+    Ok(result)
+}
+
+/// https://www.rfc-editor.org/rfc/rfc3492#section-6.3
+fn punycode_encode(input: &[char]) -> Result<String, String> {
+    const BASE: u32 = 36;
+    const T_MIN: u32 = 1;
+    const T_MAX: u32 = 26;
+    const SKEW: u32 = 38;
+    const DAMP: u32 = 700;
+    const INITIAL_BIAS: u32 = 72;
+    const INITIAL_N: u32 = 0x80;
+
+    fn adapt(mut delta: u32, num_points: u32, first_time: bool) -> u32 {
+        delta /= if first_time { DAMP } else { 2 };
+        delta += delta / num_points;
+        let mut k = 0;
+        while delta > ((BASE - T_MIN) * T_MAX) / 2 {
+            delta /= BASE - T_MIN;
+            k += BASE;
+        }
+        k + (((BASE - T_MIN + 1) * delta) / (delta + SKEW))
+    }
+
+    fn encode_digit(d: u32) -> char {
+        if d < 26 {
+            (b'a' + d as u8) as char
+        } else {
+            (b'0' + (d - 26) as u8) as char
+        }
+    }
+
+    let overflow = || "punycode overflow".to_string();
+
+    let mut output: String = input.iter().filter(|c| c.is_ascii()).collect();
+    let basic_len = output.len() as u32;
+    let mut handled = basic_len;
+    if basic_len > 0 {
+        output.push('-');
+    }
+
+    let mut n = INITIAL_N;
+    let mut delta: u32 = 0;
+    let mut bias = INITIAL_BIAS;
+    while (handled as usize) < input.len() {
+        let m = input
+            .iter()
+            .map(|&c| c as u32)
+            .filter(|&c| c >= n)
+            .min()
+            .ok_or_else(overflow)?;
+        delta = (m - n)
+            .checked_mul(handled + 1)
+            .and_then(|d| delta.checked_add(d))
+            .ok_or_else(overflow)?;
+        n = m;
+
+        for &c in input {
+            let c = c as u32;
+            if c < n {
+                delta = delta.checked_add(1).ok_or_else(overflow)?;
+            }
+            if c == n {
+                let mut q = delta;
+                let mut k = BASE;
+                loop {
+                    let t = if k <= bias {
+                        T_MIN
+                    } else if k >= bias + T_MAX {
+                        T_MAX
+                    } else {
+                        k - bias
+                    };
+                    if q < t {
+                        break;
+                    }
+                    output.push(encode_digit(t + (q - t) % (BASE - t)));
+                    q = (q - t) / (BASE - t);
+                    k += BASE;
+                }
+                output.push(encode_digit(q));
+                bias = adapt(delta, handled + 1, handled == basic_len);
+                delta = 0;
+                handled += 1;
+            }
+        }
+        delta += 1;
+        n += 1;
+    }
+
+    Ok(output)
+}
+
+/// https://url.spec.whatwg.org/#ends-in-a-number-checker
+fn ends_in_a_number(input: &str) -> bool {
+    let mut parts: Vec<&str> = input.split('.').collect();
+    if parts.last() == Some(&"") {
+        if parts.len() == 1 {
+            return false;
+        }
+        parts.pop();
+    }
+
+    let last = parts.last().copied().unwrap_or("");
+    if !last.is_empty() && last.chars().all(|c| c.is_ascii_digit()) {
+        return true;
+    }
+
+    parse_ipv4_number(last).is_ok()
+}
+
+/// https://url.spec.whatwg.org/#ipv4-number-parser
+fn parse_ipv4_number(input: &str) -> Result<u64, String> {
+    if input.is_empty() {
+        return Err("empty IPv4 number".to_string());
+    }
+
+    let (digits, radix) = if let Some(hex) = input
+        .strip_prefix("0x")
+        .or_else(|| input.strip_prefix("0X"))
+    {
+        (hex, 16)
+    } else if input.len() >= 2 && input.starts_with('0') {
+        (&input[1..], 8)
+    } else {
+        (input, 10)
+    };
+
+    if digits.is_empty() {
+        return Ok(0);
+    }
+
+    let mut value: u64 = 0;
+    for c in digits.chars() {
+        let digit = c
+            .to_digit(radix)
+            .ok_or_else(|| format!("invalid IPv4 number: {}", input))?;
+        // 32ビットを超える値はどのみち失敗になるので、桁あふれしないように頭打ちにする
+        value = value
+            .saturating_mul(radix as u64)
+            .saturating_add(digit as u64)
+            .min(u64::from(u32::MAX) + 1);
+    }
+    Ok(value)
+}
+
+/// https://url.spec.whatwg.org/#concept-ipv4-parser
+fn parse_ipv4(input: &str) -> Result<u32, String> {
+    let mut parts: Vec<&str> = input.split('.').collect();
+    if parts.last() == Some(&"") && parts.len() > 1 {
+        parts.pop();
+    }
+
+    if parts.len() > 4 {
+        return Err(format!("too many parts in IPv4 address: {}", input));
+    }
+
+    let mut numbers = Vec::new();
+    for part in parts {
+        numbers.push(parse_ipv4_number(part)?);
+    }
+
+    let (last, init) = numbers
+        .split_last()
+        .ok_or_else(|| format!("invalid IPv4 address: {}", input))?;
+    if init.iter().any(|&n| n > 255) {
+        return Err(format!("IPv4 address part out of range: {}", input));
+    }
+    if *last >= 256u64.pow(5 - numbers.len() as u32) {
+        return Err(format!("IPv4 address out of range: {}", input));
+    }
+
+    let mut ipv4 = *last;
+    for (i, n) in init.iter().enumerate() {
+        ipv4 += n * 256u64.pow(3 - i as u32);
+    }
+    Ok(ipv4 as u32)
+}
+
+/// https://url.spec.whatwg.org/#concept-ipv6-parser
+fn parse_ipv6(input: &str) -> Result<[u16; 8], String> {
+    let input: Vec<char> = input.chars().collect();
+    let invalid = || format!("invalid IPv6 address: {}", input.iter().collect::<String>());
+
+    let mut address = [0u16; 8];
+    let mut piece_index = 0;
+    let mut compress = None;
+    let mut pointer = 0;
+
+    if input.first() == Some(&':') {
+        if input.get(1) != Some(&':') {
+            return Err(invalid());
+        }
+        pointer += 2;
+        piece_index += 1;
+        compress = Some(piece_index);
+    }
+
+    while let Some(&c) = input.get(pointer) {
+        if piece_index == 8 {
+            return Err(invalid());
+        }
+
+        if c == ':' {
+            if compress.is_some() {
+                return Err(invalid());
+            }
+            pointer += 1;
+            piece_index += 1;
+            compress = Some(piece_index);
+            continue;
+        }
+
+        let mut value: u16 = 0;
+        let mut length = 0;
+        while length < 4 {
+            match input.get(pointer).and_then(|c| c.to_digit(16)) {
+                Some(digit) => {
+                    value = value * 0x10 + digit as u16;
+                    pointer += 1;
+                    length += 1;
+                }
+                None => break,
+            }
+        }
+
+        match input.get(pointer) {
+            Some('.') => {
+                if length == 0 || piece_index > 6 {
+                    return Err(invalid());
+                }
+                pointer -= length;
+
+                let mut numbers_seen = 0;
+                while pointer < input.len() {
+                    if numbers_seen > 0 {
+                        if input[pointer] == '.' && numbers_seen < 4 {
+                            pointer += 1;
+                        } else {
+                            return Err(invalid());
+                        }
+                    }
+
+                    let mut ipv4_piece: Option<u16> = None;
+                    while let Some(number) = input.get(pointer).and_then(|c| c.to_digit(10)) {
+                        ipv4_piece = match ipv4_piece {
+                            None => Some(number as u16),
+                            Some(0) => return Err(invalid()),
+                            Some(piece) => Some(piece * 10 + number as u16),
+                        };
+                        if ipv4_piece > Some(255) {
+                            return Err(invalid());
+                        }
+                        pointer += 1;
+                    }
+                    let ipv4_piece = ipv4_piece.ok_or_else(invalid)?;
+
+                    address[piece_index] = address[piece_index] * 0x100 + ipv4_piece;
+                    numbers_seen += 1;
+                    if numbers_seen == 2 || numbers_seen == 4 {
+                        piece_index += 1;
+                    }
+                }
+
+                if numbers_seen != 4 {
+                    return Err(invalid());
+                }
+                break;
+            }
+            Some(':') => {
+                pointer += 1;
+                if pointer >= input.len() {
+                    return Err(invalid());
+                }
+            }
+            Some(_) => return Err(invalid()),
+            None => {}
+        }
+
+        address[piece_index] = value;
+        piece_index += 1;
+    }
+
+    match compress {
+        Some(compress) => {
+            let mut swaps = piece_index - compress;
+            piece_index = 7;
+            while piece_index != 0 && swaps > 0 {
+                address.swap(piece_index, compress + swaps - 1);
+                piece_index -= 1;
+                swaps -= 1;
+            }
+        }
+        None => {
+            if piece_index != 8 {
+                return Err(invalid());
+            }
+        }
+    }
+
+    Ok(address)
+}
+
+/// https://url.spec.whatwg.org/#concept-ipv6-serializer
+fn serialize_ipv6(address: &[u16; 8]) -> String {
+    // 最初に現れる最長の(2つ以上連続する)0の並びを省略する
+    let mut compress = None;
+    let mut longest = 1;
+    let mut i = 0;
+    while i < 8 {
+        if address[i] != 0 {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < 8 && address[i] == 0 {
+            i += 1;
+        }
+        if i - start > longest {
+            longest = i - start;
+            compress = Some(start);
+        }
+    }
+
+    let mut output = String::new();
+    let mut ignore0 = false;
+    for (index, piece) in address.iter().enumerate() {
+        if ignore0 && *piece == 0 {
+            continue;
+        }
+        ignore0 = false;
+
+        if compress == Some(index) {
+            output.push_str(if index == 0 { "::" } else { ":" });
+            ignore0 = true;
+            continue;
+        }
+
+        output.push_str(&format!("{:x}", piece));
+        if index != 7 {
+            output.push(':');
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_domain_is_lowercased() {
+        assert_eq!(
+            Ok(Host::Domain("example.com".to_string())),
+            Host::parse("EXAMPLE.com", false)
+        );
+    }
+
+    #[test]
+    fn test_punycode() {
+        assert_eq!(
+            Ok(Host::Domain("xn--r8jz45g.jp".to_string())),
+            Host::parse("例え.jp", false)
+        );
+    }
+
+    #[test]
+    fn test_ipv4() {
+        assert_eq!(Ok(Host::Ipv4(0x7f000001)), Host::parse("127.0.0.1", false));
+        assert_eq!(Ok(Host::Ipv4(0x7f000001)), Host::parse("0x7f.1", false));
+        assert_eq!(
+            Ok(Host::Ipv4(0xc0a80001)),
+            Host::parse("0300.0250.0.1", false)
+        );
+        assert!(Host::parse("256.0.0.1", false).is_err());
+        assert_eq!("127.0.0.1".to_string(), Host::Ipv4(0x7f000001).to_string());
+    }
+
+    #[test]
+    fn test_ipv6() {
+        assert_eq!(
+            Ok(Host::Ipv6([0, 0, 0, 0, 0, 0, 0, 1])),
+            Host::parse("[::1]", false)
+        );
+        assert_eq!(
+            Ok(Host::Ipv6([0x2001, 0xdb8, 0, 0, 0, 0, 0xc000, 0x0280])),
+            Host::parse("[2001:DB8::192.0.2.128]", false)
+        );
+        assert!(Host::parse("[1:2:3:4:5:6:7:8:9]", false).is_err());
+        assert!(Host::parse("[::1", false).is_err());
+    }
+
+    #[test]
+    fn test_ipv6_serialize() {
+        assert_eq!(
+            "[2001:db8::1:0:0:1]".to_string(),
+            Host::Ipv6([0x2001, 0xdb8, 0, 0, 1, 0, 0, 1]).to_string()
+        );
+        assert_eq!(
+            "[::]".to_string(),
+            Host::Ipv6([0, 0, 0, 0, 0, 0, 0, 0]).to_string()
+        );
+    }
+
+    #[test]
+    fn test_forbidden_code_point() {
+        assert!(Host::parse("exa mple.com", false).is_err());
+        assert!(Host::parse("exa%mple.com", false).is_err());
+        assert!(Host::parse("exa<mple", true).is_err());
+    }
+}
@@ -0,0 +1,144 @@
+use alloc::{string::String, vec::Vec};
+
+/// https://url.spec.whatwg.org/#percent-encoded-bytes
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PercentEncodeSet {
+    /// https://url.spec.whatwg.org/#c0-control-percent-encode-set
+    C0Control,
+    /// https://url.spec.whatwg.org/#fragment-percent-encode-set
+    Fragment,
+    /// https://url.spec.whatwg.org/#query-percent-encode-set
+    Query,
+    /// https://url.spec.whatwg.org/#special-query-percent-encode-set
+    SpecialQuery,
+    /// https://url.spec.whatwg.org/#path-percent-encode-set
+    Path,
+    /// https://url.spec.whatwg.org/#userinfo-percent-encode-set
+    Userinfo,
+    /// https://url.spec.whatwg.org/#component-percent-encode-set
+    Component,
+    /// https://url.spec.whatwg.org/#application-x-www-form-urlencoded-percent-encode-set
+    FormUrlencoded,
+}
+
+impl PercentEncodeSet {
+    /// バイトがこの集合に含まれる（パーセントエンコードが必要な）ときにtrueを返します
+    pub fn contains(&self, byte: u8) -> bool {
+        // C0制御文字とASCII範囲外はすべての集合に含まれる
+        if byte <= 0x1f || byte > 0x7e {
+            return true;
+        }
+
+        match self {
+            PercentEncodeSet::C0Control => false,
+            PercentEncodeSet::Fragment => matches!(byte, b' ' | b'"' | b'<' | b'>' | b'`'),
+            PercentEncodeSet::Query => matches!(byte, b' ' | b'"' | b'#' | b'<' | b'>'),
+            PercentEncodeSet::SpecialQuery => {
+                PercentEncodeSet::Query.contains(byte) || byte == b'\''
+            }
+            PercentEncodeSet::Path => {
+                PercentEncodeSet::Query.contains(byte) || matches!(byte, b'?' | b'`' | b'{' | b'}')
+            }
+            PercentEncodeSet::Userinfo => {
+                PercentEncodeSet::Path.contains(byte)
+                    || matches!(
+                        byte,
+                        b'/' | b':' | b';' | b'=' | b'@' | b'[' | b'\\' | b']' | b'^' | b'|'
+                    )
+            }
+            PercentEncodeSet::Component => {
+                PercentEncodeSet::Userinfo.contains(byte)
+                    || matches!(byte, b'$' | b'%' | b'&' | b'+' | b',')
+            }
+            PercentEncodeSet::FormUrlencoded => {
+                PercentEncodeSet::Component.contains(byte)
+                    || matches!(byte, b'!' | b'\'' | b'(' | b')' | b'~')
+            }
+        }
+    }
+}
+
+fn push_percent_encoded_byte(output: &mut String, byte: u8) {
+    const HEX: &[u8; 16] = b"0123456789ABCDEF";
+    output.push('%');
+    output.push(HEX[(byte >> 4) as usize] as char);
+    output.push(HEX[(byte & 0x0f) as usize] as char);
+}
+
+/// https://url.spec.whatwg.org/#utf-8-percent-encode
+pub fn utf8_percent_encode_char(c: char, set: PercentEncodeSet, output: &mut String) {
+    let mut buf = [0u8; 4];
+    for byte in c.encode_utf8(&mut buf).bytes() {
+        if set.contains(byte) {
+            push_percent_encoded_byte(output, byte);
+        } else {
+            output.push(byte as char);
+        }
+    }
+}
+
+/// https://url.spec.whatwg.org/#string-utf-8-percent-encode
+pub fn utf8_percent_encode(input: &str, set: PercentEncodeSet) -> String {
+    let mut output = String::new();
+    for c in input.chars() {
+        utf8_percent_encode_char(c, set, &mut output);
+    }
+    output
+}
+
+/// https://url.spec.whatwg.org/#percent-decode
+pub fn percent_decode(input: &[u8]) -> Vec<u8> {
+    fn hex_value(byte: u8) -> Option<u8> {
+        (byte as char).to_digit(16).map(|d| d as u8)
+    }
+
+    let mut output = Vec::with_capacity(input.len());
+    let mut i = 0;
+    while i < input.len() {
+        if input[i] == b'%' && i + 2 < input.len() {
+            if let (Some(high), Some(low)) = (hex_value(input[i + 1]), hex_value(input[i + 2])) {
+                output.push(high << 4 | low);
+                i += 3;
+                continue;
+            }
+        }
+        output.push(input[i]);
+        i += 1;
+    }
+    output
+}
+
+/// パーセントデコードしたバイト列をUTF-8として解釈します（不正なシーケンスはU+FFFDに置き換えます）
+pub fn percent_decode_to_string(input: &str) -> String {
+    String::from_utf8_lossy(&percent_decode(input.as_bytes())).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_encode_path() {
+        assert_eq!(
+            "/a%20b%3Fc%7B%7D".to_string(),
+            utf8_percent_encode("/a b?c{}", PercentEncodeSet::Path)
+        );
+    }
+
+    #[test]
+    fn test_encode_non_ascii() {
+        assert_eq!(
+            "%E3%81%82".to_string(),
+            utf8_percent_encode("あ", PercentEncodeSet::C0Control)
+        );
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!("a b".to_string(), percent_decode_to_string("a%20b"));
+        assert_eq!("あ".to_string(), percent_decode_to_string("%E3%81%82"));
+        // 不正な形式はそのまま残す
+        assert_eq!("%zz%4".to_string(), percent_decode_to_string("%zz%4"));
+    }
+}
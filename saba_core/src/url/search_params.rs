@@ -0,0 +1,210 @@
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt::{Display, Formatter};
+
+use super::percent_encoding::{percent_decode, utf8_percent_encode_char, PercentEncodeSet};
+
+/// 名前と値の組を順序付きで保持するクエリ文字列
+/// 同じ名前が複数回現れることもあります
+/// https://url.spec.whatwg.org/#interface-urlsearchparams
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchParams {
+    list: Vec<(String, String)>,
+}
+
+impl SearchParams {
+    pub fn new() -> Self {
+        Self { list: Vec::new() }
+    }
+
+    /// application/x-www-form-urlencoded形式の文字列をパースします
+    /// 先頭の"?"は無視されます
+    /// https://url.spec.whatwg.org/#concept-urlencoded-parser
+    pub fn parse(input: &str) -> Self {
+        let input = input.strip_prefix('?').unwrap_or(input);
+
+        let mut list = Vec::new();
+        for sequence in input.split('&') {
+            if sequence.is_empty() {
+                continue;
+            }
+            let (name, value) = sequence.split_once('=').unwrap_or((sequence, ""));
+            list.push((decode(name), decode(value)));
+        }
+
+        Self { list }
+    }
+
+    /// https://url.spec.whatwg.org/#dom-urlsearchparams-get
+    pub fn get(&self, name: &str) -> Option<String> {
+        self.list
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.clone())
+    }
+
+    /// https://url.spec.whatwg.org/#dom-urlsearchparams-getall
+    pub fn get_all(&self, name: &str) -> Vec<String> {
+        self.list
+            .iter()
+            .filter(|(n, _)| n == name)
+            .map(|(_, v)| v.clone())
+            .collect()
+    }
+
+    /// https://url.spec.whatwg.org/#dom-urlsearchparams-has
+    pub fn has(&self, name: &str) -> bool {
+        self.list.iter().any(|(n, _)| n == name)
+    }
+
+    /// https://url.spec.whatwg.org/#dom-urlsearchparams-append
+    pub fn append(&mut self, name: &str, value: &str) {
+        self.list.push((name.to_string(), value.to_string()));
+    }
+
+    /// 最初に現れる同名の組の値を置き換え、残りの同名の組を削除します
+    /// 同名の組が存在しない場合は末尾に追加します
+    /// https://url.spec.whatwg.org/#dom-urlsearchparams-set
+    pub fn set(&mut self, name: &str, value: &str) {
+        match self.list.iter().position(|(n, _)| n == name) {
+            Some(index) => {
+                self.list[index].1 = value.to_string();
+                let mut i = 0;
+                self.list.retain(|(n, _)| {
+                    let keep = i <= index || n != name;
+                    i += 1;
+                    keep
+                });
+            }
+            None => self.append(name, value),
+        }
+    }
+
+    /// https://url.spec.whatwg.org/#dom-urlsearchparams-delete
+    pub fn delete(&mut self, name: &str) {
+        self.list.retain(|(n, _)| n != name);
+    }
+
+    /// 名前をUTF-16のコードユニット順で安定ソートします
+    /// https://url.spec.whatwg.org/#dom-urlsearchparams-sort
+    pub fn sort(&mut self) {
+        self.list
+            .sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+    }
+
+    pub fn pairs(&self) -> Vec<(String, String)> {
+        self.list.clone()
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+}
+
+impl From<Vec<(String, String)>> for SearchParams {
+    fn from(list: Vec<(String, String)>) -> Self {
+        Self { list }
+    }
+}
+
+impl Display for SearchParams {
+    /// https://url.spec.whatwg.org/#concept-urlencoded-serializer
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        let mut output = String::new();
+        for (i, (name, value)) in self.list.iter().enumerate() {
+            if i > 0 {
+                output.push('&');
+            }
+            encode(name, &mut output);
+            output.push('=');
+            encode(value, &mut output);
+        }
+        write!(f, "{}", output)
+    }
+}
+
+/// "+"を空白に置き換えてからパーセントデコードします
+fn decode(input: &str) -> String {
+    let replaced: Vec<u8> = input
+        .bytes()
+        .map(|b| if b == b'+' { b' ' } else { b })
+        .collect();
+    String::from_utf8_lossy(&percent_decode(&replaced)).into_owned()
+}
+
+/// https://url.spec.whatwg.org/#concept-urlencoded-byte-serializer
+fn encode(input: &str, output: &mut String) {
+    for c in input.chars() {
+        if c == ' ' {
+            output.push('+');
+        } else {
+            utf8_percent_encode_char(c, PercentEncodeSet::FormUrlencoded, output);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn test_parse() {
+        let params = SearchParams::parse("?a=1&b=x+y&c=%E3%81%82&&d&a=2");
+        assert_eq!(Some("1".to_string()), params.get("a"));
+        assert_eq!(vec!["1".to_string(), "2".to_string()], params.get_all("a"));
+        assert_eq!(Some("x y".to_string()), params.get("b"));
+        assert_eq!(Some("あ".to_string()), params.get("c"));
+        assert_eq!(Some("".to_string()), params.get("d"));
+        assert_eq!(None, params.get("e"));
+        assert_eq!(5, params.len());
+    }
+
+    #[test]
+    fn test_serialize() {
+        let mut params = SearchParams::new();
+        params.append("q", "a b&c=d");
+        params.append("lang", "日本");
+        params.append("sym", "*-._~!");
+        assert_eq!(
+            "q=a+b%26c%3Dd&lang=%E6%97%A5%E6%9C%AC&sym=*-._%7E%21",
+            params.to_string()
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let input = "a=1&b=x+y&c=%2B";
+        assert_eq!(input, SearchParams::parse(input).to_string());
+    }
+
+    #[test]
+    fn test_set_and_delete() {
+        let mut params = SearchParams::parse("a=1&b=2&a=3&c=4");
+        params.set("a", "5");
+        assert_eq!("a=5&b=2&c=4", params.to_string());
+
+        params.set("d", "6");
+        assert_eq!("a=5&b=2&c=4&d=6", params.to_string());
+
+        params.delete("b");
+        assert!(!params.has("b"));
+        assert_eq!("a=5&c=4&d=6", params.to_string());
+    }
+
+    #[test]
+    fn test_sort_is_stable() {
+        let mut params = SearchParams::parse("z=1&a=2&z=0&a=1&%F0%9F%98%80=3&%EF%AC%80=4");
+        params.sort();
+        assert_eq!(
+            "a=2&a=1&z=1&z=0&%F0%9F%98%80=3&%EF%AC%80=4",
+            params.to_string()
+        );
+    }
+}
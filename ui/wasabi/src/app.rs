@@ -21,10 +21,16 @@ use saba_core::{
     error::Error,
     http::HttpResponse,
     renderer::layout::computed_style::{FontSize, TextDecoration},
+    url::Url,
 };
 
 use crate::cursor::Cursor;
 
+/// URLに移動するときに呼ばれる関数
+/// ネットワークから受信したボディは、届いた順にリクエストのURLとともに2つ目の引数に渡されます
+pub type HandleUrl =
+    dyn Fn(String, &mut dyn FnMut(&Url, &HttpResponse, &[u8])) -> Result<HttpResponse, Error>;
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum InputMode {
     Normal,
@@ -68,10 +74,7 @@ impl WasabiUI {
         self.input_mode = InputMode::Normal;
     }
 
-    pub fn start(
-        &mut self,
-        handle_url: fn(String) -> Result<HttpResponse, Error>,
-    ) -> Result<(), Error> {
+    pub fn start(&mut self, handle_url: &HandleUrl) -> Result<(), Error> {
         self.setup()?;
 
         self.run_app(handle_url)?;
@@ -79,20 +82,14 @@ impl WasabiUI {
         Ok(())
     }
 
-    fn run_app(
-        &mut self,
-        handle_url: fn(String) -> Result<HttpResponse, Error>,
-    ) -> Result<(), Error> {
+    fn run_app(&mut self, handle_url: &HandleUrl) -> Result<(), Error> {
         loop {
             self.handle_mouse_input(handle_url)?;
             self.handle_key_input(handle_url)?;
         }
     }
 
-    fn handle_mouse_input(
-        &mut self,
-        handle_url: fn(String) -> Result<HttpResponse, Error>,
-    ) -> Result<(), Error> {
+    fn handle_mouse_input(&mut self, handle_url: &HandleUrl) -> Result<(), Error> {
         let (button, position) = match Api::get_mouse_cursor_info() {
             Some(MouseEvent { button, position }) => (button, position),
             _ => return Ok(()),
@@ -151,10 +148,7 @@ impl WasabiUI {
         Ok(())
     }
 
-    fn handle_key_input(
-        &mut self,
-        handle_url: fn(String) -> Result<HttpResponse, Error>,
-    ) -> Result<(), Error> {
+    fn handle_key_input(&mut self, handle_url: &HandleUrl) -> Result<(), Error> {
         match self.input_mode {
             InputMode::Normal => {
                 // キー入力を無視
@@ -189,24 +183,86 @@ impl WasabiUI {
 
     fn start_navigation(
         &mut self,
-        handle_url: fn(String) -> Result<HttpResponse, Error>,
+        handle_url: &HandleUrl,
         destination: String,
     ) -> Result<(), Error> {
         self.clear_content_area()?;
 
-        handle_url(destination).map(|response| {
-            self.browser
-                .borrow()
-                .current_page()
-                .borrow_mut()
-                .receive_response(response);
-        })?;
+        let url = match Url::new(destination.clone()).parse() {
+            Ok(url) => url,
+            Err(e) => return self.show_error_page(&destination, Error::UnexpectedInput(e)),
+        };
+        // 成功したレスポンスのボディは、すべて受信するのを待たずに届いた順にページに渡して描画する
+        let page = self.browser.borrow().current_page();
+        let mut loading_url: Option<Url> = None;
+        let mut ui_result = Ok(());
+        let result = handle_url(destination.clone(), &mut |hop_url, head, data| {
+            if !(200..300).contains(&head.status_code()) || ui_result.is_err() {
+                return;
+            }
+            if loading_url.as_ref() != Some(hop_url) {
+                page.borrow_mut()
+                    .start_loading(hop_url.clone(), head.header_value("Content-Type").ok());
+                loading_url = Some(hop_url.clone());
+            }
+            if page.borrow_mut().receive_data(data) {
+                ui_result = self.clear_content_area().and_then(|_| self.update_ui());
+            }
+        });
+        ui_result?;
+        let response = match result {
+            Ok(response) => response,
+            // 本文の途中で失敗した場合は、表示したところまでのページを残し、末尾に中断したことを表示する
+            // close_notifyのないまま切断されて本文の終わりがわからない場合もここに来る
+            Err(e) if loading_url.is_some() => {
+                println!("failed to finish loading {destination}: {e:?}");
+                if let Some(hop_url) = loading_url.filter(|hop_url| *hop_url != url) {
+                    self.input_url = hop_url.to_string();
+                    self.update_address_bar()?;
+                }
+                page.borrow_mut().abort_loading(&e);
+                self.clear_content_area()?;
+                return self.update_ui();
+            }
+            Err(e) => return self.show_error_page(&destination, e),
+        };
+
+        // リダイレクトされた場合は最終的なURLをアドレスバーに表示し、ページのURLとする
+        let url = match response.url() {
+            Some(final_url) if final_url != url => {
+                self.input_url = final_url.to_string();
+                self.update_address_bar()?;
+                final_url
+            }
+            _ => url,
+        };
+
+        // キャッシュから返したレスポンスやエラーの状態コードのレスポンスは、まとめてページに渡す
+        if loading_url.as_ref() == Some(&url) && (200..300).contains(&response.status_code()) {
+            page.borrow_mut().finish_loading();
+        } else {
+            page.borrow_mut().receive_response(url, response);
+        }
 
+        self.clear_content_area()?;
         self.update_ui()?;
 
         Ok(())
     }
 
+    /// ナビゲーションに失敗したとき、ブラウザを終了せずに現在のページにエラーページを表示します
+    fn show_error_page(&mut self, destination: &str, error: Error) -> Result<(), Error> {
+        println!("failed to navigate to {destination}: {error:?}");
+
+        self.browser
+            .borrow()
+            .current_page()
+            .borrow_mut()
+            .receive_error(destination, &error);
+
+        self.update_ui()
+    }
+
     fn update_ui(&mut self) -> Result<(), Error> {
         let display_items = self
             .browser